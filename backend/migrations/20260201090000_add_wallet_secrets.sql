@@ -0,0 +1,6 @@
+-- Migration: add_wallet_secrets
+-- Created: 2026-02-01 09:00:00 UTC
+
+-- Custodial wallet seed, AES-256-GCM sealed with the configured custody key.
+-- Nullable because accounts created before real keypair generation have no seed.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS encrypted_secret TEXT;
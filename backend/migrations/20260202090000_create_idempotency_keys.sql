@@ -0,0 +1,20 @@
+-- Migration: create_idempotency_keys
+-- Created: 2026-02-02 09:00:00 UTC
+
+-- Idempotency-Key records for money-moving POST routes.
+-- A row is reserved before the handler runs (response_status NULL) and
+-- completed with the response once it returns; expired rows may be reused.
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+    user_id VARCHAR(255) NOT NULL,
+    idempotency_key VARCHAR(255) NOT NULL,
+    request_method VARCHAR(10) NOT NULL,
+    request_path TEXT NOT NULL,
+    request_fingerprint CHAR(64) NOT NULL,
+    response_status SMALLINT,
+    response_body TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    PRIMARY KEY (user_id, idempotency_key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_idempotency_keys_expires_at ON idempotency_keys(expires_at);
@@ -0,0 +1,17 @@
+-- Migration: add_withdrawal_anchor_fields
+-- Created: 2026-02-04 09:00:00 UTC
+
+-- Withdrawals run through the anchor's SEP-24 interactive flow, where the
+-- off-chain destination is collected by the anchor, so it is optional here.
+-- The anchor's own status, the memo its payment must carry and its last
+-- message are kept alongside the withdrawal for the status poller.
+ALTER TABLE withdrawals ALTER COLUMN destination_address DROP NOT NULL;
+
+ALTER TABLE withdrawals
+    ADD COLUMN IF NOT EXISTS anchor_status VARCHAR(50),
+    ADD COLUMN IF NOT EXISTS interactive_url TEXT,
+    ADD COLUMN IF NOT EXISTS anchor_message TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_withdrawals_open
+    ON withdrawals (created_at)
+    WHERE status IN ('pending', 'processing') AND anchor_tx_id IS NOT NULL;
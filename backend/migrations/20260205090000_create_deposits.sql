@@ -0,0 +1,36 @@
+-- Migration: create_deposits
+-- Created: 2026-02-05 09:00:00 UTC
+
+-- SEP-24 interactive deposits. The amount is chosen in the anchor's flow, so
+-- it is only known up front when the user gave one and is updated with what
+-- the anchor delivered once the deposit completes.
+CREATE TABLE IF NOT EXISTS deposits (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tx_hash VARCHAR(64) UNIQUE,
+    user_id VARCHAR(255) NOT NULL REFERENCES users(user_id),
+    amount BIGINT,
+    asset VARCHAR(56) NOT NULL,
+    status VARCHAR(50) NOT NULL DEFAULT 'pending',
+    anchor_tx_id VARCHAR(255),
+    anchor_status VARCHAR(50),
+    interactive_url TEXT,
+    anchor_message TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_deposits_user_id ON deposits(user_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_deposits_open
+    ON deposits (created_at)
+    WHERE status IN ('pending', 'processing') AND anchor_tx_id IS NOT NULL;
+
+CREATE TABLE IF NOT EXISTS deposit_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    deposit_id UUID NOT NULL REFERENCES deposits(id) ON DELETE CASCADE,
+    from_status VARCHAR(50),
+    to_status VARCHAR(50) NOT NULL,
+    reason TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_deposit_events_deposit_id ON deposit_events(deposit_id, created_at);
@@ -0,0 +1,46 @@
+-- Migration: create_settlements
+-- Created: 2026-02-06 09:00:00 UTC
+
+-- SEP-31 payouts of a merchant's balance to its bank account through a
+-- receiving anchor. The amount is debited from the merchant's balance when the
+-- settlement is created, paid on-chain from the platform account to the
+-- anchor's account with the anchor's memo, and returned if the payout fails.
+CREATE TABLE IF NOT EXISTS settlements (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tx_hash VARCHAR(64) UNIQUE,
+    merchant_id VARCHAR(255) NOT NULL REFERENCES merchants(merchant_id),
+    amount BIGINT NOT NULL,
+    asset VARCHAR(56) NOT NULL,
+    -- Amount the receiver got, in stroops, once the anchor reports it
+    amount_out BIGINT,
+    status VARCHAR(50) NOT NULL DEFAULT 'pending',
+    -- SEP-12 customer ids registered with the receiving anchor
+    sender_id VARCHAR(255),
+    receiver_id VARCHAR(255),
+    anchor_tx_id VARCHAR(255),
+    anchor_status VARCHAR(50),
+    anchor_message TEXT,
+    -- Where and with which memo the on-chain payment must be sent
+    stellar_account_id VARCHAR(56),
+    stellar_memo TEXT,
+    stellar_memo_type VARCHAR(10),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_settlements_merchant_id ON settlements(merchant_id, created_at);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_settlements_anchor_tx_id ON settlements(anchor_tx_id);
+CREATE INDEX IF NOT EXISTS idx_settlements_open
+    ON settlements (created_at)
+    WHERE status IN ('pending', 'processing') AND anchor_tx_id IS NOT NULL;
+
+CREATE TABLE IF NOT EXISTS settlement_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    settlement_id UUID NOT NULL REFERENCES settlements(id) ON DELETE CASCADE,
+    from_status VARCHAR(50),
+    to_status VARCHAR(50) NOT NULL,
+    reason TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_settlement_events_settlement_id ON settlement_events(settlement_id, created_at);
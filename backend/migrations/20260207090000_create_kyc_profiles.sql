@@ -0,0 +1,20 @@
+-- Migration: create_kyc_profiles
+-- Created: 2026-02-07 09:00:00 UTC
+
+-- KYC state of each user with the anchor's SEP-12 server. `level` is the
+-- level the anchor has accepted; `requested_level` is the level of the latest
+-- submission, which becomes `level` once the anchor accepts it.
+CREATE TABLE IF NOT EXISTS kyc_profiles (
+    user_id VARCHAR(255) PRIMARY KEY REFERENCES users(user_id),
+    customer_id VARCHAR(255) UNIQUE,
+    level VARCHAR(20) NOT NULL DEFAULT 'none',
+    requested_level VARCHAR(20) NOT NULL,
+    status VARCHAR(20) NOT NULL,
+    message TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_kyc_profiles_open
+    ON kyc_profiles (updated_at)
+    WHERE status IN ('processing', 'needs_info');
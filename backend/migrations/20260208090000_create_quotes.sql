@@ -0,0 +1,25 @@
+-- Migration: create_quotes
+-- Created: 2026-02-08 09:00:00 UTC
+
+-- Firm quotes for paying a merchant in an asset other than its settlement
+-- asset. Amounts are in stroops; `price` is the cost of one unit of
+-- `buy_asset` in `sell_asset`. A quote is used by at most one payment, which
+-- takes its `min_receive` from `buy_amount`.
+CREATE TABLE IF NOT EXISTS quotes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    merchant_id VARCHAR(255) NOT NULL REFERENCES merchants(merchant_id),
+    payer_address VARCHAR(56) NOT NULL,
+    sell_asset VARCHAR(100) NOT NULL,
+    sell_amount BIGINT NOT NULL,
+    buy_asset VARCHAR(100) NOT NULL,
+    buy_amount BIGINT NOT NULL,
+    price VARCHAR(50) NOT NULL,
+    source VARCHAR(20) NOT NULL,
+    anchor_quote_id VARCHAR(255),
+    path JSONB NOT NULL DEFAULT '[]',
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    payment_id UUID UNIQUE REFERENCES payments(id),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_quotes_payer ON quotes(payer_address);
@@ -0,0 +1,18 @@
+-- Migration: create_payment_uri_nonces
+-- Created: 2026-02-09 09:00:00 UTC
+
+-- One row per signed SEP-7 payment request shown as a QR code. The nonce in
+-- the URI is redeemed once, when a scanned URI is verified, and only until
+-- `expires_at`.
+CREATE TABLE IF NOT EXISTS payment_uri_nonces (
+    nonce VARCHAR(64) PRIMARY KEY,
+    merchant_id VARCHAR(255) NOT NULL REFERENCES merchants(merchant_id),
+    amount BIGINT NOT NULL,
+    asset VARCHAR(100) NOT NULL,
+    memo VARCHAR(28),
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_payment_uri_nonces_expires_at ON payment_uri_nonces(expires_at);
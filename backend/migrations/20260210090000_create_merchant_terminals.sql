@@ -0,0 +1,29 @@
+-- Migration: create_merchant_terminals
+-- Created: 2026-02-10 09:00:00 UTC
+
+-- Ed25519 keys of the NFC terminals each merchant accepts payments with.
+-- Terminals sign every tap payload; a revoked terminal's taps are rejected.
+CREATE TABLE IF NOT EXISTS merchant_terminals (
+    id UUID PRIMARY KEY,
+    merchant_id VARCHAR(255) NOT NULL REFERENCES merchants(merchant_id),
+    public_key VARCHAR(56) NOT NULL UNIQUE,
+    label VARCHAR(255),
+    active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    revoked_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX IF NOT EXISTS idx_merchant_terminals_merchant_id ON merchant_terminals(merchant_id);
+
+-- Nonces of the tap payloads already accepted from each terminal. A payload
+-- is only valid for a bounded window around its timestamp, so rows older
+-- than that window can be pruned without reopening replays.
+CREATE TABLE IF NOT EXISTS nfc_tap_nonces (
+    terminal_id UUID NOT NULL REFERENCES merchant_terminals(id),
+    nonce VARCHAR(64) NOT NULL,
+    tapped_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    PRIMARY KEY (terminal_id, nonce)
+);
+
+CREATE INDEX IF NOT EXISTS idx_nfc_tap_nonces_tapped_at ON nfc_tap_nonces(tapped_at);
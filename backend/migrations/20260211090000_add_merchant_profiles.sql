@@ -0,0 +1,19 @@
+-- Migration: add_merchant_profiles
+-- Created: 2026-02-11 09:00:00 UTC
+
+-- Business profile of each merchant, kept alongside the settlement details
+-- mirrored to the zaps-registry contract.
+ALTER TABLE merchants ADD COLUMN IF NOT EXISTS business_name VARCHAR(255);
+ALTER TABLE merchants ADD COLUMN IF NOT EXISTS contact_email VARCHAR(255);
+ALTER TABLE merchants ADD COLUMN IF NOT EXISTS website VARCHAR(255);
+
+-- Users who operate a merchant: they can manage its profile, terminals and
+-- settlement details through the self-service endpoints.
+CREATE TABLE IF NOT EXISTS merchant_users (
+    merchant_id VARCHAR(255) NOT NULL REFERENCES merchants(merchant_id),
+    user_id VARCHAR(255) NOT NULL REFERENCES users(user_id),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    PRIMARY KEY (merchant_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_merchant_users_user_id ON merchant_users(user_id);
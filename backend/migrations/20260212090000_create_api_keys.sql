@@ -0,0 +1,20 @@
+-- Migration: create_api_keys
+-- Created: 2026-02-12 09:00:00 UTC
+
+-- Merchant API keys for server-to-server integrations. Only the SHA-256 of
+-- each key is stored; `prefix` is the non-secret start of the key, used to
+-- find it and to tell keys apart in listings.
+CREATE TABLE IF NOT EXISTS api_keys (
+    id UUID PRIMARY KEY,
+    merchant_id VARCHAR(255) NOT NULL REFERENCES merchants(merchant_id),
+    name VARCHAR(255) NOT NULL,
+    prefix VARCHAR(32) NOT NULL UNIQUE,
+    key_hash CHAR(64) NOT NULL,
+    scopes TEXT[] NOT NULL,
+    created_by VARCHAR(255) NOT NULL,
+    last_used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    revoked_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_keys_merchant_id ON api_keys(merchant_id);
@@ -0,0 +1,11 @@
+-- Migration: create_anchor_callback_signatures
+-- Created: 2026-02-14 09:00:00 UTC
+
+-- Signatures of accepted anchor callbacks, so a captured callback cannot be
+-- replayed while its timestamp is still within the accepted window.
+CREATE TABLE IF NOT EXISTS anchor_callback_signatures (
+    signature VARCHAR(128) PRIMARY KEY,
+    received_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_anchor_callback_signatures_received_at ON anchor_callback_signatures(received_at);
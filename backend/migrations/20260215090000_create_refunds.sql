@@ -0,0 +1,46 @@
+-- Migration: create_refunds
+-- Created: 2026-02-15 09:00:00 UTC
+
+-- Total of a payment's completed refunds, in stroops of the merchant's
+-- settlement asset. The payment becomes 'refunded' once this reaches the
+-- settled amount.
+ALTER TABLE payments ADD COLUMN IF NOT EXISTS refunded_amount BIGINT NOT NULL DEFAULT 0;
+
+-- Full or partial refunds of completed payments. The amount is debited from
+-- the merchant's balance when the refund is created, debited from the
+-- merchant-vault contract and paid on-chain from the platform account to the
+-- payer, and returned to the merchant if the refund fails.
+CREATE TABLE IF NOT EXISTS refunds (
+    id UUID PRIMARY KEY,
+    payment_id UUID NOT NULL REFERENCES payments(id),
+    merchant_id VARCHAR(255) NOT NULL REFERENCES merchants(merchant_id),
+    amount BIGINT NOT NULL CHECK (amount > 0),
+    asset VARCHAR(56) NOT NULL,
+    -- Wallet the refund is paid to: the payment's payer
+    destination_address VARCHAR(56) NOT NULL,
+    reason VARCHAR(50) NOT NULL,
+    note TEXT,
+    status VARCHAR(50) NOT NULL DEFAULT 'pending',
+    -- merchant-vault debit, then the payment to the payer
+    vault_tx_hash VARCHAR(64),
+    tx_hash VARCHAR(64) UNIQUE,
+    requested_by VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_refunds_payment_id ON refunds(payment_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_refunds_open
+    ON refunds (created_at)
+    WHERE status IN ('pending', 'processing');
+
+CREATE TABLE IF NOT EXISTS refund_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    refund_id UUID NOT NULL REFERENCES refunds(id) ON DELETE CASCADE,
+    from_status VARCHAR(50),
+    to_status VARCHAR(50) NOT NULL,
+    reason TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_refund_events_refund_id ON refund_events(refund_id, created_at);
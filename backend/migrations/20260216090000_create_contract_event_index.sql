@@ -0,0 +1,26 @@
+-- Migration: create_contract_event_index
+-- Created: 2026-02-16 09:00:00 UTC
+
+-- Position of each contract event indexer in the Soroban RPC event stream,
+-- as the `getEvents` cursor to resume from.
+CREATE TABLE IF NOT EXISTS indexer_cursors (
+    name VARCHAR(50) PRIMARY KEY,
+    cursor VARCHAR(64) NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+-- Contract events that have been applied, so an event read twice (e.g. after
+-- a restart between applying it and saving the cursor) is applied once.
+CREATE TABLE IF NOT EXISTS indexed_contract_events (
+    -- Event id assigned by the RPC server (`getEvents` id)
+    id VARCHAR(64) PRIMARY KEY,
+    contract_id VARCHAR(56) NOT NULL,
+    ledger BIGINT NOT NULL,
+    tx_hash VARCHAR(64),
+    event_type VARCHAR(50) NOT NULL,
+    -- applied, skipped, unmatched or invalid
+    outcome VARCHAR(20) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_indexed_contract_events_tx_hash ON indexed_contract_events(tx_hash);
@@ -0,0 +1,23 @@
+-- Migration: create_jobs
+-- Created: 2026-02-17 09:00:00 UTC
+
+-- Durable background jobs, e.g. writing audit logs and sending notification
+-- emails. A job runs at or after run_at, is retried with backoff when its
+-- handler fails, and is dead-lettered after jobs.max_attempts until an admin
+-- retries it. Completed jobs are kept for a retention period, then deleted.
+CREATE TABLE IF NOT EXISTS jobs (
+    id UUID PRIMARY KEY,
+    job_type VARCHAR(50) NOT NULL,
+    payload JSONB NOT NULL,
+    -- pending, completed or dead
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    run_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    last_attempt_at TIMESTAMP WITH TIME ZONE,
+    last_error TEXT,
+    completed_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_jobs_due ON jobs(run_at) WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS idx_jobs_status ON jobs(status, created_at DESC);
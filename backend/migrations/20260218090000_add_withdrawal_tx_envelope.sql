@@ -0,0 +1,8 @@
+-- Migration: add_withdrawal_tx_envelope
+-- Created: 2026-02-18 09:00:00 UTC
+
+-- The signed payment to the anchor, committed together with its tx_hash
+-- before it is submitted. Until it lands or expires the same envelope is
+-- resubmitted, so a crash or failed submission never leads to a second
+-- payment.
+ALTER TABLE withdrawals ADD COLUMN IF NOT EXISTS tx_envelope TEXT;
@@ -0,0 +1,9 @@
+-- Migration: add_refund_tx_envelopes
+-- Created: 2026-02-18 11:00:00 UTC
+
+-- The signed merchant-vault debit and payment to the payer, each committed
+-- together with its hash before it is submitted. Until it lands or expires
+-- the same envelope is resubmitted, so a crash, a failed submission or a
+-- slow confirmation never leads to a second debit or payment.
+ALTER TABLE refunds ADD COLUMN IF NOT EXISTS vault_tx_envelope TEXT;
+ALTER TABLE refunds ADD COLUMN IF NOT EXISTS tx_envelope TEXT;
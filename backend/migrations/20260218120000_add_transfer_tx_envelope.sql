@@ -0,0 +1,7 @@
+-- Migration: add_transfer_tx_envelope
+-- Created: 2026-02-18 12:00:00 UTC
+
+-- The signed payment of a transfer, committed together with its tx_hash
+-- before it is submitted. The transfer status worker resubmits it until it
+-- lands or expires, then completes or fails the transfer.
+ALTER TABLE transfers ADD COLUMN IF NOT EXISTS tx_envelope TEXT;
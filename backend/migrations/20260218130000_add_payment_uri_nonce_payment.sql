@@ -0,0 +1,6 @@
+-- Migration: add_payment_uri_nonce_payment
+-- Created: 2026-02-18 13:00:00 UTC
+
+-- A payment request's nonce is now redeemed by the payment that pays it, in
+-- the same transaction, rather than when the scanned URI is verified.
+ALTER TABLE payment_uri_nonces ADD COLUMN IF NOT EXISTS payment_id UUID REFERENCES payments(id);
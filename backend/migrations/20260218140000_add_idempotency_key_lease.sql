@@ -0,0 +1,8 @@
+-- Migration: add_idempotency_key_lease
+-- Created: 2026-02-18 14:00:00 UTC
+
+-- In-flight reservations hold their key only until locked_until, which the
+-- request renews while it runs, so a key left behind by a dead process can
+-- be taken over by a retry of the same request.
+ALTER TABLE idempotency_keys
+    ADD COLUMN IF NOT EXISTS locked_until TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW();
@@ -0,0 +1,14 @@
+-- Migration: add_merchant_registry_status
+-- Created: 2026-02-18 15:00:00 UTC
+
+-- Merchant changes are committed first and mirrored to the zaps-registry
+-- contract by a worker. registry_status is 'registering' or 'deactivating'
+-- while a change is outstanding, then 'registered', 'deactivated' or
+-- 'failed'; NULL for merchants that were never mirrored. The signed call is
+-- stored so a retry resubmits it instead of signing another.
+ALTER TABLE merchants ADD COLUMN IF NOT EXISTS registry_status VARCHAR(20);
+ALTER TABLE merchants ADD COLUMN IF NOT EXISTS registry_tx_hash VARCHAR(64);
+ALTER TABLE merchants ADD COLUMN IF NOT EXISTS registry_tx_envelope TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_merchants_registry_pending
+    ON merchants(updated_at) WHERE registry_status IN ('registering', 'deactivating');
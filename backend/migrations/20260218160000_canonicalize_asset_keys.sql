@@ -0,0 +1,34 @@
+-- Migration: canonicalize_asset_keys
+-- Created: 2026-02-18 16:00:00 UTC
+
+-- Assets are stored as XLM, an upper-case configured code, or CODE:ISSUER.
+-- Fold balances kept under other spellings of a bare code (usdc, native)
+-- into that key, and respell the rows that will later debit or credit them.
+-- CODE:ISSUER spellings of configured assets depend on config and are left
+-- for the application to reconcile.
+INSERT INTO balances (owner_id, asset, amount)
+SELECT owner_id,
+       CASE WHEN LOWER(asset) IN ('native', 'xlm') THEN 'XLM' ELSE UPPER(asset) END,
+       SUM(amount)
+FROM balances
+WHERE asset NOT LIKE '%:%'
+  AND asset <> CASE WHEN LOWER(asset) IN ('native', 'xlm') THEN 'XLM' ELSE UPPER(asset) END
+GROUP BY 1, 2
+ON CONFLICT (owner_id, asset)
+DO UPDATE SET amount = balances.amount + EXCLUDED.amount, last_updated = NOW();
+
+DELETE FROM balances
+WHERE asset NOT LIKE '%:%'
+  AND asset <> CASE WHEN LOWER(asset) IN ('native', 'xlm') THEN 'XLM' ELSE UPPER(asset) END;
+
+UPDATE transfers
+SET asset = CASE WHEN LOWER(asset) IN ('native', 'xlm') THEN 'XLM' ELSE UPPER(asset) END
+WHERE asset NOT LIKE '%:%';
+
+UPDATE withdrawals
+SET asset = CASE WHEN LOWER(asset) IN ('native', 'xlm') THEN 'XLM' ELSE UPPER(asset) END
+WHERE asset NOT LIKE '%:%';
+
+UPDATE deposits
+SET asset = CASE WHEN LOWER(asset) IN ('native', 'xlm') THEN 'XLM' ELSE UPPER(asset) END
+WHERE asset NOT LIKE '%:%';
@@ -7,6 +7,8 @@ use serde::Serialize;
 use serde_json::json;
 use thiserror::Error;
 
+use crate::stellar::contract_error::{ContractError, ContractErrorKind};
+
 #[derive(Debug, Error)]
 pub enum ApiError {
     #[error("Authentication failed: {0}")]
@@ -42,6 +44,12 @@ pub enum ApiError {
     #[error("Stellar error: {0}")]
     Stellar(String),
 
+    #[error("Contract error: {0}")]
+    Contract(ContractError),
+
+    #[error("Anchor error: {0}")]
+    Anchor(String),
+
     #[error("Compliance violation: {0}")]
     Compliance(String),
 
@@ -70,6 +78,17 @@ impl IntoResponse for ApiError {
             ApiError::Json(_) => (StatusCode::BAD_REQUEST, "INVALID_JSON"),
             ApiError::Jwt(_) => (StatusCode::UNAUTHORIZED, "INVALID_TOKEN"),
             ApiError::Stellar(_) => (StatusCode::BAD_REQUEST, "STELLAR_ERROR"),
+            ApiError::Contract(err) => {
+                let status = match err.kind() {
+                    ContractErrorKind::Validation => StatusCode::UNPROCESSABLE_ENTITY,
+                    ContractErrorKind::Authorization => StatusCode::FORBIDDEN,
+                    ContractErrorKind::NotFound => StatusCode::NOT_FOUND,
+                    ContractErrorKind::Conflict => StatusCode::CONFLICT,
+                    ContractErrorKind::Internal => StatusCode::BAD_GATEWAY,
+                };
+                (status, err.error_code())
+            }
+            ApiError::Anchor(_) => (StatusCode::BAD_GATEWAY, "ANCHOR_ERROR"),
             ApiError::Compliance(_) => (StatusCode::FORBIDDEN, "COMPLIANCE_VIOLATION"),
             ApiError::RateLimit(_) => (StatusCode::TOO_MANY_REQUESTS, "RATE_LIMIT_EXCEEDED"),
         };
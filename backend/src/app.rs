@@ -10,11 +10,13 @@ use tower_http::{cors::CorsLayer, trace::TraceLayer};
 use crate::{
     config::Config,
     http::{
-        admin, audit, auth, health, identity, metrics as metrics_http, notifications, payments,
-        transfers, withdrawals,
+        admin, anchor, api_keys, audit, auth, deposits, health, identity, kyc, merchant_api,
+        merchants, metrics as metrics_http, notifications, payments, quotes, refunds, settlements,
+        terminals, transfers, webhooks, withdrawals,
     },
     middleware::{
-        audit_logging, auth as auth_middleware, metrics, rate_limit, request_id, role_guard,
+        anchor_callback, audit_logging, auth as auth_middleware, idempotency, metrics, rate_limit,
+        request_id, role_guard,
     },
     role::Role,
     service::{MetricsService, ServiceContainer},
@@ -24,12 +26,17 @@ pub async fn create_app(
     db_pool: Pool,
     config: Config,
 ) -> Result<Router, Box<dyn std::error::Error>> {
+    // Create service container
+    let services = Arc::new(ServiceContainer::new(db_pool, config).await?);
+
+    Ok(build_router(services))
+}
+
+/// Build the HTTP router over an existing service container
+pub fn build_router(services: Arc<ServiceContainer>) -> Router {
     // Initialize metrics service
     MetricsService::init();
 
-    // Create service container
-    let services = Arc::new(ServiceContainer::new(db_pool, config.clone()).await?);
-
     // Health check routes
     let health_routes = Router::new()
         .route("/health", get(health::health_check))
@@ -57,27 +64,184 @@ pub async fn create_app(
 
     // Payment routes
     let payment_routes = Router::new()
-        .route("/payments", post(payments::create_payment))
+        .route(
+            "/payments",
+            post(payments::create_payment).layer(middleware::from_fn_with_state(
+                services.clone(),
+                idempotency,
+            )),
+        )
+        .route(
+            "/payments/on-behalf",
+            post(payments::create_payment_on_behalf)
+                .layer(middleware::from_fn_with_state(
+                    services.clone(),
+                    idempotency,
+                ))
+                .layer(middleware::from_fn(role_guard::require_any_role(vec![
+                    Role::Merchant,
+                    Role::Admin,
+                ]))),
+        )
         .route("/payments/:id", get(payments::get_payment))
         .route("/payments/:id/status", get(payments::get_payment_status))
-        .route("/qr/generate", post(payments::generate_qr))
+        .route(
+            "/payments/:id/refunds",
+            post(refunds::create_refund)
+                .layer(middleware::from_fn_with_state(
+                    services.clone(),
+                    idempotency,
+                ))
+                .get(refunds::list_refunds)
+                .layer(middleware::from_fn(role_guard::merchant_or_admin())),
+        )
+        .route(
+            "/payments/:id/refunds/:refund_id",
+            get(refunds::get_refund).layer(middleware::from_fn(role_guard::merchant_or_admin())),
+        )
+        .route(
+            "/qr/generate",
+            post(payments::generate_qr).layer(middleware::from_fn(role_guard::merchant_or_admin())),
+        )
+        .route("/qr/verify", post(payments::verify_qr))
         .route("/nfc/validate", post(payments::validate_nfc));
 
+    // Quote routes (SEP-38 through the anchor, falling back to DEX path finding)
+    let quote_routes = Router::new()
+        .route("/price", get(quotes::get_price))
+        .route("/paths", get(quotes::find_path))
+        .route("/quotes", post(quotes::create_quote))
+        .route("/quotes/:id", get(quotes::get_quote));
+
     // Transfer routes
     let transfer_routes = Router::new()
-        .route("/transfers", post(transfers::create_transfer))
+        .route(
+            "/transfers",
+            post(transfers::create_transfer).layer(middleware::from_fn_with_state(
+                services.clone(),
+                idempotency,
+            )),
+        )
         .route("/transfers/:id", get(transfers::get_transfer))
         .route("/transfers/:id/status", get(transfers::get_transfer_status));
 
     // Withdrawal routes
     let withdrawal_routes = Router::new()
-        .route("/withdrawals", post(withdrawals::create_withdrawal))
+        .route(
+            "/withdrawals",
+            post(withdrawals::create_withdrawal).layer(middleware::from_fn_with_state(
+                services.clone(),
+                idempotency,
+            )),
+        )
         .route("/withdrawals/:id", get(withdrawals::get_withdrawal))
         .route(
             "/withdrawals/:id/status",
             get(withdrawals::get_withdrawal_status),
         );
 
+    // Deposit routes (SEP-24 interactive deposits through the anchor)
+    let deposit_routes = Router::new()
+        .route("/deposits", post(deposits::create_deposit))
+        .route("/deposits/:id", get(deposits::get_deposit))
+        .route("/deposits/:id/status", get(deposits::get_deposit_status));
+
+    // Merchant settlement routes (SEP-31 payouts through the receiving anchor, admin-only)
+    let settlement_routes = Router::new()
+        .route("/info", get(settlements::get_settlement_info))
+        .route(
+            "/settlements",
+            post(settlements::create_settlement).layer(middleware::from_fn_with_state(
+                services.clone(),
+                idempotency,
+            )),
+        )
+        .route("/settlements/:id", get(settlements::get_settlement))
+        .route(
+            "/settlements/:id/status",
+            get(settlements::get_settlement_status),
+        )
+        .layer(middleware::from_fn(role_guard::require_role(Role::Admin)));
+
+    // Merchant routes (self-service for operators, everything for admins)
+    let merchant_routes = Router::new()
+        .route("/", get(merchants::list_merchants))
+        .route(
+            "/",
+            post(merchants::create_merchant)
+                .layer(middleware::from_fn(role_guard::merchant_or_admin())),
+        )
+        .route(
+            "/:merchant_id",
+            get(merchants::get_merchant).patch(merchants::update_merchant),
+        )
+        .route(
+            "/:merchant_id/deactivate",
+            post(merchants::deactivate_merchant),
+        )
+        .route(
+            "/:merchant_id/reactivate",
+            post(merchants::reactivate_merchant)
+                .layer(middleware::from_fn(role_guard::admin_only())),
+        )
+        .route(
+            "/:merchant_id/operators",
+            post(merchants::add_merchant_operator),
+        )
+        .route(
+            "/:merchant_id/api-keys",
+            post(api_keys::create_api_key).get(api_keys::list_api_keys),
+        )
+        .route(
+            "/:merchant_id/api-keys/:key_id",
+            axum::routing::delete(api_keys::revoke_api_key),
+        )
+        .route(
+            "/:merchant_id/webhooks",
+            post(webhooks::create_webhook_endpoint).get(webhooks::list_webhook_endpoints),
+        )
+        .route(
+            "/:merchant_id/webhooks/:endpoint_id",
+            axum::routing::delete(webhooks::disable_webhook_endpoint),
+        )
+        .route(
+            "/:merchant_id/webhooks/:endpoint_id/deliveries",
+            get(webhooks::list_webhook_deliveries),
+        )
+        .route(
+            "/:merchant_id/webhooks/:endpoint_id/deliveries/:delivery_id/redeliver",
+            post(webhooks::redeliver_webhook),
+        );
+
+    // NFC terminal key routes (admin-only)
+    let terminal_routes = Router::new()
+        .route(
+            "/",
+            post(terminals::register_terminal).get(terminals::list_terminals),
+        )
+        .route("/:id", axum::routing::delete(terminals::revoke_terminal))
+        .layer(middleware::from_fn(role_guard::require_role(Role::Admin)));
+
+    // KYC routes (SEP-12 customer verification through the anchor)
+    let kyc_routes =
+        Router::new().route("/profile", get(kyc::get_kyc_profile).put(kyc::submit_kyc));
+
+    // Anchor callback routes (public: a SEP-12 callback only triggers a refresh
+    // from the anchor, SEP-24 and SEP-31 callbacks must carry its signature)
+    let signed_callback_routes = Router::new()
+        .route("/sep24", post(withdrawals::sep24_callback))
+        .route("/sep31", post(settlements::sep31_callback))
+        .layer(middleware::from_fn_with_state(
+            services.clone(),
+            anchor_callback::verify_anchor_callback,
+        ));
+    let callback_routes = Router::new()
+        .route("/sep12", post(kyc::sep12_callback))
+        .merge(signed_callback_routes);
+
+    // Anchor discovery routes
+    let anchor_routes = Router::new().route("/info", get(anchor::get_anchor_info));
+
     // Notification routes
     let notification_routes = Router::new()
         .route("/notifications", post(notifications::create_notification))
@@ -93,6 +257,9 @@ pub async fn create_app(
         .route("/transactions", get(admin::get_transactions))
         .route("/users/:user_id/activity", get(admin::get_user_activity))
         .route("/system/health", get(admin::get_system_health))
+        .route("/jobs", get(admin::list_jobs))
+        .route("/jobs/:id", get(admin::get_job))
+        .route("/jobs/:id/retry", post(admin::retry_job))
         .layer(middleware::from_fn(role_guard::require_role(Role::Admin)));
 
     // Audit log routes (admin-only)
@@ -105,8 +272,15 @@ pub async fn create_app(
     let protected_routes = Router::new()
         .nest("/identity", identity_routes)
         .nest("/payments", payment_routes)
+        .nest("/quotes", quote_routes)
         .nest("/transfers", transfer_routes)
         .nest("/withdrawals", withdrawal_routes)
+        .nest("/deposits", deposit_routes)
+        .nest("/settlements", settlement_routes)
+        .nest("/merchants", merchant_routes)
+        .nest("/terminals", terminal_routes)
+        .nest("/kyc", kyc_routes)
+        .nest("/anchor", anchor_routes)
         .nest("/notifications", notification_routes)
         .nest("/admin", admin_routes)
         .merge(audit_routes) // Audit routes at root level under /audit-logs
@@ -119,16 +293,44 @@ pub async fn create_app(
             auth_middleware::authenticate,
         ));
 
+    // Merchant API routes (server-to-server, authenticated with API keys instead of JWTs)
+    let merchant_api_routes = Router::new()
+        .route(
+            "/payments",
+            post(merchant_api::create_payment).layer(middleware::from_fn_with_state(
+                services.clone(),
+                idempotency,
+            )),
+        )
+        .route("/payments/:id", get(merchant_api::get_payment))
+        .route(
+            "/payments/:id/refunds",
+            post(merchant_api::create_refund).layer(middleware::from_fn_with_state(
+                services.clone(),
+                idempotency,
+            )),
+        )
+        .layer(middleware::from_fn_with_state(
+            services.clone(),
+            audit_logging,
+        ))
+        .layer(middleware::from_fn_with_state(
+            services.clone(),
+            auth_middleware::authenticate_api_key,
+        ));
+
     // Public routes
     let public_routes = Router::new()
         .nest("/auth", auth_routes)
         .nest("/health", health_routes)
+        .nest("/callbacks", callback_routes)
         .merge(metrics_routes);
 
     // Combine all routes
-    let app = Router::new()
+    Router::new()
         .merge(public_routes)
         .merge(protected_routes)
+        .nest("/merchant-api", merchant_api_routes)
         .layer(middleware::from_fn_with_state(
             services.clone(),
             rate_limit::rate_limit,
@@ -137,7 +339,5 @@ pub async fn create_app(
         .layer(middleware::from_fn(metrics::track_metrics))
         .layer(TraceLayer::new_for_http())
         .layer(CorsLayer::permissive())
-        .with_state(services);
-
-    Ok(app)
+        .with_state(services)
 }
@@ -19,7 +19,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Seed Users
     let user_id = "user_123";
-    let stellar_address = "GAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA";
+    let stellar_address = "GA7QYNF7SOWQ3GLR2BGMZEHXAVIRZA4KVWLTJJFC7MGXUA74P7UJVSGZ";
 
     // We use raw SQL query here since we might not have access to specific macros if sqlx-data.json is not set up
     // or if we want generic execution. usage of sqlx::query! requires compile-time DB connection or offline mode.
@@ -1,6 +1,7 @@
 use crate::models::{RateLimitConfig, RateLimitScope};
 use config::{Config as ConfigBuilder, ConfigError, Environment, File};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::env;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,6 +11,8 @@ pub struct Config {
     pub jwt: JwtConfig,
     #[serde(rename = "stellar")]
     pub stellar_network: StellarNetwork,
+    pub custody: CustodyConfig,
+    pub contracts: ContractsConfig,
     #[serde(rename = "anchor")]
     pub anchor_config: AnchorConfig,
     #[serde(rename = "bridge")]
@@ -18,6 +21,27 @@ pub struct Config {
     pub compliance_config: ComplianceConfig,
     pub environment: EnvironmentType,
     pub rate_limit: RateLimitConfig,
+    pub idempotency: IdempotencyConfig,
+    #[serde(default)]
+    pub quotes: QuoteConfig,
+    #[serde(default)]
+    pub sep7: Sep7Config,
+    #[serde(default)]
+    pub nfc: NfcConfig,
+    #[serde(default)]
+    pub webhooks: WebhookConfig,
+    #[serde(default)]
+    pub merchants: MerchantConfig,
+    #[serde(default)]
+    pub transfers: TransferConfig,
+    #[serde(default)]
+    pub refunds: RefundConfig,
+    #[serde(default)]
+    pub indexer: IndexerConfig,
+    #[serde(default)]
+    pub workers: WorkerConfig,
+    #[serde(default)]
+    pub jobs: JobConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,14 +75,406 @@ pub struct StellarNetwork {
     pub horizon_url: String,
     pub rpc_url: String,
     pub network_id: String,
+    /// Issuer (`G...`) of each credit asset accepted by code, e.g. `USDC`
+    #[serde(default)]
+    pub assets: HashMap<String, String>,
+    /// How long Horizon path finding results are reused, in seconds
+    #[serde(default = "default_path_cache_secs")]
+    pub path_cache_secs: u64,
+}
+
+fn default_path_cache_secs() -> u64 {
+    10
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustodyConfig {
+    /// Secret used to encrypt custodial wallet seeds at rest
+    pub encryption_key: String,
+}
+
+/// Addresses (`C...`) of the deployed Zaps contracts
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContractsConfig {
+    pub payment_router: String,
+    pub merchant_vault: String,
+    pub zaps_registry: String,
+    pub escrow: String,
+    /// Secret seed (`S...`) of the zaps-registry admin, which merchant changes are signed with
+    #[serde(default)]
+    pub registry_admin_secret: Option<String>,
+    /// Secret seed of the merchant-vault's payout account, which refund debits are signed with
+    #[serde(default)]
+    pub vault_payout_secret: Option<String>,
+}
+
+/// Retention of `Idempotency-Key` records for money-moving requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdempotencyConfig {
+    pub ttl_hours: i64,
+    /// How long an in-flight reservation is held, renewed while the handler runs; once it
+    /// lapses without an outcome, retries are told the outcome is unknown
+    #[serde(default = "default_idempotency_lock_lease_secs")]
+    pub lock_lease_secs: i64,
+}
+
+fn default_idempotency_lock_lease_secs() -> i64 {
+    30
+}
+
+/// Pricing of payments between assets, through the anchor's SEP-38 server or the DEX
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuoteConfig {
+    /// How long a quote priced from the DEX stays valid; anchor quotes carry their own expiry
+    #[serde(default = "default_quote_ttl_secs")]
+    pub ttl_secs: i64,
+    /// Margin taken off a DEX price before it is locked, in basis points
+    #[serde(default = "default_quote_slippage_bps")]
+    pub slippage_bps: i64,
+}
+
+impl Default for QuoteConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: default_quote_ttl_secs(),
+            slippage_bps: default_quote_slippage_bps(),
+        }
+    }
+}
+
+fn default_quote_ttl_secs() -> i64 {
+    60
+}
+
+fn default_quote_slippage_bps() -> i64 {
+    50
+}
+
+/// Signing of SEP-7 payment request URIs shown as QR codes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sep7Config {
+    /// Domain whose `stellar.toml` publishes `URI_REQUEST_SIGNING_KEY`
+    #[serde(default)]
+    pub origin_domain: String,
+    /// Secret seed (`S...`) of `URI_REQUEST_SIGNING_KEY`
+    #[serde(default)]
+    pub signing_secret: Option<String>,
+    /// Longest a payment request may stay valid for
+    #[serde(default = "default_sep7_max_ttl_secs")]
+    pub max_ttl_secs: i64,
+}
+
+impl Default for Sep7Config {
+    fn default() -> Self {
+        Self {
+            origin_domain: String::new(),
+            signing_secret: None,
+            max_ttl_secs: default_sep7_max_ttl_secs(),
+        }
+    }
+}
+
+fn default_sep7_max_ttl_secs() -> i64 {
+    3600
+}
+
+/// Validity window of signed NFC tap payloads
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NfcConfig {
+    /// How long after its timestamp a tap can still be paid
+    #[serde(default = "default_nfc_max_age_secs")]
+    pub max_age_secs: i64,
+    /// How far ahead of our clock a terminal's timestamp may be
+    #[serde(default = "default_nfc_max_clock_skew_secs")]
+    pub max_clock_skew_secs: i64,
+}
+
+impl Default for NfcConfig {
+    fn default() -> Self {
+        Self {
+            max_age_secs: default_nfc_max_age_secs(),
+            max_clock_skew_secs: default_nfc_max_clock_skew_secs(),
+        }
+    }
+}
+
+fn default_nfc_max_age_secs() -> i64 {
+    120
+}
+
+fn default_nfc_max_clock_skew_secs() -> i64 {
+    30
+}
+
+/// Delivery of merchant webhooks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    /// Attempts made at a delivery before it is marked failed
+    #[serde(default = "default_webhook_max_attempts")]
+    pub max_attempts: i32,
+    /// Delay before the first retry, doubled after every further failure
+    #[serde(default = "default_webhook_retry_base_secs")]
+    pub retry_base_secs: i64,
+    /// Longest delay between two attempts
+    #[serde(default = "default_webhook_retry_max_secs")]
+    pub retry_max_secs: i64,
+    /// How long an endpoint has to answer a delivery
+    #[serde(default = "default_webhook_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Interval between sweeps for due deliveries
+    #[serde(default = "default_webhook_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+    /// Allow endpoints on loopback, private and link-local addresses, e.g. in local development
+    #[serde(default)]
+    pub allow_private_networks: bool,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_webhook_max_attempts(),
+            retry_base_secs: default_webhook_retry_base_secs(),
+            retry_max_secs: default_webhook_retry_max_secs(),
+            timeout_secs: default_webhook_timeout_secs(),
+            poll_interval_secs: default_webhook_poll_interval_secs(),
+            allow_private_networks: false,
+        }
+    }
+}
+
+fn default_webhook_max_attempts() -> i32 {
+    8
+}
+
+fn default_webhook_retry_base_secs() -> i64 {
+    30
+}
+
+fn default_webhook_retry_max_secs() -> i64 {
+    6 * 3600
+}
+
+fn default_webhook_timeout_secs() -> u64 {
+    10
+}
+
+fn default_webhook_poll_interval_secs() -> u64 {
+    5
+}
+
+/// Mirroring of merchant changes to the zaps-registry contract
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MerchantConfig {
+    /// Interval between sweeps for merchant changes to send to the registry
+    #[serde(default = "default_registry_poll_interval_secs")]
+    pub registry_poll_interval_secs: u64,
+}
+
+impl Default for MerchantConfig {
+    fn default() -> Self {
+        Self {
+            registry_poll_interval_secs: default_registry_poll_interval_secs(),
+        }
+    }
+}
+
+fn default_registry_poll_interval_secs() -> u64 {
+    5
+}
+
+/// Confirmation of peer-to-peer transfers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferConfig {
+    /// Interval between sweeps for submitted transfers to confirm
+    #[serde(default = "default_transfer_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+}
+
+impl Default for TransferConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_secs: default_transfer_poll_interval_secs(),
+        }
+    }
+}
+
+fn default_transfer_poll_interval_secs() -> u64 {
+    5
+}
+
+/// Execution of merchant refunds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefundConfig {
+    /// Interval between sweeps for refunds to send or confirm
+    #[serde(default = "default_refund_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+}
+
+impl Default for RefundConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_secs: default_refund_poll_interval_secs(),
+        }
+    }
+}
+
+fn default_refund_poll_interval_secs() -> u64 {
+    10
+}
+
+/// Indexing of payment-router and merchant-vault contract events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexerConfig {
+    /// Interval between `getEvents` polls
+    #[serde(default = "default_indexer_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+    /// Ledger to start from when no cursor has been saved; 0 starts at the latest ledger
+    #[serde(default)]
+    pub start_ledger: u32,
+    /// Events requested per `getEvents` page
+    #[serde(default = "default_indexer_page_size")]
+    pub page_size: u32,
+}
+
+impl Default for IndexerConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_secs: default_indexer_poll_interval_secs(),
+            start_ledger: 0,
+            page_size: default_indexer_page_size(),
+        }
+    }
+}
+
+fn default_indexer_poll_interval_secs() -> u64 {
+    5
+}
+
+fn default_indexer_page_size() -> u32 {
+    100
+}
+
+/// The background job queue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobConfig {
+    /// Attempts made at a job before it is dead-lettered
+    #[serde(default = "default_job_max_attempts")]
+    pub max_attempts: i32,
+    /// Delay before the first retry, doubled after every further failure
+    #[serde(default = "default_job_retry_base_secs")]
+    pub retry_base_secs: i64,
+    /// Longest delay between two attempts
+    #[serde(default = "default_job_retry_max_secs")]
+    pub retry_max_secs: i64,
+    /// How long a claimed job may run before another sweep may claim it again
+    #[serde(default = "default_job_lease_secs")]
+    pub lease_secs: i64,
+    /// How long completed jobs are kept
+    #[serde(default = "default_job_retention_hours")]
+    pub retention_hours: i64,
+    /// Interval between sweeps for due jobs
+    #[serde(default = "default_job_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+}
+
+impl Default for JobConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_job_max_attempts(),
+            retry_base_secs: default_job_retry_base_secs(),
+            retry_max_secs: default_job_retry_max_secs(),
+            lease_secs: default_job_lease_secs(),
+            retention_hours: default_job_retention_hours(),
+            poll_interval_secs: default_job_poll_interval_secs(),
+        }
+    }
+}
+
+fn default_job_max_attempts() -> i32 {
+    5
+}
+
+fn default_job_retry_base_secs() -> i64 {
+    10
+}
+
+fn default_job_retry_max_secs() -> i64 {
+    3600
+}
+
+fn default_job_lease_secs() -> i64 {
+    300
+}
+
+fn default_job_retention_hours() -> i64 {
+    24
+}
+
+fn default_job_poll_interval_secs() -> u64 {
+    2
+}
+
+/// Coordination of background workers between backend instances
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkerConfig {
+    /// Name this instance is reported under as a worker leader; defaults to `HOSTNAME`
+    #[serde(default)]
+    pub instance_id: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AnchorConfig {
     pub sep24_url: String,
     pub sep31_url: String,
-    pub webhook_secret: String,
     pub kyc_required: bool,
+    /// SEP-12 `KYC_SERVER`
+    pub sep12_url: String,
+    /// Public URL the anchor calls back when a user's KYC status changes
+    #[serde(default)]
+    pub sep12_callback_url: Option<String>,
+    /// Payments of at least this many stroops require basic KYC when `kyc_required` is set
+    #[serde(default = "default_kyc_payment_threshold")]
+    pub kyc_payment_threshold: i64,
+    /// Domain the anchor's `stellar.toml` is served from, named in SEP-10 challenges
+    pub home_domain: String,
+    /// SEP-10 `WEB_AUTH_ENDPOINT`
+    pub web_auth_url: String,
+    /// SEP-10 `SIGNING_KEY` (`G...`) that challenges must be signed with
+    pub signing_key: String,
+    /// Secret seed (`S...`) the platform authenticates with for its own transactions
+    #[serde(default)]
+    pub platform_secret: Option<String>,
+    /// Public URL the SEP-24 anchor calls back when a withdrawal's status changes
+    #[serde(default)]
+    pub sep24_callback_url: Option<String>,
+    /// Public URL the SEP-31 anchor calls back when a settlement's status changes
+    #[serde(default)]
+    pub sep31_callback_url: Option<String>,
+    /// SEP-38 `ANCHOR_QUOTE_SERVER`; quotes are priced from the DEX alone when unset
+    #[serde(default)]
+    pub sep38_url: Option<String>,
+    /// Interval between polls of the anchor for open withdrawals, deposits, settlements and KYC
+    #[serde(default = "default_anchor_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+    /// How far a signed callback's timestamp may be from our clock, either way
+    #[serde(default = "default_anchor_callback_max_age_secs")]
+    pub callback_max_age_secs: i64,
+    /// Deprecated and ignored: callbacks are verified against `signing_key` instead.
+    /// Still read so a deployment that sets it is warned at startup
+    #[serde(default)]
+    pub webhook_secret: Option<String>,
+}
+
+fn default_anchor_poll_interval_secs() -> u64 {
+    30
+}
+
+fn default_anchor_callback_max_age_secs() -> i64 {
+    300
+}
+
+fn default_kyc_payment_threshold() -> i64 {
+    10_000_000_000
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -109,8 +525,25 @@ impl Config {
                 builder.add_source(File::with_name(&format!("config/{}", env)).required(false));
         }
 
-        let config = builder.build()?;
-        config.try_deserialize()
+        let config: Self = builder.build()?.try_deserialize()?;
+        config.warn_deprecated();
+        Ok(config)
+    }
+
+    /// Log settings that are still accepted but no longer have any effect
+    fn warn_deprecated(&self) {
+        if self
+            .anchor_config
+            .webhook_secret
+            .as_deref()
+            .is_some_and(|secret| !secret.is_empty())
+        {
+            tracing::warn!(
+                "anchor.webhook_secret (ZAPS_ANCHOR__WEBHOOK_SECRET) is deprecated and ignored: \
+                 anchor callbacks are now verified as signatures by anchor.signing_key \
+                 (ZAPS_ANCHOR__SIGNING_KEY), the anchor's SEP-10 SIGNING_KEY"
+            );
+        }
     }
 }
 
@@ -131,12 +564,30 @@ impl Default for Config {
                 horizon_url: "https://horizon-testnet.stellar.org".to_string(),
                 rpc_url: "https://soroban-testnet.stellar.org".to_string(),
                 network_id: "Test SDF Network ; September 2015".to_string(),
+                assets: HashMap::new(),
+                path_cache_secs: default_path_cache_secs(),
             },
+            custody: CustodyConfig {
+                encryption_key: "change-this-custody-key-in-production".to_string(),
+            },
+            contracts: ContractsConfig::default(),
             anchor_config: AnchorConfig {
                 sep24_url: "https://anchor.example.com/sep24".to_string(),
                 sep31_url: "https://anchor.example.com/sep31".to_string(),
-                webhook_secret: "webhook-secret".to_string(),
                 kyc_required: true,
+                sep12_url: "https://anchor.example.com/kyc".to_string(),
+                sep12_callback_url: None,
+                kyc_payment_threshold: default_kyc_payment_threshold(),
+                home_domain: "anchor.example.com".to_string(),
+                web_auth_url: "https://anchor.example.com/auth".to_string(),
+                signing_key: String::new(),
+                platform_secret: None,
+                sep24_callback_url: None,
+                sep31_callback_url: None,
+                sep38_url: None,
+                poll_interval_secs: default_anchor_poll_interval_secs(),
+                callback_max_age_secs: default_anchor_callback_max_age_secs(),
+                webhook_secret: None,
             },
             bridge_config: BridgeConfig {
                 ethereum_rpc_url: "https://mainnet.infura.io/v3/YOUR_PROJECT_ID".to_string(),
@@ -166,6 +617,20 @@ impl Default for Config {
                 max_requests: 100,
                 scope: RateLimitScope::Ip,
             },
+            idempotency: IdempotencyConfig {
+                ttl_hours: 24,
+                lock_lease_secs: default_idempotency_lock_lease_secs(),
+            },
+            quotes: QuoteConfig::default(),
+            sep7: Sep7Config::default(),
+            nfc: NfcConfig::default(),
+            webhooks: WebhookConfig::default(),
+            merchants: MerchantConfig::default(),
+            transfers: TransferConfig::default(),
+            refunds: RefundConfig::default(),
+            indexer: IndexerConfig::default(),
+            workers: WorkerConfig::default(),
+            jobs: JobConfig::default(),
         }
     }
 }
@@ -1,11 +1,16 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     Json,
 };
 use serde::Serialize;
 use std::sync::Arc;
+use uuid::Uuid;
 
-use crate::{api_error::ApiError, service::ServiceContainer};
+use crate::{
+    api_error::ApiError,
+    models::QueuedJob,
+    service::{job_service::JobQuery, leader_service::WorkerLeader, ServiceContainer},
+};
 
 #[derive(Debug, Serialize)]
 pub struct DashboardStats {
@@ -20,6 +25,10 @@ pub struct DashboardStats {
 pub struct SystemHealth {
     pub database: String,
     pub services: Vec<String>,
+    /// Instance serving this request
+    pub instance_id: String,
+    /// Which instance runs each background worker
+    pub workers: Vec<WorkerLeader>,
 }
 
 pub async fn get_dashboard_stats(
@@ -51,11 +60,43 @@ pub async fn get_user_activity(
 }
 
 pub async fn get_system_health(
-    State(_services): State<Arc<ServiceContainer>>,
+    State(services): State<Arc<ServiceContainer>>,
 ) -> Result<Json<SystemHealth>, ApiError> {
-    // Placeholder implementation
+    let (database, workers) = match services.leader.leaders().await {
+        Ok(workers) => ("healthy", workers),
+        Err(e) => {
+            tracing::error!("Failed to read worker leaders: {}", e);
+            ("unhealthy", Vec::new())
+        }
+    };
+
     Ok(Json(SystemHealth {
-        database: "healthy".to_string(),
+        database: database.to_string(),
         services: vec!["identity".to_string(), "payment".to_string()],
+        instance_id: services.leader.instance_id().to_string(),
+        workers,
     }))
 }
+
+/// Background jobs, e.g. `?status=dead` for the dead-lettered ones
+pub async fn list_jobs(
+    State(services): State<Arc<ServiceContainer>>,
+    Query(query): Query<JobQuery>,
+) -> Result<Json<Vec<QueuedJob>>, ApiError> {
+    Ok(Json(services.job.list_jobs(query).await?))
+}
+
+pub async fn get_job(
+    State(services): State<Arc<ServiceContainer>>,
+    Path(job_id): Path<Uuid>,
+) -> Result<Json<QueuedJob>, ApiError> {
+    Ok(Json(services.job.get_job(job_id).await?))
+}
+
+/// Queue a dead-lettered job to run again
+pub async fn retry_job(
+    State(services): State<Arc<ServiceContainer>>,
+    Path(job_id): Path<Uuid>,
+) -> Result<Json<QueuedJob>, ApiError> {
+    Ok(Json(services.job.retry_job(job_id).await?))
+}
@@ -0,0 +1,11 @@
+use axum::{extract::State, Json};
+use std::sync::Arc;
+
+use crate::{api_error::ApiError, service::anchor_service::Sep24Info, service::ServiceContainer};
+
+/// Assets, limits and fees the anchor supports for deposits and withdrawals
+pub async fn get_anchor_info(
+    State(services): State<Arc<ServiceContainer>>,
+) -> Result<Json<Sep24Info>, ApiError> {
+    Ok(Json(services.anchor.get_info().await?))
+}
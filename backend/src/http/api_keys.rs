@@ -0,0 +1,61 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    api_error::ApiError,
+    http::merchants::operated_merchant,
+    middleware::AuthenticatedUser,
+    models::ApiKey,
+    service::{
+        api_key_service::{CreateApiKeyRequest, CreatedApiKey},
+        ServiceContainer,
+    },
+};
+
+/// Create an API key for the merchant; the key is only returned here
+pub async fn create_api_key(
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Path(merchant_id): Path<String>,
+    Json(request): Json<CreateApiKeyRequest>,
+) -> Result<Json<CreatedApiKey>, ApiError> {
+    let merchant = operated_merchant(&services, &user, &merchant_id).await?;
+    if !merchant.active {
+        return Err(ApiError::Validation(
+            "API keys cannot be created for an inactive merchant".to_string(),
+        ));
+    }
+
+    Ok(Json(
+        services
+            .api_key
+            .create_key(&merchant_id, &user.user_id, request)
+            .await?,
+    ))
+}
+
+pub async fn list_api_keys(
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Path(merchant_id): Path<String>,
+) -> Result<Json<Vec<ApiKey>>, ApiError> {
+    operated_merchant(&services, &user, &merchant_id).await?;
+
+    Ok(Json(services.api_key.list_keys(&merchant_id).await?))
+}
+
+pub async fn revoke_api_key(
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Path((merchant_id, key_id)): Path<(String, Uuid)>,
+) -> Result<Json<ApiKey>, ApiError> {
+    operated_merchant(&services, &user, &merchant_id).await?;
+
+    Ok(Json(
+        services.api_key.revoke_key(&merchant_id, key_id).await?,
+    ))
+}
@@ -0,0 +1,111 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    api_error::ApiError,
+    middleware::AuthenticatedUser,
+    models::{Deposit, StatusEvent},
+    role::Role,
+    service::{deposit_service::CreateDepositRequest, ServiceContainer},
+};
+
+#[derive(Debug, Serialize)]
+pub struct DepositResponse {
+    pub id: Uuid,
+    pub tx_hash: Option<String>,
+    pub user_id: String,
+    pub amount: Option<i64>,
+    pub asset: String,
+    pub status: String,
+    pub anchor_tx_id: Option<String>,
+    pub anchor_status: Option<String>,
+    /// Anchor page the user must open to complete the deposit
+    pub interactive_url: Option<String>,
+    pub anchor_message: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// A deposit together with its status history, oldest first
+#[derive(Debug, Serialize)]
+pub struct DepositDetailResponse {
+    #[serde(flatten)]
+    pub deposit: DepositResponse,
+    pub history: Vec<StatusEvent>,
+}
+
+pub async fn create_deposit(
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Json(request): Json<CreateDepositRequest>,
+) -> Result<Json<DepositResponse>, ApiError> {
+    let deposit = services
+        .deposit
+        .create_deposit(&user.user_id, request)
+        .await?;
+
+    Ok(Json(deposit_response(deposit)))
+}
+
+pub async fn get_deposit(
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Path(deposit_id): Path<Uuid>,
+) -> Result<Json<DepositDetailResponse>, ApiError> {
+    let deposit = visible_deposit(&services, &user, deposit_id).await?;
+    let history = services.deposit.get_deposit_history(deposit_id).await?;
+
+    Ok(Json(DepositDetailResponse {
+        deposit: deposit_response(deposit),
+        history,
+    }))
+}
+
+pub async fn get_deposit_status(
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Path(deposit_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let deposit = visible_deposit(&services, &user, deposit_id).await?;
+
+    Ok(Json(serde_json::json!({
+        "id": deposit.id,
+        "status": deposit.status.to_string(),
+        "anchor_status": deposit.anchor_status,
+        "tx_hash": deposit.tx_hash,
+        "updated_at": deposit.updated_at,
+    })))
+}
+
+/// Deposits are visible to their owner and admins
+async fn visible_deposit(
+    services: &ServiceContainer,
+    user: &AuthenticatedUser,
+    deposit_id: Uuid,
+) -> Result<Deposit, ApiError> {
+    let deposit = services.deposit.get_deposit(deposit_id).await?;
+    if user.role != Role::Admin && deposit.user_id != user.user_id {
+        return Err(ApiError::NotFound("Deposit not found".to_string()));
+    }
+    Ok(deposit)
+}
+
+fn deposit_response(deposit: Deposit) -> DepositResponse {
+    DepositResponse {
+        id: Uuid::parse_str(&deposit.id).unwrap_or_default(),
+        tx_hash: deposit.tx_hash,
+        user_id: deposit.user_id,
+        amount: deposit.amount,
+        asset: deposit.asset,
+        status: deposit.status.to_string(),
+        anchor_tx_id: deposit.anchor_tx_id,
+        anchor_status: deposit.anchor_status,
+        interactive_url: deposit.interactive_url,
+        anchor_message: deposit.anchor_message,
+        created_at: deposit.created_at,
+    }
+}
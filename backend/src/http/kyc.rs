@@ -0,0 +1,49 @@
+use axum::{extract::State, Json};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::{
+    api_error::ApiError,
+    middleware::AuthenticatedUser,
+    models::KycProfile,
+    service::{kyc_service::SubmitKycRequest, ServiceContainer},
+};
+
+/// Body the SEP-12 anchor posts to the callback URL
+#[derive(Debug, Deserialize)]
+pub struct Sep12Callback {
+    pub id: String,
+}
+
+/// The authenticated user's KYC level and where their latest submission stands
+pub async fn get_kyc_profile(
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+) -> Result<Json<KycProfile>, ApiError> {
+    Ok(Json(services.kyc.get_profile(&user.user_id).await?))
+}
+
+/// Submit the authenticated user's details to the anchor for review
+pub async fn submit_kyc(
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Json(request): Json<SubmitKycRequest>,
+) -> Result<Json<KycProfile>, ApiError> {
+    Ok(Json(services.kyc.submit(&user.user_id, request).await?))
+}
+
+/// SEP-12 status callback from the anchor
+///
+/// Only the customer id is read from the body; the status is fetched from
+/// the anchor, so a forged callback can do no more than trigger an early poll.
+pub async fn sep12_callback(
+    State(services): State<Arc<ServiceContainer>>,
+    Json(callback): Json<Sep12Callback>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let profile = services.kyc.handle_callback(&callback.id).await?;
+
+    Ok(Json(serde_json::json!({
+        "level": profile.level,
+        "status": profile.status,
+    })))
+}
@@ -0,0 +1,91 @@
+//! Server-to-server endpoints for merchants, authenticated with API keys
+//!
+//! Every handler checks the key's scope and keeps it to its own merchant.
+
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    api_error::ApiError,
+    http::{
+        payments::{
+            pay_on_behalf, payment_response, OnBehalfPaymentRequest, PaymentDetailResponse,
+            PaymentResponse,
+        },
+        refunds::{refund_response, RefundResponse},
+    },
+    middleware::AuthenticatedApiKey,
+    models::ApiKeyScope,
+    service::{refund_service::CreateRefundRequest, ServiceContainer},
+};
+
+/// Charge a customer's wallet; the payment must be to the key's merchant
+pub async fn create_payment(
+    State(services): State<Arc<ServiceContainer>>,
+    api_key: AuthenticatedApiKey,
+    headers: HeaderMap,
+    Json(request): Json<OnBehalfPaymentRequest>,
+) -> Result<Json<PaymentResponse>, ApiError> {
+    api_key.require_scope(ApiKeyScope::PaymentsWrite)?;
+    if request.payment.merchant_id != api_key.merchant_id {
+        return Err(ApiError::Authorization(
+            "API keys can only create payments to their own merchant".to_string(),
+        ));
+    }
+
+    let payment = pay_on_behalf(
+        &services,
+        api_key.actor_id(),
+        serde_json::json!("api_key"),
+        &headers,
+        request,
+    )
+    .await?;
+
+    Ok(Json(payment_response(payment)))
+}
+
+pub async fn get_payment(
+    State(services): State<Arc<ServiceContainer>>,
+    api_key: AuthenticatedApiKey,
+    Path(payment_id): Path<Uuid>,
+) -> Result<Json<PaymentDetailResponse>, ApiError> {
+    api_key.require_scope(ApiKeyScope::PaymentsRead)?;
+
+    let payment = services.payment.get_payment(payment_id).await?;
+    if payment.merchant_id != api_key.merchant_id {
+        return Err(ApiError::NotFound("Payment not found".to_string()));
+    }
+    let history = services.payment.get_payment_history(payment_id).await?;
+
+    Ok(Json(PaymentDetailResponse {
+        payment: payment_response(payment),
+        history,
+    }))
+}
+
+/// Refund one of the merchant's payments, in full or in part
+pub async fn create_refund(
+    State(services): State<Arc<ServiceContainer>>,
+    api_key: AuthenticatedApiKey,
+    Path(payment_id): Path<Uuid>,
+    Json(request): Json<CreateRefundRequest>,
+) -> Result<Json<RefundResponse>, ApiError> {
+    api_key.require_scope(ApiKeyScope::RefundsWrite)?;
+
+    let payment = services.payment.get_payment(payment_id).await?;
+    if payment.merchant_id != api_key.merchant_id {
+        return Err(ApiError::NotFound("Payment not found".to_string()));
+    }
+    let refund = services
+        .refund
+        .create_refund(payment_id, &api_key.actor_id(), request)
+        .await?;
+
+    Ok(Json(refund_response(refund)))
+}
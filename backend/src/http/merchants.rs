@@ -0,0 +1,133 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::{
+    api_error::ApiError,
+    middleware::AuthenticatedUser,
+    models::Merchant,
+    role::Role,
+    service::{
+        merchant_service::{CreateMerchantRequest, UpdateMerchantRequest},
+        ServiceContainer,
+    },
+};
+
+#[derive(Debug, Deserialize)]
+pub struct AddOperatorRequest {
+    pub user_id: String,
+}
+
+/// Onboard a merchant; merchants are linked as its operator, admins may name one
+pub async fn create_merchant(
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Json(mut request): Json<CreateMerchantRequest>,
+) -> Result<Json<Merchant>, ApiError> {
+    if user.role != Role::Admin {
+        request.operator_user_id = Some(user.user_id);
+    }
+
+    Ok(Json(services.merchant.create_merchant(request).await?))
+}
+
+/// All merchants for admins, the merchants they operate for everyone else
+pub async fn list_merchants(
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+) -> Result<Json<Vec<Merchant>>, ApiError> {
+    let merchants = if user.role == Role::Admin {
+        services.merchant.list_merchants().await?
+    } else {
+        services
+            .merchant
+            .list_operated_merchants(&user.user_id)
+            .await?
+    };
+
+    Ok(Json(merchants))
+}
+
+pub async fn get_merchant(
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Path(merchant_id): Path<String>,
+) -> Result<Json<Merchant>, ApiError> {
+    Ok(Json(
+        operated_merchant(&services, &user, &merchant_id).await?,
+    ))
+}
+
+pub async fn update_merchant(
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Path(merchant_id): Path<String>,
+    Json(request): Json<UpdateMerchantRequest>,
+) -> Result<Json<Merchant>, ApiError> {
+    operated_merchant(&services, &user, &merchant_id).await?;
+
+    Ok(Json(
+        services
+            .merchant
+            .update_merchant(&merchant_id, request)
+            .await?,
+    ))
+}
+
+pub async fn deactivate_merchant(
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Path(merchant_id): Path<String>,
+) -> Result<Json<Merchant>, ApiError> {
+    operated_merchant(&services, &user, &merchant_id).await?;
+
+    Ok(Json(
+        services.merchant.deactivate_merchant(&merchant_id).await?,
+    ))
+}
+
+/// Admin-only: a merchant deactivated by its operators cannot bring itself back
+pub async fn reactivate_merchant(
+    State(services): State<Arc<ServiceContainer>>,
+    Path(merchant_id): Path<String>,
+) -> Result<Json<Merchant>, ApiError> {
+    Ok(Json(
+        services.merchant.reactivate_merchant(&merchant_id).await?,
+    ))
+}
+
+pub async fn add_merchant_operator(
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Path(merchant_id): Path<String>,
+    Json(request): Json<AddOperatorRequest>,
+) -> Result<Json<Merchant>, ApiError> {
+    let merchant = operated_merchant(&services, &user, &merchant_id).await?;
+    services
+        .merchant
+        .add_operator(&merchant_id, &request.user_id)
+        .await?;
+
+    Ok(Json(merchant))
+}
+
+/// Merchants are visible to their operators and admins
+pub(crate) async fn operated_merchant(
+    services: &ServiceContainer,
+    user: &AuthenticatedUser,
+    merchant_id: &str,
+) -> Result<Merchant, ApiError> {
+    let merchant = services.merchant.get_merchant(merchant_id).await?;
+    if user.role != Role::Admin
+        && !services
+            .merchant
+            .is_operator(merchant_id, &user.user_id)
+            .await?
+    {
+        return Err(ApiError::NotFound("Merchant not found".to_string()));
+    }
+    Ok(merchant)
+}
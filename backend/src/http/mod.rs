@@ -1,21 +1,42 @@
 pub mod admin;
+pub mod anchor;
+pub mod api_keys;
 pub mod audit;
 pub mod auth;
+pub mod deposits;
 pub mod health;
 pub mod identity;
+pub mod kyc;
+pub mod merchant_api;
+pub mod merchants;
 pub mod metrics;
 pub mod notifications;
 pub mod payments;
+pub mod quotes;
+pub mod refunds;
+pub mod settlements;
+pub mod terminals;
 pub mod transfers;
+pub mod webhooks;
 pub mod withdrawals;
 
 pub use admin::*;
+pub use anchor::*;
+pub use api_keys::*;
 pub use audit::*;
 pub use auth::*;
+pub use deposits::*;
 pub use health::*;
 pub use identity::*;
+pub use kyc::*;
+pub use merchants::*;
 pub use metrics::*;
 pub use notifications::*;
 pub use payments::*;
+pub use quotes::*;
+pub use refunds::*;
+pub use settlements::*;
+pub use terminals::*;
 pub use transfers::*;
+pub use webhooks::*;
 pub use withdrawals::*;
@@ -1,5 +1,6 @@
 use axum::{
     extract::{Path, State},
+    http::HeaderMap,
     Json,
 };
 use serde::{Deserialize, Serialize};
@@ -8,7 +9,14 @@ use uuid::Uuid;
 
 use crate::{
     api_error::ApiError,
-    service::{payment_service::CreatePaymentRequest, ServiceContainer},
+    http::merchants::operated_merchant,
+    middleware::AuthenticatedUser,
+    models::{CreateAuditLogParams, Payment, StatusEvent},
+    role::Role,
+    service::{
+        payment_service::{CreatePaymentRequest, VerifiedPaymentUri},
+        ServiceContainer,
+    },
 };
 
 #[derive(Debug, Serialize)]
@@ -22,9 +30,29 @@ pub struct PaymentResponse {
     pub receive_amount: Option<i64>,
     pub status: String,
     pub memo: Option<String>,
+    /// Total refunded so far, in the merchant's settlement asset
+    pub refunded_amount: i64,
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
+/// A payment together with its status history, oldest first
+#[derive(Debug, Serialize)]
+pub struct PaymentDetailResponse {
+    #[serde(flatten)]
+    pub payment: PaymentResponse,
+    pub history: Vec<StatusEvent>,
+}
+
+/// A payment created by a merchant or admin for a customer's wallet
+#[derive(Debug, Deserialize)]
+pub struct OnBehalfPaymentRequest {
+    pub customer_user_id: String,
+    /// Why the payment is being created for the customer; recorded in the audit log
+    pub reason: String,
+    #[serde(flatten)]
+    pub payment: CreatePaymentRequest,
+}
+
 #[derive(Debug, Serialize)]
 pub struct PaymentStatusResponse {
     pub id: Uuid,
@@ -38,47 +66,165 @@ pub struct QrPaymentRequest {
     pub amount: i64,
     pub asset: String,
     pub memo: Option<String>,
+    /// Unix time after which the request can no longer be redeemed
     pub expiry: i64,
 }
 
 #[derive(Debug, Serialize)]
 pub struct QrPaymentResponse {
+    /// Signed SEP-7 `web+stellar:pay` URI
     pub qr_data: String,
     pub merchant_id: String,
     pub amount: i64,
     pub asset: String,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// A URI read from a scanned QR code
+#[derive(Debug, Deserialize)]
+pub struct VerifyQrRequest {
+    pub uri: String,
+}
+
+/// A tap payload read from a merchant terminal, signed by the terminal's key
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NfcPaymentRequest {
+    pub terminal_id: Uuid,
     pub merchant_id: String,
     pub amount: i64,
     pub asset: String,
     pub memo: Option<String>,
+    /// Unix time the terminal emitted the payload
     pub timestamp: i64,
+    /// Random value the terminal never reuses
+    pub nonce: String,
+    /// Base64 ed25519 signature over `payment_service::nfc_signing_payload`
+    pub signature: String,
 }
 
 #[derive(Debug, Serialize)]
 pub struct NfcValidationResponse {
     pub valid: bool,
+    pub terminal_id: String,
     pub merchant_id: String,
     pub amount: i64,
+    pub asset: String,
+    pub memo: Option<String>,
 }
 
 pub async fn create_payment(
     State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
     Json(request): Json<CreatePaymentRequest>,
 ) -> Result<Json<PaymentResponse>, ApiError> {
-    // Get user from auth context (would need to implement proper auth extraction)
-    // For now, using a placeholder address
-    let from_address = "GEXAMPLE_ADDRESS".to_string();
+    // The payer is always the caller's own wallet
+    let wallet = services.identity.get_user_wallet(&user.user_id).await?;
+    if let Some(from_address) = &request.from_address {
+        if from_address != &wallet.address {
+            return Err(ApiError::Authorization(
+                "Payments can only be made from your own wallet".to_string(),
+            ));
+        }
+    }
 
     let payment = services
         .payment
-        .create_payment(from_address, request)
+        .create_payment(wallet.address, request)
         .await?;
 
-    Ok(Json(PaymentResponse {
+    Ok(Json(payment_response(payment)))
+}
+
+/// Merchant/admin path for creating a payment from a customer's wallet
+///
+/// Every payment created here is written to the audit log before it is returned;
+/// if the audit entry cannot be stored the request fails.
+pub async fn create_payment_on_behalf(
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    headers: HeaderMap,
+    Json(request): Json<OnBehalfPaymentRequest>,
+) -> Result<Json<PaymentResponse>, ApiError> {
+    let payment = pay_on_behalf(
+        &services,
+        user.user_id,
+        serde_json::json!(user.role),
+        &headers,
+        request,
+    )
+    .await?;
+
+    Ok(Json(payment_response(payment)))
+}
+
+/// Create a payment from a customer's wallet and audit who asked for it
+///
+/// `actor_role` is recorded in the audit entry, e.g. the user's role or `"api_key"`.
+pub(crate) async fn pay_on_behalf(
+    services: &ServiceContainer,
+    actor_id: String,
+    actor_role: serde_json::Value,
+    headers: &HeaderMap,
+    request: OnBehalfPaymentRequest,
+) -> Result<Payment, ApiError> {
+    if request.reason.trim().is_empty() {
+        return Err(ApiError::Validation(
+            "A reason is required when paying on behalf of a customer".to_string(),
+        ));
+    }
+
+    let wallet = services
+        .identity
+        .get_user_wallet(&request.customer_user_id)
+        .await?;
+    if let Some(from_address) = &request.payment.from_address {
+        if from_address != &wallet.address {
+            return Err(ApiError::Validation(
+                "from_address does not match the customer's wallet".to_string(),
+            ));
+        }
+    }
+
+    let merchant_id = request.payment.merchant_id.clone();
+    let payment = services
+        .payment
+        .create_payment(wallet.address, request.payment)
+        .await?;
+
+    services
+        .audit
+        .create_audit_log(CreateAuditLogParams {
+            actor_id,
+            action: "create_payment_on_behalf".to_string(),
+            resource: "payments".to_string(),
+            resource_id: Some(payment.id.clone()),
+            metadata: Some(serde_json::json!({
+                "actor_role": actor_role,
+                "customer_user_id": request.customer_user_id,
+                "merchant_id": merchant_id,
+                "send_asset": payment.send_asset,
+                "send_amount": payment.send_amount,
+                "reason": request.reason,
+            })),
+            ip_address: header_value(headers, "x-forwarded-for")
+                .or_else(|| header_value(headers, "x-real-ip")),
+            user_agent: header_value(headers, "user-agent"),
+            occurred_at: None,
+        })
+        .await?;
+
+    Ok(payment)
+}
+
+fn header_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.to_string())
+}
+
+pub(crate) fn payment_response(payment: Payment) -> PaymentResponse {
+    PaymentResponse {
         id: Uuid::parse_str(&payment.id).unwrap_or_default(),
         tx_hash: payment.tx_hash,
         from_address: payment.from_address,
@@ -88,41 +234,37 @@ pub async fn create_payment(
         receive_amount: payment.receive_amount,
         status: payment.status.to_string(),
         memo: payment.memo,
+        refunded_amount: payment.refunded_amount,
         created_at: payment.created_at,
-    }))
+    }
 }
 
 pub async fn get_payment(
     State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
     Path(payment_id): Path<String>,
-) -> Result<Json<PaymentResponse>, ApiError> {
+) -> Result<Json<PaymentDetailResponse>, ApiError> {
     let payment_uuid = Uuid::parse_str(&payment_id)
         .map_err(|_| ApiError::Validation("Invalid Payment ID".to_string()))?;
 
-    let payment = services.payment.get_payment(payment_uuid).await?;
+    let payment = visible_payment(&services, &user, payment_uuid).await?;
+    let history = services.payment.get_payment_history(payment_uuid).await?;
 
-    Ok(Json(PaymentResponse {
-        id: Uuid::parse_str(&payment.id).unwrap_or_default(),
-        tx_hash: payment.tx_hash,
-        from_address: payment.from_address,
-        merchant_id: payment.merchant_id,
-        send_asset: payment.send_asset,
-        send_amount: payment.send_amount,
-        receive_amount: payment.receive_amount,
-        status: payment.status.to_string(),
-        memo: payment.memo,
-        created_at: payment.created_at,
+    Ok(Json(PaymentDetailResponse {
+        payment: payment_response(payment),
+        history,
     }))
 }
 
 pub async fn get_payment_status(
     State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
     Path(payment_id): Path<String>,
 ) -> Result<Json<PaymentStatusResponse>, ApiError> {
     let payment_uuid = Uuid::parse_str(&payment_id)
         .map_err(|_| ApiError::Validation("Invalid Payment ID".to_string()))?;
 
-    let payment = services.payment.get_payment(payment_uuid).await?;
+    let payment = visible_payment(&services, &user, payment_uuid).await?;
 
     Ok(Json(PaymentStatusResponse {
         id: Uuid::parse_str(&payment.id).unwrap_or_default(),
@@ -131,35 +273,80 @@ pub async fn get_payment_status(
     }))
 }
 
+/// Payments are visible to their payer, their merchant's operators and admins
+async fn visible_payment(
+    services: &ServiceContainer,
+    user: &AuthenticatedUser,
+    payment_id: Uuid,
+) -> Result<Payment, ApiError> {
+    let payment = services.payment.get_payment(payment_id).await?;
+    if user.role == Role::Admin {
+        return Ok(payment);
+    }
+
+    let is_payer = match services.identity.get_user_wallet(&user.user_id).await {
+        Ok(wallet) => wallet.address == payment.from_address,
+        Err(ApiError::NotFound(_)) => false,
+        Err(e) => return Err(e),
+    };
+    if is_payer
+        || services
+            .merchant
+            .is_operator(&payment.merchant_id, &user.user_id)
+            .await?
+    {
+        return Ok(payment);
+    }
+    Err(ApiError::NotFound("Payment not found".to_string()))
+}
+
+/// Sign a payment request for one of the caller's merchants
 pub async fn generate_qr(
     State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
     Json(request): Json<QrPaymentRequest>,
 ) -> Result<Json<QrPaymentResponse>, ApiError> {
-    let qr_data = services
+    operated_merchant(&services, &user, &request.merchant_id).await?;
+    let payment_uri = services
         .payment
         .generate_qr_payment(request.clone())
         .await?;
 
     Ok(Json(QrPaymentResponse {
-        qr_data,
+        qr_data: payment_uri.uri,
         merchant_id: request.merchant_id,
         amount: request.amount,
         asset: request.asset,
+        expires_at: payment_uri.expires_at,
     }))
 }
 
+/// Verify a scanned payment request before paying it with its `payment_uri`
+pub async fn verify_qr(
+    State(services): State<Arc<ServiceContainer>>,
+    Json(request): Json<VerifyQrRequest>,
+) -> Result<Json<VerifiedPaymentUri>, ApiError> {
+    Ok(Json(
+        services.payment.verify_payment_uri(&request.uri).await?,
+    ))
+}
+
+/// Verify a tap payload before paying it with its `nfc_tap`
 pub async fn validate_nfc(
     State(services): State<Arc<ServiceContainer>>,
     Json(request): Json<NfcPaymentRequest>,
 ) -> Result<Json<NfcValidationResponse>, ApiError> {
-    let valid = services
+    let terminal = services
         .payment
         .validate_nfc_payment(request.clone())
         .await?;
 
     Ok(Json(NfcValidationResponse {
-        valid,
+        valid: true,
+        terminal_id: terminal.id,
         merchant_id: request.merchant_id,
         amount: request.amount,
+        asset: request.asset,
+        memo: request.memo,
     }))
 }
@@ -0,0 +1,55 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    api_error::ApiError,
+    middleware::AuthenticatedUser,
+    models::Quote,
+    service::{
+        quote_service::{BestPath, IndicativePrice, QuoteRequest},
+        ServiceContainer,
+    },
+};
+
+/// Indicative price of paying a merchant in `send_asset`; nothing is locked
+pub async fn get_price(
+    State(services): State<Arc<ServiceContainer>>,
+    Query(request): Query<QuoteRequest>,
+) -> Result<Json<IndicativePrice>, ApiError> {
+    Ok(Json(services.quote.get_price(&request).await?))
+}
+
+/// Best DEX path into the merchant's settlement asset and what it is expected to deliver
+pub async fn find_path(
+    State(services): State<Arc<ServiceContainer>>,
+    Query(request): Query<QuoteRequest>,
+) -> Result<Json<BestPath>, ApiError> {
+    Ok(Json(services.quote.find_path(&request).await?))
+}
+
+/// Lock a price for the authenticated user's wallet, to be passed as `quote_id` when paying
+pub async fn create_quote(
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Json(request): Json<QuoteRequest>,
+) -> Result<Json<Quote>, ApiError> {
+    Ok(Json(
+        services.quote.create_quote(&user.user_id, request).await?,
+    ))
+}
+
+pub async fn get_quote(
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Path(quote_id): Path<Uuid>,
+) -> Result<Json<Quote>, ApiError> {
+    let wallet = services.identity.get_user_wallet(&user.user_id).await?;
+
+    Ok(Json(
+        services.quote.get_quote(quote_id, &wallet.address).await?,
+    ))
+}
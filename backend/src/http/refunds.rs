@@ -0,0 +1,115 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    api_error::ApiError,
+    http::merchants::operated_merchant,
+    middleware::AuthenticatedUser,
+    models::{Refund, StatusEvent},
+    service::{refund_service::CreateRefundRequest, ServiceContainer},
+};
+
+#[derive(Debug, Serialize)]
+pub struct RefundResponse {
+    pub id: Uuid,
+    pub payment_id: Uuid,
+    pub merchant_id: String,
+    pub amount: i64,
+    pub asset: String,
+    pub destination_address: String,
+    pub reason: String,
+    pub note: Option<String>,
+    pub status: String,
+    pub tx_hash: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// A refund together with its status history, oldest first
+#[derive(Debug, Serialize)]
+pub struct RefundDetailResponse {
+    #[serde(flatten)]
+    pub refund: RefundResponse,
+    pub history: Vec<StatusEvent>,
+}
+
+/// Refund a payment to one of the merchants the caller operates
+pub async fn create_refund(
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Path(payment_id): Path<Uuid>,
+    Json(request): Json<CreateRefundRequest>,
+) -> Result<Json<RefundResponse>, ApiError> {
+    operated_payment(&services, &user, payment_id).await?;
+
+    let refund = services
+        .refund
+        .create_refund(payment_id, &user.user_id, request)
+        .await?;
+
+    Ok(Json(refund_response(refund)))
+}
+
+pub async fn list_refunds(
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Path(payment_id): Path<Uuid>,
+) -> Result<Json<Vec<RefundResponse>>, ApiError> {
+    operated_payment(&services, &user, payment_id).await?;
+
+    let refunds = services.refund.list_refunds(payment_id).await?;
+
+    Ok(Json(refunds.into_iter().map(refund_response).collect()))
+}
+
+pub async fn get_refund(
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Path((payment_id, refund_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<RefundDetailResponse>, ApiError> {
+    operated_payment(&services, &user, payment_id).await?;
+
+    let refund = services.refund.get_refund(refund_id).await?;
+    if refund.payment_id != payment_id.to_string() {
+        return Err(ApiError::NotFound("Refund not found".to_string()));
+    }
+    let history = services.refund.get_refund_history(refund_id).await?;
+
+    Ok(Json(RefundDetailResponse {
+        refund: refund_response(refund),
+        history,
+    }))
+}
+
+/// Payments are refundable by their merchant's operators and admins
+async fn operated_payment(
+    services: &ServiceContainer,
+    user: &AuthenticatedUser,
+    payment_id: Uuid,
+) -> Result<(), ApiError> {
+    let payment = services.payment.get_payment(payment_id).await?;
+    operated_merchant(services, user, &payment.merchant_id)
+        .await
+        .map_err(|_| ApiError::NotFound("Payment not found".to_string()))?;
+    Ok(())
+}
+
+pub(crate) fn refund_response(refund: Refund) -> RefundResponse {
+    RefundResponse {
+        id: Uuid::parse_str(&refund.id).unwrap_or_default(),
+        payment_id: Uuid::parse_str(&refund.payment_id).unwrap_or_default(),
+        merchant_id: refund.merchant_id,
+        amount: refund.amount,
+        asset: refund.asset,
+        destination_address: refund.destination_address,
+        reason: refund.reason.to_string(),
+        note: refund.note,
+        status: refund.status.to_string(),
+        tx_hash: refund.tx_hash,
+        created_at: refund.created_at,
+    }
+}
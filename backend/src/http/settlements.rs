@@ -0,0 +1,133 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    api_error::ApiError,
+    models::{Settlement, StatusEvent},
+    service::{
+        anchor_service::{Sep31Info, Sep31Transaction},
+        settlement_service::CreateSettlementRequest,
+        ServiceContainer,
+    },
+};
+
+#[derive(Debug, Serialize)]
+pub struct SettlementResponse {
+    pub id: Uuid,
+    pub tx_hash: Option<String>,
+    pub merchant_id: String,
+    pub amount: i64,
+    pub asset: String,
+    pub amount_out: Option<i64>,
+    pub status: String,
+    pub sender_id: Option<String>,
+    pub receiver_id: Option<String>,
+    pub anchor_tx_id: Option<String>,
+    pub anchor_status: Option<String>,
+    pub anchor_message: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// A settlement together with its status history, oldest first
+#[derive(Debug, Serialize)]
+pub struct SettlementDetailResponse {
+    #[serde(flatten)]
+    pub settlement: SettlementResponse,
+    pub history: Vec<StatusEvent>,
+}
+
+/// Body the SEP-31 anchor posts to the callback URL
+#[derive(Debug, Deserialize)]
+pub struct Sep31Callback {
+    pub transaction: Sep31Transaction,
+}
+
+/// What the receiving anchor requires to pay out each asset
+pub async fn get_settlement_info(
+    State(services): State<Arc<ServiceContainer>>,
+) -> Result<Json<Sep31Info>, ApiError> {
+    let token = services.anchor.authenticate_platform().await?;
+    Ok(Json(services.anchor.get_sep31_info(&token).await?))
+}
+
+pub async fn create_settlement(
+    State(services): State<Arc<ServiceContainer>>,
+    Json(request): Json<CreateSettlementRequest>,
+) -> Result<Json<SettlementResponse>, ApiError> {
+    let settlement = services.settlement.create_settlement(request).await?;
+
+    Ok(Json(settlement_response(settlement)))
+}
+
+pub async fn get_settlement(
+    State(services): State<Arc<ServiceContainer>>,
+    Path(settlement_id): Path<Uuid>,
+) -> Result<Json<SettlementDetailResponse>, ApiError> {
+    let settlement = services.settlement.get_settlement(settlement_id).await?;
+    let history = services
+        .settlement
+        .get_settlement_history(settlement_id)
+        .await?;
+
+    Ok(Json(SettlementDetailResponse {
+        settlement: settlement_response(settlement),
+        history,
+    }))
+}
+
+pub async fn get_settlement_status(
+    State(services): State<Arc<ServiceContainer>>,
+    Path(settlement_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let settlement = services.settlement.get_settlement(settlement_id).await?;
+
+    Ok(Json(serde_json::json!({
+        "id": settlement.id,
+        "status": settlement.status.to_string(),
+        "anchor_status": settlement.anchor_status,
+        "tx_hash": settlement.tx_hash,
+        "updated_at": settlement.updated_at,
+    })))
+}
+
+/// SEP-31 status callback from the receiving anchor
+///
+/// Only reached once `verify_anchor_callback` has checked the anchor's
+/// signature, so the reported transaction is applied as it is.
+pub async fn sep31_callback(
+    State(services): State<Arc<ServiceContainer>>,
+    Json(callback): Json<Sep31Callback>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let settlement = services
+        .settlement
+        .handle_callback(&callback.transaction)
+        .await?;
+
+    Ok(Json(serde_json::json!({
+        "id": settlement.id,
+        "status": settlement.status.to_string(),
+    })))
+}
+
+pub(crate) fn settlement_response(settlement: Settlement) -> SettlementResponse {
+    SettlementResponse {
+        id: Uuid::parse_str(&settlement.id).unwrap_or_default(),
+        tx_hash: settlement.tx_hash,
+        merchant_id: settlement.merchant_id,
+        amount: settlement.amount,
+        asset: settlement.asset,
+        amount_out: settlement.amount_out,
+        status: settlement.status.to_string(),
+        sender_id: settlement.sender_id,
+        receiver_id: settlement.receiver_id,
+        anchor_tx_id: settlement.anchor_tx_id,
+        anchor_status: settlement.anchor_status,
+        anchor_message: settlement.anchor_message,
+        created_at: settlement.created_at,
+    }
+}
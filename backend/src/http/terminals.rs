@@ -0,0 +1,43 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    api_error::ApiError,
+    models::MerchantTerminal,
+    service::{terminal_service::RegisterTerminalRequest, ServiceContainer},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct TerminalQuery {
+    pub merchant_id: String,
+}
+
+/// Register an NFC terminal key whose signed taps the merchant accepts
+pub async fn register_terminal(
+    State(services): State<Arc<ServiceContainer>>,
+    Json(request): Json<RegisterTerminalRequest>,
+) -> Result<Json<MerchantTerminal>, ApiError> {
+    Ok(Json(services.terminal.register_terminal(request).await?))
+}
+
+pub async fn list_terminals(
+    State(services): State<Arc<ServiceContainer>>,
+    Query(query): Query<TerminalQuery>,
+) -> Result<Json<Vec<MerchantTerminal>>, ApiError> {
+    Ok(Json(
+        services.terminal.list_terminals(&query.merchant_id).await?,
+    ))
+}
+
+/// Revoke a lost or retired terminal; its taps are rejected from then on
+pub async fn revoke_terminal(
+    State(services): State<Arc<ServiceContainer>>,
+    Path(terminal_id): Path<Uuid>,
+) -> Result<Json<MerchantTerminal>, ApiError> {
+    Ok(Json(services.terminal.revoke_terminal(terminal_id).await?))
+}
@@ -2,50 +2,107 @@ use axum::{
     extract::{Path, State},
     Json,
 };
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 use std::sync::Arc;
 use uuid::Uuid;
 
-use crate::{api_error::ApiError, service::ServiceContainer};
+use crate::{
+    api_error::ApiError,
+    middleware::AuthenticatedUser,
+    models::{StatusEvent, Transfer},
+    role::Role,
+    service::{transfer_service::CreateTransferRequest, ServiceContainer},
+};
 
 #[derive(Debug, Serialize)]
 pub struct TransferResponse {
     pub id: Uuid,
+    pub tx_hash: Option<String>,
     pub from_user_id: String,
     pub to_user_id: String,
     pub amount: i64,
     pub asset: String,
     pub status: String,
+    pub memo: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct CreateTransferRequest {
-    pub to_user_id: String,
-    pub amount: i64,
-    pub asset: String,
-    pub memo: Option<String>,
+/// A transfer together with its status history, oldest first
+#[derive(Debug, Serialize)]
+pub struct TransferDetailResponse {
+    #[serde(flatten)]
+    pub transfer: TransferResponse,
+    pub history: Vec<StatusEvent>,
 }
 
 pub async fn create_transfer(
-    State(_services): State<Arc<ServiceContainer>>,
-    Json(_request): Json<CreateTransferRequest>,
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Json(request): Json<CreateTransferRequest>,
 ) -> Result<Json<TransferResponse>, ApiError> {
-    // Placeholder implementation
-    Err(ApiError::NotFound("Not implemented".to_string()))
+    let transfer = services
+        .transfer
+        .create_transfer(&user.user_id, request)
+        .await?;
+
+    Ok(Json(transfer_response(transfer)))
 }
 
 pub async fn get_transfer(
-    State(_services): State<Arc<ServiceContainer>>,
-    Path(_transfer_id): Path<Uuid>,
-) -> Result<Json<TransferResponse>, ApiError> {
-    // Placeholder implementation
-    Err(ApiError::NotFound("Not implemented".to_string()))
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Path(transfer_id): Path<Uuid>,
+) -> Result<Json<TransferDetailResponse>, ApiError> {
+    let transfer = visible_transfer(&services, &user, transfer_id).await?;
+    let history = services.transfer.get_transfer_history(transfer_id).await?;
+
+    Ok(Json(TransferDetailResponse {
+        transfer: transfer_response(transfer),
+        history,
+    }))
 }
 
 pub async fn get_transfer_status(
-    State(_services): State<Arc<ServiceContainer>>,
-    Path(_transfer_id): Path<Uuid>,
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Path(transfer_id): Path<Uuid>,
 ) -> Result<Json<serde_json::Value>, ApiError> {
-    // Placeholder implementation
-    Err(ApiError::NotFound("Not implemented".to_string()))
+    let transfer = visible_transfer(&services, &user, transfer_id).await?;
+
+    Ok(Json(serde_json::json!({
+        "id": transfer.id,
+        "status": transfer.status.to_string(),
+        "tx_hash": transfer.tx_hash,
+        "updated_at": transfer.updated_at,
+    })))
+}
+
+/// Transfers are visible to their sender, their recipient and admins
+async fn visible_transfer(
+    services: &ServiceContainer,
+    user: &AuthenticatedUser,
+    transfer_id: Uuid,
+) -> Result<Transfer, ApiError> {
+    let transfer = services.transfer.get_transfer(transfer_id).await?;
+    if user.role != Role::Admin
+        && transfer.from_user_id != user.user_id
+        && transfer.to_user_id != user.user_id
+    {
+        return Err(ApiError::NotFound("Transfer not found".to_string()));
+    }
+    Ok(transfer)
+}
+
+fn transfer_response(transfer: Transfer) -> TransferResponse {
+    TransferResponse {
+        id: Uuid::parse_str(&transfer.id).unwrap_or_default(),
+        tx_hash: transfer.tx_hash,
+        from_user_id: transfer.from_user_id,
+        to_user_id: transfer.to_user_id,
+        amount: transfer.amount,
+        asset: transfer.asset,
+        status: transfer.status.to_string(),
+        memo: transfer.memo,
+        created_at: transfer.created_at,
+    }
 }
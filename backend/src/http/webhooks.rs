@@ -0,0 +1,91 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    api_error::ApiError,
+    http::merchants::operated_merchant,
+    middleware::AuthenticatedUser,
+    models::{WebhookDelivery, WebhookEndpoint},
+    service::{
+        webhook_service::{CreateWebhookEndpointRequest, CreatedWebhookEndpoint},
+        ServiceContainer,
+    },
+};
+
+/// Register a webhook endpoint; its signing secret is only returned here
+pub async fn create_webhook_endpoint(
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Path(merchant_id): Path<String>,
+    Json(request): Json<CreateWebhookEndpointRequest>,
+) -> Result<Json<CreatedWebhookEndpoint>, ApiError> {
+    operated_merchant(&services, &user, &merchant_id).await?;
+
+    Ok(Json(
+        services
+            .webhook
+            .create_endpoint(&merchant_id, request)
+            .await?,
+    ))
+}
+
+pub async fn list_webhook_endpoints(
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Path(merchant_id): Path<String>,
+) -> Result<Json<Vec<WebhookEndpoint>>, ApiError> {
+    operated_merchant(&services, &user, &merchant_id).await?;
+
+    Ok(Json(services.webhook.list_endpoints(&merchant_id).await?))
+}
+
+pub async fn disable_webhook_endpoint(
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Path((merchant_id, endpoint_id)): Path<(String, Uuid)>,
+) -> Result<Json<WebhookEndpoint>, ApiError> {
+    operated_merchant(&services, &user, &merchant_id).await?;
+
+    Ok(Json(
+        services
+            .webhook
+            .disable_endpoint(&merchant_id, endpoint_id)
+            .await?,
+    ))
+}
+
+/// Delivery log of an endpoint, newest first
+pub async fn list_webhook_deliveries(
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Path((merchant_id, endpoint_id)): Path<(String, Uuid)>,
+) -> Result<Json<Vec<WebhookDelivery>>, ApiError> {
+    operated_merchant(&services, &user, &merchant_id).await?;
+
+    Ok(Json(
+        services
+            .webhook
+            .list_deliveries(&merchant_id, endpoint_id)
+            .await?,
+    ))
+}
+
+/// Send a delivery's event again, as a new delivery
+pub async fn redeliver_webhook(
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Path((merchant_id, endpoint_id, delivery_id)): Path<(String, Uuid, Uuid)>,
+) -> Result<Json<WebhookDelivery>, ApiError> {
+    operated_merchant(&services, &user, &merchant_id).await?;
+
+    Ok(Json(
+        services
+            .webhook
+            .redeliver(&merchant_id, endpoint_id, delivery_id)
+            .await?,
+    ))
+}
@@ -6,45 +6,139 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
-use crate::{api_error::ApiError, service::ServiceContainer};
+use crate::{
+    api_error::ApiError,
+    middleware::AuthenticatedUser,
+    models::{StatusEvent, Withdrawal},
+    role::Role,
+    service::{
+        anchor_service::AnchorTransaction, withdrawal_service::CreateWithdrawalRequest,
+        ServiceContainer,
+    },
+};
 
 #[derive(Debug, Serialize)]
 pub struct WithdrawalResponse {
     pub id: Uuid,
+    pub tx_hash: Option<String>,
     pub user_id: String,
-    pub destination_address: String,
+    pub destination_address: Option<String>,
     pub amount: i64,
     pub asset: String,
     pub status: String,
+    pub anchor_tx_id: Option<String>,
+    pub anchor_status: Option<String>,
+    /// Anchor page the user must open to complete the withdrawal
+    pub interactive_url: Option<String>,
+    pub anchor_message: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
+/// Body the SEP-24 anchor posts to the callback URL
 #[derive(Debug, Deserialize)]
-pub struct CreateWithdrawalRequest {
-    pub destination_address: String,
-    pub amount: i64,
-    pub asset: String,
+pub struct Sep24Callback {
+    pub transaction: AnchorTransaction,
+}
+
+/// A withdrawal together with its status history, oldest first
+#[derive(Debug, Serialize)]
+pub struct WithdrawalDetailResponse {
+    #[serde(flatten)]
+    pub withdrawal: WithdrawalResponse,
+    pub history: Vec<StatusEvent>,
 }
 
 pub async fn create_withdrawal(
-    State(_services): State<Arc<ServiceContainer>>,
-    Json(_request): Json<CreateWithdrawalRequest>,
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Json(request): Json<CreateWithdrawalRequest>,
 ) -> Result<Json<WithdrawalResponse>, ApiError> {
-    // Placeholder implementation
-    Err(ApiError::NotFound("Not implemented".to_string()))
+    let withdrawal = services
+        .withdrawal
+        .create_withdrawal(&user.user_id, request)
+        .await?;
+
+    Ok(Json(withdrawal_response(withdrawal)))
 }
 
 pub async fn get_withdrawal(
-    State(_services): State<Arc<ServiceContainer>>,
-    Path(_withdrawal_id): Path<Uuid>,
-) -> Result<Json<WithdrawalResponse>, ApiError> {
-    // Placeholder implementation
-    Err(ApiError::NotFound("Not implemented".to_string()))
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Path(withdrawal_id): Path<Uuid>,
+) -> Result<Json<WithdrawalDetailResponse>, ApiError> {
+    let withdrawal = visible_withdrawal(&services, &user, withdrawal_id).await?;
+    let history = services
+        .withdrawal
+        .get_withdrawal_history(withdrawal_id)
+        .await?;
+
+    Ok(Json(WithdrawalDetailResponse {
+        withdrawal: withdrawal_response(withdrawal),
+        history,
+    }))
 }
 
 pub async fn get_withdrawal_status(
-    State(_services): State<Arc<ServiceContainer>>,
-    Path(_withdrawal_id): Path<Uuid>,
+    State(services): State<Arc<ServiceContainer>>,
+    user: AuthenticatedUser,
+    Path(withdrawal_id): Path<Uuid>,
 ) -> Result<Json<serde_json::Value>, ApiError> {
-    // Placeholder implementation
-    Err(ApiError::NotFound("Not implemented".to_string()))
+    let withdrawal = visible_withdrawal(&services, &user, withdrawal_id).await?;
+
+    Ok(Json(serde_json::json!({
+        "id": withdrawal.id,
+        "status": withdrawal.status.to_string(),
+        "anchor_status": withdrawal.anchor_status,
+        "tx_hash": withdrawal.tx_hash,
+        "updated_at": withdrawal.updated_at,
+    })))
+}
+
+/// Withdrawals are visible to their owner and admins
+async fn visible_withdrawal(
+    services: &ServiceContainer,
+    user: &AuthenticatedUser,
+    withdrawal_id: Uuid,
+) -> Result<Withdrawal, ApiError> {
+    let withdrawal = services.withdrawal.get_withdrawal(withdrawal_id).await?;
+    if user.role != Role::Admin && withdrawal.user_id != user.user_id {
+        return Err(ApiError::NotFound("Withdrawal not found".to_string()));
+    }
+    Ok(withdrawal)
+}
+
+fn withdrawal_response(withdrawal: Withdrawal) -> WithdrawalResponse {
+    WithdrawalResponse {
+        id: Uuid::parse_str(&withdrawal.id).unwrap_or_default(),
+        tx_hash: withdrawal.tx_hash,
+        user_id: withdrawal.user_id,
+        destination_address: withdrawal.destination_address,
+        amount: withdrawal.amount,
+        asset: withdrawal.asset,
+        status: withdrawal.status.to_string(),
+        anchor_tx_id: withdrawal.anchor_tx_id,
+        anchor_status: withdrawal.anchor_status,
+        interactive_url: withdrawal.interactive_url,
+        anchor_message: withdrawal.anchor_message,
+        created_at: withdrawal.created_at,
+    }
+}
+
+/// SEP-24 status callback from the anchor for a withdrawal
+///
+/// Only reached once `verify_anchor_callback` has checked the anchor's
+/// signature, so the reported transaction is applied as it is.
+pub async fn sep24_callback(
+    State(services): State<Arc<ServiceContainer>>,
+    Json(callback): Json<Sep24Callback>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let withdrawal = services
+        .withdrawal
+        .handle_callback(&callback.transaction)
+        .await?;
+
+    Ok(Json(serde_json::json!({
+        "id": withdrawal.id,
+        "status": withdrawal.status.to_string(),
+    })))
 }
@@ -9,7 +9,9 @@ pub mod models;
 pub mod role;
 // pub mod realtime; // TODO: Implement when needed
 pub mod service;
+pub mod stellar;
 pub mod telemetry;
+pub mod workers;
 
 pub use api_error::ApiError;
 pub use app::create_app;
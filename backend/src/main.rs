@@ -1,6 +1,8 @@
-use std::net::SocketAddr;
+use std::{net::SocketAddr, sync::Arc};
 use tracing::info;
-use zaps_backend::{app::create_app, config::Config, db, telemetry};
+use zaps_backend::{
+    app::build_router, config::Config, db, service::ServiceContainer, telemetry, workers,
+};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -16,8 +18,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Run database migrations
     db::run_migrations(&config.database.url).await?;
 
+    // Create services and start background workers
+    let services = Arc::new(ServiceContainer::new(db_pool, config.clone()).await?);
+    workers::spawn_workers(services.clone());
+
     // Create application
-    let app = create_app(db_pool, config.clone()).await?;
+    let app = build_router(services);
 
     // Start server
     let addr = SocketAddr::from(([0, 0, 0, 0], config.server.port));
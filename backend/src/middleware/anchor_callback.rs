@@ -0,0 +1,78 @@
+//! Signature verification for SEP-24 and SEP-31 status callbacks
+//!
+//! Callback routes carry no user credentials: the anchor signs each body
+//! with its SEP-10 signing key, and a callback is only handled if the
+//! signature matches, is recent and has not been seen before.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+use crate::{
+    api_error::ApiError,
+    service::{
+        anchor_service::{
+            callback_host, verify_callback_signature, CALLBACK_SIGNATURE_HEADER,
+            LEGACY_CALLBACK_SIGNATURE_HEADER,
+        },
+        ServiceContainer,
+    },
+};
+
+/// Largest callback body that is accepted
+const MAX_BODY_BYTES: usize = 256 * 1024;
+
+pub async fn verify_anchor_callback(
+    State(services): State<Arc<ServiceContainer>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let header = request
+        .headers()
+        .get(CALLBACK_SIGNATURE_HEADER)
+        .or_else(|| request.headers().get(LEGACY_CALLBACK_SIGNATURE_HEADER))
+        .ok_or_else(|| ApiError::Authentication("Callback signature required".to_string()))?
+        .to_str()
+        .map_err(|_| ApiError::Authentication("Invalid callback signature".to_string()))?
+        .to_string();
+
+    let (parts, body) = request.into_parts();
+    let body = to_bytes(body, MAX_BODY_BYTES)
+        .await
+        .map_err(|_| ApiError::Validation("Request body too large".to_string()))?;
+
+    // The anchor signs for the host of the callback URL we registered with it
+    let anchor = &services.config.anchor_config;
+    let callback_url = match parts.uri.path().rsplit('/').next() {
+        Some("sep24") => anchor.sep24_callback_url.as_deref(),
+        Some("sep31") => anchor.sep31_callback_url.as_deref(),
+        _ => None,
+    }
+    .ok_or_else(|| {
+        ApiError::Authentication("No callback URL is registered with the anchor".to_string())
+    })?;
+    let signature = verify_callback_signature(
+        &anchor.signing_key,
+        &callback_host(callback_url)?,
+        &header,
+        &body,
+        chrono::Utc::now().timestamp(),
+        anchor.callback_max_age_secs,
+    )?;
+    services.anchor.claim_callback(&signature).await?;
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+
+    // The anchor retries callbacks we failed to handle; let the retry through
+    if response.status().is_server_error() {
+        if let Err(e) = services.anchor.release_callback(&signature).await {
+            tracing::warn!("Failed to release anchor callback signature: {}", e);
+        }
+    }
+
+    Ok(response)
+}
@@ -4,9 +4,14 @@ use axum::{
     middleware::Next,
     response::Response,
 };
+use chrono::Utc;
 use std::sync::Arc;
 
-use crate::service::ServiceContainer;
+use crate::{
+    middleware::auth::request_actor_id,
+    models::CreateAuditLogParams,
+    service::{job_service::Job, ServiceContainer},
+};
 
 /// Audit logging middleware that automatically logs all authenticated requests
 pub async fn audit_logging(
@@ -15,11 +20,7 @@ pub async fn audit_logging(
     next: Next,
 ) -> Response {
     // Extract actor_id from request extensions (set by auth middleware)
-    let actor_id = request
-        .extensions()
-        .get::<String>()
-        .cloned()
-        .unwrap_or_else(|| "anonymous".to_string());
+    let actor_id = request_actor_id(&request).unwrap_or_else(|| "anonymous".to_string());
 
     // Extract IP address from headers
     let ip_address = request
@@ -52,23 +53,20 @@ pub async fn audit_logging(
     // Only log successful requests (2xx status codes)
     let status = response.status();
     if status.is_success() {
-        // Clone services for async task
-        let audit_service = services.audit.clone();
-
-        // Log asynchronously to avoid blocking the response
-        tokio::spawn(async move {
-            let _ = audit_service
-                .create_audit_log(crate::models::CreateAuditLogParams {
-                    actor_id,
-                    action,
-                    resource,
-                    resource_id,
-                    metadata: None, // metadata can be extended later
-                    ip_address,
-                    user_agent,
-                })
-                .await;
+        // Queue the entry as a job, so writing it is retried if it fails
+        let job = Job::AuditLog(CreateAuditLogParams {
+            actor_id,
+            action,
+            resource,
+            resource_id,
+            metadata: None, // metadata can be extended later
+            ip_address,
+            user_agent,
+            occurred_at: Some(Utc::now()),
         });
+        if let Err(e) = services.job.enqueue(&job, None).await {
+            tracing::error!("Failed to queue audit log entry: {}", e);
+        }
     }
 
     response
@@ -1,5 +1,6 @@
+use crate::models::{ApiKey, ApiKeyScope};
 use crate::role::Role;
-use crate::{auth, service::ServiceContainer};
+use crate::{api_error::ApiError, auth, service::ServiceContainer};
 use axum::{
     async_trait,
     extract::{FromRequestParts, Request, State},
@@ -48,6 +49,82 @@ pub async fn authenticate(
     }
 }
 
+/// Merchant API key a server-to-server request authenticated with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthenticatedApiKey {
+    pub key_id: String,
+    pub merchant_id: String,
+    pub scopes: Vec<ApiKeyScope>,
+}
+
+impl AuthenticatedApiKey {
+    /// Actor recorded for the key's requests in audit logs and idempotency records
+    pub fn actor_id(&self) -> String {
+        format!("api_key:{}", self.key_id)
+    }
+
+    pub fn require_scope(&self, scope: ApiKeyScope) -> Result<(), ApiError> {
+        if self.scopes.contains(&scope) {
+            Ok(())
+        } else {
+            Err(ApiError::Authorization(format!(
+                "API key lacks the {} scope",
+                scope
+            )))
+        }
+    }
+}
+
+impl From<ApiKey> for AuthenticatedApiKey {
+    fn from(key: ApiKey) -> Self {
+        Self {
+            key_id: key.id,
+            merchant_id: key.merchant_id,
+            scopes: key.scopes,
+        }
+    }
+}
+
+/// Authentication middleware for the merchant API - validates the `X-API-KEY` header
+///
+/// This is a separate path from `authenticate`: routes behind it accept API
+/// keys only, so a key can never reach an endpoint meant for users.
+pub async fn authenticate_api_key(
+    State(services): State<Arc<ServiceContainer>>,
+    mut req: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let key = req
+        .headers()
+        .get("x-api-key")
+        .and_then(|header| header.to_str().ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    match services.api_key.authenticate(key).await {
+        Ok(api_key) => {
+            req.extensions_mut()
+                .insert(AuthenticatedApiKey::from(api_key));
+            Ok(next.run(req).await)
+        }
+        Err(ApiError::Authentication(_)) => Err(StatusCode::UNAUTHORIZED),
+        Err(e) => {
+            tracing::error!("Failed to authenticate API key: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Actor behind an authenticated request: the user's id, or the API key's
+pub fn request_actor_id(req: &Request) -> Option<String> {
+    get_authenticated_user(req)
+        .map(|user| user.user_id)
+        .or_else(|| {
+            req.extensions()
+                .get::<AuthenticatedApiKey>()
+                .map(AuthenticatedApiKey::actor_id)
+        })
+}
+
 /// Axum extractor for getting the authenticated user from request
 #[async_trait]
 impl<S> FromRequestParts<S> for AuthenticatedUser
@@ -65,6 +142,22 @@ where
     }
 }
 
+#[async_trait]
+impl<S> FromRequestParts<S> for AuthenticatedApiKey
+where
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<AuthenticatedApiKey>()
+            .cloned()
+            .ok_or(StatusCode::UNAUTHORIZED)
+    }
+}
+
 /// Get authenticated user from request extensions
 pub fn get_authenticated_user(req: &Request) -> Option<AuthenticatedUser> {
     req.extensions().get::<AuthenticatedUser>().cloned()
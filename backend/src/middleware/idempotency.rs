@@ -0,0 +1,168 @@
+//! `Idempotency-Key` support for money-moving POST routes
+//!
+//! A client that retries a request with the same key gets the original
+//! response back instead of creating a second payment, transfer or
+//! withdrawal. Keys are scoped to the authenticated user.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{OriginalUri, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+use crate::{
+    api_error::ApiError,
+    middleware::auth::request_actor_id,
+    service::{IdempotencyOutcome, IdempotencyService, ServiceContainer},
+};
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// Largest request or response body that is buffered for fingerprinting and replay
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Attempts at storing a response before the key is left in flight
+const RECORD_ATTEMPTS: u32 = 3;
+
+pub async fn idempotency(
+    State(services): State<Arc<ServiceContainer>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let Some(key) = request.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(next.run(request).await);
+    };
+    let key = key
+        .to_str()
+        .map_err(|_| ApiError::Validation("Invalid Idempotency-Key header".to_string()))?
+        .to_string();
+    IdempotencyService::validate_key(&key)?;
+
+    let user_id = request_actor_id(&request)
+        .ok_or_else(|| ApiError::Authentication("Authentication required".to_string()))?;
+
+    let method = request.method().to_string();
+    let path = request
+        .extensions()
+        .get::<OriginalUri>()
+        .map(|uri| uri.path().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+
+    let (parts, body) = request.into_parts();
+    let body = to_bytes(body, MAX_BODY_BYTES)
+        .await
+        .map_err(|_| ApiError::Validation("Request body too large".to_string()))?;
+    let fingerprint = IdempotencyService::fingerprint(&method, &path, &body);
+
+    match services
+        .idempotency
+        .begin(&user_id, &key, &method, &path, &fingerprint)
+        .await?
+    {
+        IdempotencyOutcome::Replay { status, body } => return Ok(replay_response(status, body)),
+        IdempotencyOutcome::Proceed => {}
+    }
+
+    // Run detached so a client that disconnects does not abandon a handler
+    // that may already have committed; the key stays in flight until its
+    // response is recorded, and retries are refused rather than run again
+    let idempotency = services.idempotency.clone();
+    let request = Request::from_parts(parts, Body::from(body));
+    tokio::spawn(async move {
+        let _heartbeat = renew_lease(idempotency.clone(), user_id.clone(), key.clone());
+        let response = next.run(request).await;
+
+        // Every response is stored, server errors included: the handler may have
+        // committed side effects before failing, so a retry must not run it again
+        let (parts, body) = response.into_parts();
+        let (parts, body) = match to_bytes(body, MAX_BODY_BYTES).await {
+            Ok(body) => (parts, body),
+            Err(e) => {
+                tracing::error!("Failed to buffer response for idempotency key: {}", e);
+                let (parts, body) = ApiError::InternalServerError.into_response().into_parts();
+                let body = to_bytes(body, MAX_BODY_BYTES).await.unwrap_or_default();
+                (parts, body)
+            }
+        };
+
+        record(&idempotency, &user_id, &key, parts.status, &body).await;
+        Response::from_parts(parts, Body::from(body))
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("Idempotent request handler panicked: {}", e);
+        ApiError::InternalServerError
+    })
+}
+
+/// Keep the reservation's lease alive until the returned guard is dropped
+fn renew_lease(idempotency: IdempotencyService, user_id: String, key: String) -> AbortOnDrop {
+    AbortOnDrop(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(idempotency.renew_interval());
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if let Err(e) = idempotency.renew(&user_id, &key).await {
+                tracing::warn!("Failed to renew idempotency key lease: {}", e);
+            }
+        }
+    }))
+}
+
+/// Store the response for replay, retrying a few times
+///
+/// If it still cannot be stored the key is left in flight, so retries are
+/// refused instead of running the handler a second time.
+async fn record(
+    idempotency: &IdempotencyService,
+    user_id: &str,
+    key: &str,
+    status: StatusCode,
+    body: &[u8],
+) {
+    let body = String::from_utf8_lossy(body);
+    for attempt in 1..=RECORD_ATTEMPTS {
+        match idempotency
+            .complete(user_id, key, status.as_u16(), &body)
+            .await
+        {
+            Ok(()) => return,
+            Err(e) if attempt == RECORD_ATTEMPTS => {
+                tracing::error!(
+                    "Failed to record the response for idempotency key {}; retries will be refused: {}",
+                    key,
+                    e
+                );
+            }
+            Err(e) => {
+                tracing::warn!("Failed to record idempotent response, retrying: {}", e);
+                tokio::time::sleep(std::time::Duration::from_millis(200 * attempt as u64)).await;
+            }
+        }
+    }
+}
+
+/// Stops the lease renewal when the handler finishes or its task unwinds
+struct AbortOnDrop(tokio::task::JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+fn replay_response(status: u16, body: String) -> Response {
+    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::OK);
+    let mut response = (status, body).into_response();
+    let headers = response.headers_mut();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    headers.insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+    response
+}
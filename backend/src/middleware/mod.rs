@@ -1,12 +1,16 @@
+pub mod anchor_callback;
 pub mod audit;
 pub mod auth;
+pub mod idempotency;
 pub mod metrics;
 pub mod rate_limit;
 pub mod request_id;
 pub mod role_guard;
 
+pub use anchor_callback::*;
 pub use audit::*;
 pub use auth::*;
+pub use idempotency::*;
 pub use metrics::*;
 pub use request_id::*;
 pub use role_guard::*;
@@ -25,14 +25,36 @@ pub struct Wallet {
 pub struct Merchant {
     pub id: String,
     pub merchant_id: String,
+    pub business_name: Option<String>,
+    pub contact_email: Option<String>,
+    pub website: Option<String>,
     pub vault_address: String,
     pub settlement_asset: String,
     pub active: bool,
+    /// Where mirroring to the registry contract stands: `registering`, `registered`,
+    /// `deactivating`, `deactivated` or `failed`; absent for merchants never mirrored
+    pub registry_status: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A status in an entity's lifecycle, with the transitions it may move to
+///
+/// Terminal statuses have no outgoing transitions. Moving a status anywhere
+/// not listed in `next_statuses` is rejected.
+pub trait Lifecycle: Copy + PartialEq + fmt::Display + FromStr + 'static {
+    fn next_statuses(&self) -> &'static [Self];
+
+    fn can_transition_to(&self, next: Self) -> bool {
+        self.next_statuses().contains(&next)
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.next_statuses().is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PaymentStatus {
     Pending,
     Processing,
@@ -42,16 +64,28 @@ pub enum PaymentStatus {
 }
 
 impl FromStr for PaymentStatus {
-    type Err = std::convert::Infallible;
+    type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(match s {
-            "completed" => PaymentStatus::Completed,
-            "processing" => PaymentStatus::Processing,
-            "failed" => PaymentStatus::Failed,
-            "refunded" => PaymentStatus::Refunded,
-            _ => PaymentStatus::Pending,
-        })
+        match s {
+            "pending" => Ok(PaymentStatus::Pending),
+            "processing" => Ok(PaymentStatus::Processing),
+            "completed" => Ok(PaymentStatus::Completed),
+            "failed" => Ok(PaymentStatus::Failed),
+            "refunded" => Ok(PaymentStatus::Refunded),
+            _ => Err(format!("Unknown payment status: {}", s)),
+        }
+    }
+}
+
+impl Lifecycle for PaymentStatus {
+    fn next_statuses(&self) -> &'static [Self] {
+        match self {
+            PaymentStatus::Pending => &[PaymentStatus::Processing, PaymentStatus::Failed],
+            PaymentStatus::Processing => &[PaymentStatus::Completed, PaymentStatus::Failed],
+            PaymentStatus::Completed => &[PaymentStatus::Refunded],
+            PaymentStatus::Failed | PaymentStatus::Refunded => &[],
+        }
     }
 }
 
@@ -79,11 +113,13 @@ pub struct Payment {
     pub receive_amount: Option<i64>,
     pub status: PaymentStatus,
     pub memo: Option<String>,
+    /// Total of completed refunds, in the merchant's settlement asset
+    pub refunded_amount: i64,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TransferStatus {
     Pending,
     Processing,
@@ -92,15 +128,26 @@ pub enum TransferStatus {
 }
 
 impl FromStr for TransferStatus {
-    type Err = std::convert::Infallible;
+    type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(match s {
-            "processing" => TransferStatus::Processing,
-            "completed" => TransferStatus::Completed,
-            "failed" => TransferStatus::Failed,
-            _ => TransferStatus::Pending,
-        })
+        match s {
+            "pending" => Ok(TransferStatus::Pending),
+            "processing" => Ok(TransferStatus::Processing),
+            "completed" => Ok(TransferStatus::Completed),
+            "failed" => Ok(TransferStatus::Failed),
+            _ => Err(format!("Unknown transfer status: {}", s)),
+        }
+    }
+}
+
+impl Lifecycle for TransferStatus {
+    fn next_statuses(&self) -> &'static [Self] {
+        match self {
+            TransferStatus::Pending => &[TransferStatus::Processing, TransferStatus::Failed],
+            TransferStatus::Processing => &[TransferStatus::Completed, TransferStatus::Failed],
+            TransferStatus::Completed | TransferStatus::Failed => &[],
+        }
     }
 }
 
@@ -130,7 +177,7 @@ pub struct Transfer {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum WithdrawalStatus {
     Pending,
     Processing,
@@ -139,15 +186,28 @@ pub enum WithdrawalStatus {
 }
 
 impl FromStr for WithdrawalStatus {
-    type Err = std::convert::Infallible;
+    type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(match s {
-            "processing" => WithdrawalStatus::Processing,
-            "completed" => WithdrawalStatus::Completed,
-            "failed" => WithdrawalStatus::Failed,
-            _ => WithdrawalStatus::Pending,
-        })
+        match s {
+            "pending" => Ok(WithdrawalStatus::Pending),
+            "processing" => Ok(WithdrawalStatus::Processing),
+            "completed" => Ok(WithdrawalStatus::Completed),
+            "failed" => Ok(WithdrawalStatus::Failed),
+            _ => Err(format!("Unknown withdrawal status: {}", s)),
+        }
+    }
+}
+
+impl Lifecycle for WithdrawalStatus {
+    fn next_statuses(&self) -> &'static [Self] {
+        match self {
+            WithdrawalStatus::Pending => &[WithdrawalStatus::Processing, WithdrawalStatus::Failed],
+            WithdrawalStatus::Processing => {
+                &[WithdrawalStatus::Completed, WithdrawalStatus::Failed]
+            }
+            WithdrawalStatus::Completed | WithdrawalStatus::Failed => &[],
+        }
     }
 }
 
@@ -168,15 +228,546 @@ pub struct Withdrawal {
     pub id: String,
     pub tx_hash: Option<String>,
     pub user_id: String,
-    pub destination_address: String,
+    pub destination_address: Option<String>,
     pub amount: i64,
     pub asset: String,
     pub status: WithdrawalStatus,
     pub anchor_tx_id: Option<String>,
+    /// Status last reported by the anchor, e.g. `pending_user_transfer_start`
+    pub anchor_status: Option<String>,
+    /// Anchor page where the user completes the withdrawal
+    pub interactive_url: Option<String>,
+    pub anchor_message: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DepositStatus {
+    Pending,
+    Processing,
+    Completed,
+    Failed,
+}
+
+impl FromStr for DepositStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(DepositStatus::Pending),
+            "processing" => Ok(DepositStatus::Processing),
+            "completed" => Ok(DepositStatus::Completed),
+            "failed" => Ok(DepositStatus::Failed),
+            _ => Err(format!("Unknown deposit status: {}", s)),
+        }
+    }
+}
+
+impl Lifecycle for DepositStatus {
+    fn next_statuses(&self) -> &'static [Self] {
+        match self {
+            DepositStatus::Pending => &[DepositStatus::Processing, DepositStatus::Failed],
+            DepositStatus::Processing => &[DepositStatus::Completed, DepositStatus::Failed],
+            DepositStatus::Completed | DepositStatus::Failed => &[],
+        }
+    }
+}
+
+impl fmt::Display for DepositStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            DepositStatus::Pending => "pending",
+            DepositStatus::Processing => "processing",
+            DepositStatus::Completed => "completed",
+            DepositStatus::Failed => "failed",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Deposit {
+    pub id: String,
+    pub tx_hash: Option<String>,
+    pub user_id: String,
+    /// Requested amount until the deposit completes, then the amount delivered
+    pub amount: Option<i64>,
+    pub asset: String,
+    pub status: DepositStatus,
+    pub anchor_tx_id: Option<String>,
+    pub anchor_status: Option<String>,
+    /// Anchor page where the user completes the deposit
+    pub interactive_url: Option<String>,
+    pub anchor_message: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SettlementStatus {
+    Pending,
+    Processing,
+    Completed,
+    Failed,
+}
+
+impl FromStr for SettlementStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(SettlementStatus::Pending),
+            "processing" => Ok(SettlementStatus::Processing),
+            "completed" => Ok(SettlementStatus::Completed),
+            "failed" => Ok(SettlementStatus::Failed),
+            _ => Err(format!("Unknown settlement status: {}", s)),
+        }
+    }
+}
+
+impl Lifecycle for SettlementStatus {
+    fn next_statuses(&self) -> &'static [Self] {
+        match self {
+            SettlementStatus::Pending => &[SettlementStatus::Processing, SettlementStatus::Failed],
+            SettlementStatus::Processing => {
+                &[SettlementStatus::Completed, SettlementStatus::Failed]
+            }
+            SettlementStatus::Completed | SettlementStatus::Failed => &[],
+        }
+    }
+}
+
+impl fmt::Display for SettlementStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            SettlementStatus::Pending => "pending",
+            SettlementStatus::Processing => "processing",
+            SettlementStatus::Completed => "completed",
+            SettlementStatus::Failed => "failed",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A SEP-31 payout of a merchant's balance through a receiving anchor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Settlement {
+    pub id: String,
+    pub tx_hash: Option<String>,
+    pub merchant_id: String,
+    pub amount: i64,
+    pub asset: String,
+    /// Amount the receiver got, once the anchor reports it
+    pub amount_out: Option<i64>,
+    pub status: SettlementStatus,
+    pub sender_id: Option<String>,
+    pub receiver_id: Option<String>,
+    pub anchor_tx_id: Option<String>,
+    pub anchor_status: Option<String>,
+    pub anchor_message: Option<String>,
+    /// Anchor account the on-chain payment is sent to
+    pub stellar_account_id: Option<String>,
+    pub stellar_memo: Option<String>,
+    pub stellar_memo_type: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RefundStatus {
+    Pending,
+    Processing,
+    Completed,
+    Failed,
+}
+
+impl FromStr for RefundStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(RefundStatus::Pending),
+            "processing" => Ok(RefundStatus::Processing),
+            "completed" => Ok(RefundStatus::Completed),
+            "failed" => Ok(RefundStatus::Failed),
+            _ => Err(format!("Unknown refund status: {}", s)),
+        }
+    }
+}
+
+impl Lifecycle for RefundStatus {
+    fn next_statuses(&self) -> &'static [Self] {
+        match self {
+            RefundStatus::Pending => &[RefundStatus::Processing, RefundStatus::Failed],
+            RefundStatus::Processing => &[RefundStatus::Completed, RefundStatus::Failed],
+            RefundStatus::Completed | RefundStatus::Failed => &[],
+        }
+    }
+}
+
+impl fmt::Display for RefundStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            RefundStatus::Pending => "pending",
+            RefundStatus::Processing => "processing",
+            RefundStatus::Completed => "completed",
+            RefundStatus::Failed => "failed",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Why a merchant refunded a payment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefundReason {
+    RequestedByCustomer,
+    Duplicate,
+    Fraudulent,
+    ProductNotDelivered,
+    Other,
+}
+
+impl FromStr for RefundReason {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "requested_by_customer" => Ok(RefundReason::RequestedByCustomer),
+            "duplicate" => Ok(RefundReason::Duplicate),
+            "fraudulent" => Ok(RefundReason::Fraudulent),
+            "product_not_delivered" => Ok(RefundReason::ProductNotDelivered),
+            "other" => Ok(RefundReason::Other),
+            _ => Err(format!("Unknown refund reason: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for RefundReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            RefundReason::RequestedByCustomer => "requested_by_customer",
+            RefundReason::Duplicate => "duplicate",
+            RefundReason::Fraudulent => "fraudulent",
+            RefundReason::ProductNotDelivered => "product_not_delivered",
+            RefundReason::Other => "other",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A full or partial refund of a completed payment, paid back to its payer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Refund {
+    pub id: String,
+    pub payment_id: String,
+    pub merchant_id: String,
+    /// Amount in stroops of the merchant's settlement asset
+    pub amount: i64,
+    pub asset: String,
+    pub destination_address: String,
+    pub reason: RefundReason,
+    pub note: Option<String>,
+    pub status: RefundStatus,
+    /// Debit of the merchant-vault contract, when one is configured
+    pub vault_tx_hash: Option<String>,
+    /// Payment of the refund to the payer
+    pub tx_hash: Option<String>,
+    pub requested_by: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Where a quote's price came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuoteSource {
+    /// A SEP-38 quote from the anchor
+    Anchor,
+    /// Horizon path finding over the Stellar DEX
+    Dex,
+    /// The payer already holds the merchant's settlement asset
+    Direct,
+}
+
+impl FromStr for QuoteSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "anchor" => Ok(QuoteSource::Anchor),
+            "dex" => Ok(QuoteSource::Dex),
+            "direct" => Ok(QuoteSource::Direct),
+            _ => Err(format!("Unknown quote source: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for QuoteSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            QuoteSource::Anchor => "anchor",
+            QuoteSource::Dex => "dex",
+            QuoteSource::Direct => "direct",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A locked rate for paying a merchant in one asset while it settles in another
+///
+/// Amounts are in stroops. `price` is what one unit of `buy_asset` costs in
+/// `sell_asset`, as a decimal string.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Quote {
+    pub id: String,
+    pub merchant_id: String,
+    /// Wallet the quote was given to; only it can pay with the quote
+    pub payer_address: String,
+    pub sell_asset: String,
+    pub sell_amount: i64,
+    pub buy_asset: String,
+    pub buy_amount: i64,
+    pub price: String,
+    pub source: QuoteSource,
+    /// The anchor's SEP-38 quote id, for anchor quotes
+    pub anchor_quote_id: Option<String>,
+    /// Intermediate assets of the DEX path, for DEX quotes
+    pub path: Vec<String>,
+    pub expires_at: DateTime<Utc>,
+    /// Payment that used the quote; a quote can be used once
+    pub payment_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An NFC terminal whose signed tap payloads a merchant accepts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MerchantTerminal {
+    pub id: String,
+    pub merchant_id: String,
+    /// Ed25519 key the terminal signs taps with, as a `G...` address
+    pub public_key: String,
+    pub label: Option<String>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+/// What a merchant API key may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ApiKeyScope {
+    #[serde(rename = "payments:write")]
+    PaymentsWrite,
+    #[serde(rename = "payments:read")]
+    PaymentsRead,
+    #[serde(rename = "refunds:write")]
+    RefundsWrite,
+}
+
+impl FromStr for ApiKeyScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "payments:write" => Ok(ApiKeyScope::PaymentsWrite),
+            "payments:read" => Ok(ApiKeyScope::PaymentsRead),
+            "refunds:write" => Ok(ApiKeyScope::RefundsWrite),
+            _ => Err(format!("Unknown API key scope: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for ApiKeyScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ApiKeyScope::PaymentsWrite => "payments:write",
+            ApiKeyScope::PaymentsRead => "payments:read",
+            ApiKeyScope::RefundsWrite => "refunds:write",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A merchant API key; the secret itself is only shown when the key is created
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: String,
+    pub merchant_id: String,
+    pub name: String,
+    /// Start of the key, e.g. `zk_3f9a1c0b7d2e`, to tell keys apart
+    pub prefix: String,
+    pub scopes: Vec<ApiKeyScope>,
+    /// User who created the key
+    pub created_by: String,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+/// Events merchants can subscribe webhook endpoints to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WebhookEventType {
+    #[serde(rename = "payment.completed")]
+    PaymentCompleted,
+    #[serde(rename = "payment.failed")]
+    PaymentFailed,
+    #[serde(rename = "refund.created")]
+    RefundCreated,
+    #[serde(rename = "settlement.paid")]
+    SettlementPaid,
+}
+
+impl FromStr for WebhookEventType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "payment.completed" => Ok(WebhookEventType::PaymentCompleted),
+            "payment.failed" => Ok(WebhookEventType::PaymentFailed),
+            "refund.created" => Ok(WebhookEventType::RefundCreated),
+            "settlement.paid" => Ok(WebhookEventType::SettlementPaid),
+            _ => Err(format!("Unknown webhook event type: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for WebhookEventType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            WebhookEventType::PaymentCompleted => "payment.completed",
+            WebhookEventType::PaymentFailed => "payment.failed",
+            WebhookEventType::RefundCreated => "refund.created",
+            WebhookEventType::SettlementPaid => "settlement.paid",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A merchant endpoint that receives signed webhooks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEndpoint {
+    pub id: String,
+    pub merchant_id: String,
+    pub url: String,
+    pub event_types: Vec<WebhookEventType>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub disabled_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookDeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+impl FromStr for WebhookDeliveryStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(WebhookDeliveryStatus::Pending),
+            "delivered" => Ok(WebhookDeliveryStatus::Delivered),
+            "failed" => Ok(WebhookDeliveryStatus::Failed),
+            _ => Err(format!("Unknown webhook delivery status: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for WebhookDeliveryStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            WebhookDeliveryStatus::Pending => "pending",
+            WebhookDeliveryStatus::Delivered => "delivered",
+            WebhookDeliveryStatus::Failed => "failed",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// One event queued for one endpoint, with the outcome of its latest attempt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub endpoint_id: String,
+    pub event_id: String,
+    pub event_type: WebhookEventType,
+    /// The body that is signed and sent
+    pub payload: serde_json::Value,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: i32,
+    /// When the next attempt is due, while the delivery is pending
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Pending,
+    Completed,
+    /// Out of attempts, waiting for an admin to retry it
+    Dead,
+}
+
+impl FromStr for JobStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(JobStatus::Pending),
+            "completed" => Ok(JobStatus::Completed),
+            "dead" => Ok(JobStatus::Dead),
+            _ => Err(format!("Unknown job status: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            JobStatus::Pending => "pending",
+            JobStatus::Completed => "completed",
+            JobStatus::Dead => "dead",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A background job as stored in the queue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedJob {
+    pub id: String,
+    pub job_type: String,
+    pub payload: serde_json::Value,
+    pub status: JobStatus,
+    pub attempts: i32,
+    /// When the job is next due, while it is pending
+    pub run_at: DateTime<Utc>,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// One recorded status change of a payment, transfer, withdrawal, deposit or settlement
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusEvent {
+    pub id: String,
+    /// `None` for the event recorded when the entity was created
+    pub from_status: Option<String>,
+    pub to_status: String,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Balance {
     pub id: String,
@@ -208,6 +799,9 @@ pub struct CreateAuditLogParams {
     pub metadata: Option<serde_json::Value>,
     pub ip_address: Option<String>,
     pub user_agent: Option<String>,
+    /// When the audited action happened; defaults to when the entry is written
+    #[serde(default)]
+    pub occurred_at: Option<DateTime<Utc>>,
 }
 
 // DTOs for Audit Log API
@@ -294,6 +888,113 @@ pub struct BridgeTransaction {
     pub updated_at: DateTime<Utc>,
 }
 
+/// How thoroughly the anchor has verified a user, in increasing order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KycLevel {
+    None,
+    /// Name and contact details
+    Basic,
+    /// Basic plus date of birth, address and an identity document
+    Full,
+}
+
+impl KycLevel {
+    /// SEP-9 fields a submission for this level must include
+    pub fn required_fields(&self) -> &'static [&'static str] {
+        match self {
+            KycLevel::None => &[],
+            KycLevel::Basic => &["first_name", "last_name", "email_address"],
+            KycLevel::Full => &[
+                "first_name",
+                "last_name",
+                "email_address",
+                "birth_date",
+                "address",
+                "id_type",
+                "id_number",
+            ],
+        }
+    }
+}
+
+impl FromStr for KycLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(KycLevel::None),
+            "basic" => Ok(KycLevel::Basic),
+            "full" => Ok(KycLevel::Full),
+            _ => Err(format!("Unknown KYC level: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for KycLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            KycLevel::None => "none",
+            KycLevel::Basic => "basic",
+            KycLevel::Full => "full",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// SEP-12 status of a user's latest KYC submission
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KycStatus {
+    Processing,
+    NeedsInfo,
+    Accepted,
+    Rejected,
+}
+
+impl FromStr for KycStatus {
+    type Err = String;
+
+    /// Accepts both our lowercase form and SEP-12's `NEEDS_INFO` style
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "processing" => Ok(KycStatus::Processing),
+            "needs_info" => Ok(KycStatus::NeedsInfo),
+            "accepted" => Ok(KycStatus::Accepted),
+            "rejected" => Ok(KycStatus::Rejected),
+            _ => Err(format!("Unknown KYC status: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for KycStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            KycStatus::Processing => "processing",
+            KycStatus::NeedsInfo => "needs_info",
+            KycStatus::Accepted => "accepted",
+            KycStatus::Rejected => "rejected",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KycProfile {
+    pub user_id: String,
+    /// The anchor's SEP-12 customer id
+    pub customer_id: Option<String>,
+    /// Level the anchor has accepted
+    pub level: KycLevel,
+    /// Level of the latest submission
+    pub requested_level: KycLevel,
+    pub status: KycStatus,
+    /// Why the anchor needs more information or rejected the submission
+    pub message: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum NotificationType {
     SYSTEM,
@@ -355,12 +1056,16 @@ pub struct RateLimitConfig {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BuildTransactionDto {
+    pub source_account: String,
+    /// Current sequence number of `source_account`; the transaction uses the next one
+    pub sequence: i64,
     pub contract_id: String,
     pub method: String,
+    /// Tagged arguments, see `stellar::scval`
     pub args: Vec<serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TransactionStatus {
     PENDING,
     CONFIRMED,
@@ -1,38 +1,1075 @@
-use crate::{config::Config, models::Withdrawal};
+use crate::{
+    api_error::ApiError,
+    config::Config,
+    stellar::{
+        keypair::verify_signature,
+        sep10::{self, ChallengeParams},
+        strkey, transaction, Keypair,
+    },
+};
+use base64::{
+    engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD},
+    Engine as _,
+};
+use dashmap::DashMap;
 use deadpool_postgres::Pool;
-use std::sync::Arc;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+
+/// Fetch a new SEP-10 token this long before the cached one expires, in seconds
+const TOKEN_REFRESH_MARGIN_SECS: i64 = 60;
+
+/// Header anchors sign SEP-24 and SEP-31 callbacks with
+pub const CALLBACK_SIGNATURE_HEADER: &str = "Signature";
+
+/// Older name of [`CALLBACK_SIGNATURE_HEADER`], still sent by some anchors
+pub const LEGACY_CALLBACK_SIGNATURE_HEADER: &str = "X-Stellar-Signature";
 
 #[derive(Clone)]
 #[allow(dead_code)]
 pub struct AnchorService {
     db_pool: Arc<Pool>,
     config: Config,
+    http: reqwest::Client,
+    /// SEP-10 tokens by authenticated account
+    tokens: Arc<DashMap<String, CachedToken>>,
+}
+
+struct CachedToken {
+    token: String,
+    expires_at: i64,
+}
+
+#[derive(Deserialize)]
+struct ChallengeResponse {
+    transaction: String,
+    network_passphrase: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    token: String,
+}
+
+/// Parameters of a SEP-24 interactive withdrawal
+#[derive(Debug, Serialize)]
+pub struct StartWithdrawalRequest {
+    pub asset_code: String,
+    /// Stellar account the withdrawn funds are sent from
+    pub account: String,
+    /// Amount in whole units of the asset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<String>,
+    /// Off-chain destination, e.g. a bank account, when already known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dest: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dest_extra: Option<String>,
+    /// URL the anchor posts signed status changes to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_change_callback: Option<String>,
+}
+
+/// Parameters of a SEP-24 interactive deposit
+#[derive(Debug, Serialize)]
+pub struct StartDepositRequest {
+    pub asset_code: String,
+    /// Stellar account the deposited funds are sent to
+    pub account: String,
+    /// Amount in whole units of the asset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<String>,
+}
+
+/// Deposit or withdrawal limits and fees the anchor publishes for one asset
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Sep24AssetInfo {
+    #[serde(default)]
+    pub enabled: bool,
+    pub min_amount: Option<f64>,
+    pub max_amount: Option<f64>,
+    pub fee_fixed: Option<f64>,
+    pub fee_percent: Option<f64>,
+}
+
+/// The anchor's SEP-24 `GET /info` response
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Sep24Info {
+    #[serde(default)]
+    pub deposit: HashMap<String, Sep24AssetInfo>,
+    #[serde(default)]
+    pub withdraw: HashMap<String, Sep24AssetInfo>,
+    #[serde(default)]
+    pub fee: serde_json::Value,
+    #[serde(default)]
+    pub features: serde_json::Value,
+}
+
+/// A field the receiving anchor asks for in a SEP-31 transaction
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Sep31Field {
+    pub description: Option<String>,
+    /// Accepted values, when the field is a choice
+    pub choices: Option<Vec<String>>,
+    #[serde(default)]
+    pub optional: bool,
+}
+
+/// SEP-12 customer types the anchor requires for one side of a payment
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Sep31CustomerTypes {
+    #[serde(default)]
+    pub types: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Sep31Customers {
+    #[serde(default)]
+    pub sender: Sep31CustomerTypes,
+    #[serde(default)]
+    pub receiver: Sep31CustomerTypes,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Sep31Fields {
+    #[serde(default)]
+    pub transaction: HashMap<String, Sep31Field>,
+}
+
+/// What the receiving anchor requires to pay out one asset
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Sep31AssetInfo {
+    #[serde(default)]
+    pub enabled: bool,
+    pub min_amount: Option<f64>,
+    pub max_amount: Option<f64>,
+    pub fee_fixed: Option<f64>,
+    pub fee_percent: Option<f64>,
+    #[serde(default)]
+    pub sep12: Sep31Customers,
+    #[serde(default)]
+    pub fields: Sep31Fields,
+}
+
+/// The receiving anchor's SEP-31 `GET /info` response
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Sep31Info {
+    #[serde(default)]
+    pub receive: HashMap<String, Sep31AssetInfo>,
+}
+
+/// Body of a SEP-31 `POST /transactions`
+#[derive(Debug, Serialize)]
+pub struct Sep31TransactionRequest {
+    /// Amount in whole units of the asset
+    pub amount: String,
+    pub asset_code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset_issuer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sender_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub receiver_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fields: Option<Sep31TransactionFields>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Sep31TransactionFields {
+    pub transaction: HashMap<String, String>,
+}
+
+/// Response to a SEP-31 `POST /transactions`
+///
+/// Anchors on older versions of the protocol return where to send the
+/// payment here; newer ones only report it on the transaction itself.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Sep31CreatedTransaction {
+    pub id: String,
+    pub stellar_account_id: Option<String>,
+    pub stellar_memo: Option<String>,
+    pub stellar_memo_type: Option<String>,
+}
+
+/// A payout as reported by the anchor's SEP-31 `GET /transactions/:id`
+#[derive(Debug, Clone, Deserialize)]
+pub struct Sep31Transaction {
+    pub id: String,
+    pub status: String,
+    pub amount_in: Option<String>,
+    pub amount_out: Option<String>,
+    pub amount_fee: Option<String>,
+    /// Account the sender's payment must be sent to
+    pub stellar_account_id: Option<String>,
+    pub stellar_memo: Option<String>,
+    /// `text`, `id` or `hash`
+    pub stellar_memo_type: Option<String>,
+    pub stellar_transaction_id: Option<String>,
+    /// Why the anchor is waiting on updated customer or transaction info
+    pub required_info_message: Option<String>,
+    /// What the anchor returned of the payment it received
+    #[serde(default)]
+    pub refunds: Option<AnchorRefunds>,
+}
+
+#[derive(Deserialize)]
+struct Sep31TransactionResponse {
+    transaction: Sep31Transaction,
+}
+
+/// A customer as reported by the anchor's SEP-12 `GET /customer`
+#[derive(Debug, Clone, Deserialize)]
+pub struct Sep12Customer {
+    pub id: Option<String>,
+    /// `ACCEPTED`, `PROCESSING`, `NEEDS_INFO` or `REJECTED`
+    pub status: String,
+    pub message: Option<String>,
+    /// Fields the anchor still needs
+    #[serde(default)]
+    pub fields: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct Sep12PutResponse {
+    id: String,
+}
+
+/// Parameters of a SEP-38 `GET /price` or `POST /quote`
+///
+/// Assets use SEP-38's `stellar:CODE:ISSUER` form and amounts are in whole
+/// units; exactly one of `sell_amount` and `buy_amount` is set.
+#[derive(Debug, Clone, Serialize)]
+pub struct Sep38QuoteRequest {
+    pub sell_asset: String,
+    pub buy_asset: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sell_amount: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buy_amount: Option<String>,
+    /// `sep6`, `sep24` or `sep31`
+    pub context: String,
+}
+
+/// The anchor's SEP-38 `GET /price` response: an indicative price, not honoured later
+#[derive(Debug, Clone, Deserialize)]
+pub struct Sep38Price {
+    /// Price of one unit of the buy asset in the sell asset, fees included
+    pub total_price: String,
+    pub sell_amount: String,
+    pub buy_amount: String,
+}
+
+/// A firm SEP-38 quote, honoured by the anchor until `expires_at`
+#[derive(Debug, Clone, Deserialize)]
+pub struct Sep38Quote {
+    pub id: String,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+    pub total_price: String,
+    pub sell_amount: String,
+    pub buy_amount: String,
+}
+
+/// Response to `POST /transactions/{deposit,withdraw}/interactive`
+#[derive(Debug, Clone, Deserialize)]
+pub struct InteractiveResponse {
+    pub id: String,
+    pub url: String,
+}
+
+/// A transaction as reported by the anchor's `GET /transaction`
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnchorTransaction {
+    pub id: String,
+    /// `deposit` or `withdrawal`
+    pub kind: Option<String>,
+    pub status: String,
+    pub amount_in: Option<String>,
+    pub amount_out: Option<String>,
+    pub stellar_transaction_id: Option<String>,
+    /// Account the user's payment must be sent to
+    pub withdraw_anchor_account: Option<String>,
+    pub withdraw_memo: Option<String>,
+    /// `text`, `id` or `hash`
+    pub withdraw_memo_type: Option<String>,
+    pub message: Option<String>,
+    /// What the anchor returned of the payment it received
+    #[serde(default)]
+    pub refunds: Option<AnchorRefunds>,
+}
+
+/// The `refunds` of a SEP-24 or SEP-31 transaction
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnchorRefunds {
+    #[serde(default)]
+    pub payments: Vec<AnchorRefundPayment>,
+}
+
+impl AnchorRefunds {
+    /// Hashes of the refund payments made on Stellar, each once
+    pub fn stellar_payment_ids(&self) -> Vec<&str> {
+        let mut ids: Vec<&str> = Vec::new();
+        for payment in &self.payments {
+            if payment.id_type == "stellar" && !ids.contains(&payment.id.as_str()) {
+                ids.push(&payment.id);
+            }
+        }
+        ids
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnchorRefundPayment {
+    /// Stellar transaction hash when `id_type` is `stellar`
+    pub id: String,
+    /// `stellar` or `external`
+    pub id_type: String,
+}
+
+/// What a SEP-24 transaction status means for the deposit or withdrawal it backs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnchorProgress {
+    /// The user has not finished the interactive flow, or the anchor waits on them
+    Waiting,
+    /// The anchor expects the user's payment: on Stellar for a withdrawal,
+    /// off-chain for a deposit
+    AwaitingPayment,
+    Processing,
+    Completed,
+    Failed,
+}
+
+impl AnchorProgress {
+    pub fn from_status(status: &str) -> Self {
+        match status {
+            "pending_user_transfer_start" => AnchorProgress::AwaitingPayment,
+            "pending_user_transfer_complete"
+            | "pending_external"
+            | "pending_anchor"
+            | "pending_stellar" => AnchorProgress::Processing,
+            "completed" => AnchorProgress::Completed,
+            "refunded" | "expired" | "error" | "no_market" | "too_small" | "too_large" => {
+                AnchorProgress::Failed
+            }
+            // incomplete, pending_trust, pending_user and anything unknown
+            _ => AnchorProgress::Waiting,
+        }
+    }
+
+    /// The same for a SEP-31 payout, where the platform is the sender
+    pub fn from_sep31_status(status: &str) -> Self {
+        match status {
+            "pending_sender" => AnchorProgress::AwaitingPayment,
+            "pending_stellar" | "pending_receiver" | "pending_external" => {
+                AnchorProgress::Processing
+            }
+            "completed" => AnchorProgress::Completed,
+            "refunded" | "expired" | "error" => AnchorProgress::Failed,
+            // pending_customer_info_update, pending_transaction_info_update and anything unknown
+            _ => AnchorProgress::Waiting,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TransactionResponse {
+    transaction: AnchorTransaction,
+}
+
+#[derive(Deserialize)]
+struct AnchorErrorResponse {
+    error: String,
 }
 
 impl AnchorService {
     pub fn new(db_pool: Arc<Pool>, config: Config) -> Self {
-        Self { db_pool, config }
+        Self {
+            db_pool,
+            config,
+            http: reqwest::Client::new(),
+            tokens: Arc::new(DashMap::new()),
+        }
     }
 
-    // Placeholder implementations
-    pub async fn process_sep24_deposit(
+    /// SEP-10 token for the keypair's account, reusing a cached one until it expires
+    ///
+    /// The challenge must be issued by the configured signing key for the
+    /// configured home domain, be within its time bounds and be bound to the
+    /// web authentication endpoint before it is countersigned.
+    pub async fn authenticate(&self, keypair: &Keypair) -> Result<String, ApiError> {
+        let account = keypair.address();
+        let now = chrono::Utc::now().timestamp();
+        if let Some(cached) = self.tokens.get(&account) {
+            if cached.expires_at - TOKEN_REFRESH_MARGIN_SECS > now {
+                return Ok(cached.token.clone());
+            }
+        }
+
+        let anchor = &self.config.anchor_config;
+        if anchor.signing_key.is_empty() {
+            return Err(ApiError::Anchor(
+                "No SEP-10 signing key is configured for the anchor".to_string(),
+            ));
+        }
+        let network_passphrase = &self.config.stellar_network.passphrase;
+
+        let response = self
+            .http
+            .get(&anchor.web_auth_url)
+            .query(&[
+                ("account", account.as_str()),
+                ("home_domain", anchor.home_domain.as_str()),
+            ])
+            .send()
+            .await
+            .map_err(|e| ApiError::Anchor(format!("Challenge request failed: {}", e)))?;
+        let challenge: ChallengeResponse = Self::parse(response).await?;
+
+        if challenge
+            .network_passphrase
+            .as_deref()
+            .is_some_and(|passphrase| passphrase != network_passphrase)
+        {
+            return Err(ApiError::Anchor(
+                "SEP-10 challenge is for another network".to_string(),
+            ));
+        }
+
+        let web_auth_domain = web_auth_domain(&anchor.web_auth_url)?;
+        let mut envelope = sep10::verify_challenge(
+            &challenge.transaction,
+            &ChallengeParams {
+                server_key: &anchor.signing_key,
+                client_account: &account,
+                home_domain: &anchor.home_domain,
+                web_auth_domain: &web_auth_domain,
+                network_passphrase,
+            },
+            now as u64,
+        )?;
+        transaction::sign_envelope(&mut envelope, keypair, network_passphrase)?;
+
+        let response = self
+            .http
+            .post(&anchor.web_auth_url)
+            .json(&serde_json::json!({
+                "transaction": transaction::encode_envelope(&envelope)?,
+            }))
+            .send()
+            .await
+            .map_err(|e| ApiError::Anchor(format!("Token request failed: {}", e)))?;
+        let TokenResponse { token } = Self::parse(response).await?;
+
+        match jwt_expiry(&token) {
+            Some(expires_at) => {
+                self.tokens.insert(
+                    account,
+                    CachedToken {
+                        token: token.clone(),
+                        expires_at,
+                    },
+                );
+            }
+            None => tracing::warn!("SEP-10 token for {} has no expiry, not caching it", account),
+        }
+
+        Ok(token)
+    }
+
+    /// SEP-10 token for the platform's own account
+    pub async fn authenticate_platform(&self) -> Result<String, ApiError> {
+        self.authenticate(&self.platform_keypair()?).await
+    }
+
+    /// Keypair of the account the platform transacts with the anchor as
+    pub fn platform_keypair(&self) -> Result<Keypair, ApiError> {
+        let secret = self
+            .config
+            .anchor_config
+            .platform_secret
+            .as_deref()
+            .filter(|secret| !secret.is_empty())
+            .ok_or_else(|| {
+                ApiError::Anchor("No platform key is configured for the anchor".to_string())
+            })?;
+
+        Keypair::from_secret_seed(secret)
+    }
+
+    /// Assets the anchor supports for SEP-24 deposits and withdrawals
+    pub async fn get_info(&self) -> Result<Sep24Info, ApiError> {
+        let response = self
+            .http
+            .get(self.sep24_endpoint("info"))
+            .send()
+            .await
+            .map_err(|e| ApiError::Anchor(format!("Info request failed: {}", e)))?;
+
+        Self::parse(response).await
+    }
+
+    /// Start a SEP-24 interactive deposit, returning the anchor's id and page
+    ///
+    /// `token` is the SEP-10 token of the account the deposit is made to.
+    pub async fn start_deposit(
         &self,
-        _request: serde_json::Value,
-    ) -> Result<(), crate::api_error::ApiError> {
-        Ok(())
+        token: &str,
+        request: &StartDepositRequest,
+    ) -> Result<InteractiveResponse, ApiError> {
+        let response = self
+            .http
+            .post(self.sep24_endpoint("transactions/deposit/interactive"))
+            .bearer_auth(token)
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| ApiError::Anchor(format!("Deposit request failed: {}", e)))?;
+
+        Self::parse(response).await
     }
 
-    pub async fn process_sep31_payout(
+    /// Start a SEP-24 interactive withdrawal, returning the anchor's id and page
+    ///
+    /// `token` is the SEP-10 token of the account the withdrawal is made from.
+    pub async fn start_withdrawal(
         &self,
-        _withdrawal: &Withdrawal,
-    ) -> Result<String, crate::api_error::ApiError> {
-        Ok("anchor_tx_123".to_string())
+        token: &str,
+        request: &StartWithdrawalRequest,
+    ) -> Result<InteractiveResponse, ApiError> {
+        let response = self
+            .http
+            .post(self.sep24_endpoint("transactions/withdraw/interactive"))
+            .bearer_auth(token)
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| ApiError::Anchor(format!("Withdrawal request failed: {}", e)))?;
+
+        Self::parse(response).await
     }
 
-    pub async fn check_kyc_status(
+    /// Current state of an anchor transaction
+    pub async fn get_transaction(
         &self,
-        _user_id: &str,
-    ) -> Result<bool, crate::api_error::ApiError> {
-        Ok(true)
+        token: &str,
+        anchor_tx_id: &str,
+    ) -> Result<AnchorTransaction, ApiError> {
+        let response = self
+            .http
+            .get(self.sep24_endpoint("transaction"))
+            .bearer_auth(token)
+            .query(&[("id", anchor_tx_id)])
+            .send()
+            .await
+            .map_err(|e| ApiError::Anchor(format!("Transaction lookup failed: {}", e)))?;
+
+        Ok(Self::parse::<TransactionResponse>(response)
+            .await?
+            .transaction)
+    }
+
+    /// Assets the receiving anchor pays out over SEP-31 and what it requires for each
+    pub async fn get_sep31_info(&self, token: &str) -> Result<Sep31Info, ApiError> {
+        let response = self
+            .http
+            .get(self.sep31_endpoint("info"))
+            .bearer_auth(token)
+            .send()
+            .await
+            .map_err(|e| ApiError::Anchor(format!("SEP-31 info request failed: {}", e)))?;
+
+        Self::parse(response).await
+    }
+
+    /// Create a SEP-31 payout; `token` is the platform's SEP-10 token
+    pub async fn create_sep31_transaction(
+        &self,
+        token: &str,
+        request: &Sep31TransactionRequest,
+    ) -> Result<Sep31CreatedTransaction, ApiError> {
+        let response = self
+            .http
+            .post(self.sep31_endpoint("transactions"))
+            .bearer_auth(token)
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| ApiError::Anchor(format!("SEP-31 transaction request failed: {}", e)))?;
+
+        Self::parse(response).await
+    }
+
+    /// Current state of a SEP-31 payout
+    pub async fn get_sep31_transaction(
+        &self,
+        token: &str,
+        anchor_tx_id: &str,
+    ) -> Result<Sep31Transaction, ApiError> {
+        let response = self
+            .http
+            .get(self.sep31_endpoint(&format!("transactions/{}", anchor_tx_id)))
+            .bearer_auth(token)
+            .send()
+            .await
+            .map_err(|e| ApiError::Anchor(format!("SEP-31 transaction lookup failed: {}", e)))?;
+
+        Ok(Self::parse::<Sep31TransactionResponse>(response)
+            .await?
+            .transaction)
+    }
+
+    /// Ask the anchor to call `url` whenever the payout's status changes
+    pub async fn register_sep31_callback(
+        &self,
+        token: &str,
+        anchor_tx_id: &str,
+        url: &str,
+    ) -> Result<(), ApiError> {
+        let response = self
+            .http
+            .put(self.sep31_endpoint(&format!("transactions/{}/callback", anchor_tx_id)))
+            .bearer_auth(token)
+            .json(&serde_json::json!({ "url": url }))
+            .send()
+            .await
+            .map_err(|e| ApiError::Anchor(format!("SEP-31 callback registration failed: {}", e)))?;
+
+        Self::success_body(response).await.map(|_| ())
+    }
+
+    /// A SEP-12 customer, by the anchor's id once known or else by account
+    ///
+    /// `token` is the SEP-10 token of the customer's account.
+    pub async fn get_customer(
+        &self,
+        token: &str,
+        customer_id: Option<&str>,
+        account: &str,
+    ) -> Result<Sep12Customer, ApiError> {
+        let query = match customer_id {
+            Some(id) => [("id", id)],
+            None => [("account", account)],
+        };
+        let response = self
+            .http
+            .get(self.sep12_endpoint("customer"))
+            .bearer_auth(token)
+            .query(&query)
+            .send()
+            .await
+            .map_err(|e| ApiError::Anchor(format!("Customer lookup failed: {}", e)))?;
+
+        Self::parse(response).await
+    }
+
+    /// Create or update a SEP-12 customer with SEP-9 `fields`, returning its id
+    pub async fn put_customer(
+        &self,
+        token: &str,
+        customer_id: Option<&str>,
+        account: &str,
+        fields: &HashMap<String, String>,
+    ) -> Result<String, ApiError> {
+        let mut body = serde_json::json!(fields);
+        body["account"] = serde_json::json!(account);
+        if let Some(id) = customer_id {
+            body["id"] = serde_json::json!(id);
+        }
+
+        let response = self
+            .http
+            .put(self.sep12_endpoint("customer"))
+            .bearer_auth(token)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| ApiError::Anchor(format!("Customer update failed: {}", e)))?;
+
+        Ok(Self::parse::<Sep12PutResponse>(response).await?.id)
+    }
+
+    /// Ask the anchor to call `url` whenever the customer's status changes
+    pub async fn register_customer_callback(
+        &self,
+        token: &str,
+        customer_id: &str,
+        url: &str,
+    ) -> Result<(), ApiError> {
+        let response = self
+            .http
+            .put(self.sep12_endpoint("customer/callback"))
+            .bearer_auth(token)
+            .json(&serde_json::json!({ "id": customer_id, "url": url }))
+            .send()
+            .await
+            .map_err(|e| {
+                ApiError::Anchor(format!("Customer callback registration failed: {}", e))
+            })?;
+
+        Self::success_body(response).await.map(|_| ())
+    }
+
+    /// Indicative SEP-38 price for a conversion
+    pub async fn get_sep38_price(
+        &self,
+        request: &Sep38QuoteRequest,
+    ) -> Result<Sep38Price, ApiError> {
+        let response = self
+            .http
+            .get(self.sep38_endpoint("price")?)
+            .query(request)
+            .send()
+            .await
+            .map_err(|e| ApiError::Anchor(format!("SEP-38 price request failed: {}", e)))?;
+
+        Self::parse(response).await
+    }
+
+    /// Request a firm SEP-38 quote; `token` is the SEP-10 token of the account paying
+    pub async fn create_sep38_quote(
+        &self,
+        token: &str,
+        request: &Sep38QuoteRequest,
+    ) -> Result<Sep38Quote, ApiError> {
+        let response = self
+            .http
+            .post(self.sep38_endpoint("quote")?)
+            .bearer_auth(token)
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| ApiError::Anchor(format!("SEP-38 quote request failed: {}", e)))?;
+
+        Self::parse(response).await
+    }
+
+    fn sep24_endpoint(&self, path: &str) -> String {
+        format!(
+            "{}/{}",
+            self.config.anchor_config.sep24_url.trim_end_matches('/'),
+            path
+        )
+    }
+
+    fn sep12_endpoint(&self, path: &str) -> String {
+        format!(
+            "{}/{}",
+            self.config.anchor_config.sep12_url.trim_end_matches('/'),
+            path
+        )
+    }
+
+    fn sep31_endpoint(&self, path: &str) -> String {
+        format!(
+            "{}/{}",
+            self.config.anchor_config.sep31_url.trim_end_matches('/'),
+            path
+        )
+    }
+
+    fn sep38_endpoint(&self, path: &str) -> Result<String, ApiError> {
+        let base = self
+            .config
+            .anchor_config
+            .sep38_url
+            .as_deref()
+            .ok_or_else(|| ApiError::Anchor("The anchor has no SEP-38 quote server".to_string()))?;
+
+        Ok(format!("{}/{}", base.trim_end_matches('/'), path))
+    }
+
+    /// Accept a signed callback once, by its signature
+    ///
+    /// A signature seen before is a replay and rejected with a conflict.
+    /// Signatures are only kept while their timestamp could still pass
+    /// [`verify_callback_signature`].
+    pub async fn claim_callback(&self, signature: &str) -> Result<(), ApiError> {
+        let client = self.db_pool.get().await?;
+
+        let retention_secs = (self.config.anchor_config.callback_max_age_secs * 2) as f64;
+        client
+            .execute(
+                "DELETE FROM anchor_callback_signatures WHERE received_at < NOW() - make_interval(secs => $1)",
+                &[&retention_secs],
+            )
+            .await?;
+        let inserted = client
+            .execute(
+                "INSERT INTO anchor_callback_signatures (signature) VALUES ($1) ON CONFLICT DO NOTHING",
+                &[&signature],
+            )
+            .await?;
+        if inserted == 0 {
+            return Err(ApiError::Conflict(
+                "Callback has already been received".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Forget a claimed callback whose handling failed, so the anchor's retry is accepted
+    pub async fn release_callback(&self, signature: &str) -> Result<(), ApiError> {
+        let client = self.db_pool.get().await?;
+        client
+            .execute(
+                "DELETE FROM anchor_callback_signatures WHERE signature = $1",
+                &[&signature],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Decode a successful response, surfacing the anchor's `error` message otherwise
+    async fn parse<T: DeserializeOwned>(response: reqwest::Response) -> Result<T, ApiError> {
+        let body = Self::success_body(response).await?;
+
+        serde_json::from_str(&body)
+            .map_err(|e| ApiError::Anchor(format!("Invalid anchor response: {}", e)))
+    }
+
+    /// Body of a successful response, which may be empty
+    async fn success_body(response: reqwest::Response) -> Result<String, ApiError> {
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| ApiError::Anchor(format!("Failed to read anchor response: {}", e)))?;
+
+        if !status.is_success() {
+            let message = serde_json::from_str::<AnchorErrorResponse>(&body)
+                .map(|error| error.error)
+                .unwrap_or(body);
+            return Err(if status.is_client_error() {
+                ApiError::Validation(format!("Anchor rejected the request: {}", message))
+            } else {
+                ApiError::Anchor(format!("{} {}", status, message))
+            });
+        }
+
+        Ok(body)
+    }
+}
+
+/// Check a callback's `Signature: t=<timestamp>, s=<signature>` header against its body
+///
+/// As in SEP-24 and SEP-31, `s` is the base64 ed25519 signature of
+/// `{t}.{host}.{body}` by the anchor's `SIGNING_KEY`, `host` being the host of
+/// the callback URL, and `t` must be within `max_age_secs` of `now`. Returns
+/// `s`, which identifies the delivery for replay protection.
+pub fn verify_callback_signature(
+    signing_key: &str,
+    host: &str,
+    header: &str,
+    body: &[u8],
+    now: i64,
+    max_age_secs: i64,
+) -> Result<String, ApiError> {
+    let invalid =
+        |reason: &str| ApiError::Authentication(format!("Invalid callback signature: {}", reason));
+
+    let mut timestamp = None;
+    let mut signature = None;
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+            Some(("s", value)) => signature = Some(value),
+            _ => {}
+        }
+    }
+    let timestamp = timestamp.ok_or_else(|| invalid("missing timestamp"))?;
+    let signature = signature.ok_or_else(|| invalid("missing signature"))?;
+
+    if (now - timestamp).abs() > max_age_secs {
+        return Err(invalid("timestamp is outside the accepted window"));
+    }
+
+    let public_key = strkey::decode_account_id(signing_key)
+        .map_err(|e| ApiError::Anchor(format!("Invalid anchor signing key: {}", e)))?;
+    let sig = BASE64
+        .decode(signature)
+        .map_err(|_| invalid("signature is not base64"))?;
+    if !verify_signature(&public_key, &callback_payload(timestamp, host, body), &sig) {
+        return Err(invalid("signature does not match"));
+    }
+
+    Ok(signature.to_string())
+}
+
+/// `Signature` header value for a callback body, as the anchor computes it
+pub fn sign_callback(signing_key: &Keypair, timestamp: i64, host: &str, body: &[u8]) -> String {
+    let sig = signing_key.sign(&callback_payload(timestamp, host, body));
+
+    format!("t={}, s={}", timestamp, BASE64.encode(sig))
+}
+
+fn callback_payload(timestamp: i64, host: &str, body: &[u8]) -> Vec<u8> {
+    let mut payload = format!("{}.{}.", timestamp, host).into_bytes();
+    payload.extend_from_slice(body);
+    payload
+}
+
+/// Host a callback URL is signed for
+pub fn callback_host(callback_url: &str) -> Result<String, ApiError> {
+    reqwest::Url::parse(callback_url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .ok_or_else(|| ApiError::Anchor(format!("Invalid callback URL {}", callback_url)))
+}
+
+/// Host (and non-default port) of the web authentication endpoint
+fn web_auth_domain(web_auth_url: &str) -> Result<String, ApiError> {
+    let url = reqwest::Url::parse(web_auth_url)
+        .map_err(|e| ApiError::Anchor(format!("Invalid web auth URL {}: {}", web_auth_url, e)))?;
+    let host = url
+        .host_str()
+        .ok_or_else(|| ApiError::Anchor(format!("Web auth URL {} has no host", web_auth_url)))?;
+
+    Ok(match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    })
+}
+
+/// `exp` claim of a JWT, read without verifying it: the anchor verifies its own tokens
+fn jwt_expiry(token: &str) -> Option<i64> {
+    let payload = URL_SAFE_NO_PAD.decode(token.split('.').nth(1)?).ok()?;
+    serde_json::from_slice::<serde_json::Value>(&payload)
+        .ok()?
+        .get("exp")?
+        .as_i64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_callback_signature() {
+        let anchor = Keypair::from_seed([9u8; 32]).unwrap();
+        let key = anchor.address();
+        let other = Keypair::from_seed([7u8; 32]).unwrap().address();
+        let host = "api.example.com";
+        let body = br#"{"transaction":{"id":"abc","status":"completed"}}"#;
+        let header = sign_callback(&anchor, 1_700_000_000, host, body);
+
+        let signature =
+            verify_callback_signature(&key, host, &header, body, 1_700_000_100, 300).unwrap();
+        assert!(header.ends_with(&signature));
+
+        // Tampered body, other key or host, stale or future timestamp, malformed header
+        for (key, host, header, body, now) in [
+            (
+                key.as_str(),
+                host,
+                header.as_str(),
+                &br#"{"transaction":{"id":"abc","status":"error"}}"#[..],
+                1_700_000_000,
+            ),
+            (
+                other.as_str(),
+                host,
+                header.as_str(),
+                &body[..],
+                1_700_000_000,
+            ),
+            (
+                key.as_str(),
+                "evil.example.com",
+                header.as_str(),
+                &body[..],
+                1_700_000_000,
+            ),
+            (
+                key.as_str(),
+                host,
+                header.as_str(),
+                &body[..],
+                1_700_000_301,
+            ),
+            (
+                key.as_str(),
+                host,
+                header.as_str(),
+                &body[..],
+                1_699_999_699,
+            ),
+            (key.as_str(), host, "s=AAAA", &body[..], 1_700_000_000),
+            (key.as_str(), host, "t=1700000000", &body[..], 1_700_000_000),
+            (
+                key.as_str(),
+                host,
+                "t=1700000000, s=not base64!",
+                &body[..],
+                1_700_000_000,
+            ),
+        ] {
+            assert!(matches!(
+                verify_callback_signature(key, host, header, body, now, 300),
+                Err(ApiError::Authentication(_))
+            ));
+        }
+
+        assert_eq!(
+            callback_host("https://api.example.com:8443/callbacks/sep24").unwrap(),
+            host
+        );
+    }
+
+    #[test]
+    fn test_anchor_progress() {
+        use AnchorProgress::*;
+        assert_eq!(AnchorProgress::from_status("incomplete"), Waiting);
+        assert_eq!(AnchorProgress::from_status("pending_user"), Waiting);
+        assert_eq!(
+            AnchorProgress::from_status("pending_user_transfer_start"),
+            AwaitingPayment
+        );
+        assert_eq!(AnchorProgress::from_status("pending_anchor"), Processing);
+        assert_eq!(AnchorProgress::from_status("completed"), Completed);
+        assert_eq!(AnchorProgress::from_status("expired"), Failed);
+        assert_eq!(AnchorProgress::from_status("something_new"), Waiting);
+    }
+
+    #[test]
+    fn test_sep31_progress() {
+        use AnchorProgress::*;
+        assert_eq!(
+            AnchorProgress::from_sep31_status("pending_sender"),
+            AwaitingPayment
+        );
+        assert_eq!(
+            AnchorProgress::from_sep31_status("pending_receiver"),
+            Processing
+        );
+        assert_eq!(
+            AnchorProgress::from_sep31_status("pending_customer_info_update"),
+            Waiting
+        );
+        assert_eq!(AnchorProgress::from_sep31_status("completed"), Completed);
+        assert_eq!(AnchorProgress::from_sep31_status("refunded"), Failed);
+    }
+
+    #[test]
+    fn test_web_auth_domain() {
+        assert_eq!(
+            web_auth_domain("https://anchor.example.com/auth").unwrap(),
+            "anchor.example.com"
+        );
+        assert_eq!(
+            web_auth_domain("http://127.0.0.1:8080/auth").unwrap(),
+            "127.0.0.1:8080"
+        );
+        assert!(web_auth_domain("not a url").is_err());
+    }
+
+    #[test]
+    fn test_jwt_expiry() {
+        let payload = URL_SAFE_NO_PAD.encode(r#"{"sub":"GABC","exp":1767225600}"#);
+        assert_eq!(
+            jwt_expiry(&format!("header.{}.signature", payload)),
+            Some(1_767_225_600)
+        );
+        assert_eq!(jwt_expiry("not-a-jwt"), None);
     }
 }
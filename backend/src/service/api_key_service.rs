@@ -0,0 +1,264 @@
+use crate::{
+    api_error::ApiError,
+    models::{ApiKey, ApiKeyScope},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use deadpool_postgres::Pool;
+use ring::{
+    digest,
+    rand::{SecureRandom, SystemRandom},
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+const API_KEY_COLUMNS: &str =
+    "id, merchant_id, name, prefix, scopes, created_by, last_used_at, created_at, revoked_at";
+
+/// Start of every key, so leaked keys are easy to recognise
+const KEY_PREFIX: &str = "zk_";
+
+/// Random bytes in the public part of a key, which keys are looked up by
+const PREFIX_BYTES: usize = 6;
+
+/// Random bytes in the secret part of a key
+const SECRET_BYTES: usize = 32;
+
+/// Merchant API keys for server-to-server integrations
+///
+/// A key reads `zk_{prefix}_{secret}`. Only `zk_{prefix}` and a SHA-256 of
+/// the whole key are stored; the key is shown once, when it is created.
+/// Keys act for one merchant and only within their scopes.
+#[derive(Clone)]
+pub struct ApiKeyService {
+    db_pool: Arc<Pool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub scopes: Vec<ApiKeyScope>,
+}
+
+/// A newly created key, the only time its secret is available
+#[derive(Debug, Serialize)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub key: String,
+}
+
+impl ApiKeyService {
+    pub fn new(db_pool: Arc<Pool>) -> Self {
+        Self { db_pool }
+    }
+
+    pub async fn create_key(
+        &self,
+        merchant_id: &str,
+        created_by: &str,
+        request: CreateApiKeyRequest,
+    ) -> Result<CreatedApiKey, ApiError> {
+        let name = request.name.trim();
+        if name.is_empty() || name.chars().count() > 255 {
+            return Err(ApiError::Validation(
+                "name must be 1 to 255 characters".to_string(),
+            ));
+        }
+        let mut scopes = request.scopes;
+        scopes.sort_by_key(|scope| scope.to_string());
+        scopes.dedup();
+        if scopes.is_empty() {
+            return Err(ApiError::Validation(
+                "At least one scope is required".to_string(),
+            ));
+        }
+
+        let rng = SystemRandom::new();
+        let prefix = format!(
+            "{}{}",
+            KEY_PREFIX,
+            hex(&random_bytes::<PREFIX_BYTES>(&rng)?)
+        );
+        let key = format!(
+            "{}_{}",
+            prefix,
+            URL_SAFE_NO_PAD.encode(random_bytes::<SECRET_BYTES>(&rng)?)
+        );
+
+        let client = self.db_pool.get().await?;
+        let row = client
+            .query_one(
+                &format!(
+                    r#"
+                    INSERT INTO api_keys (id, merchant_id, name, prefix, key_hash, scopes, created_by)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7)
+                    RETURNING {}
+                    "#,
+                    API_KEY_COLUMNS
+                ),
+                &[
+                    &Uuid::new_v4(),
+                    &merchant_id,
+                    &name,
+                    &prefix,
+                    &hash_key(&key),
+                    &scopes.iter().map(ToString::to_string).collect::<Vec<_>>(),
+                    &created_by,
+                ],
+            )
+            .await?;
+
+        Ok(CreatedApiKey {
+            api_key: api_key_from_row(&row)?,
+            key,
+        })
+    }
+
+    /// The merchant's keys, revoked ones included, newest first
+    pub async fn list_keys(&self, merchant_id: &str) -> Result<Vec<ApiKey>, ApiError> {
+        let client = self.db_pool.get().await?;
+        let rows = client
+            .query(
+                &format!(
+                    "SELECT {} FROM api_keys WHERE merchant_id = $1 ORDER BY created_at DESC",
+                    API_KEY_COLUMNS
+                ),
+                &[&merchant_id],
+            )
+            .await?;
+
+        rows.iter().map(api_key_from_row).collect()
+    }
+
+    /// Stop accepting a key; revoking twice is a no-op
+    pub async fn revoke_key(&self, merchant_id: &str, key_id: Uuid) -> Result<ApiKey, ApiError> {
+        let client = self.db_pool.get().await?;
+        let row = client
+            .query_opt(
+                &format!(
+                    r#"
+                    UPDATE api_keys SET revoked_at = COALESCE(revoked_at, NOW())
+                    WHERE id = $1 AND merchant_id = $2
+                    RETURNING {}
+                    "#,
+                    API_KEY_COLUMNS
+                ),
+                &[&key_id, &merchant_id],
+            )
+            .await?
+            .ok_or_else(|| ApiError::NotFound("API key not found".to_string()))?;
+
+        api_key_from_row(&row)
+    }
+
+    /// The active key a presented `X-API-KEY` value belongs to
+    ///
+    /// Keys of inactive merchants are rejected along with revoked ones.
+    pub async fn authenticate(&self, key: &str) -> Result<ApiKey, ApiError> {
+        let invalid = || ApiError::Authentication("Invalid API key".to_string());
+
+        let prefix = key
+            .strip_prefix(KEY_PREFIX)
+            .and_then(|rest| rest.split_once('_'))
+            .map(|(prefix, _)| format!("{}{}", KEY_PREFIX, prefix))
+            .ok_or_else(invalid)?;
+
+        let client = self.db_pool.get().await?;
+        let row = client
+            .query_opt(
+                &format!(
+                    r#"
+                    UPDATE api_keys SET last_used_at = NOW()
+                    WHERE prefix = $1 AND key_hash = $2 AND revoked_at IS NULL
+                      AND merchant_id IN (SELECT merchant_id FROM merchants WHERE active = true)
+                    RETURNING {}
+                    "#,
+                    API_KEY_COLUMNS
+                ),
+                &[&prefix, &hash_key(key)],
+            )
+            .await?
+            .ok_or_else(invalid)?;
+
+        api_key_from_row(&row)
+    }
+}
+
+fn random_bytes<const N: usize>(rng: &SystemRandom) -> Result<[u8; N], ApiError> {
+    let mut bytes = [0u8; N];
+    rng.fill(&mut bytes)
+        .map_err(|_| ApiError::InternalServerError)?;
+    Ok(bytes)
+}
+
+fn hash_key(key: &str) -> String {
+    hex(digest::digest(&digest::SHA256, key.as_bytes()).as_ref())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn api_key_from_row(row: &tokio_postgres::Row) -> Result<ApiKey, ApiError> {
+    let scopes = row
+        .get::<_, Vec<String>>(4)
+        .iter()
+        .map(|scope| {
+            scope.parse().map_err(|e| {
+                tracing::error!("{} in database", e);
+                ApiError::InternalServerError
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(ApiKey {
+        id: row.get::<_, Uuid>(0).to_string(),
+        merchant_id: row.get(1),
+        name: row.get(2),
+        prefix: row.get(3),
+        scopes,
+        created_by: row.get(5),
+        last_used_at: row.get::<_, Option<chrono::DateTime<chrono::Utc>>>(6),
+        created_at: row.get::<_, chrono::DateTime<chrono::Utc>>(7),
+        revoked_at: row.get::<_, Option<chrono::DateTime<chrono::Utc>>>(8),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_key() {
+        // FIPS 180-2 test vectors
+        assert_eq!(
+            hash_key(""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hash_key("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_ne!(
+            hash_key("zk_000000000000_secret"),
+            hash_key("zk_000000000000_secreT")
+        );
+    }
+
+    #[test]
+    fn test_scopes_round_trip() {
+        for scope in [
+            ApiKeyScope::PaymentsWrite,
+            ApiKeyScope::PaymentsRead,
+            ApiKeyScope::RefundsWrite,
+        ] {
+            assert_eq!(scope.to_string().parse::<ApiKeyScope>(), Ok(scope));
+            assert_eq!(
+                serde_json::to_value(scope).unwrap(),
+                serde_json::json!(scope.to_string())
+            );
+        }
+        assert!("payments:delete".parse::<ApiKeyScope>().is_err());
+    }
+}
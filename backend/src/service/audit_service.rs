@@ -27,14 +27,14 @@ impl AuditService {
     ) -> Result<AuditLogEntry, ApiError> {
         let client = self.db_pool.get().await?;
 
-        let id = Uuid::new_v4().to_string();
-        let timestamp = Utc::now();
+        let id = Uuid::new_v4();
+        let timestamp = params.occurred_at.unwrap_or_else(Utc::now);
 
         let row = client
             .query_one(
                 "INSERT INTO audit_logs (id, actor_id, action, resource, resource_id, metadata, timestamp, ip_address, user_agent)
-                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
-                 RETURNING id, actor_id, action, resource, resource_id, metadata, timestamp, ip_address, user_agent",
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8::text::inet, $9)
+                 RETURNING id::text AS id, actor_id, action, resource, resource_id, metadata, timestamp, host(ip_address) AS ip_address, user_agent",
                 &[
                     &id,
                     &params.actor_id,
@@ -65,15 +65,49 @@ impl AuditService {
         })
     }
 
+    /// Write an audit log entry under `id`, unless one was already written
+    ///
+    /// Used by the audit log job, which may run more than once.
+    pub async fn record_audit_log(
+        &self,
+        id: Uuid,
+        params: CreateAuditLogParams,
+    ) -> Result<(), ApiError> {
+        let client = self.db_pool.get().await?;
+
+        let timestamp = params.occurred_at.unwrap_or_else(Utc::now);
+
+        client
+            .execute(
+                "INSERT INTO audit_logs (id, actor_id, action, resource, resource_id, metadata, timestamp, ip_address, user_agent)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8::text::inet, $9)
+                 ON CONFLICT (id) DO NOTHING",
+                &[
+                    &id,
+                    &params.actor_id,
+                    &params.action,
+                    &params.resource,
+                    &params.resource_id,
+                    &params.metadata,
+                    &timestamp,
+                    &params.ip_address,
+                    &params.user_agent,
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
     /// Get a single audit log entry by ID
     pub async fn get_audit_log(&self, id: &str) -> Result<AuditLogEntry, ApiError> {
         let client = self.db_pool.get().await?;
 
         let row = client
             .query_opt(
-                "SELECT id, actor_id, action, resource, resource_id, metadata, timestamp, ip_address, user_agent
+                "SELECT id::text AS id, actor_id, action, resource, resource_id, metadata, timestamp, host(ip_address) AS ip_address, user_agent
                  FROM audit_logs
-                 WHERE id = $1",
+                 WHERE id::text = $1",
                 &[&id],
             )
             .await?
@@ -104,7 +138,7 @@ impl AuditService {
 
         // Build dynamic query based on filters
         let mut query = String::from(
-            "SELECT id, actor_id, action, resource, resource_id, metadata, timestamp, ip_address, user_agent
+            "SELECT id::text AS id, actor_id, action, resource, resource_id, metadata, timestamp, host(ip_address) AS ip_address, user_agent
              FROM audit_logs
              WHERE 1=1",
         );
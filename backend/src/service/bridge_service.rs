@@ -1,7 +1,8 @@
 use crate::{
     api_error::ApiError,
     config::Config,
-    models::{BridgeTransaction, BridgeTransactionStatus},
+    models::{BridgeTransaction, BridgeTransactionStatus, KycLevel},
+    service::KycService,
 };
 use deadpool_postgres::Pool;
 use serde::{Deserialize, Serialize};
@@ -13,6 +14,7 @@ use uuid::Uuid;
 pub struct BridgeService {
     db_pool: Arc<Pool>,
     config: Config,
+    kyc: KycService,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -38,8 +40,12 @@ pub struct BridgeTransactionResponse {
 }
 
 impl BridgeService {
-    pub fn new(db_pool: Arc<Pool>, config: Config) -> Self {
-        Self { db_pool, config }
+    pub fn new(db_pool: Arc<Pool>, config: Config, kyc: KycService) -> Self {
+        Self {
+            db_pool,
+            config,
+            kyc,
+        }
     }
 
     pub async fn initiate_bridge_transfer(
@@ -48,6 +54,9 @@ impl BridgeService {
     ) -> Result<BridgeTransactionResponse, ApiError> {
         // Validate bridge configuration
         self.validate_bridge_request(&request)?;
+        self.kyc
+            .require_kyc(&request.user_id, KycLevel::Full, "bridging funds")
+            .await?;
 
         let client = self.db_pool.get().await?;
 
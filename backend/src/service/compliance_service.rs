@@ -3,7 +3,6 @@ use deadpool_postgres::Pool;
 use std::sync::Arc;
 
 #[derive(Clone)]
-#[allow(dead_code)]
 pub struct ComplianceService {
     db_pool: Arc<Pool>,
     config: Config,
@@ -14,7 +13,7 @@ impl ComplianceService {
         Self { db_pool, config }
     }
 
-    // Placeholder implementations
+    // Placeholder implementation
     pub async fn check_sanctions(
         &self,
         _user_id: &str,
@@ -22,12 +21,45 @@ impl ComplianceService {
         Ok(false) // Not sanctioned
     }
 
+    /// Whether sending `amount` keeps the user within the configured velocity limits
+    ///
+    /// Daily and monthly totals are rolling 24-hour and 30-day windows over the
+    /// user's outgoing transfers and withdrawals that have not failed.
     pub async fn check_velocity_limits(
         &self,
-        _user_id: &str,
-        _amount: i64,
+        user_id: &str,
+        amount: i64,
     ) -> Result<bool, crate::api_error::ApiError> {
-        Ok(true) // Within limits
+        let limits = &self.config.compliance_config.velocity_limits;
+        if amount < 0 || amount as u64 > limits.max_transaction_amount {
+            return Ok(false);
+        }
+
+        let client = self.db_pool.get().await?;
+        let row = client
+            .query_one(
+                r#"
+                SELECT
+                    COALESCE(SUM(amount) FILTER (WHERE created_at >= NOW() - INTERVAL '1 day'), 0)::BIGINT,
+                    COALESCE(SUM(amount), 0)::BIGINT
+                FROM (
+                    SELECT amount, created_at FROM transfers
+                    WHERE from_user_id = $1 AND status <> 'failed'
+                      AND created_at >= NOW() - INTERVAL '30 days'
+                    UNION ALL
+                    SELECT amount, created_at FROM withdrawals
+                    WHERE user_id = $1 AND status <> 'failed'
+                      AND created_at >= NOW() - INTERVAL '30 days'
+                ) outgoing
+                "#,
+                &[&user_id],
+            )
+            .await?;
+
+        let daily = row.get::<_, i64>(0).max(0) as u64 + amount as u64;
+        let monthly = row.get::<_, i64>(1).max(0) as u64 + amount as u64;
+
+        Ok(daily <= limits.daily_transaction_limit && monthly <= limits.monthly_transaction_limit)
     }
 
     pub async fn log_audit_event(
@@ -0,0 +1,350 @@
+use crate::{
+    api_error::ApiError,
+    config::Config,
+    models::{Deposit, DepositStatus, KycLevel, StatusEvent},
+    service::{
+        anchor_service::{AnchorProgress, AnchorTransaction, StartDepositRequest},
+        lifecycle::{self, LifecycleEntity},
+        AnchorService, ComplianceService, IdentityService, KycService, NotificationService,
+    },
+    stellar::asset,
+};
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+const DEPOSIT_COLUMNS: &str = "id, tx_hash, user_id, amount, asset, status, anchor_tx_id, anchor_status, interactive_url, anchor_message, created_at, updated_at";
+
+#[derive(Clone)]
+pub struct DepositService {
+    db_pool: Arc<Pool>,
+    config: Config,
+    identity: IdentityService,
+    compliance: ComplianceService,
+    kyc: KycService,
+    anchor: AnchorService,
+    notification: NotificationService,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateDepositRequest {
+    /// Amount in stroops (1e-7 of the asset); chosen in the anchor's flow when omitted
+    pub amount: Option<i64>,
+    /// A configured asset code such as `USDC`, or `CODE:ISSUER`
+    pub asset: String,
+}
+
+impl DepositService {
+    pub fn new(
+        db_pool: Arc<Pool>,
+        config: Config,
+        identity: IdentityService,
+        compliance: ComplianceService,
+        kyc: KycService,
+        anchor: AnchorService,
+        notification: NotificationService,
+    ) -> Self {
+        Self {
+            db_pool,
+            config,
+            identity,
+            compliance,
+            kyc,
+            anchor,
+            notification,
+        }
+    }
+
+    /// Start an interactive deposit into the user's wallet
+    ///
+    /// The returned deposit carries the anchor page the app opens for the user
+    /// to finish the deposit. `poll_pending` follows it from there and credits
+    /// the user's balance with what the anchor delivered once it completes.
+    pub async fn create_deposit(
+        &self,
+        user_id: &str,
+        mut request: CreateDepositRequest,
+    ) -> Result<Deposit, ApiError> {
+        if request.amount.is_some_and(|amount| amount <= 0) {
+            return Err(ApiError::Validation(
+                "Amount must be greater than zero".to_string(),
+            ));
+        }
+        let stellar_asset =
+            asset::resolve_asset(&request.asset, &self.config.stellar_network.assets)?;
+        request.asset = asset::asset_key(&stellar_asset, &self.config.stellar_network.assets);
+
+        if self.compliance.check_sanctions(user_id).await? {
+            return Err(ApiError::Compliance(
+                "Deposit blocked by sanctions screening".to_string(),
+            ));
+        }
+        self.kyc
+            .require_kyc(user_id, KycLevel::Basic, "depositing")
+            .await?;
+
+        let keypair = self.identity.get_user_keypair(user_id).await?;
+        let token = self.anchor.authenticate(&keypair).await?;
+        let interactive = self
+            .anchor
+            .start_deposit(
+                &token,
+                &StartDepositRequest {
+                    asset_code: asset::asset_code(&request.asset).to_string(),
+                    account: keypair.address(),
+                    amount: request.amount.map(asset::format_stroops),
+                },
+            )
+            .await?;
+
+        let deposit_id = Uuid::new_v4();
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+        tx.execute(
+            r#"
+            INSERT INTO deposits (id, user_id, amount, asset, status, anchor_tx_id, anchor_status, interactive_url)
+            VALUES ($1, $2, $3, $4, $5, $6, 'incomplete', $7)
+            "#,
+            &[
+                &deposit_id,
+                &user_id,
+                &request.amount,
+                &request.asset,
+                &DepositStatus::Pending.to_string(),
+                &interactive.id,
+                &interactive.url,
+            ],
+        )
+        .await?;
+        lifecycle::record_created(
+            &tx,
+            LifecycleEntity::Deposit,
+            deposit_id,
+            DepositStatus::Pending,
+            None,
+        )
+        .await?;
+        tx.commit().await?;
+
+        self.get_deposit(deposit_id).await
+    }
+
+    pub async fn get_deposit(&self, deposit_id: Uuid) -> Result<Deposit, ApiError> {
+        let client = self.db_pool.get().await?;
+
+        let row = client
+            .query_opt(
+                &format!("SELECT {} FROM deposits WHERE id = $1", DEPOSIT_COLUMNS),
+                &[&deposit_id],
+            )
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Deposit not found".to_string()))?;
+
+        deposit_from_row(&row)
+    }
+
+    pub async fn get_deposit_history(
+        &self,
+        deposit_id: Uuid,
+    ) -> Result<Vec<StatusEvent>, ApiError> {
+        let client = self.db_pool.get().await?;
+        lifecycle::history(&client, LifecycleEntity::Deposit, deposit_id).await
+    }
+
+    /// Poll the anchor for every open deposit and apply what it reports
+    ///
+    /// Returns the number of deposits checked. A failure on one deposit is
+    /// logged and does not stop the others.
+    pub async fn poll_pending(&self) -> Result<usize, ApiError> {
+        let client = self.db_pool.get().await?;
+        let rows = client
+            .query(
+                &format!(
+                    "SELECT {} FROM deposits WHERE status IN ('pending', 'processing') AND anchor_tx_id IS NOT NULL ORDER BY created_at",
+                    DEPOSIT_COLUMNS
+                ),
+                &[],
+            )
+            .await?;
+        drop(client);
+
+        for row in &rows {
+            let deposit = deposit_from_row(row)?;
+            if let Err(e) = self.poll_deposit(&deposit).await {
+                tracing::warn!("Failed to update deposit {}: {}", deposit.id, e);
+            }
+        }
+
+        Ok(rows.len())
+    }
+
+    async fn poll_deposit(&self, deposit: &Deposit) -> Result<(), ApiError> {
+        let Some(anchor_tx_id) = deposit.anchor_tx_id.as_deref() else {
+            return Ok(());
+        };
+        let deposit_id = Uuid::parse_str(&deposit.id).map_err(|_| {
+            tracing::error!("Invalid deposit id in database: {}", deposit.id);
+            ApiError::InternalServerError
+        })?;
+
+        let keypair = self.identity.get_user_keypair(&deposit.user_id).await?;
+        let token = self.anchor.authenticate(&keypair).await?;
+        let anchor_tx = self.anchor.get_transaction(&token, anchor_tx_id).await?;
+
+        let client = self.db_pool.get().await?;
+        client
+            .execute(
+                "UPDATE deposits SET anchor_status = $1, anchor_message = COALESCE($2, anchor_message), updated_at = NOW() WHERE id = $3",
+                &[&anchor_tx.status, &anchor_tx.message, &deposit_id],
+            )
+            .await?;
+        drop(client);
+
+        let reason = format!("Anchor reported {}", anchor_tx.status);
+        match AnchorProgress::from_status(&anchor_tx.status) {
+            AnchorProgress::Waiting => {}
+            AnchorProgress::AwaitingPayment | AnchorProgress::Processing => {
+                if deposit.status == DepositStatus::Pending {
+                    self.transition(deposit_id, DepositStatus::Processing, &reason)
+                        .await?;
+                }
+            }
+            AnchorProgress::Completed => {
+                self.complete_deposit(deposit, deposit_id, &anchor_tx, &reason)
+                    .await?
+            }
+            AnchorProgress::Failed => {
+                let reason = match &anchor_tx.message {
+                    Some(message) => format!("{}: {}", reason, message),
+                    None => reason,
+                };
+                self.transition(deposit_id, DepositStatus::Failed, &reason)
+                    .await?;
+                self.notification
+                    .notify_best_effort(
+                        &deposit.user_id,
+                        "Deposit failed",
+                        format!("Your {} deposit did not complete", deposit.asset),
+                        serde_json::json!({ "deposit_id": deposit_id.to_string() }),
+                    )
+                    .await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Mark the deposit completed and credit the user with the amount delivered
+    async fn complete_deposit(
+        &self,
+        deposit: &Deposit,
+        deposit_id: Uuid,
+        anchor_tx: &AnchorTransaction,
+        reason: &str,
+    ) -> Result<(), ApiError> {
+        let amount = match anchor_tx.amount_out.as_deref() {
+            Some(amount_out) => asset::parse_stroops(amount_out)?,
+            None => deposit.amount.ok_or_else(|| {
+                ApiError::Anchor(format!(
+                    "Completed deposit {} reports no amount_out",
+                    anchor_tx.id
+                ))
+            })?,
+        };
+
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+
+        if deposit.status == DepositStatus::Pending {
+            lifecycle::transition(
+                &tx,
+                LifecycleEntity::Deposit,
+                deposit_id,
+                DepositStatus::Processing,
+                reason,
+                None,
+            )
+            .await?;
+        }
+        lifecycle::transition(
+            &tx,
+            LifecycleEntity::Deposit,
+            deposit_id,
+            DepositStatus::Completed,
+            reason,
+            anchor_tx.stellar_transaction_id.as_deref(),
+        )
+        .await?;
+        tx.execute(
+            "UPDATE deposits SET amount = $1 WHERE id = $2",
+            &[&amount, &deposit_id],
+        )
+        .await?;
+        tx.execute(
+            r#"
+            INSERT INTO balances (owner_id, asset, amount)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (owner_id, asset)
+            DO UPDATE SET amount = balances.amount + EXCLUDED.amount, last_updated = NOW()
+            "#,
+            &[&deposit.user_id, &deposit.asset, &amount],
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        self.notification
+            .notify_best_effort(
+                &deposit.user_id,
+                "Deposit completed",
+                format!(
+                    "{} {} was added to your balance",
+                    asset::format_stroops(amount),
+                    deposit.asset
+                ),
+                serde_json::json!({ "deposit_id": deposit_id.to_string() }),
+            )
+            .await;
+
+        Ok(())
+    }
+
+    async fn transition(
+        &self,
+        deposit_id: Uuid,
+        status: DepositStatus,
+        reason: &str,
+    ) -> Result<(), ApiError> {
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+        lifecycle::transition(
+            &tx,
+            LifecycleEntity::Deposit,
+            deposit_id,
+            status,
+            reason,
+            None,
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+fn deposit_from_row(row: &tokio_postgres::Row) -> Result<Deposit, ApiError> {
+    Ok(Deposit {
+        id: row.get::<_, Uuid>(0).to_string(),
+        tx_hash: row.get(1),
+        user_id: row.get(2),
+        amount: row.get(3),
+        asset: row.get(4),
+        status: lifecycle::parse_status(LifecycleEntity::Deposit, row.get(5))?,
+        anchor_tx_id: row.get(6),
+        anchor_status: row.get(7),
+        interactive_url: row.get(8),
+        anchor_message: row.get(9),
+        created_at: row.get::<_, chrono::DateTime<chrono::Utc>>(10),
+        updated_at: row.get::<_, chrono::DateTime<chrono::Utc>>(11),
+    })
+}
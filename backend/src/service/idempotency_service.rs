@@ -0,0 +1,260 @@
+use crate::{api_error::ApiError, config::Config};
+use deadpool_postgres::Pool;
+use ring::digest;
+use std::sync::{
+    atomic::{AtomicI64, Ordering},
+    Arc,
+};
+
+/// How often expired keys are swept, in seconds
+const PURGE_INTERVAL_SECS: i64 = 3600;
+
+/// Result of reserving an idempotency key for a request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdempotencyOutcome {
+    /// First use of the key: run the handler, renewing the lease, then complete the key
+    Proceed,
+    /// The key was already used for an identical request that has finished
+    Replay { status: u16, body: String },
+}
+
+#[derive(Clone)]
+pub struct IdempotencyService {
+    db_pool: Arc<Pool>,
+    config: Config,
+    last_purge: Arc<AtomicI64>,
+}
+
+impl IdempotencyService {
+    pub fn new(db_pool: Arc<Pool>, config: Config) -> Self {
+        Self {
+            db_pool,
+            config,
+            last_purge: Arc::new(AtomicI64::new(0)),
+        }
+    }
+
+    /// Fingerprint of a request: SHA-256 over method, path and raw body
+    pub fn fingerprint(method: &str, path: &str, body: &[u8]) -> String {
+        let mut ctx = digest::Context::new(&digest::SHA256);
+        ctx.update(method.as_bytes());
+        ctx.update(b"\n");
+        ctx.update(path.as_bytes());
+        ctx.update(b"\n");
+        ctx.update(body);
+        ctx.finish()
+            .as_ref()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    pub fn validate_key(key: &str) -> Result<(), ApiError> {
+        if key.is_empty() || key.len() > 255 || !key.chars().all(|c| c.is_ascii_graphic()) {
+            return Err(ApiError::Validation(
+                "Idempotency-Key must be 1-255 printable ASCII characters".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Reserve `key` for this request, or return how to answer a repeated one
+    ///
+    /// Reusing a key with a different request, or while the first request is
+    /// still in flight, is a conflict. So is reusing a key whose request never
+    /// recorded an outcome: it may have committed, so it is never run again.
+    /// Expired keys are treated as unused.
+    pub async fn begin(
+        &self,
+        user_id: &str,
+        key: &str,
+        method: &str,
+        path: &str,
+        fingerprint: &str,
+    ) -> Result<IdempotencyOutcome, ApiError> {
+        Self::validate_key(key)?;
+        self.purge_expired_if_due().await;
+
+        let client = self.db_pool.get().await?;
+
+        let reserved = client
+            .query_opt(
+                r#"
+                INSERT INTO idempotency_keys (
+                    user_id, idempotency_key, request_method, request_path,
+                    request_fingerprint, expires_at, locked_until
+                )
+                VALUES (
+                    $1, $2, $3, $4, $5,
+                    NOW() + make_interval(hours => $6),
+                    NOW() + make_interval(secs => $7)
+                )
+                ON CONFLICT (user_id, idempotency_key) DO UPDATE SET
+                    request_method = EXCLUDED.request_method,
+                    request_path = EXCLUDED.request_path,
+                    request_fingerprint = EXCLUDED.request_fingerprint,
+                    response_status = NULL,
+                    response_body = NULL,
+                    created_at = NOW(),
+                    expires_at = EXCLUDED.expires_at,
+                    locked_until = EXCLUDED.locked_until
+                WHERE idempotency_keys.expires_at < NOW()
+                RETURNING user_id
+                "#,
+                &[
+                    &user_id,
+                    &key,
+                    &method,
+                    &path,
+                    &fingerprint,
+                    &(self.config.idempotency.ttl_hours as i32),
+                    &(self.config.idempotency.lock_lease_secs as f64),
+                ],
+            )
+            .await?;
+
+        if reserved.is_some() {
+            return Ok(IdempotencyOutcome::Proceed);
+        }
+
+        let row = client
+            .query_one(
+                r#"
+                SELECT request_fingerprint, response_status, response_body, locked_until < NOW()
+                FROM idempotency_keys
+                WHERE user_id = $1 AND idempotency_key = $2
+                "#,
+                &[&user_id, &key],
+            )
+            .await?;
+
+        let stored_fingerprint: String = row.get(0);
+        if stored_fingerprint != fingerprint {
+            return Err(ApiError::Conflict(
+                "Idempotency-Key was already used with a different request".to_string(),
+            ));
+        }
+
+        match (
+            row.get::<_, Option<i16>>(1),
+            row.get::<_, Option<String>>(2),
+        ) {
+            (Some(status), body) => Ok(IdempotencyOutcome::Replay {
+                status: status as u16,
+                body: body.unwrap_or_default(),
+            }),
+            (None, _) if row.get::<_, bool>(3) => Err(ApiError::Conflict(
+                "A request with this Idempotency-Key did not finish and its outcome is unknown"
+                    .to_string(),
+            )),
+            (None, _) => Err(ApiError::Conflict(
+                "A request with this Idempotency-Key is still being processed".to_string(),
+            )),
+        }
+    }
+
+    /// Store the response for a reserved key so retries replay it
+    pub async fn complete(
+        &self,
+        user_id: &str,
+        key: &str,
+        status: u16,
+        body: &str,
+    ) -> Result<(), ApiError> {
+        let client = self.db_pool.get().await?;
+
+        client
+            .execute(
+                "UPDATE idempotency_keys SET response_status = $3, response_body = $4 WHERE user_id = $1 AND idempotency_key = $2",
+                &[&user_id, &key, &(status as i16), &body],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Extend the lease on a key whose request is still running
+    pub async fn renew(&self, user_id: &str, key: &str) -> Result<(), ApiError> {
+        let client = self.db_pool.get().await?;
+
+        client
+            .execute(
+                r#"
+                UPDATE idempotency_keys
+                SET locked_until = NOW() + make_interval(secs => $3)
+                WHERE user_id = $1 AND idempotency_key = $2 AND response_status IS NULL
+                "#,
+                &[
+                    &user_id,
+                    &key,
+                    &(self.config.idempotency.lock_lease_secs as f64),
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// How often a running request renews its lease
+    pub fn renew_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs((self.config.idempotency.lock_lease_secs / 3).max(1) as u64)
+    }
+
+    pub async fn purge_expired(&self) -> Result<u64, ApiError> {
+        let client = self.db_pool.get().await?;
+
+        let deleted = client
+            .execute("DELETE FROM idempotency_keys WHERE expires_at < NOW()", &[])
+            .await?;
+
+        Ok(deleted)
+    }
+
+    async fn purge_expired_if_due(&self) {
+        let now = chrono::Utc::now().timestamp();
+        let last = self.last_purge.load(Ordering::Relaxed);
+        if now - last < PURGE_INTERVAL_SECS
+            || self
+                .last_purge
+                .compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_err()
+        {
+            return;
+        }
+
+        if let Err(e) = self.purge_expired().await {
+            tracing::warn!("Failed to purge expired idempotency keys: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_covers_method_path_and_body() {
+        let base = IdempotencyService::fingerprint("POST", "/payments/payments", b"{\"a\":1}");
+        assert_eq!(base.len(), 64);
+        assert_eq!(
+            base,
+            IdempotencyService::fingerprint("POST", "/payments/payments", b"{\"a\":1}")
+        );
+        assert_ne!(
+            base,
+            IdempotencyService::fingerprint("POST", "/payments/payments", b"{\"a\":2}")
+        );
+        assert_ne!(
+            base,
+            IdempotencyService::fingerprint("POST", "/transfers/transfers", b"{\"a\":1}")
+        );
+    }
+
+    #[test]
+    fn test_validate_key() {
+        assert!(IdempotencyService::validate_key("3f1c9a2e-7b4d-4e0a-9c55-1a2b3c4d5e6f").is_ok());
+        assert!(IdempotencyService::validate_key("").is_err());
+        assert!(IdempotencyService::validate_key("has space").is_err());
+        assert!(IdempotencyService::validate_key(&"k".repeat(256)).is_err());
+    }
+}
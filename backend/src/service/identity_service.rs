@@ -3,6 +3,7 @@ use crate::{
     config::Config,
     models::{User, Wallet},
     role::Role,
+    stellar::{custody, Keypair},
 };
 use deadpool_postgres::Pool;
 use std::str::FromStr;
@@ -10,7 +11,6 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 #[derive(Clone)]
-#[allow(dead_code)]
 pub struct IdentityService {
     db_pool: Arc<Pool>,
     config: Config,
@@ -24,15 +24,18 @@ impl IdentityService {
     pub async fn create_user(&self, user_id: String, pin_hash: String) -> Result<User, ApiError> {
         let client = self.db_pool.get().await?;
 
-        // Generate a unique Stellar address (in production, this would be generated properly)
-        let stellar_address = format!("G{}", Uuid::new_v4().simple().to_string().to_uppercase());
+        // Generate the user's wallet keypair; the seed is only stored sealed
+        let keypair = Keypair::random()?;
+        let stellar_address = keypair.address();
+        let encrypted_secret =
+            custody::seal_secret(&self.config.custody.encryption_key, &keypair.secret_seed())?;
         let user_id_db = Uuid::new_v4(); // ensure that a UUID type is used
 
         let role_str = Role::User.as_str();
         let row = client
             .query_one(
-                "INSERT INTO users (id, user_id, stellar_address, role, pin_hash) VALUES ($1, $2, $3, $4, $5) RETURNING id, user_id, stellar_address, role, created_at, updated_at",
-                &[&user_id_db, &user_id, &stellar_address, &role_str, &pin_hash],
+                "INSERT INTO users (id, user_id, stellar_address, role, pin_hash, encrypted_secret) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id, user_id, stellar_address, role, created_at, updated_at",
+                &[&user_id_db, &user_id, &stellar_address, &role_str, &pin_hash, &encrypted_secret],
             )
             .await?;
 
@@ -100,6 +103,27 @@ impl IdentityService {
         })
     }
 
+    /// Custodial keypair of a user's wallet, opened with the custody key
+    pub async fn get_user_keypair(&self, user_id: &str) -> Result<Keypair, ApiError> {
+        let client = self.db_pool.get().await?;
+
+        let row = client
+            .query_opt(
+                "SELECT encrypted_secret FROM users WHERE user_id = $1",
+                &[&user_id],
+            )
+            .await?
+            .ok_or_else(|| ApiError::NotFound("User not found".to_string()))?;
+
+        let sealed: Option<String> = row.get(0);
+        let sealed = sealed.ok_or_else(|| {
+            ApiError::Validation("Wallet has no custodial key and cannot sign".to_string())
+        })?;
+
+        let secret = custody::open_secret(&self.config.custody.encryption_key, &sealed)?;
+        Keypair::from_secret_seed(&secret)
+    }
+
     pub async fn resolve_user_id(&self, user_id: &str) -> Result<String, ApiError> {
         let user = self.get_user_by_id(user_id).await?;
         Ok(user.stellar_address)
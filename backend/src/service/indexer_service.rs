@@ -1,28 +1,714 @@
-use crate::config::Config;
-use deadpool_postgres::Pool;
-use std::sync::Arc;
+use crate::{
+    api_error::ApiError,
+    config::{Config, ContractsConfig},
+    models::PaymentStatus,
+    service::{payment_service, SorobanService},
+    stellar::{
+        asset,
+        rpc::{EventFilter, EventInfo, EventsStart},
+        scval,
+    },
+};
+use deadpool_postgres::{Pool, Transaction};
+use soroban_sdk::xdr::ScVal;
+use std::{str::FromStr, sync::Arc};
+use uuid::Uuid;
 
+/// Cursor of the payment-router and merchant-vault event stream
+const CURSOR_NAME: &str = "contract_events";
+
+/// Most `getEvents` pages read in one poll, so a long backlog is caught up over several
+const MAX_PAGES_PER_POLL: usize = 10;
+
+const SAVE_CURSOR: &str = r#"
+    INSERT INTO indexer_cursors (name, cursor) VALUES ($1, $2)
+    ON CONFLICT (name) DO UPDATE SET cursor = EXCLUDED.cursor, updated_at = NOW()
+"#;
+
+/// Follows the payment-router and merchant-vault contracts' events into the database
+///
+/// Router events move payments through their lifecycle and record the
+/// on-chain hash and settled amount; vault events credit and debit merchant
+/// balances. Each event is applied in one transaction with its id and the
+/// cursor after it, so an event is never applied twice nor skipped.
 #[derive(Clone)]
-#[allow(dead_code)]
 pub struct IndexerService {
     db_pool: Arc<Pool>,
     config: Config,
+    soroban: SorobanService,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaymentEventKind {
+    Initiated,
+    Settled,
+    Failed,
+}
+
+/// A payment-router or merchant-vault event the indexer applies
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContractEvent {
+    /// `("payment", PaymentInitiated | PaymentSettled | PaymentFailed)` from the payment-router
+    Payment {
+        kind: PaymentEventKind,
+        payer: String,
+        merchant_id: String,
+        /// Token contract (`C...`) of the asset sent
+        send_asset: String,
+        send_amount: i64,
+        settled_amount: i64,
+    },
+    /// `balance_credited` from the merchant-vault, keyed by the merchant's vault address
+    VaultCredited { vault_address: String, amount: i64 },
+    /// `balance_debited` from the merchant-vault
+    VaultDebited { vault_address: String, amount: i64 },
+}
+
+impl ContractEvent {
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            ContractEvent::Payment { kind, .. } => match kind {
+                PaymentEventKind::Initiated => "PaymentInitiated",
+                PaymentEventKind::Settled => "PaymentSettled",
+                PaymentEventKind::Failed => "PaymentFailed",
+            },
+            ContractEvent::VaultCredited { .. } => "balance_credited",
+            ContractEvent::VaultDebited { .. } => "balance_debited",
+        }
+    }
+}
+
+/// What indexing an event did, as recorded in `indexed_contract_events`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Applied,
+    /// Already reflected in the database, or not an event the indexer follows
+    Skipped,
+    /// No payment or merchant the event refers to
+    Unmatched,
+    /// Could not be decoded
+    Invalid,
+}
+
+impl Outcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Outcome::Applied => "applied",
+            Outcome::Skipped => "skipped",
+            Outcome::Unmatched => "unmatched",
+            Outcome::Invalid => "invalid",
+        }
+    }
 }
 
 impl IndexerService {
-    pub fn new(db_pool: Arc<Pool>, config: Config) -> Self {
-        Self { db_pool, config }
+    pub fn new(db_pool: Arc<Pool>, config: Config, soroban: SorobanService) -> Self {
+        Self {
+            db_pool,
+            config,
+            soroban,
+        }
     }
 
-    // Placeholder implementations
-    pub async fn start_indexing(&self) -> Result<(), crate::api_error::ApiError> {
-        Ok(())
+    /// Read the events after the saved cursor and apply them, returning how many were applied
+    pub async fn index_events(&self) -> Result<usize, ApiError> {
+        let contract_ids: Vec<String> = [
+            &self.config.contracts.payment_router,
+            &self.config.contracts.merchant_vault,
+        ]
+        .into_iter()
+        .filter(|id| !id.is_empty())
+        .cloned()
+        .collect();
+        if contract_ids.is_empty() {
+            return Ok(0);
+        }
+        let filter = EventFilter {
+            event_type: "contract".to_string(),
+            contract_ids,
+            topics: vec![],
+        };
+        let page_size = self.config.indexer.page_size.max(1);
+
+        let mut start = match self.load_cursor().await? {
+            Some(cursor) => EventsStart::Cursor(cursor),
+            None => EventsStart::Ledger(self.start_ledger().await?),
+        };
+        let mut applied = 0;
+
+        for _ in 0..MAX_PAGES_PER_POLL {
+            let page = self
+                .soroban
+                .rpc()
+                .get_events(start, std::slice::from_ref(&filter), page_size)
+                .await?;
+
+            for event in &page.events {
+                if self.index_event(event).await? == Outcome::Applied {
+                    applied += 1;
+                }
+            }
+
+            let Some(cursor) = page.cursor.or_else(|| page.events.last().map(event_cursor)) else {
+                break;
+            };
+            let client = self.db_pool.get().await?;
+            client
+                .execute(SAVE_CURSOR, &[&CURSOR_NAME, &cursor])
+                .await?;
+
+            if page.events.len() < page_size as usize {
+                break;
+            }
+            start = EventsStart::Cursor(cursor);
+        }
+
+        Ok(applied)
     }
 
-    pub async fn index_transaction(
+    async fn index_event(&self, event: &EventInfo) -> Result<Outcome, ApiError> {
+        let decoded = decode_event(&self.config.contracts, event);
+        let event_type = match &decoded {
+            Ok(Some(contract_event)) => contract_event.event_type(),
+            _ => "unknown",
+        };
+
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+
+        let claimed = tx
+            .execute(
+                r#"
+                INSERT INTO indexed_contract_events (id, contract_id, ledger, tx_hash, event_type, outcome)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (id) DO NOTHING
+                "#,
+                &[
+                    &event.id,
+                    &event.contract_id,
+                    &(event.ledger as i64),
+                    &event.tx_hash,
+                    &event_type,
+                    &Outcome::Skipped.as_str(),
+                ],
+            )
+            .await?;
+
+        let outcome = if claimed == 0 {
+            Outcome::Skipped
+        } else {
+            let tx_hash = event.tx_hash.as_deref();
+            let outcome = match decoded {
+                Ok(Some(ContractEvent::Payment {
+                    kind,
+                    payer,
+                    merchant_id,
+                    send_asset,
+                    send_amount,
+                    settled_amount,
+                })) => {
+                    let payment = self
+                        .match_payment(&tx, tx_hash, &payer, &merchant_id, &send_asset, send_amount)
+                        .await?;
+                    match payment {
+                        Some((payment_id, status)) => {
+                            apply_payment_event(
+                                &tx,
+                                payment_id,
+                                status,
+                                kind,
+                                settled_amount,
+                                tx_hash,
+                            )
+                            .await?
+                        }
+                        None => Outcome::Unmatched,
+                    }
+                }
+                Ok(Some(ContractEvent::VaultCredited {
+                    vault_address,
+                    amount,
+                })) => apply_vault_event(&tx, &vault_address, amount, tx_hash).await?,
+                Ok(Some(ContractEvent::VaultDebited {
+                    vault_address,
+                    amount,
+                })) => apply_vault_event(&tx, &vault_address, -amount, tx_hash).await?,
+                Ok(None) => Outcome::Skipped,
+                Err(e) => {
+                    tracing::warn!("Contract event {} could not be decoded: {}", event.id, e);
+                    Outcome::Invalid
+                }
+            };
+            if outcome == Outcome::Unmatched {
+                tracing::warn!(
+                    "{} event {} matches no payment or merchant",
+                    event_type,
+                    event.id
+                );
+            }
+            tx.execute(
+                "UPDATE indexed_contract_events SET outcome = $1 WHERE id = $2",
+                &[&outcome.as_str(), &event.id],
+            )
+            .await?;
+            outcome
+        };
+
+        tx.execute(SAVE_CURSOR, &[&CURSOR_NAME, &event_cursor(event)])
+            .await?;
+        tx.commit().await?;
+
+        Ok(outcome)
+    }
+
+    /// The payment a router event reports on, locked, with its status
+    ///
+    /// Once a payment has been seen on-chain it is found by its transaction
+    /// hash; before that, by the oldest pending payment of the same payer,
+    /// merchant, asset and amount.
+    async fn match_payment(
         &self,
-        _tx_hash: &str,
-    ) -> Result<(), crate::api_error::ApiError> {
-        Ok(())
+        tx: &Transaction<'_>,
+        tx_hash: Option<&str>,
+        payer: &str,
+        merchant_id: &str,
+        send_asset: &str,
+        send_amount: i64,
+    ) -> Result<Option<(Uuid, PaymentStatus)>, ApiError> {
+        if let Some(tx_hash) = tx_hash {
+            if let Some(row) = tx
+                .query_opt(
+                    "SELECT id, status FROM payments WHERE tx_hash = $1 FOR UPDATE",
+                    &[&tx_hash],
+                )
+                .await?
+            {
+                return Ok(Some((row.get(0), parse_status(row.get(1))?)));
+            }
+        }
+
+        let rows = tx
+            .query(
+                r#"
+                SELECT id, send_asset FROM payments
+                WHERE from_address = $1 AND merchant_id = $2 AND send_amount = $3 AND status = 'pending'
+                ORDER BY created_at, id
+                FOR UPDATE
+                "#,
+                &[&payer, &merchant_id, &send_amount],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .find(|row| self.asset_contract(row.get(1)).as_deref() == Some(send_asset))
+            .map(|row| (row.get(0), PaymentStatus::Pending)))
+    }
+
+    fn asset_contract(&self, send_asset: &str) -> Option<String> {
+        let stellar_asset =
+            asset::resolve_asset(send_asset, &self.config.stellar_network.assets).ok()?;
+        asset::contract_address(&stellar_asset, &self.config.stellar_network.passphrase).ok()
+    }
+
+    async fn load_cursor(&self) -> Result<Option<String>, ApiError> {
+        let client = self.db_pool.get().await?;
+        let row = client
+            .query_opt(
+                "SELECT cursor FROM indexer_cursors WHERE name = $1",
+                &[&CURSOR_NAME],
+            )
+            .await?;
+        Ok(row.map(|row| row.get(0)))
+    }
+
+    async fn start_ledger(&self) -> Result<u32, ApiError> {
+        if self.config.indexer.start_ledger > 0 {
+            return Ok(self.config.indexer.start_ledger);
+        }
+        Ok(self.soroban.rpc().get_latest_ledger().await?.sequence)
+    }
+}
+
+async fn apply_payment_event(
+    tx: &Transaction<'_>,
+    payment_id: Uuid,
+    status: PaymentStatus,
+    kind: PaymentEventKind,
+    settled_amount: i64,
+    tx_hash: Option<&str>,
+) -> Result<Outcome, ApiError> {
+    let open = matches!(status, PaymentStatus::Pending | PaymentStatus::Processing);
+
+    match kind {
+        PaymentEventKind::Initiated if status == PaymentStatus::Pending => {
+            payment_service::transition_payment(
+                tx,
+                payment_id,
+                PaymentStatus::Processing,
+                "Payment initiated on-chain",
+                tx_hash,
+            )
+            .await?;
+        }
+        PaymentEventKind::Settled if open => {
+            tx.execute(
+                "UPDATE payments SET receive_amount = $1 WHERE id = $2",
+                &[&settled_amount, &payment_id],
+            )
+            .await?;
+            if status == PaymentStatus::Pending {
+                payment_service::transition_payment(
+                    tx,
+                    payment_id,
+                    PaymentStatus::Processing,
+                    "Payment initiated on-chain",
+                    tx_hash,
+                )
+                .await?;
+            }
+            payment_service::transition_payment(
+                tx,
+                payment_id,
+                PaymentStatus::Completed,
+                "Payment settled to the merchant vault",
+                tx_hash,
+            )
+            .await?;
+        }
+        PaymentEventKind::Failed if open => {
+            payment_service::transition_payment(
+                tx,
+                payment_id,
+                PaymentStatus::Failed,
+                "Payment failed on-chain",
+                tx_hash,
+            )
+            .await?;
+        }
+        _ => return Ok(Outcome::Skipped),
+    }
+
+    Ok(Outcome::Applied)
+}
+
+/// Credit (positive `amount`) or debit a merchant's balance from a vault event
+///
+/// Refund debits were taken from the balance when the refund was created, so
+/// the vault debits refunds made are skipped.
+async fn apply_vault_event(
+    tx: &Transaction<'_>,
+    vault_address: &str,
+    amount: i64,
+    tx_hash: Option<&str>,
+) -> Result<Outcome, ApiError> {
+    let rows = tx
+        .query(
+            "SELECT merchant_id, settlement_asset FROM merchants WHERE vault_address = $1",
+            &[&vault_address],
+        )
+        .await?;
+    // A vault shared by several merchants cannot be attributed to one of them
+    let [merchant] = rows.as_slice() else {
+        return Ok(Outcome::Unmatched);
+    };
+    let merchant_id: String = merchant.get(0);
+    let settlement_asset: String = merchant.get(1);
+
+    if amount < 0 {
+        // Wait for refunds with a vault debit in flight to record its hash
+        tx.query(
+            "SELECT id FROM refunds WHERE merchant_id = $1 AND status = 'pending' AND vault_tx_hash IS NULL FOR UPDATE",
+            &[&merchant_id],
+        )
+        .await?;
+        if let Some(tx_hash) = tx_hash {
+            let refund = tx
+                .query_opt(
+                    "SELECT id FROM refunds WHERE vault_tx_hash = $1",
+                    &[&tx_hash],
+                )
+                .await?;
+            if refund.is_some() {
+                return Ok(Outcome::Skipped);
+            }
+        }
+    }
+
+    tx.execute(
+        r#"
+        INSERT INTO balances (owner_id, asset, amount)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (owner_id, asset)
+        DO UPDATE SET amount = balances.amount + EXCLUDED.amount, last_updated = NOW()
+        "#,
+        &[&merchant_id, &settlement_asset, &amount],
+    )
+    .await?;
+
+    Ok(Outcome::Applied)
+}
+
+/// Decode an event of the payment-router or merchant-vault
+///
+/// Returns `None` for events of other contracts and events the indexer does
+/// not follow, and an error for followed events that are malformed.
+pub fn decode_event(
+    contracts: &ContractsConfig,
+    event: &EventInfo,
+) -> Result<Option<ContractEvent>, String> {
+    let topics = event
+        .topic
+        .iter()
+        .map(|topic| scval::from_base64(topic).ok_or_else(|| "invalid topic".to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let first = topics.first().and_then(scval::scval_to_symbol);
+    let second = topics.get(1);
+
+    if !contracts.payment_router.is_empty() && event.contract_id == contracts.payment_router {
+        if first.as_deref() != Some("payment") {
+            return Ok(None);
+        }
+        let kind = match second.and_then(scval::scval_to_symbol).as_deref() {
+            Some("PaymentInitiated") => PaymentEventKind::Initiated,
+            Some("PaymentSettled") => PaymentEventKind::Settled,
+            Some("PaymentFailed") => PaymentEventKind::Failed,
+            _ => return Ok(None),
+        };
+        let value = event_value(event)?;
+        let merchant_id = field(&value, "merchant_id", scval::scval_to_bytes)?;
+
+        return Ok(Some(ContractEvent::Payment {
+            kind,
+            payer: field(&value, "payer", scval::scval_to_address)?,
+            merchant_id: String::from_utf8(merchant_id.to_vec())
+                .map_err(|_| "merchant_id is not UTF-8".to_string())?,
+            send_asset: field(&value, "send_asset", scval::scval_to_address)?,
+            send_amount: amount_field(&value, "send_amount")?,
+            settled_amount: amount_field(&value, "settled_amount")?,
+        }));
+    }
+
+    if !contracts.merchant_vault.is_empty() && event.contract_id == contracts.merchant_vault {
+        let credited = match first.as_deref() {
+            Some("balance_credited") => true,
+            Some("balance_debited") => false,
+            _ => return Ok(None),
+        };
+        let vault_address = second
+            .and_then(scval::scval_to_address)
+            .ok_or_else(|| "missing merchant address topic".to_string())?;
+        let amount = amount_field(&event_value(event)?, "amount")?;
+
+        return Ok(Some(if credited {
+            ContractEvent::VaultCredited {
+                vault_address,
+                amount,
+            }
+        } else {
+            ContractEvent::VaultDebited {
+                vault_address,
+                amount,
+            }
+        }));
+    }
+
+    Ok(None)
+}
+
+fn event_value(event: &EventInfo) -> Result<ScVal, String> {
+    scval::from_base64(&event.value).ok_or_else(|| "invalid value".to_string())
+}
+
+fn field<'a, T>(
+    value: &'a ScVal,
+    name: &str,
+    convert: impl Fn(&'a ScVal) -> Option<T>,
+) -> Result<T, String> {
+    scval::struct_field(value, name)
+        .and_then(convert)
+        .ok_or_else(|| format!("missing or invalid {}", name))
+}
+
+/// An `i128` amount field, which must fit the `BIGINT` stroop columns
+fn amount_field(value: &ScVal, name: &str) -> Result<i64, String> {
+    let amount = field(value, name, scval::scval_to_i128)?;
+    i64::try_from(amount)
+        .ok()
+        .filter(|amount| *amount >= 0)
+        .ok_or_else(|| format!("{} out of range", name))
+}
+
+/// Cursor resuming after `event`
+fn event_cursor(event: &EventInfo) -> String {
+    event
+        .paging_token
+        .clone()
+        .unwrap_or_else(|| event.id.clone())
+}
+
+fn parse_status(status: String) -> Result<PaymentStatus, ApiError> {
+    PaymentStatus::from_str(&status).map_err(|e| {
+        tracing::error!("{}", e);
+        ApiError::InternalServerError
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use soroban_sdk::xdr::{Limits, WriteXdr};
+
+    const ROUTER: &str = "CAAACAQDAQCQMBYIBEFAWDANBYHRAEISCMKBKFQXDAMRUGY4DUPB6N4O";
+    const VAULT: &str = "CBDRHDQCGK5OM3LHJOCGUAQTCLA3LNTKB5XK5I5EL6VVWDPB2AVDKEON";
+    const PAYER: &str = "GA7QYNF7SOWQ3GLR2BGMZEHXAVIRZA4KVWLTJJFC7MGXUA74P7UJVSGZ";
+
+    fn contracts() -> ContractsConfig {
+        ContractsConfig {
+            payment_router: ROUTER.to_string(),
+            merchant_vault: VAULT.to_string(),
+            zaps_registry: String::new(),
+            escrow: String::new(),
+            registry_admin_secret: None,
+            vault_payout_secret: None,
+        }
+    }
+
+    fn encode(value: serde_json::Value) -> String {
+        scval::json_to_scval(&value)
+            .unwrap()
+            .to_xdr_base64(Limits::none())
+            .unwrap()
+    }
+
+    fn symbol(value: &str) -> String {
+        encode(json!({ "type": "symbol", "value": value }))
+    }
+
+    fn event(contract_id: &str, topic: Vec<String>, value: serde_json::Value) -> EventInfo {
+        EventInfo {
+            event_type: "contract".to_string(),
+            ledger: 100,
+            ledger_closed_at: "2026-02-16T09:00:00Z".to_string(),
+            contract_id: contract_id.to_string(),
+            id: "0000429496729600-0000000001".to_string(),
+            paging_token: None,
+            topic,
+            value: encode(value),
+            in_successful_contract_call: true,
+            tx_hash: None,
+        }
+    }
+
+    fn entry(key: &str, value: serde_json::Value) -> serde_json::Value {
+        json!({ "key": { "type": "symbol", "value": key }, "value": value })
+    }
+
+    fn payment_event(kind: &str, settled_amount: i128) -> EventInfo {
+        event(
+            ROUTER,
+            vec![symbol("payment"), symbol(kind)],
+            json!({ "type": "map", "value": [
+                entry("merchant_id", json!({ "type": "bytes", "value": "6d65726368616e745f31" })),
+                entry("payer", json!({ "type": "address", "value": PAYER })),
+                entry("send_amount", json!({ "type": "i128", "value": "5000000" })),
+                entry("send_asset", json!({ "type": "address", "value": ROUTER })),
+                entry("settled_amount", json!({ "type": "i128", "value": settled_amount.to_string() })),
+                entry("settlement_asset", json!({ "type": "address", "value": ROUTER })),
+            ] }),
+        )
+    }
+
+    fn vault_event(name: &str, amount: i128) -> EventInfo {
+        event(
+            VAULT,
+            vec![
+                symbol(name),
+                encode(json!({ "type": "address", "value": PAYER })),
+            ],
+            json!({ "type": "map", "value": [
+                entry("amount", json!({ "type": "i128", "value": amount.to_string() })),
+                entry("merchant_id", json!({ "type": "address", "value": PAYER })),
+                entry("resulting_balance", json!({ "type": "i128", "value": "0" })),
+            ] }),
+        )
+    }
+
+    #[test]
+    fn test_decode_payment_events() {
+        let decoded = decode_event(&contracts(), &payment_event("PaymentSettled", 4_900_000))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            decoded,
+            ContractEvent::Payment {
+                kind: PaymentEventKind::Settled,
+                payer: PAYER.to_string(),
+                merchant_id: "merchant_1".to_string(),
+                send_asset: ROUTER.to_string(),
+                send_amount: 5_000_000,
+                settled_amount: 4_900_000,
+            }
+        );
+        assert_eq!(decoded.event_type(), "PaymentSettled");
+
+        for (kind, expected) in [
+            ("PaymentInitiated", PaymentEventKind::Initiated),
+            ("PaymentFailed", PaymentEventKind::Failed),
+        ] {
+            let decoded = decode_event(&contracts(), &payment_event(kind, 0)).unwrap();
+            assert!(
+                matches!(decoded, Some(ContractEvent::Payment { kind, .. }) if kind == expected)
+            );
+        }
+
+        assert_eq!(
+            decode_event(&contracts(), &payment_event("PaymentRetried", 0)).unwrap(),
+            None
+        );
+        assert!(decode_event(&contracts(), &payment_event("PaymentSettled", -1)).is_err());
+        assert!(decode_event(
+            &contracts(),
+            &payment_event("PaymentSettled", i128::from(i64::MAX) + 1)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_decode_vault_events() {
+        assert_eq!(
+            decode_event(&contracts(), &vault_event("balance_credited", 250)).unwrap(),
+            Some(ContractEvent::VaultCredited {
+                vault_address: PAYER.to_string(),
+                amount: 250,
+            })
+        );
+        assert_eq!(
+            decode_event(&contracts(), &vault_event("balance_debited", 100)).unwrap(),
+            Some(ContractEvent::VaultDebited {
+                vault_address: PAYER.to_string(),
+                amount: 100,
+            })
+        );
+        assert_eq!(
+            decode_event(&contracts(), &vault_event("admin_changed", 0)).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_decode_ignores_other_contracts_and_rejects_malformed_events() {
+        let mut unknown = vault_event("balance_credited", 250);
+        unknown.contract_id = PAYER.to_string();
+        assert_eq!(decode_event(&contracts(), &unknown).unwrap(), None);
+
+        let mut malformed = vault_event("balance_credited", 250);
+        malformed.value = encode(json!({ "type": "u32", "value": 1 }));
+        assert!(decode_event(&contracts(), &malformed).is_err());
+
+        let mut garbage = payment_event("PaymentSettled", 1);
+        garbage.topic.push("not xdr".to_string());
+        assert!(decode_event(&contracts(), &garbage).is_err());
     }
 }
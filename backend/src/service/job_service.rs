@@ -0,0 +1,372 @@
+use crate::{
+    api_error::ApiError,
+    config::Config,
+    models::{CreateAuditLogParams, JobStatus, QueuedJob},
+    service::{webhook_service::retry_delay_secs, AuditService, NotificationService},
+};
+use chrono::{DateTime, Utc};
+use deadpool_postgres::{Pool, Transaction};
+use serde::{Deserialize, Serialize};
+use std::{str::FromStr, sync::Arc};
+use tokio::task::JoinSet;
+use uuid::Uuid;
+
+const JOB_COLUMNS: &str = "id, job_type, payload, status, attempts, run_at, last_attempt_at, last_error, completed_at, created_at";
+
+/// Most jobs claimed by one sweep
+const JOB_BATCH_SIZE: i64 = 50;
+
+/// Most jobs returned by one listing
+const MAX_LIST_LIMIT: i64 = 200;
+
+/// A background job, with the payload its handler receives
+///
+/// Jobs may run more than once (e.g. when marking one completed fails after
+/// its handler succeeded), so handlers should tolerate repeats.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "job_type", content = "payload", rename_all = "snake_case")]
+pub enum Job {
+    /// Write an audit log entry
+    AuditLog(CreateAuditLogParams),
+    /// Email a user about one of their notifications
+    NotificationEmail { notification_id: Uuid },
+}
+
+impl Job {
+    /// The `job_type` and `payload` columns of the job
+    fn to_parts(&self) -> Result<(String, serde_json::Value), ApiError> {
+        let mut tagged = serde_json::to_value(self)?;
+        let job_type = tagged["job_type"].as_str().unwrap_or_default().to_string();
+        Ok((job_type, tagged["payload"].take()))
+    }
+
+    fn from_parts(job_type: &str, payload: serde_json::Value) -> Result<Self, serde_json::Error> {
+        serde_json::from_value(serde_json::json!({ "job_type": job_type, "payload": payload }))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JobQuery {
+    pub status: Option<JobStatus>,
+    pub job_type: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// Runs queued jobs with their handlers
+#[derive(Clone)]
+pub struct JobService {
+    db_pool: Arc<Pool>,
+    config: Config,
+    audit: AuditService,
+    notification: NotificationService,
+}
+
+impl JobService {
+    pub fn new(
+        db_pool: Arc<Pool>,
+        config: Config,
+        audit: AuditService,
+        notification: NotificationService,
+    ) -> Self {
+        Self {
+            db_pool,
+            config,
+            audit,
+            notification,
+        }
+    }
+
+    /// Queue `job` on its own, to run at `run_at` or as soon as possible
+    pub async fn enqueue(
+        &self,
+        job: &Job,
+        run_at: Option<DateTime<Utc>>,
+    ) -> Result<Uuid, ApiError> {
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+        let job_id = enqueue(&tx, job, run_at).await?;
+        tx.commit().await?;
+        Ok(job_id)
+    }
+
+    /// Run every due job, returning how many were run
+    ///
+    /// Jobs are claimed with `SKIP LOCKED` and pushed back by the lease before
+    /// they run, so concurrent sweeps never run the same job and one
+    /// interrupted mid-run is run again later.
+    pub async fn run_due(&self) -> Result<usize, ApiError> {
+        let jobs = &self.config.jobs;
+        let client = self.db_pool.get().await?;
+
+        client
+            .execute(
+                "DELETE FROM jobs WHERE status = 'completed' AND completed_at < NOW() - make_interval(secs => $1)",
+                &[&((jobs.retention_hours * 3600) as f64)],
+            )
+            .await?;
+
+        let rows = client
+            .query(
+                r#"
+                WITH due AS (
+                    SELECT id FROM jobs
+                    WHERE status = 'pending' AND run_at <= NOW()
+                    ORDER BY run_at
+                    LIMIT $1
+                    FOR UPDATE SKIP LOCKED
+                )
+                UPDATE jobs j
+                SET run_at = NOW() + make_interval(secs => $2)
+                FROM due
+                WHERE j.id = due.id
+                RETURNING j.id, j.job_type, j.payload, j.attempts
+                "#,
+                &[&JOB_BATCH_SIZE, &(jobs.lease_secs.max(1) as f64)],
+            )
+            .await?;
+        drop(client);
+
+        let mut runs = JoinSet::new();
+        for row in &rows {
+            let (id, job_type, payload, attempts): (Uuid, String, serde_json::Value, i32) =
+                (row.get(0), row.get(1), row.get(2), row.get(3));
+            let service = self.clone();
+            runs.spawn(async move {
+                if let Err(e) = service.run(id, &job_type, payload, attempts).await {
+                    tracing::warn!("Failed to record the outcome of job {}: {}", id, e);
+                }
+            });
+        }
+        while runs.join_next().await.is_some() {}
+
+        Ok(rows.len())
+    }
+
+    async fn run(
+        &self,
+        id: Uuid,
+        job_type: &str,
+        payload: serde_json::Value,
+        attempts: i32,
+    ) -> Result<(), ApiError> {
+        let attempts = attempts + 1;
+        let jobs = &self.config.jobs;
+
+        let (error, retry) = match Job::from_parts(job_type, payload) {
+            Ok(job) => match self.handle(id, job).await {
+                Ok(()) => (None, false),
+                Err(e) => (Some(e.to_string()), attempts < jobs.max_attempts),
+            },
+            // Running it again will not make the payload readable
+            Err(e) => (Some(format!("Invalid {} job: {}", job_type, e)), false),
+        };
+
+        let client = self.db_pool.get().await?;
+        let Some(error) = error else {
+            client
+                .execute(
+                    r#"
+                    UPDATE jobs
+                    SET status = $1, attempts = $2, last_attempt_at = NOW(), last_error = NULL, completed_at = NOW()
+                    WHERE id = $3
+                    "#,
+                    &[&JobStatus::Completed.to_string(), &attempts, &id],
+                )
+                .await?;
+            return Ok(());
+        };
+
+        let (status, retry_secs) = if retry {
+            let delay = retry_delay_secs(attempts, jobs.retry_base_secs, jobs.retry_max_secs);
+            (JobStatus::Pending, delay as f64)
+        } else {
+            tracing::warn!(
+                "{} job {} failed after {} attempts and was dead-lettered: {}",
+                job_type,
+                id,
+                attempts,
+                error
+            );
+            (JobStatus::Dead, 0.0)
+        };
+
+        client
+            .execute(
+                r#"
+                UPDATE jobs
+                SET status = $1, attempts = $2, last_attempt_at = NOW(), last_error = $3,
+                    run_at = NOW() + make_interval(secs => $4)
+                WHERE id = $5
+                "#,
+                &[&status.to_string(), &attempts, &error, &retry_secs, &id],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn handle(&self, id: Uuid, job: Job) -> Result<(), ApiError> {
+        match job {
+            // The entry takes the job's id, so a repeated run writes it once
+            Job::AuditLog(params) => self.audit.record_audit_log(id, params).await,
+            Job::NotificationEmail { notification_id } => {
+                self.notification.send_email(notification_id).await
+            }
+        }
+    }
+
+    /// Jobs newest first, optionally of one status and type
+    pub async fn list_jobs(&self, query: JobQuery) -> Result<Vec<QueuedJob>, ApiError> {
+        let client = self.db_pool.get().await?;
+        let status = query.status.map(|status| status.to_string());
+        let limit = query.limit.unwrap_or(50).clamp(1, MAX_LIST_LIMIT);
+
+        let rows = client
+            .query(
+                &format!(
+                    r#"
+                    SELECT {} FROM jobs
+                    WHERE ($1::text IS NULL OR status = $1) AND ($2::text IS NULL OR job_type = $2)
+                    ORDER BY created_at DESC, id
+                    LIMIT $3
+                    "#,
+                    JOB_COLUMNS
+                ),
+                &[&status, &query.job_type, &limit],
+            )
+            .await?;
+
+        rows.iter().map(job_from_row).collect()
+    }
+
+    pub async fn get_job(&self, job_id: Uuid) -> Result<QueuedJob, ApiError> {
+        let client = self.db_pool.get().await?;
+        let row = client
+            .query_opt(
+                &format!("SELECT {} FROM jobs WHERE id = $1", JOB_COLUMNS),
+                &[&job_id],
+            )
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Job not found".to_string()))?;
+
+        job_from_row(&row)
+    }
+
+    /// Queue a dead-lettered job again with a fresh set of attempts
+    pub async fn retry_job(&self, job_id: Uuid) -> Result<QueuedJob, ApiError> {
+        let client = self.db_pool.get().await?;
+        let row = client
+            .query_opt(
+                &format!(
+                    r#"
+                    UPDATE jobs SET status = $1, attempts = 0, run_at = NOW()
+                    WHERE id = $2 AND status = $3
+                    RETURNING {}
+                    "#,
+                    JOB_COLUMNS
+                ),
+                &[
+                    &JobStatus::Pending.to_string(),
+                    &job_id,
+                    &JobStatus::Dead.to_string(),
+                ],
+            )
+            .await?;
+
+        match row {
+            Some(row) => job_from_row(&row),
+            None => {
+                let job = self.get_job(job_id).await?;
+                Err(ApiError::Conflict(format!(
+                    "Only dead jobs can be retried; this job is {}",
+                    job.status
+                )))
+            }
+        }
+    }
+}
+
+/// Queue `job` as part of `tx`, to run at `run_at` or as soon as possible
+///
+/// Queued in the same transaction as the change that calls for it, a job
+/// runs only if that change is committed.
+pub async fn enqueue(
+    tx: &Transaction<'_>,
+    job: &Job,
+    run_at: Option<DateTime<Utc>>,
+) -> Result<Uuid, ApiError> {
+    let job_id = Uuid::new_v4();
+    let (job_type, payload) = job.to_parts()?;
+
+    tx.execute(
+        "INSERT INTO jobs (id, job_type, payload, run_at) VALUES ($1, $2, $3, COALESCE($4, NOW()))",
+        &[&job_id, &job_type, &payload, &run_at],
+    )
+    .await?;
+
+    Ok(job_id)
+}
+
+fn job_from_row(row: &tokio_postgres::Row) -> Result<QueuedJob, ApiError> {
+    Ok(QueuedJob {
+        id: row.get::<_, Uuid>(0).to_string(),
+        job_type: row.get(1),
+        payload: row.get(2),
+        status: JobStatus::from_str(row.get(3)).map_err(|e| {
+            tracing::error!("{}", e);
+            ApiError::InternalServerError
+        })?,
+        attempts: row.get(4),
+        run_at: row.get(5),
+        last_attempt_at: row.get(6),
+        last_error: row.get(7),
+        completed_at: row.get(8),
+        created_at: row.get(9),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_parts_round_trip() {
+        let notification_id = Uuid::new_v4();
+        let job = Job::NotificationEmail { notification_id };
+
+        let (job_type, payload) = job.to_parts().unwrap();
+        assert_eq!(job_type, "notification_email");
+        assert_eq!(
+            payload,
+            serde_json::json!({ "notification_id": notification_id })
+        );
+        assert!(matches!(
+            Job::from_parts(&job_type, payload).unwrap(),
+            Job::NotificationEmail { notification_id: id } if id == notification_id
+        ));
+
+        let (job_type, payload) = Job::AuditLog(CreateAuditLogParams {
+            actor_id: "user_1".to_string(),
+            action: "create_payments".to_string(),
+            resource: "payments".to_string(),
+            resource_id: None,
+            metadata: None,
+            ip_address: None,
+            user_agent: None,
+            occurred_at: None,
+        })
+        .to_parts()
+        .unwrap();
+        assert_eq!(job_type, "audit_log");
+        assert!(matches!(
+            Job::from_parts(&job_type, payload).unwrap(),
+            Job::AuditLog(params) if params.actor_id == "user_1"
+        ));
+    }
+
+    #[test]
+    fn test_unknown_jobs_are_rejected() {
+        assert!(Job::from_parts("fax", serde_json::json!({})).is_err());
+        assert!(Job::from_parts("notification_email", serde_json::json!({})).is_err());
+    }
+}
@@ -0,0 +1,355 @@
+use crate::{
+    api_error::ApiError,
+    config::Config,
+    models::{KycLevel, KycProfile, KycStatus},
+    service::{anchor_service::Sep12Customer, AnchorService, IdentityService},
+};
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+
+const PROFILE_COLUMNS: &str =
+    "user_id, customer_id, level, requested_level, status, message, created_at, updated_at";
+
+/// KYC of users with the anchor's SEP-12 server, and the checks gated on it
+///
+/// Users submit their SEP-9 details here; they are forwarded to the anchor
+/// and the outcome is kept in `kyc_profiles`, updated by polling and by the
+/// anchor's status callbacks. Money-moving services ask `require_kyc` before
+/// acting, which only enforces anything when `anchor.kyc_required` is set.
+#[derive(Clone)]
+pub struct KycService {
+    db_pool: Arc<Pool>,
+    config: Config,
+    identity: IdentityService,
+    anchor: AnchorService,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubmitKycRequest {
+    /// Level the submission is for: `basic` or `full`
+    pub level: KycLevel,
+    /// SEP-9 fields such as `first_name` or `id_number`
+    pub fields: HashMap<String, String>,
+}
+
+impl KycService {
+    pub fn new(
+        db_pool: Arc<Pool>,
+        config: Config,
+        identity: IdentityService,
+        anchor: AnchorService,
+    ) -> Self {
+        Self {
+            db_pool,
+            config,
+            identity,
+            anchor,
+        }
+    }
+
+    /// Whether the anchor has accepted the user at `level` or above
+    pub async fn check_kyc_status(&self, user_id: &str, level: KycLevel) -> Result<bool, ApiError> {
+        Ok(self
+            .find_profile(user_id)
+            .await?
+            .is_some_and(|profile| profile.level >= level))
+    }
+
+    /// Fail with a compliance error unless the user has `level` KYC, when KYC is required
+    ///
+    /// `action` completes the message, e.g. "withdrawing".
+    pub async fn require_kyc(
+        &self,
+        user_id: &str,
+        level: KycLevel,
+        action: &str,
+    ) -> Result<(), ApiError> {
+        if !self.config.anchor_config.kyc_required || self.check_kyc_status(user_id, level).await? {
+            return Ok(());
+        }
+
+        Err(ApiError::Compliance(format!(
+            "{} KYC verification is required before {}",
+            capitalize(&level.to_string()),
+            action
+        )))
+    }
+
+    pub async fn get_profile(&self, user_id: &str) -> Result<KycProfile, ApiError> {
+        self.find_profile(user_id)
+            .await?
+            .ok_or_else(|| ApiError::NotFound("KYC profile not found".to_string()))
+    }
+
+    /// Send the user's details to the anchor and record where the review stands
+    ///
+    /// A level already accepted is kept while a higher one is under review.
+    pub async fn submit(
+        &self,
+        user_id: &str,
+        request: SubmitKycRequest,
+    ) -> Result<KycProfile, ApiError> {
+        if request.level == KycLevel::None {
+            return Err(ApiError::Validation(
+                "level must be basic or full".to_string(),
+            ));
+        }
+        let missing: Vec<&str> = request
+            .level
+            .required_fields()
+            .iter()
+            .copied()
+            .filter(|field| {
+                request
+                    .fields
+                    .get(*field)
+                    .is_none_or(|value| value.trim().is_empty())
+            })
+            .collect();
+        if !missing.is_empty() {
+            return Err(ApiError::Validation(format!(
+                "Missing KYC fields: {}",
+                missing.join(", ")
+            )));
+        }
+
+        let existing = self.find_profile(user_id).await?;
+        let keypair = self.identity.get_user_keypair(user_id).await?;
+        let account = keypair.address();
+        let token = self.anchor.authenticate(&keypair).await?;
+
+        let known_id = existing
+            .as_ref()
+            .and_then(|profile| profile.customer_id.as_deref());
+        let customer_id = self
+            .anchor
+            .put_customer(&token, known_id, &account, &request.fields)
+            .await?;
+        let customer = self
+            .anchor
+            .get_customer(&token, Some(&customer_id), &account)
+            .await?;
+        let status = parse_customer_status(&customer)?;
+
+        let current_level = existing
+            .as_ref()
+            .map_or(KycLevel::None, |profile| profile.level);
+        let level = granted_level(current_level, request.level, status);
+
+        let client = self.db_pool.get().await?;
+        client
+            .execute(
+                r#"
+                INSERT INTO kyc_profiles (user_id, customer_id, level, requested_level, status, message)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (user_id) DO UPDATE SET
+                    customer_id = EXCLUDED.customer_id,
+                    level = EXCLUDED.level,
+                    requested_level = EXCLUDED.requested_level,
+                    status = EXCLUDED.status,
+                    message = EXCLUDED.message,
+                    updated_at = NOW()
+                "#,
+                &[
+                    &user_id,
+                    &customer_id,
+                    &level.to_string(),
+                    &request.level.to_string(),
+                    &status.to_string(),
+                    &customer.message,
+                ],
+            )
+            .await?;
+        drop(client);
+
+        if let (Some(url), None) = (&self.config.anchor_config.sep12_callback_url, known_id) {
+            if let Err(e) = self
+                .anchor
+                .register_customer_callback(&token, &customer_id, url)
+                .await
+            {
+                tracing::warn!(
+                    "Failed to register KYC callback for {}, relying on polling: {}",
+                    user_id,
+                    e
+                );
+            }
+        }
+
+        self.get_profile(user_id).await
+    }
+
+    /// Poll the anchor for every profile still under review
+    ///
+    /// Returns the number of profiles checked. A failure on one profile is
+    /// logged and does not stop the others.
+    pub async fn poll_pending(&self) -> Result<usize, ApiError> {
+        let client = self.db_pool.get().await?;
+        let rows = client
+            .query(
+                &format!(
+                    "SELECT {} FROM kyc_profiles WHERE status IN ('processing', 'needs_info') AND customer_id IS NOT NULL ORDER BY updated_at",
+                    PROFILE_COLUMNS
+                ),
+                &[],
+            )
+            .await?;
+        drop(client);
+
+        for row in &rows {
+            let profile = profile_from_row(row)?;
+            if let Err(e) = self.refresh(&profile).await {
+                tracing::warn!("Failed to update KYC of {}: {}", profile.user_id, e);
+            }
+        }
+
+        Ok(rows.len())
+    }
+
+    /// Handle the anchor's status callback for a customer
+    ///
+    /// Only the customer id is taken from the callback; the status is fetched
+    /// from the anchor itself.
+    pub async fn handle_callback(&self, customer_id: &str) -> Result<KycProfile, ApiError> {
+        let client = self.db_pool.get().await?;
+        let row = client
+            .query_opt(
+                &format!(
+                    "SELECT {} FROM kyc_profiles WHERE customer_id = $1",
+                    PROFILE_COLUMNS
+                ),
+                &[&customer_id],
+            )
+            .await?
+            .ok_or_else(|| ApiError::NotFound("KYC profile not found".to_string()))?;
+        drop(client);
+
+        let profile = profile_from_row(&row)?;
+        self.refresh(&profile).await?;
+        self.get_profile(&profile.user_id).await
+    }
+
+    async fn refresh(&self, profile: &KycProfile) -> Result<(), ApiError> {
+        let keypair = self.identity.get_user_keypair(&profile.user_id).await?;
+        let token = self.anchor.authenticate(&keypair).await?;
+        let customer = self
+            .anchor
+            .get_customer(&token, profile.customer_id.as_deref(), &keypair.address())
+            .await?;
+        let status = parse_customer_status(&customer)?;
+        let level = granted_level(profile.level, profile.requested_level, status);
+
+        let client = self.db_pool.get().await?;
+        client
+            .execute(
+                "UPDATE kyc_profiles SET level = $1, status = $2, message = $3, updated_at = NOW() WHERE user_id = $4",
+                &[
+                    &level.to_string(),
+                    &status.to_string(),
+                    &customer.message,
+                    &profile.user_id,
+                ],
+            )
+            .await?;
+
+        if status != profile.status {
+            tracing::info!(
+                "KYC of {} moved from {} to {} at level {}",
+                profile.user_id,
+                profile.status,
+                status,
+                level
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn find_profile(&self, user_id: &str) -> Result<Option<KycProfile>, ApiError> {
+        let client = self.db_pool.get().await?;
+        let row = client
+            .query_opt(
+                &format!(
+                    "SELECT {} FROM kyc_profiles WHERE user_id = $1",
+                    PROFILE_COLUMNS
+                ),
+                &[&user_id],
+            )
+            .await?;
+
+        row.as_ref().map(profile_from_row).transpose()
+    }
+}
+
+/// Level a user holds once the anchor reports `status` for a submission at `requested`
+///
+/// Acceptance grants the requested level and rejection revokes everything;
+/// while the anchor is still reviewing, the level already held stands.
+fn granted_level(current: KycLevel, requested: KycLevel, status: KycStatus) -> KycLevel {
+    match status {
+        KycStatus::Accepted => requested,
+        KycStatus::Rejected => KycLevel::None,
+        KycStatus::Processing | KycStatus::NeedsInfo => current,
+    }
+}
+
+fn parse_customer_status(customer: &Sep12Customer) -> Result<KycStatus, ApiError> {
+    customer.status.parse().map_err(ApiError::Anchor)
+}
+
+fn capitalize(value: &str) -> String {
+    let mut chars = value.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn profile_from_row(row: &tokio_postgres::Row) -> Result<KycProfile, ApiError> {
+    Ok(KycProfile {
+        user_id: row.get(0),
+        customer_id: row.get(1),
+        level: parse_column(row.get(2))?,
+        requested_level: parse_column(row.get(3))?,
+        status: parse_column(row.get(4))?,
+        message: row.get(5),
+        created_at: row.get::<_, chrono::DateTime<chrono::Utc>>(6),
+        updated_at: row.get::<_, chrono::DateTime<chrono::Utc>>(7),
+    })
+}
+
+/// Parse a level or status read from the database, treating unknown values as an internal error
+fn parse_column<T: std::str::FromStr<Err = String>>(value: &str) -> Result<T, ApiError> {
+    value.parse().map_err(|e| {
+        tracing::error!("{} in database", e);
+        ApiError::InternalServerError
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_granted_level() {
+        use KycLevel::*;
+        assert_eq!(granted_level(None, Basic, KycStatus::Accepted), Basic);
+        assert_eq!(granted_level(Basic, Full, KycStatus::Processing), Basic);
+        assert_eq!(granted_level(Basic, Full, KycStatus::NeedsInfo), Basic);
+        assert_eq!(granted_level(Basic, Full, KycStatus::Accepted), Full);
+        assert_eq!(granted_level(Full, Full, KycStatus::Rejected), None);
+    }
+
+    #[test]
+    fn test_levels_and_statuses() {
+        assert!(KycLevel::Full > KycLevel::Basic && KycLevel::Basic > KycLevel::None);
+        assert!(KycLevel::Basic
+            .required_fields()
+            .iter()
+            .all(|field| KycLevel::Full.required_fields().contains(field)));
+        assert_eq!("NEEDS_INFO".parse::<KycStatus>(), Ok(KycStatus::NeedsInfo));
+        assert_eq!("accepted".parse::<KycStatus>(), Ok(KycStatus::Accepted));
+        assert!("VERIFIED".parse::<KycStatus>().is_err());
+    }
+}
@@ -0,0 +1,238 @@
+use crate::{api_error::ApiError, config::Config};
+use deadpool_postgres::Pool;
+use serde::Serialize;
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+use tokio_postgres::{Client, NoTls};
+
+/// First key of every worker's advisory lock, keeping them apart from other advisory locks
+const LOCK_NAMESPACE: i32 = 0x5a61_7073;
+
+/// Prefix of the `application_name` of lock sessions, followed by the instance id
+const APPLICATION_NAME_PREFIX: &str = "zaps-worker:";
+
+/// Elects one backend instance to run each background worker
+///
+/// Leadership of a worker is a Postgres session-level advisory lock held on a
+/// connection of its own. When the leader exits or loses its connection the
+/// lock is released with the session, and the next instance to try takes it.
+#[derive(Clone)]
+pub struct LeaderService {
+    db_pool: Arc<Pool>,
+    config: Config,
+    instance_id: String,
+    /// Workers this instance has competed for, in the order they were started
+    workers: Arc<Mutex<Vec<&'static str>>>,
+}
+
+/// Which instance leads a worker, as shown on the admin system health endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkerLeader {
+    pub worker: String,
+    /// Instance holding the worker's lock, if any
+    pub leader: Option<String>,
+    pub is_this_instance: bool,
+}
+
+/// Leadership of one worker, owned by the task that runs it
+pub struct LeaderLock {
+    worker: &'static str,
+    key: i32,
+    database_url: String,
+    application_name: String,
+    session: Option<Client>,
+    leading: bool,
+}
+
+impl LeaderService {
+    pub fn new(db_pool: Arc<Pool>, config: Config) -> Self {
+        let instance_id = Some(config.workers.instance_id.clone())
+            .filter(|id| !id.is_empty())
+            .or_else(|| std::env::var("HOSTNAME").ok().filter(|id| !id.is_empty()))
+            .unwrap_or_else(|| uuid::Uuid::new_v4().simple().to_string());
+
+        Self {
+            db_pool,
+            config,
+            instance_id,
+            workers: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn instance_id(&self) -> &str {
+        &self.instance_id
+    }
+
+    /// The lock `worker` is run under on this instance
+    pub fn lock(&self, worker: &'static str) -> LeaderLock {
+        let mut workers = self.workers.lock().unwrap_or_else(|e| e.into_inner());
+        if !workers.contains(&worker) {
+            workers.push(worker);
+        }
+
+        LeaderLock {
+            worker,
+            key: lock_key(worker),
+            database_url: self.config.database.url.clone(),
+            application_name: format!("{}{}", APPLICATION_NAME_PREFIX, self.instance_id),
+            session: None,
+            leading: false,
+        }
+    }
+
+    /// Current leader of each worker this instance runs
+    pub async fn leaders(&self) -> Result<Vec<WorkerLeader>, ApiError> {
+        let workers = self
+            .workers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        let client = self.db_pool.get().await?;
+        let rows = client
+            .query(
+                r#"
+                SELECT l.objid::bigint, a.application_name
+                FROM pg_locks l
+                JOIN pg_stat_activity a ON a.pid = l.pid
+                WHERE l.locktype = 'advisory' AND l.granted
+                  AND l.classid::bigint = $1 AND l.objsubid = 2
+                "#,
+                &[&i64::from(LOCK_NAMESPACE)],
+            )
+            .await?;
+
+        Ok(workers
+            .into_iter()
+            .map(|worker| {
+                // pg_locks reports the key as an unsigned oid
+                let objid = i64::from(lock_key(worker) as u32);
+                let leader = rows
+                    .iter()
+                    .find(|row| row.get::<_, i64>(0) == objid)
+                    .and_then(|row| row.get::<_, Option<String>>(1))
+                    .map(|name| {
+                        name.strip_prefix(APPLICATION_NAME_PREFIX)
+                            .unwrap_or(&name)
+                            .to_string()
+                    });
+                WorkerLeader {
+                    worker: worker.to_string(),
+                    is_this_instance: leader.as_deref() == Some(self.instance_id.as_str()),
+                    leader,
+                }
+            })
+            .collect())
+    }
+}
+
+impl LeaderLock {
+    /// Whether this instance leads the worker, taking the lock if it is free
+    ///
+    /// Failures are logged and reported as not leading, so the worker is
+    /// skipped rather than possibly run twice.
+    pub async fn acquire(&mut self) -> bool {
+        let was_leading = self.leading;
+        self.leading = match self.try_acquire().await {
+            Ok(leading) => leading,
+            Err(e) => {
+                tracing::error!(
+                    "Leader election for the {} worker failed: {}",
+                    self.worker,
+                    e
+                );
+                false
+            }
+        };
+
+        if self.leading && !was_leading {
+            tracing::info!("This instance now leads the {} worker", self.worker);
+        } else if was_leading && !self.leading {
+            tracing::warn!(
+                "This instance lost leadership of the {} worker",
+                self.worker
+            );
+        }
+        self.leading
+    }
+
+    /// A session that fails a query is dropped, since it may or may not still
+    /// hold the lock; closing it releases the lock either way.
+    async fn try_acquire(&mut self) -> Result<bool, tokio_postgres::Error> {
+        let session = match self.session.take() {
+            Some(session) if !session.is_closed() => session,
+            _ => {
+                self.leading = false;
+                self.connect().await?
+            }
+        };
+
+        let leading = if self.leading {
+            // The lock lives as long as the session; check the session is still there
+            session.execute("SELECT 1", &[]).await?;
+            true
+        } else {
+            session
+                .query_one(
+                    "SELECT pg_try_advisory_lock($1, $2)",
+                    &[&LOCK_NAMESPACE, &self.key],
+                )
+                .await?
+                .get(0)
+        };
+
+        self.session = Some(session);
+        Ok(leading)
+    }
+
+    async fn connect(&self) -> Result<Client, tokio_postgres::Error> {
+        let mut config = tokio_postgres::Config::from_str(&self.database_url)?;
+        config.application_name(&self.application_name);
+
+        let (client, connection) = config.connect(NoTls).await?;
+        let worker = self.worker;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                tracing::warn!("Leader lock session of the {} worker ended: {}", worker, e);
+            }
+        });
+        Ok(client)
+    }
+}
+
+/// Second key of a worker's advisory lock: the FNV-1a hash of its name, stable across builds
+fn lock_key(worker: &str) -> i32 {
+    let hash = worker.bytes().fold(0x811c_9dc5_u32, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    });
+    hash as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_keys() {
+        assert_eq!(lock_key(""), 0x811c_9dc5_u32 as i32);
+        assert_eq!(lock_key("a"), 0xe40c_292c_u32 as i32);
+
+        let workers = [
+            "withdrawal status",
+            "deposit status",
+            "settlement status",
+            "transfer status",
+            "merchant registry",
+            "KYC status",
+            "webhook delivery",
+            "refund processing",
+            "contract event indexing",
+            "job queue",
+        ];
+        let mut keys: Vec<i32> = workers.iter().map(|worker| lock_key(worker)).collect();
+        keys.sort_unstable();
+        keys.dedup();
+        assert_eq!(keys.len(), workers.len());
+    }
+}
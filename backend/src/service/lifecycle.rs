@@ -0,0 +1,253 @@
+//! Enforced status transitions with a recorded history
+//!
+//! Payments, transfers, withdrawals, deposits, settlements and refunds share the same shape: a `status`
+//! column on the entity table and an `<entity>_events` table holding every
+//! change. Status writes go through `transition` so illegal moves are
+//! rejected and the history cannot drift from the current status.
+
+use crate::{
+    api_error::ApiError,
+    models::{Lifecycle, StatusEvent},
+};
+use deadpool_postgres::{Client, Transaction};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LifecycleEntity {
+    Payment,
+    Transfer,
+    Withdrawal,
+    Deposit,
+    Settlement,
+    Refund,
+}
+
+impl LifecycleEntity {
+    fn name(&self) -> &'static str {
+        match self {
+            LifecycleEntity::Payment => "payment",
+            LifecycleEntity::Transfer => "transfer",
+            LifecycleEntity::Withdrawal => "withdrawal",
+            LifecycleEntity::Deposit => "deposit",
+            LifecycleEntity::Settlement => "settlement",
+            LifecycleEntity::Refund => "refund",
+        }
+    }
+
+    fn table(&self) -> &'static str {
+        match self {
+            LifecycleEntity::Payment => "payments",
+            LifecycleEntity::Transfer => "transfers",
+            LifecycleEntity::Withdrawal => "withdrawals",
+            LifecycleEntity::Deposit => "deposits",
+            LifecycleEntity::Settlement => "settlements",
+            LifecycleEntity::Refund => "refunds",
+        }
+    }
+
+    fn events_table(&self) -> &'static str {
+        match self {
+            LifecycleEntity::Payment => "payment_events",
+            LifecycleEntity::Transfer => "transfer_events",
+            LifecycleEntity::Withdrawal => "withdrawal_events",
+            LifecycleEntity::Deposit => "deposit_events",
+            LifecycleEntity::Settlement => "settlement_events",
+            LifecycleEntity::Refund => "refund_events",
+        }
+    }
+
+    fn foreign_key(&self) -> &'static str {
+        match self {
+            LifecycleEntity::Payment => "payment_id",
+            LifecycleEntity::Transfer => "transfer_id",
+            LifecycleEntity::Withdrawal => "withdrawal_id",
+            LifecycleEntity::Deposit => "deposit_id",
+            LifecycleEntity::Settlement => "settlement_id",
+            LifecycleEntity::Refund => "refund_id",
+        }
+    }
+}
+
+/// Parse a status read from the database, treating unknown values as an internal error
+pub fn parse_status<S: Lifecycle>(entity: LifecycleEntity, value: &str) -> Result<S, ApiError> {
+    value.parse().map_err(|_| {
+        tracing::error!("Unknown {} status in database: {}", entity.name(), value);
+        ApiError::InternalServerError
+    })
+}
+
+/// Record the initial status of a newly inserted entity
+pub async fn record_created<S: Lifecycle>(
+    tx: &Transaction<'_>,
+    entity: LifecycleEntity,
+    id: Uuid,
+    status: S,
+    reason: Option<&str>,
+) -> Result<(), ApiError> {
+    insert_event(tx, entity, id, None, &status.to_string(), reason).await
+}
+
+/// Move an entity to `next`, rejecting transitions its lifecycle does not allow
+///
+/// The row is locked for the duration of the transaction so concurrent
+/// updates are serialised. `tx_hash`, when given, is stored alongside the new
+/// status. Returns the status the entity moved from.
+pub async fn transition<S: Lifecycle>(
+    tx: &Transaction<'_>,
+    entity: LifecycleEntity,
+    id: Uuid,
+    next: S,
+    reason: &str,
+    tx_hash: Option<&str>,
+) -> Result<S, ApiError> {
+    let row = tx
+        .query_opt(
+            &format!(
+                "SELECT status FROM {} WHERE id = $1 FOR UPDATE",
+                entity.table()
+            ),
+            &[&id],
+        )
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("{} not found", capitalize(entity.name()))))?;
+
+    let current: S = parse_status(entity, row.get(0))?;
+    if !current.can_transition_to(next) {
+        return Err(ApiError::Conflict(format!(
+            "Cannot move {} from {} to {}",
+            entity.name(),
+            current,
+            next
+        )));
+    }
+
+    tx.execute(
+        &format!(
+            "UPDATE {} SET status = $1, tx_hash = COALESCE($2, tx_hash), updated_at = NOW() WHERE id = $3",
+            entity.table()
+        ),
+        &[&next.to_string(), &tx_hash, &id],
+    )
+    .await?;
+
+    insert_event(
+        tx,
+        entity,
+        id,
+        Some(&current.to_string()),
+        &next.to_string(),
+        Some(reason),
+    )
+    .await?;
+
+    Ok(current)
+}
+
+/// Status history of an entity, oldest first
+pub async fn history(
+    client: &Client,
+    entity: LifecycleEntity,
+    id: Uuid,
+) -> Result<Vec<StatusEvent>, ApiError> {
+    let rows = client
+        .query(
+            &format!(
+                "SELECT id, from_status, to_status, reason, created_at FROM {} WHERE {} = $1 ORDER BY created_at, id",
+                entity.events_table(),
+                entity.foreign_key()
+            ),
+            &[&id],
+        )
+        .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| StatusEvent {
+            id: row.get::<_, Uuid>(0).to_string(),
+            from_status: row.get(1),
+            to_status: row.get(2),
+            reason: row.get(3),
+            created_at: row.get::<_, chrono::DateTime<chrono::Utc>>(4),
+        })
+        .collect())
+}
+
+async fn insert_event(
+    tx: &Transaction<'_>,
+    entity: LifecycleEntity,
+    id: Uuid,
+    from_status: Option<&str>,
+    to_status: &str,
+    reason: Option<&str>,
+) -> Result<(), ApiError> {
+    tx.execute(
+        &format!(
+            "INSERT INTO {} ({}, from_status, to_status, reason) VALUES ($1, $2, $3, $4)",
+            entity.events_table(),
+            entity.foreign_key()
+        ),
+        &[&id, &from_status, &to_status, &reason],
+    )
+    .await?;
+
+    Ok(())
+}
+
+fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        DepositStatus, PaymentStatus, RefundStatus, SettlementStatus, TransferStatus,
+        WithdrawalStatus,
+    };
+
+    #[test]
+    fn test_payment_transitions() {
+        use PaymentStatus::*;
+        assert!(Pending.can_transition_to(Processing));
+        assert!(Pending.can_transition_to(Failed));
+        assert!(Processing.can_transition_to(Completed));
+        assert!(Completed.can_transition_to(Refunded));
+
+        assert!(!Pending.can_transition_to(Completed));
+        assert!(!Pending.can_transition_to(Pending));
+        assert!(!Completed.can_transition_to(Failed));
+        assert!(!Failed.can_transition_to(Processing));
+        assert!(Failed.is_terminal());
+        assert!(Refunded.is_terminal());
+        assert!(!Completed.is_terminal());
+    }
+
+    #[test]
+    fn test_transfer_withdrawal_deposit_settlement_and_refund_transitions() {
+        assert!(TransferStatus::Processing.can_transition_to(TransferStatus::Completed));
+        assert!(!TransferStatus::Completed.can_transition_to(TransferStatus::Failed));
+        assert!(WithdrawalStatus::Pending.can_transition_to(WithdrawalStatus::Failed));
+        assert!(!WithdrawalStatus::Failed.can_transition_to(WithdrawalStatus::Pending));
+        assert!(DepositStatus::Processing.can_transition_to(DepositStatus::Completed));
+        assert!(!DepositStatus::Pending.can_transition_to(DepositStatus::Completed));
+        assert!(SettlementStatus::Pending.can_transition_to(SettlementStatus::Failed));
+        assert!(SettlementStatus::Completed.is_terminal());
+        assert!(RefundStatus::Pending.can_transition_to(RefundStatus::Failed));
+        assert!(!RefundStatus::Pending.can_transition_to(RefundStatus::Completed));
+        assert!(RefundStatus::Completed.is_terminal());
+    }
+
+    #[test]
+    fn test_parse_status_rejects_unknown_values() {
+        assert_eq!(
+            parse_status::<PaymentStatus>(LifecycleEntity::Payment, "refunded").unwrap(),
+            PaymentStatus::Refunded
+        );
+        assert!(parse_status::<PaymentStatus>(LifecycleEntity::Payment, "settled").is_err());
+        assert!(parse_status::<TransferStatus>(LifecycleEntity::Transfer, "refunded").is_err());
+    }
+}
@@ -0,0 +1,630 @@
+use crate::{
+    api_error::ApiError,
+    config::Config,
+    models::Merchant,
+    service::{soroban_service::Submission, SorobanService},
+    stellar::{asset, validate_account_or_contract_address},
+};
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub(crate) const MERCHANT_COLUMNS: &str = "id, merchant_id, business_name, contact_email, website, vault_address, settlement_asset, active, created_at, updated_at, registry_status";
+
+/// Longest merchant id; the id is also the merchant's key in the registry contract
+const MAX_MERCHANT_ID_LEN: usize = 64;
+
+/// Onboarding and management of merchants and the users who operate them
+///
+/// Merchants are registered with the zaps-registry contract when it is
+/// configured, and deactivated there too. Changes are committed here first
+/// and mirrored by the registry worker, which signs and records each call
+/// before sending it and reconciles it by hash. The contract cannot change a
+/// registered merchant's vault or settlement asset, nor reactivate one, so
+/// those changes are refused while it is in use rather than letting the
+/// registry and the database disagree about where payments go.
+#[derive(Clone)]
+pub struct MerchantService {
+    db_pool: Arc<Pool>,
+    config: Config,
+    soroban: SorobanService,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateMerchantRequest {
+    pub merchant_id: String,
+    pub business_name: String,
+    pub contact_email: Option<String>,
+    pub website: Option<String>,
+    /// Vault contract (`C...`) or account (`G...`) payments settle into
+    pub vault_address: String,
+    /// `XLM` or one of the configured asset codes
+    pub settlement_asset: String,
+    /// User to link as the merchant's operator; only admins may set it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operator_user_id: Option<String>,
+}
+
+/// Fields to change; those left out keep their value
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UpdateMerchantRequest {
+    pub business_name: Option<String>,
+    pub contact_email: Option<String>,
+    pub website: Option<String>,
+    pub vault_address: Option<String>,
+    pub settlement_asset: Option<String>,
+}
+
+impl MerchantService {
+    pub fn new(db_pool: Arc<Pool>, config: Config, soroban: SorobanService) -> Self {
+        Self {
+            db_pool,
+            config,
+            soroban,
+        }
+    }
+
+    /// Create a merchant and link its operator
+    ///
+    /// With the registry contract configured the merchant is created inactive
+    /// and `registering`; the registry worker activates it once registered.
+    pub async fn create_merchant(
+        &self,
+        request: CreateMerchantRequest,
+    ) -> Result<Merchant, ApiError> {
+        validate_merchant_id(&request.merchant_id)?;
+        let business_name = required_text("business_name", &request.business_name)?;
+        let contact_email = validate_email(request.contact_email)?;
+        let website = optional_text("website", request.website)?;
+        validate_account_or_contract_address("vault_address", &request.vault_address)?;
+        let settlement_asset = self.settlement_asset(&request.settlement_asset)?;
+
+        let registering = self.soroban.registry_enabled();
+
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+
+        let row = tx
+            .query_opt(
+                &format!(
+                    r#"
+                    INSERT INTO merchants (id, merchant_id, business_name, contact_email, website, vault_address, settlement_asset, active, registry_status)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, NOT $8, CASE WHEN $8 THEN 'registering' END)
+                    ON CONFLICT (merchant_id) DO NOTHING
+                    RETURNING {}
+                    "#,
+                    MERCHANT_COLUMNS
+                ),
+                &[
+                    &Uuid::new_v4(),
+                    &request.merchant_id,
+                    &business_name,
+                    &contact_email,
+                    &website,
+                    &request.vault_address,
+                    &settlement_asset,
+                    &registering,
+                ],
+            )
+            .await?
+            .ok_or_else(|| ApiError::Conflict("Merchant id is already taken".to_string()))?;
+        let merchant = merchant_from_row(&row);
+
+        if let Some(user_id) = &request.operator_user_id {
+            link_operator(&tx, &merchant.merchant_id, user_id).await?;
+        }
+
+        tx.commit().await?;
+        Ok(merchant)
+    }
+
+    /// A merchant, active or not
+    pub async fn get_merchant(&self, merchant_id: &str) -> Result<Merchant, ApiError> {
+        let client = self.db_pool.get().await?;
+        let row = client
+            .query_opt(
+                &format!(
+                    "SELECT {} FROM merchants WHERE merchant_id = $1",
+                    MERCHANT_COLUMNS
+                ),
+                &[&merchant_id],
+            )
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Merchant not found".to_string()))?;
+
+        Ok(merchant_from_row(&row))
+    }
+
+    /// Every merchant, newest first
+    pub async fn list_merchants(&self) -> Result<Vec<Merchant>, ApiError> {
+        let client = self.db_pool.get().await?;
+        let rows = client
+            .query(
+                &format!(
+                    "SELECT {} FROM merchants ORDER BY created_at DESC",
+                    MERCHANT_COLUMNS
+                ),
+                &[],
+            )
+            .await?;
+
+        Ok(rows.iter().map(merchant_from_row).collect())
+    }
+
+    /// The merchants a user operates, newest first
+    pub async fn list_operated_merchants(&self, user_id: &str) -> Result<Vec<Merchant>, ApiError> {
+        let client = self.db_pool.get().await?;
+        let rows = client
+            .query(
+                &format!(
+                    r#"
+                    SELECT {} FROM merchants
+                    WHERE merchant_id IN (SELECT merchant_id FROM merchant_users WHERE user_id = $1)
+                    ORDER BY created_at DESC
+                    "#,
+                    MERCHANT_COLUMNS
+                ),
+                &[&user_id],
+            )
+            .await?;
+
+        Ok(rows.iter().map(merchant_from_row).collect())
+    }
+
+    pub async fn is_operator(&self, merchant_id: &str, user_id: &str) -> Result<bool, ApiError> {
+        let client = self.db_pool.get().await?;
+        let row = client
+            .query_opt(
+                "SELECT 1 FROM merchant_users WHERE merchant_id = $1 AND user_id = $2",
+                &[&merchant_id, &user_id],
+            )
+            .await?;
+
+        Ok(row.is_some())
+    }
+
+    /// Let another user operate the merchant; adding an operator twice is a no-op
+    pub async fn add_operator(&self, merchant_id: &str, user_id: &str) -> Result<(), ApiError> {
+        self.get_merchant(merchant_id).await?;
+
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+        link_operator(&tx, merchant_id, user_id).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Change a merchant's profile or settlement details
+    pub async fn update_merchant(
+        &self,
+        merchant_id: &str,
+        request: UpdateMerchantRequest,
+    ) -> Result<Merchant, ApiError> {
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+
+        // Locked so a concurrent update or the registry worker cannot change it under us
+        let row = tx
+            .query_opt(
+                &format!(
+                    "SELECT {} FROM merchants WHERE merchant_id = $1 FOR UPDATE",
+                    MERCHANT_COLUMNS
+                ),
+                &[&merchant_id],
+            )
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Merchant not found".to_string()))?;
+        let current = merchant_from_row(&row);
+
+        let business_name = match &request.business_name {
+            Some(name) => Some(required_text("business_name", name)?),
+            None => current.business_name.clone(),
+        };
+        let contact_email = match request.contact_email {
+            Some(email) => validate_email(Some(email))?,
+            None => current.contact_email.clone(),
+        };
+        let website = match request.website {
+            Some(website) => optional_text("website", Some(website))?,
+            None => current.website.clone(),
+        };
+        let vault_address = match request.vault_address {
+            Some(vault) => {
+                validate_account_or_contract_address("vault_address", &vault)?;
+                vault
+            }
+            None => current.vault_address.clone(),
+        };
+        let settlement_asset = match &request.settlement_asset {
+            Some(asset) => self.settlement_asset(asset)?,
+            None => current.settlement_asset.clone(),
+        };
+
+        let settlement_changed =
+            vault_address != current.vault_address || settlement_asset != current.settlement_asset;
+        if settlement_changed && self.soroban.registry_enabled() {
+            return Err(ApiError::Conflict(
+                "The registry contract cannot change a registered merchant's vault or settlement asset"
+                    .to_string(),
+            ));
+        }
+
+        let row = tx
+            .query_one(
+                &format!(
+                    r#"
+                    UPDATE merchants
+                    SET business_name = $1, contact_email = $2, website = $3,
+                        vault_address = $4, settlement_asset = $5, updated_at = NOW()
+                    WHERE merchant_id = $6
+                    RETURNING {}
+                    "#,
+                    MERCHANT_COLUMNS
+                ),
+                &[
+                    &business_name,
+                    &contact_email,
+                    &website,
+                    &vault_address,
+                    &settlement_asset,
+                    &merchant_id,
+                ],
+            )
+            .await?;
+        tx.commit().await?;
+
+        Ok(merchant_from_row(&row))
+    }
+
+    /// Stop the merchant accepting payments, here and in the registry contract
+    ///
+    /// The merchant is inactive once this returns; with the registry contract
+    /// configured it is left `deactivating` for the registry worker. Deactivating
+    /// an inactive merchant is a no-op.
+    pub async fn deactivate_merchant(&self, merchant_id: &str) -> Result<Merchant, ApiError> {
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+
+        let row = tx
+            .query_opt(
+                &format!(
+                    "SELECT {} FROM merchants WHERE merchant_id = $1 FOR UPDATE",
+                    MERCHANT_COLUMNS
+                ),
+                &[&merchant_id],
+            )
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Merchant not found".to_string()))?;
+        let merchant = merchant_from_row(&row);
+        if merchant.registry_status.as_deref() == Some("registering") {
+            return Err(ApiError::Conflict(
+                "Merchant is still being registered with the registry contract".to_string(),
+            ));
+        }
+        if !merchant.active {
+            return Ok(merchant);
+        }
+
+        let row = tx
+            .query_one(
+                &format!(
+                    r#"
+                    UPDATE merchants
+                    SET active = false,
+                        registry_status = CASE WHEN $2 THEN 'deactivating' ELSE registry_status END,
+                        updated_at = NOW()
+                    WHERE merchant_id = $1
+                    RETURNING {}
+                    "#,
+                    MERCHANT_COLUMNS
+                ),
+                &[&merchant_id, &self.soroban.registry_enabled()],
+            )
+            .await?;
+
+        tx.commit().await?;
+        Ok(merchant_from_row(&row))
+    }
+
+    /// Let an inactive merchant accept payments again
+    pub async fn reactivate_merchant(&self, merchant_id: &str) -> Result<Merchant, ApiError> {
+        if self.soroban.registry_enabled() {
+            return Err(ApiError::Conflict(
+                "The registry contract cannot reactivate a deactivated merchant".to_string(),
+            ));
+        }
+
+        let client = self.db_pool.get().await?;
+        let row = client
+            .query_opt(
+                &format!(
+                    "UPDATE merchants SET active = true, updated_at = NOW() WHERE merchant_id = $1 RETURNING {}",
+                    MERCHANT_COLUMNS
+                ),
+                &[&merchant_id],
+            )
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Merchant not found".to_string()))?;
+
+        Ok(merchant_from_row(&row))
+    }
+
+    /// Send outstanding registrations and deactivations to the registry contract
+    ///
+    /// Each call is signed and recorded with its hash before it is submitted,
+    /// then resubmitted as it is until it lands or expires, so a merchant is
+    /// never registered or deactivated twice.
+    pub async fn sync_registry(&self) -> Result<usize, ApiError> {
+        if !self.soroban.registry_enabled() {
+            return Ok(0);
+        }
+
+        let client = self.db_pool.get().await?;
+        let rows = client
+            .query(
+                "SELECT merchant_id FROM merchants WHERE registry_status IN ('registering', 'deactivating') ORDER BY updated_at",
+                &[],
+            )
+            .await?;
+        drop(client);
+
+        for row in &rows {
+            let merchant_id: String = row.get(0);
+            if let Err(e) = self.mirror(&merchant_id).await {
+                tracing::warn!(
+                    "Failed to mirror merchant {} to the registry: {}",
+                    merchant_id,
+                    e
+                );
+            }
+        }
+
+        Ok(rows.len())
+    }
+
+    /// Submit the merchant's outstanding registry call and settle it once it is final
+    async fn mirror(&self, merchant_id: &str) -> Result<(), ApiError> {
+        let (status, envelope) = match self.signed_registry_call(merchant_id).await {
+            Ok(Some(signed)) => signed,
+            Ok(None) => return Ok(()),
+            // The registry refused the call, e.g. a merchant id it already has: retrying will not help
+            Err(ApiError::Contract(e)) => {
+                tracing::error!("Registry refused merchant {}: {}", merchant_id, e.message());
+                let client = self.db_pool.get().await?;
+                client
+                    .execute(
+                        "UPDATE merchants SET registry_status = 'failed', updated_at = NOW() WHERE merchant_id = $1 AND registry_status IN ('registering', 'deactivating')",
+                        &[&merchant_id],
+                    )
+                    .await?;
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+
+        let client = self.db_pool.get().await?;
+        match self.soroban.reconcile(&envelope).await? {
+            Submission::Pending => {}
+            Submission::Confirmed => {
+                let (done, active) = match status.as_str() {
+                    "registering" => ("registered", true),
+                    _ => ("deactivated", false),
+                };
+                client
+                    .execute(
+                        "UPDATE merchants SET registry_status = $2, active = $3, registry_tx_envelope = NULL, updated_at = NOW() WHERE merchant_id = $1 AND registry_tx_envelope = $4",
+                        &[&merchant_id, &done, &active, &envelope],
+                    )
+                    .await?;
+                tracing::info!("Merchant {} is {} in the registry", merchant_id, done);
+            }
+            Submission::Failed => {
+                tracing::error!(
+                    "Registry call for merchant {} failed on-chain while {}",
+                    merchant_id,
+                    status
+                );
+                client
+                    .execute(
+                        "UPDATE merchants SET registry_status = 'failed', registry_tx_envelope = NULL, updated_at = NOW() WHERE merchant_id = $1 AND registry_tx_envelope = $2",
+                        &[&merchant_id, &envelope],
+                    )
+                    .await?;
+            }
+            Submission::Expired => {
+                // It can no longer land, so the next sweep signs a new one
+                client
+                    .execute(
+                        "UPDATE merchants SET registry_tx_hash = NULL, registry_tx_envelope = NULL, updated_at = NOW() WHERE merchant_id = $1 AND registry_tx_envelope = $2",
+                        &[&merchant_id, &envelope],
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The merchant's outstanding registry status and signed call, signed and recorded first if there is none
+    ///
+    /// Returns `None` once nothing is outstanding or another worker holds the merchant.
+    async fn signed_registry_call(
+        &self,
+        merchant_id: &str,
+    ) -> Result<Option<(String, String)>, ApiError> {
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+        let Some(row) = tx
+            .query_opt(
+                r#"
+                SELECT registry_status, registry_tx_envelope, vault_address, settlement_asset
+                FROM merchants
+                WHERE merchant_id = $1 AND registry_status IN ('registering', 'deactivating')
+                FOR UPDATE SKIP LOCKED
+                "#,
+                &[&merchant_id],
+            )
+            .await?
+        else {
+            return Ok(None);
+        };
+        let status: String = row.get(0);
+        if let Some(envelope) = row.get::<_, Option<String>>(1) {
+            return Ok(Some((status, envelope)));
+        }
+        let vault_address: String = row.get(2);
+        let settlement_asset: String = row.get(3);
+
+        let signed = match status.as_str() {
+            "registering" => {
+                let asset =
+                    asset::resolve_asset(&settlement_asset, &self.config.stellar_network.assets)?;
+                self.soroban
+                    .sign_merchant_registration(merchant_id, &vault_address, &asset)
+                    .await?
+            }
+            _ => self.soroban.sign_merchant_deactivation(merchant_id).await?,
+        };
+        tx.execute(
+            "UPDATE merchants SET registry_tx_hash = $1, registry_tx_envelope = $2 WHERE merchant_id = $3",
+            &[&signed.tx_hash, &signed.envelope_xdr, &merchant_id],
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(Some((status, signed.envelope_xdr)))
+    }
+
+    /// Check a settlement asset resolves, returning it as stored: `XLM` or a configured code
+    fn settlement_asset(&self, settlement_asset: &str) -> Result<String, ApiError> {
+        let settlement_asset = settlement_asset.trim().to_uppercase();
+        if settlement_asset.contains(':') {
+            return Err(ApiError::Validation(
+                "settlement_asset must be XLM or one of the configured asset codes".to_string(),
+            ));
+        }
+        let stellar_asset =
+            asset::resolve_asset(&settlement_asset, &self.config.stellar_network.assets)?;
+
+        Ok(asset::asset_key(
+            &stellar_asset,
+            &self.config.stellar_network.assets,
+        ))
+    }
+}
+
+async fn link_operator(
+    tx: &deadpool_postgres::Transaction<'_>,
+    merchant_id: &str,
+    user_id: &str,
+) -> Result<(), ApiError> {
+    let user = tx
+        .query_opt("SELECT 1 FROM users WHERE user_id = $1", &[&user_id])
+        .await?;
+    if user.is_none() {
+        return Err(ApiError::NotFound("Operator user not found".to_string()));
+    }
+
+    tx.execute(
+        "INSERT INTO merchant_users (merchant_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        &[&merchant_id, &user_id],
+    )
+    .await?;
+    Ok(())
+}
+
+/// Merchant ids are letters, digits, `_` and `-`, as they appear in URLs and on-chain
+fn validate_merchant_id(merchant_id: &str) -> Result<(), ApiError> {
+    let valid = (3..=MAX_MERCHANT_ID_LEN).contains(&merchant_id.len())
+        && merchant_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !valid {
+        return Err(ApiError::Validation(format!(
+            "merchant_id must be 3 to {} letters, digits, '_' or '-'",
+            MAX_MERCHANT_ID_LEN
+        )));
+    }
+    Ok(())
+}
+
+fn required_text(field: &str, value: &str) -> Result<String, ApiError> {
+    optional_text(field, Some(value.to_string()))?
+        .ok_or_else(|| ApiError::Validation(format!("{} is required", field)))
+}
+
+/// Trim a text field, treating blank as absent; columns hold 255 characters
+fn optional_text(field: &str, value: Option<String>) -> Result<Option<String>, ApiError> {
+    let value = value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+    if value
+        .as_ref()
+        .is_some_and(|value| value.chars().count() > 255)
+    {
+        return Err(ApiError::Validation(format!(
+            "{} may be at most 255 characters",
+            field
+        )));
+    }
+    Ok(value)
+}
+
+fn validate_email(email: Option<String>) -> Result<Option<String>, ApiError> {
+    let email = optional_text("contact_email", email)?;
+    if let Some(email) = &email {
+        let valid = email
+            .split_once('@')
+            .is_some_and(|(local, domain)| !local.is_empty() && domain.contains('.'));
+        if !valid || email.contains(char::is_whitespace) {
+            return Err(ApiError::Validation(
+                "contact_email is not a valid email address".to_string(),
+            ));
+        }
+    }
+    Ok(email)
+}
+
+pub(crate) fn merchant_from_row(row: &tokio_postgres::Row) -> Merchant {
+    Merchant {
+        id: row.get::<_, Uuid>(0).to_string(),
+        merchant_id: row.get(1),
+        business_name: row.get(2),
+        contact_email: row.get(3),
+        website: row.get(4),
+        vault_address: row.get(5),
+        settlement_asset: row.get(6),
+        active: row.get::<_, Option<bool>>(7).unwrap_or(false),
+        created_at: row.get::<_, chrono::DateTime<chrono::Utc>>(8),
+        updated_at: row.get::<_, chrono::DateTime<chrono::Utc>>(9),
+        registry_status: row.get(10),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_merchant_id() {
+        assert!(validate_merchant_id("coffee-shop_42").is_ok());
+        assert!(validate_merchant_id("ab").is_err());
+        assert!(validate_merchant_id("has space").is_err());
+        assert!(validate_merchant_id("slash/id").is_err());
+        assert!(validate_merchant_id(&"m".repeat(MAX_MERCHANT_ID_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn test_profile_fields() {
+        assert_eq!(
+            validate_email(Some(" owner@shop.example ".to_string())).unwrap(),
+            Some("owner@shop.example".to_string())
+        );
+        assert_eq!(validate_email(Some("  ".to_string())).unwrap(), None);
+        assert!(validate_email(Some("owner".to_string())).is_err());
+        assert!(validate_email(Some("@shop.example".to_string())).is_err());
+
+        assert!(required_text("business_name", " ").is_err());
+        assert!(optional_text("website", Some("w".repeat(256))).is_err());
+    }
+}
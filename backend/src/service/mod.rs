@@ -1,28 +1,57 @@
 pub mod anchor_service;
+pub mod api_key_service;
 pub mod audit_service;
 pub mod bridge_service;
 pub mod compliance_service;
+pub mod deposit_service;
+pub mod idempotency_service;
 pub mod identity_service;
 pub mod indexer_service;
+pub mod job_service;
+pub mod kyc_service;
+pub mod leader_service;
+pub mod lifecycle;
+pub mod merchant_service;
 pub mod metrics_service;
 pub mod notification_service;
 pub mod payment_service;
+pub mod quote_service;
 pub mod rate_limit_service;
+pub mod refund_service;
+pub mod settlement_service;
 pub mod soroban_service;
+pub mod terminal_service;
+pub mod transfer_service;
+pub mod webhook_service;
+pub mod withdrawal_service;
 
 pub use anchor_service::AnchorService;
+pub use api_key_service::ApiKeyService;
 pub use audit_service::AuditService;
 pub use bridge_service::BridgeService;
 pub use compliance_service::ComplianceService;
+pub use deposit_service::DepositService;
+pub use idempotency_service::{IdempotencyOutcome, IdempotencyService};
 pub use identity_service::IdentityService;
 pub use indexer_service::IndexerService;
+pub use job_service::JobService;
+pub use kyc_service::KycService;
+pub use leader_service::LeaderService;
+pub use merchant_service::MerchantService;
 pub use metrics_service::{
     AlertPayload, AlertSeverity, DetailedMetrics, MetricsPayload, MetricsService,
 };
 pub use notification_service::NotificationService;
 pub use payment_service::PaymentService;
+pub use quote_service::QuoteService;
 pub use rate_limit_service::RateLimitService;
+pub use refund_service::RefundService;
+pub use settlement_service::SettlementService;
 pub use soroban_service::SorobanService;
+pub use terminal_service::TerminalService;
+pub use transfer_service::TransferService;
+pub use webhook_service::WebhookService;
+pub use withdrawal_service::WithdrawalService;
 
 use crate::config::Config;
 use deadpool_postgres::Pool;
@@ -35,11 +64,25 @@ pub struct ServiceContainer {
     pub bridge: BridgeService,
     pub anchor: AnchorService,
     pub compliance: ComplianceService,
+    pub kyc: KycService,
+    pub merchant: MerchantService,
+    pub api_key: ApiKeyService,
+    pub quote: QuoteService,
     pub audit: AuditService,
     pub indexer: IndexerService,
+    pub job: JobService,
+    pub leader: LeaderService,
     pub notification: NotificationService,
     pub rate_limit: RateLimitService,
     pub soroban: SorobanService,
+    pub terminal: TerminalService,
+    pub transfer: TransferService,
+    pub withdrawal: WithdrawalService,
+    pub deposit: DepositService,
+    pub settlement: SettlementService,
+    pub refund: RefundService,
+    pub webhook: WebhookService,
+    pub idempotency: IdempotencyService,
     pub config: Config,
     pub db_pool: Arc<Pool>,
 }
@@ -49,15 +92,79 @@ impl ServiceContainer {
         let db_pool = Arc::new(db_pool);
 
         let identity = IdentityService::new(db_pool.clone(), config.clone());
-        let payment = PaymentService::new(db_pool.clone(), config.clone());
-        let bridge = BridgeService::new(db_pool.clone(), config.clone());
         let anchor = AnchorService::new(db_pool.clone(), config.clone());
+        let kyc = KycService::new(
+            db_pool.clone(),
+            config.clone(),
+            identity.clone(),
+            anchor.clone(),
+        );
+        let quote = QuoteService::new(
+            db_pool.clone(),
+            config.clone(),
+            identity.clone(),
+            anchor.clone(),
+        );
+        let payment = PaymentService::new(db_pool.clone(), config.clone(), kyc.clone());
+        let bridge = BridgeService::new(db_pool.clone(), config.clone(), kyc.clone());
         let compliance = ComplianceService::new(db_pool.clone(), config.clone());
         let audit = AuditService::new(db_pool.clone(), config.clone());
-        let indexer = IndexerService::new(db_pool.clone(), config.clone());
         let notification = NotificationService::new(db_pool.clone(), config.clone());
+        let job = JobService::new(
+            db_pool.clone(),
+            config.clone(),
+            audit.clone(),
+            notification.clone(),
+        );
         let rate_limit = RateLimitService::new(config.clone());
         let soroban = SorobanService::new(config.clone());
+        let indexer = IndexerService::new(db_pool.clone(), config.clone(), soroban.clone());
+        let leader = LeaderService::new(db_pool.clone(), config.clone());
+        let terminal = TerminalService::new(db_pool.clone(), config.clone());
+        let merchant = MerchantService::new(db_pool.clone(), config.clone(), soroban.clone());
+        let api_key = ApiKeyService::new(db_pool.clone());
+        let webhook = WebhookService::new(db_pool.clone(), config.clone());
+        let idempotency = IdempotencyService::new(db_pool.clone(), config.clone());
+        let transfer = TransferService::new(
+            db_pool.clone(),
+            config.clone(),
+            identity.clone(),
+            compliance.clone(),
+            soroban.clone(),
+            notification.clone(),
+        );
+        let withdrawal = WithdrawalService::new(
+            db_pool.clone(),
+            config.clone(),
+            identity.clone(),
+            compliance.clone(),
+            kyc.clone(),
+            anchor.clone(),
+            soroban.clone(),
+            notification.clone(),
+        );
+        let deposit = DepositService::new(
+            db_pool.clone(),
+            config.clone(),
+            identity.clone(),
+            compliance.clone(),
+            kyc.clone(),
+            anchor.clone(),
+            notification.clone(),
+        );
+        let settlement = SettlementService::new(
+            db_pool.clone(),
+            config.clone(),
+            anchor.clone(),
+            soroban.clone(),
+        );
+        let refund = RefundService::new(
+            db_pool.clone(),
+            config.clone(),
+            anchor.clone(),
+            soroban.clone(),
+            notification.clone(),
+        );
 
         Ok(Self {
             identity,
@@ -65,11 +172,25 @@ impl ServiceContainer {
             bridge,
             anchor,
             compliance,
+            kyc,
+            merchant,
+            api_key,
+            quote,
             audit,
             indexer,
+            job,
+            leader,
             notification,
             rate_limit,
             soroban,
+            terminal,
+            transfer,
+            withdrawal,
+            deposit,
+            settlement,
+            refund,
+            webhook,
+            idempotency,
             config,
             db_pool,
         })
@@ -2,6 +2,7 @@ use crate::{
     api_error::ApiError,
     config::Config,
     models::{Notification, NotificationType},
+    service::job_service::{self, Job},
 };
 use deadpool_postgres::Pool;
 use serde::{Deserialize, Serialize};
@@ -34,18 +35,19 @@ impl NotificationService {
         &self,
         request: CreateNotificationRequest,
     ) -> Result<Notification, ApiError> {
-        let client = self.db_pool.get().await?;
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
 
         let notification_id = Uuid::new_v4();
 
-        let row = client
+        let row = tx
             .query_one(
                 r#"
                 INSERT INTO notifications (
                     id, user_id, type, title, message, metadata, read
                 )
-                VALUES ($1, $2, $3::notification_type, $4, $5, $6, $7)
-                RETURNING id, user_id, type, title, message, metadata, read, created_at, updated_at
+                VALUES ($1, $2, $3::text::notification_type, $4, $5, $6, $7)
+                RETURNING id, user_id, type::text, title, message, metadata, read, created_at, updated_at
                 "#,
                 &[
                     &notification_id,
@@ -59,22 +61,36 @@ impl NotificationService {
             )
             .await?;
 
-        let notification = Notification {
-            id: row.get::<_, Uuid>(0).to_string(),
-            user_id: row.get(1),
-            notification_type: NotificationType::from_str(row.get(2)).unwrap(),
-            title: row.get(3),
-            message: row.get(4),
-            metadata: row.get(5),
-            read: row.get(6),
-            created_at: row.get::<_, chrono::DateTime<chrono::Utc>>(7),
-            updated_at: row.get::<_, chrono::DateTime<chrono::Utc>>(8),
-        };
-
-        // Mock email notification
-        self.send_email_notification(&notification).await?;
-
-        Ok(notification)
+        job_service::enqueue(&tx, &Job::NotificationEmail { notification_id }, None).await?;
+        tx.commit().await?;
+
+        Ok(notification_from_row(&row))
+    }
+
+    /// Notify a user of something that already happened, logging rather than returning a failure
+    ///
+    /// For side effects of a committed change, which a failed notification
+    /// must not turn into an error.
+    pub async fn notify_best_effort(
+        &self,
+        user_id: &str,
+        title: &str,
+        message: String,
+        metadata: serde_json::Value,
+    ) {
+        let result = self
+            .create_notification(CreateNotificationRequest {
+                user_id: user_id.to_string(),
+                notification_type: NotificationType::ACTION,
+                title: title.to_string(),
+                message,
+                metadata: Some(metadata),
+            })
+            .await;
+
+        if let Err(e) = result {
+            tracing::warn!("Failed to notify {} ({}): {}", user_id, title, e);
+        }
     }
 
     pub async fn get_user_notifications(
@@ -86,7 +102,7 @@ impl NotificationService {
         let rows = client
             .query(
                 r#"
-                SELECT id, user_id, type, title, message, metadata, read, created_at, updated_at
+                SELECT id, user_id, type::text, title, message, metadata, read, created_at, updated_at
                 FROM notifications
                 WHERE user_id = $1
                 ORDER BY created_at DESC
@@ -95,22 +111,7 @@ impl NotificationService {
             )
             .await?;
 
-        let notifications = rows
-            .into_iter()
-            .map(|row| Notification {
-                id: row.get::<_, Uuid>(0).to_string(),
-                user_id: row.get(1),
-                notification_type: NotificationType::from_str(row.get(2)).unwrap(),
-                title: row.get(3),
-                message: row.get(4),
-                metadata: row.get(5),
-                read: row.get(6),
-                created_at: row.get::<_, chrono::DateTime<chrono::Utc>>(7),
-                updated_at: row.get::<_, chrono::DateTime<chrono::Utc>>(8),
-            })
-            .collect();
-
-        Ok(notifications)
+        Ok(rows.iter().map(notification_from_row).collect())
     }
 
     pub async fn mark_as_read(&self, notification_id: Uuid) -> Result<(), ApiError> {
@@ -130,7 +131,22 @@ impl NotificationService {
         Ok(())
     }
 
-    async fn send_email_notification(&self, notification: &Notification) -> Result<(), ApiError> {
+    /// Email a user about one of their notifications, as the `NotificationEmail` job
+    pub async fn send_email(&self, notification_id: Uuid) -> Result<(), ApiError> {
+        let client = self.db_pool.get().await?;
+        let row = client
+            .query_opt(
+                r#"
+                SELECT id, user_id, type::text, title, message, metadata, read, created_at, updated_at
+                FROM notifications
+                WHERE id = $1
+                "#,
+                &[&notification_id],
+            )
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Notification not found".to_string()))?;
+        let notification = notification_from_row(&row);
+
         // MOCK EMAIL PROVIDER
         println!(
             "[MOCK EMAIL] Sending {} email to {}: {}",
@@ -140,3 +156,17 @@ impl NotificationService {
         Ok(())
     }
 }
+
+fn notification_from_row(row: &tokio_postgres::Row) -> Notification {
+    Notification {
+        id: row.get::<_, Uuid>(0).to_string(),
+        user_id: row.get(1),
+        notification_type: NotificationType::from_str(row.get(2)).unwrap(),
+        title: row.get(3),
+        message: row.get(4),
+        metadata: row.get(5),
+        read: row.get(6),
+        created_at: row.get::<_, chrono::DateTime<chrono::Utc>>(7),
+        updated_at: row.get::<_, chrono::DateTime<chrono::Utc>>(8),
+    }
+}
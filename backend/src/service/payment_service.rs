@@ -1,28 +1,84 @@
 use crate::{
     api_error::ApiError,
-    config::Config,
-    models::{Merchant, Payment, PaymentStatus},
+    config::{Config, NfcConfig},
+    models::{
+        KycLevel, Merchant, MerchantTerminal, Payment, PaymentStatus, StatusEvent, WebhookEventType,
+    },
+    service::{
+        lifecycle::{self, LifecycleEntity},
+        merchant_service, quote_service, terminal_service, webhook_service, KycService,
+    },
+    stellar::{
+        asset, keypair::verify_signature, sep7, strkey::decode_account_id,
+        validate_account_address, Keypair,
+    },
 };
-use deadpool_postgres::Pool;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use deadpool_postgres::{Pool, Transaction};
 use serde::{Deserialize, Serialize};
-use std::str::FromStr;
 use std::sync::Arc;
 use uuid::Uuid;
 
+pub(crate) const PAYMENT_COLUMNS: &str = "id, tx_hash, from_address, merchant_id, send_asset, send_amount, receive_amount, status, memo, created_at, updated_at, refunded_amount";
+
+/// Longest `MEMO_TEXT` a Stellar transaction can carry, in bytes
+const MAX_TEXT_MEMO_BYTES: usize = 28;
+
+/// First line of the bytes NFC terminals sign, versioning the layout
+const NFC_SIGNATURE_PREFIX: &str = "zaps-nfc-tap-v1";
+
+/// Bounds on tap nonce length; the lower one keeps nonces unguessable
+const MIN_NFC_NONCE_LEN: usize = 16;
+const MAX_NFC_NONCE_LEN: usize = 64;
+
 #[derive(Clone)]
 #[allow(dead_code)]
 pub struct PaymentService {
     db_pool: Arc<Pool>,
     config: Config,
+    kyc: KycService,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreatePaymentRequest {
+    /// Ignored when present: the payer is always the authenticated user's wallet,
+    /// and a mismatching address is rejected rather than silently replaced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_address: Option<String>,
     pub merchant_id: String,
     pub send_asset: String,
     pub send_amount: i64,
     pub min_receive: Option<i64>,
+    /// Firm quote to pay with; `min_receive` is then taken from the quote
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quote_id: Option<Uuid>,
     pub memo: Option<String>,
+    /// Signed SEP-7 URI scanned from the merchant's QR code; the payment must
+    /// match it, and redeems its nonce
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_uri: Option<String>,
+    /// Signed tap read from the merchant's terminal; the payment must match
+    /// it, and spends its nonce
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nfc_tap: Option<crate::http::payments::NfcPaymentRequest>,
+}
+
+/// A signed SEP-7 payment request and the nonce it is redeemed with
+#[derive(Debug, Clone)]
+pub struct PaymentUri {
+    pub uri: String,
+    pub nonce: String,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// What a verified payment request asks the payer to pay
+#[derive(Debug, Serialize)]
+pub struct VerifiedPaymentUri {
+    pub merchant_id: String,
+    pub amount: i64,
+    pub asset: String,
+    pub memo: Option<String>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -44,8 +100,12 @@ pub struct NfcPaymentPayload {
 }
 
 impl PaymentService {
-    pub fn new(db_pool: Arc<Pool>, config: Config) -> Self {
-        Self { db_pool, config }
+    pub fn new(db_pool: Arc<Pool>, config: Config, kyc: KycService) -> Self {
+        Self {
+            db_pool,
+            config,
+            kyc,
+        }
     }
 
     pub async fn create_payment(
@@ -53,16 +113,68 @@ impl PaymentService {
         from_address: String,
         request: CreatePaymentRequest,
     ) -> Result<Payment, ApiError> {
-        let client = self.db_pool.get().await?;
+        validate_account_address("from_address", &from_address)?;
 
         // Validate merchant exists and is active
         let _merchant = self.get_merchant(&request.merchant_id).await?;
 
+        if request.send_amount >= self.config.anchor_config.kyc_payment_threshold {
+            self.require_payer_kyc(&from_address).await?;
+        }
+
+        if request.quote_id.is_some() && request.min_receive.is_some() {
+            return Err(ApiError::Validation(
+                "min_receive is taken from the quote and cannot be set with quote_id".to_string(),
+            ));
+        }
+        if request.payment_uri.is_some() && request.nfc_tap.is_some() {
+            return Err(ApiError::Validation(
+                "A payment can redeem a payment_uri or an nfc_tap, not both".to_string(),
+            ));
+        }
+        let uri_nonce = request
+            .payment_uri
+            .as_deref()
+            .map(|uri| self.payment_uri_nonce(uri))
+            .transpose()?;
+        if let Some(tap) = &request.nfc_tap {
+            let memo = request.memo.as_deref().filter(|memo| !memo.is_empty());
+            if tap.merchant_id != request.merchant_id
+                || tap.amount != request.send_amount
+                || tap.asset != request.send_asset
+                || tap.memo.as_deref().filter(|memo| !memo.is_empty()) != memo
+            {
+                return Err(ApiError::Validation(
+                    "Payment does not match the NFC tap".to_string(),
+                ));
+            }
+            self.verify_tap(tap).await?;
+        }
+
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+
+        let min_receive = match request.quote_id {
+            Some(quote_id) => {
+                let quote = quote_service::lock_quote_for_payment(
+                    &tx,
+                    quote_id,
+                    &from_address,
+                    &request.merchant_id,
+                    &request.send_asset,
+                    request.send_amount,
+                )
+                .await?;
+                Some(quote.buy_amount)
+            }
+            None => request.min_receive,
+        };
+
         // Generate transaction hash (in production, this would be from Stellar)
         let tx_hash = format!("tx_{}", Uuid::new_v4().simple());
-        let payment_id = Uuid::new_v4().to_string();
+        let payment_id = Uuid::new_v4();
 
-        let row = client
+        let row = tx
             .query_one(
                 r#"
                 INSERT INTO payments (
@@ -71,7 +183,8 @@ impl PaymentService {
                 )
                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
                 RETURNING id, tx_hash, from_address, merchant_id, send_asset,
-                         send_amount, receive_amount, status, memo, created_at, updated_at
+                         send_amount, receive_amount, status, memo, created_at, updated_at,
+                         refunded_amount
                 "#,
                 &[
                     &payment_id,
@@ -80,15 +193,34 @@ impl PaymentService {
                     &request.merchant_id,
                     &request.send_asset,
                     &request.send_amount,
-                    &request.min_receive,
-                    &"pending".to_string(),
+                    &min_receive,
+                    &PaymentStatus::Pending.to_string(),
                     &request.memo,
                 ],
             )
             .await?;
 
+        if let Some(quote_id) = request.quote_id {
+            quote_service::mark_quote_used(&tx, quote_id, payment_id).await?;
+        }
+        if let Some(nonce) = &uri_nonce {
+            redeem_payment_uri(&tx, nonce, payment_id, &request).await?;
+        }
+        if let Some(tap) = &request.nfc_tap {
+            spend_tap(&tx, tap, &self.config.nfc).await?;
+        }
+        lifecycle::record_created(
+            &tx,
+            LifecycleEntity::Payment,
+            payment_id,
+            PaymentStatus::Pending,
+            None,
+        )
+        .await?;
+        tx.commit().await?;
+
         Ok(Payment {
-            id: row.get(0),
+            id: row.get::<_, Uuid>(0).to_string(),
             tx_hash: row.get(1),
             from_address: row.get(2),
             merchant_id: row.get(3),
@@ -99,6 +231,7 @@ impl PaymentService {
             memo: row.get(8),
             created_at: row.get::<_, chrono::DateTime<chrono::Utc>>(9),
             updated_at: row.get::<_, chrono::DateTime<chrono::Utc>>(10),
+            refunded_amount: row.get(11),
         })
     }
 
@@ -107,93 +240,301 @@ impl PaymentService {
 
         let row = client
             .query_one(
-                r#"
-                SELECT id, tx_hash, from_address, merchant_id, send_asset,
-                       send_amount, receive_amount, status, memo, created_at, updated_at
-                FROM payments WHERE id = $1
-                "#,
+                &format!("SELECT {} FROM payments WHERE id = $1", PAYMENT_COLUMNS),
                 &[&payment_id],
             )
             .await
             .map_err(|_| ApiError::NotFound("Payment not found".to_string()))?;
 
-        Ok(Payment {
-            id: row.get(0),
-            tx_hash: row.get(1),
-            from_address: row.get(2),
-            merchant_id: row.get(3),
-            send_asset: row.get(4),
-            send_amount: row.get(5),
-            receive_amount: row.get(6),
-            status: PaymentStatus::from_str(row.get(7)).unwrap(),
-            memo: row.get(8),
-            created_at: row.get::<_, chrono::DateTime<chrono::Utc>>(9),
-            updated_at: row.get::<_, chrono::DateTime<chrono::Utc>>(10),
-        })
+        payment_from_row(&row)
     }
 
+    /// Move a payment to `status`, rejecting transitions its lifecycle does not allow
     pub async fn update_payment_status(
         &self,
         payment_id: Uuid,
         status: PaymentStatus,
+        reason: &str,
         tx_hash: Option<String>,
     ) -> Result<(), ApiError> {
-        let client = self.db_pool.get().await?;
-
-        if let Some(hash) = tx_hash {
-            client
-                .execute(
-                    "UPDATE payments SET status = $1, tx_hash = $2, updated_at = NOW() WHERE id = $3",
-                    &[&status.to_string(), &hash, &payment_id],
-                )
-                .await?;
-        } else {
-            client
-                .execute(
-                    "UPDATE payments SET status = $1, updated_at = NOW() WHERE id = $2",
-                    &[&status.to_string(), &payment_id],
-                )
-                .await?;
-        }
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+        transition_payment(&tx, payment_id, status, reason, tx_hash.as_deref()).await?;
+        tx.commit().await?;
 
         Ok(())
     }
 
+    pub async fn get_payment_history(
+        &self,
+        payment_id: Uuid,
+    ) -> Result<Vec<StatusEvent>, ApiError> {
+        let client = self.db_pool.get().await?;
+        lifecycle::history(&client, LifecycleEntity::Payment, payment_id).await
+    }
+
+    /// Build a signed SEP-7 `pay` URI for a merchant, redeemable once until `expiry`
     pub async fn generate_qr_payment(
         &self,
         payload: crate::http::payments::QrPaymentRequest,
-    ) -> Result<String, ApiError> {
-        // Validate merchant exists
-        self.get_merchant(&payload.merchant_id).await?;
+    ) -> Result<PaymentUri, ApiError> {
+        let merchant = self.get_merchant(&payload.merchant_id).await?;
+        // A `pay` destination must be an account; paying a contract would need a `tx` request
+        validate_account_address("vault_address", &merchant.vault_address).map_err(|_| {
+            ApiError::Validation(
+                "Merchants with a contract vault cannot take QR payment requests".to_string(),
+            )
+        })?;
 
-        // In production, this would generate a proper SEP-7 URI
-        let qr_data = format!(
-            "zaps://pay?merchant={}&amount={}&asset={}&expiry={}&memo={}",
-            payload.merchant_id,
-            payload.amount,
-            payload.asset,
-            payload.expiry,
-            payload.memo.unwrap_or_default()
-        );
+        if payload.amount <= 0 {
+            return Err(ApiError::Validation("Amount must be positive".to_string()));
+        }
+        let now = chrono::Utc::now().timestamp();
+        if payload.expiry <= now {
+            return Err(ApiError::Validation(
+                "expiry must be in the future".to_string(),
+            ));
+        }
+        if payload.expiry - now > self.config.sep7.max_ttl_secs {
+            return Err(ApiError::Validation(format!(
+                "expiry may be at most {} seconds away",
+                self.config.sep7.max_ttl_secs
+            )));
+        }
+        let memo = payload.memo.filter(|memo| !memo.is_empty());
+        if memo
+            .as_ref()
+            .is_some_and(|memo| memo.len() > MAX_TEXT_MEMO_BYTES)
+        {
+            return Err(ApiError::Validation(format!(
+                "memo may be at most {} bytes",
+                MAX_TEXT_MEMO_BYTES
+            )));
+        }
+        let asset = asset::resolve_asset(&payload.asset, &self.config.stellar_network.assets)?;
+        let keypair = self.uri_signing_keypair()?;
+
+        let nonce = Uuid::new_v4().simple().to_string();
+        let mut params = vec![
+            ("destination", merchant.vault_address),
+            ("amount", asset::format_stroops(payload.amount)),
+        ];
+        if let Some(issuer) = asset::issuer(&asset) {
+            let key = asset::asset_key(&asset, &self.config.stellar_network.assets);
+            params.push(("asset_code", asset::asset_code(&key).to_string()));
+            params.push(("asset_issuer", issuer));
+        }
+        if let Some(memo) = &memo {
+            params.push(("memo", memo.clone()));
+            params.push(("memo_type", "MEMO_TEXT".to_string()));
+        }
+        params.extend([
+            (
+                "network_passphrase",
+                self.config.stellar_network.passphrase.clone(),
+            ),
+            ("origin_domain", self.config.sep7.origin_domain.clone()),
+            ("nonce", nonce.clone()),
+            ("expires", payload.expiry.to_string()),
+        ]);
+        let uri = sep7::sign(&sep7::build("pay", &params), &keypair);
+
+        let expires_at = chrono::DateTime::from_timestamp(payload.expiry, 0)
+            .ok_or_else(|| ApiError::Validation("Invalid expiry".to_string()))?;
+        let client = self.db_pool.get().await?;
+        client
+            .execute(
+                "INSERT INTO payment_uri_nonces (nonce, merchant_id, amount, asset, memo, expires_at) VALUES ($1, $2, $3, $4, $5, $6)",
+                &[
+                    &nonce,
+                    &payload.merchant_id,
+                    &payload.amount,
+                    &payload.asset,
+                    &memo,
+                    &expires_at,
+                ],
+            )
+            .await?;
+
+        Ok(PaymentUri {
+            uri,
+            nonce,
+            expires_at,
+        })
+    }
+
+    /// Check a scanned SEP-7 URI is one of ours and can still be paid
+    ///
+    /// The details returned are the ones stored when the URI was made, so
+    /// they are what the payment should be created with. The nonce is only
+    /// redeemed by that payment.
+    pub async fn verify_payment_uri(&self, uri: &str) -> Result<VerifiedPaymentUri, ApiError> {
+        let nonce = self.payment_uri_nonce(uri)?;
+
+        let client = self.db_pool.get().await?;
+        let row = client
+            .query_opt(
+                "SELECT merchant_id, amount, asset, memo, expires_at, used_at IS NOT NULL FROM payment_uri_nonces WHERE nonce = $1",
+                &[&nonce],
+            )
+            .await?;
 
-        Ok(qr_data)
+        payable_request(row)
     }
 
+    /// The nonce of a SEP-7 URI signed by us for a `pay` request
+    fn payment_uri_nonce(&self, uri: &str) -> Result<String, ApiError> {
+        let keypair = self.uri_signing_keypair()?;
+        sep7::verify(uri, &keypair.public_key())?;
+
+        let request = sep7::parse(uri)?;
+        if request.operation != "pay" {
+            return Err(ApiError::Validation(
+                "Only pay requests can be verified".to_string(),
+            ));
+        }
+        if request.param("origin_domain") != Some(self.config.sep7.origin_domain.as_str()) {
+            return Err(ApiError::Validation(
+                "Payment request is from another origin".to_string(),
+            ));
+        }
+        request
+            .param("nonce")
+            .map(str::to_string)
+            .ok_or_else(|| ApiError::Validation("Payment request has no nonce".to_string()))
+    }
+
+    /// Check a tap payload was signed by one of the merchant's terminals, recently, and not yet paid
+    ///
+    /// Returns the terminal that signed it. The nonce is only spent by the
+    /// payment made with the tap.
     pub async fn validate_nfc_payment(
         &self,
         payload: crate::http::payments::NfcPaymentRequest,
-    ) -> Result<bool, ApiError> {
-        // Validate merchant exists
+    ) -> Result<MerchantTerminal, ApiError> {
+        let terminal = self.verify_tap(&payload).await?;
+
+        let client = self.db_pool.get().await?;
+        let spent = client
+            .query_opt(
+                "SELECT 1 FROM nfc_tap_nonces WHERE terminal_id = $1 AND nonce = $2",
+                &[&payload.terminal_id, &payload.nonce],
+            )
+            .await?;
+        if spent.is_some() {
+            return Err(ApiError::Conflict(
+                "NFC payload has already been used".to_string(),
+            ));
+        }
+
+        Ok(terminal)
+    }
+
+    /// Check a tap payload was signed by an active terminal of its merchant, within the tap window
+    async fn verify_tap(
+        &self,
+        payload: &crate::http::payments::NfcPaymentRequest,
+    ) -> Result<MerchantTerminal, ApiError> {
         self.get_merchant(&payload.merchant_id).await?;
 
-        // Basic validation - in production, check expiry, signature, etc.
-        let current_time = chrono::Utc::now().timestamp();
-        if payload.timestamp > current_time + 300 {
-            // 5 minutes grace
-            return Ok(false);
+        if payload.amount <= 0 {
+            return Err(ApiError::Validation("Amount must be positive".to_string()));
         }
+        if payload.nonce.len() < MIN_NFC_NONCE_LEN
+            || payload.nonce.len() > MAX_NFC_NONCE_LEN
+            || !payload.nonce.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            return Err(ApiError::Validation(format!(
+                "nonce must be {} to {} letters or digits",
+                MIN_NFC_NONCE_LEN, MAX_NFC_NONCE_LEN
+            )));
+        }
+        let signed = nfc_signing_payload(payload)?;
+        let invalid_signature =
+            || ApiError::Validation("NFC payload signature is invalid".to_string());
+        let signature = BASE64
+            .decode(&payload.signature)
+            .map_err(|_| invalid_signature())?;
+
+        let client = self.db_pool.get().await?;
+        let row = client
+            .query_opt(
+                &format!(
+                    "SELECT {} FROM merchant_terminals WHERE id = $1",
+                    terminal_service::TERMINAL_COLUMNS
+                ),
+                &[&payload.terminal_id],
+            )
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Terminal not found".to_string()))?;
+        let terminal = terminal_service::terminal_from_row(&row);
 
-        Ok(true)
+        if terminal.merchant_id != payload.merchant_id {
+            return Err(ApiError::Validation(
+                "Terminal is not registered to this merchant".to_string(),
+            ));
+        }
+        if !terminal.active {
+            return Err(ApiError::Validation(
+                "Terminal has been revoked".to_string(),
+            ));
+        }
+        let public_key = decode_account_id(&terminal.public_key).map_err(|e| {
+            tracing::error!("Invalid key of terminal {}: {}", terminal.id, e);
+            ApiError::InternalServerError
+        })?;
+        if !verify_signature(&public_key, &signed, &signature) {
+            return Err(invalid_signature());
+        }
+
+        check_tap_window(
+            payload.timestamp,
+            chrono::Utc::now().timestamp(),
+            &self.config.nfc,
+        )?;
+
+        Ok(terminal)
+    }
+
+    /// The key published as `URI_REQUEST_SIGNING_KEY` for our origin domain
+    fn uri_signing_keypair(&self) -> Result<Keypair, ApiError> {
+        let secret = self
+            .config
+            .sep7
+            .signing_secret
+            .as_deref()
+            .filter(|secret| !secret.is_empty())
+            .ok_or_else(|| {
+                tracing::error!("sep7.signing_secret is not configured");
+                ApiError::InternalServerError
+            })?;
+
+        Keypair::from_secret_seed(secret)
+    }
+
+    /// Large payments need the paying wallet's owner to have basic KYC
+    async fn require_payer_kyc(&self, from_address: &str) -> Result<(), ApiError> {
+        let client = self.db_pool.get().await?;
+        let owner = client
+            .query_opt(
+                "SELECT user_id FROM users WHERE stellar_address = $1",
+                &[&from_address],
+            )
+            .await?;
+        drop(client);
+
+        match owner {
+            Some(row) => {
+                let user_id: String = row.get(0);
+                self.kyc
+                    .require_kyc(&user_id, KycLevel::Basic, "making large payments")
+                    .await
+            }
+            None if self.config.anchor_config.kyc_required => Err(ApiError::Compliance(
+                "Large payments must come from a verified wallet".to_string(),
+            )),
+            None => Ok(()),
+        }
     }
 
     async fn get_merchant(&self, merchant_id: &str) -> Result<Merchant, ApiError> {
@@ -201,20 +542,281 @@ impl PaymentService {
 
         let row = client
             .query_one(
-                "SELECT id, merchant_id, vault_address, settlement_asset, active, created_at, updated_at FROM merchants WHERE merchant_id = $1 AND active = true",
+                &format!(
+                    "SELECT {} FROM merchants WHERE merchant_id = $1 AND active = true",
+                    merchant_service::MERCHANT_COLUMNS
+                ),
                 &[&merchant_id],
             )
             .await
             .map_err(|_| ApiError::NotFound("Merchant not found or inactive".to_string()))?;
 
-        Ok(Merchant {
-            id: row.get(0),
-            merchant_id: row.get(1),
-            vault_address: row.get(2),
-            settlement_asset: row.get(3),
-            active: row.get(4),
-            created_at: row.get::<_, chrono::DateTime<chrono::Utc>>(5),
-            updated_at: row.get::<_, chrono::DateTime<chrono::Utc>>(6),
-        })
+        Ok(merchant_service::merchant_from_row(&row))
+    }
+}
+
+/// Redeem a payment request's nonce for `payment_id` within `tx`
+///
+/// The payment must be for the merchant, amount, asset and memo the request asks for.
+async fn redeem_payment_uri(
+    tx: &Transaction<'_>,
+    nonce: &str,
+    payment_id: Uuid,
+    payment: &CreatePaymentRequest,
+) -> Result<(), ApiError> {
+    let row = tx
+        .query_opt(
+            "SELECT merchant_id, amount, asset, memo, expires_at, used_at IS NOT NULL FROM payment_uri_nonces WHERE nonce = $1 FOR UPDATE",
+            &[&nonce],
+        )
+        .await?;
+    let requested = payable_request(row)?;
+
+    let memo = payment.memo.as_deref().filter(|memo| !memo.is_empty());
+    if requested.merchant_id != payment.merchant_id
+        || requested.amount != payment.send_amount
+        || requested.asset != payment.send_asset
+        || requested.memo.as_deref() != memo
+    {
+        return Err(ApiError::Validation(
+            "Payment does not match the payment request".to_string(),
+        ));
+    }
+
+    tx.execute(
+        "UPDATE payment_uri_nonces SET used_at = NOW(), payment_id = $2 WHERE nonce = $1",
+        &[&nonce, &payment_id],
+    )
+    .await?;
+    Ok(())
+}
+
+/// What a stored payment request asks for, if it is neither used nor expired
+///
+/// `row` holds merchant_id, amount, asset, memo, expires_at and whether it was used.
+fn payable_request(row: Option<tokio_postgres::Row>) -> Result<VerifiedPaymentUri, ApiError> {
+    let row = row.ok_or_else(|| ApiError::NotFound("Payment request not found".to_string()))?;
+    if row.get::<_, bool>(5) {
+        return Err(ApiError::Conflict(
+            "Payment request has already been used".to_string(),
+        ));
+    }
+    let expires_at: chrono::DateTime<chrono::Utc> = row.get(4);
+    if expires_at <= chrono::Utc::now() {
+        return Err(ApiError::Validation(
+            "Payment request has expired".to_string(),
+        ));
+    }
+
+    Ok(VerifiedPaymentUri {
+        merchant_id: row.get(0),
+        amount: row.get(1),
+        asset: row.get(2),
+        memo: row.get(3),
+        expires_at,
+    })
+}
+
+/// Spend a verified tap's nonce within `tx`, failing if it was spent before
+async fn spend_tap(
+    tx: &Transaction<'_>,
+    payload: &crate::http::payments::NfcPaymentRequest,
+    config: &NfcConfig,
+) -> Result<(), ApiError> {
+    // Nonces older than the window cannot be replayed anyway
+    let window_secs = (config.max_age_secs + config.max_clock_skew_secs) as f64;
+    tx.execute(
+        "DELETE FROM nfc_tap_nonces WHERE tapped_at < NOW() - make_interval(secs => $1)",
+        &[&window_secs],
+    )
+    .await?;
+    let tapped_at = chrono::DateTime::from_timestamp(payload.timestamp, 0)
+        .ok_or_else(|| ApiError::Validation("timestamp is out of range".to_string()))?;
+    let inserted = tx
+        .execute(
+            r#"
+            INSERT INTO nfc_tap_nonces (terminal_id, nonce, tapped_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (terminal_id, nonce) DO NOTHING
+            "#,
+            &[&payload.terminal_id, &payload.nonce, &tapped_at],
+        )
+        .await?;
+    if inserted == 0 {
+        return Err(ApiError::Conflict(
+            "NFC payload has already been used".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Bytes a terminal signs for a tap: the fields below, one per line
+///
+/// ```text
+/// zaps-nfc-tap-v1
+/// {terminal_id}
+/// {merchant_id}
+/// {amount}
+/// {asset}
+/// {memo, or empty}
+/// {timestamp}
+/// {nonce}
+/// ```
+pub fn nfc_signing_payload(
+    payload: &crate::http::payments::NfcPaymentRequest,
+) -> Result<Vec<u8>, ApiError> {
+    let memo = payload.memo.as_deref().unwrap_or_default();
+    if [payload.merchant_id.as_str(), payload.asset.as_str(), memo]
+        .iter()
+        .any(|field| field.contains('\n'))
+    {
+        return Err(ApiError::Validation(
+            "NFC payload fields cannot contain line breaks".to_string(),
+        ));
+    }
+
+    Ok(format!(
+        "{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}",
+        NFC_SIGNATURE_PREFIX,
+        payload.terminal_id,
+        payload.merchant_id,
+        payload.amount,
+        payload.asset,
+        memo,
+        payload.timestamp,
+        payload.nonce
+    )
+    .into_bytes())
+}
+
+/// A tap can be paid from its timestamp until `max_age_secs` later, and
+/// terminal clocks may run up to `max_clock_skew_secs` ahead of ours
+fn check_tap_window(timestamp: i64, now: i64, config: &NfcConfig) -> Result<(), ApiError> {
+    if timestamp > now + config.max_clock_skew_secs {
+        return Err(ApiError::Validation(
+            "NFC payload timestamp is in the future".to_string(),
+        ));
+    }
+    if timestamp < now - config.max_age_secs {
+        return Err(ApiError::Validation("NFC payload has expired".to_string()));
+    }
+    Ok(())
+}
+
+/// Move a payment to `status` within `tx`
+///
+/// Completed and failed payments are reported to the merchant's webhooks,
+/// in the shape the payments API returns them.
+pub(crate) async fn transition_payment(
+    tx: &Transaction<'_>,
+    payment_id: Uuid,
+    status: PaymentStatus,
+    reason: &str,
+    tx_hash: Option<&str>,
+) -> Result<PaymentStatus, ApiError> {
+    let previous = lifecycle::transition(
+        tx,
+        LifecycleEntity::Payment,
+        payment_id,
+        status,
+        reason,
+        tx_hash,
+    )
+    .await?;
+
+    let event_type = match status {
+        PaymentStatus::Completed => Some(WebhookEventType::PaymentCompleted),
+        PaymentStatus::Failed => Some(WebhookEventType::PaymentFailed),
+        _ => None,
+    };
+    if let Some(event_type) = event_type {
+        let row = tx
+            .query_one(
+                &format!("SELECT {} FROM payments WHERE id = $1", PAYMENT_COLUMNS),
+                &[&payment_id],
+            )
+            .await?;
+        let payment = payment_from_row(&row)?;
+        let merchant_id = payment.merchant_id.clone();
+        webhook_service::enqueue_event(
+            tx,
+            &merchant_id,
+            event_type,
+            serde_json::to_value(crate::http::payments::payment_response(payment))?,
+        )
+        .await?;
+    }
+
+    Ok(previous)
+}
+
+pub(crate) fn payment_from_row(row: &tokio_postgres::Row) -> Result<Payment, ApiError> {
+    Ok(Payment {
+        id: row.get::<_, Uuid>(0).to_string(),
+        tx_hash: row.get(1),
+        from_address: row.get(2),
+        merchant_id: row.get(3),
+        send_asset: row.get(4),
+        send_amount: row.get(5),
+        receive_amount: row.get(6),
+        status: lifecycle::parse_status(LifecycleEntity::Payment, row.get(7))?,
+        memo: row.get(8),
+        created_at: row.get::<_, chrono::DateTime<chrono::Utc>>(9),
+        updated_at: row.get::<_, chrono::DateTime<chrono::Utc>>(10),
+        refunded_amount: row.get(11),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::payments::NfcPaymentRequest;
+
+    fn tap() -> NfcPaymentRequest {
+        NfcPaymentRequest {
+            terminal_id: Uuid::nil(),
+            merchant_id: "merchant-1".to_string(),
+            amount: 12_500_000,
+            asset: "USDC".to_string(),
+            memo: None,
+            timestamp: 1_700_000_000,
+            nonce: "0123456789abcdef".to_string(),
+            signature: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_nfc_signing_payload() {
+        let payload = nfc_signing_payload(&tap()).unwrap();
+        assert_eq!(
+            String::from_utf8(payload).unwrap(),
+            "zaps-nfc-tap-v1\n00000000-0000-0000-0000-000000000000\nmerchant-1\n12500000\nUSDC\n\n1700000000\n0123456789abcdef"
+        );
+
+        let mut other = tap();
+        other.amount += 1;
+        assert_ne!(
+            nfc_signing_payload(&other).unwrap(),
+            nfc_signing_payload(&tap()).unwrap()
+        );
+
+        let mut smuggled = tap();
+        smuggled.memo = Some("a\n1".to_string());
+        assert!(nfc_signing_payload(&smuggled).is_err());
+    }
+
+    #[test]
+    fn test_check_tap_window() {
+        let config = NfcConfig {
+            max_age_secs: 120,
+            max_clock_skew_secs: 30,
+        };
+        let now = 1_700_000_000;
+
+        assert!(check_tap_window(now, now, &config).is_ok());
+        assert!(check_tap_window(now - 120, now, &config).is_ok());
+        assert!(check_tap_window(now + 30, now, &config).is_ok());
+        assert!(check_tap_window(now - 121, now, &config).is_err());
+        assert!(check_tap_window(now + 31, now, &config).is_err());
     }
 }
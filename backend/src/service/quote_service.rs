@@ -0,0 +1,531 @@
+use crate::{
+    api_error::ApiError,
+    config::Config,
+    models::{Quote, QuoteSource},
+    service::{anchor_service::Sep38QuoteRequest, AnchorService, IdentityService},
+    stellar::{
+        asset::{self, format_stroops, parse_stroops},
+        horizon::{HorizonClient, PaymentPath},
+        Keypair,
+    },
+};
+use deadpool_postgres::{Pool, Transaction};
+use serde::{Deserialize, Serialize};
+use soroban_sdk::xdr::Asset;
+use std::{sync::Arc, time::Duration};
+use uuid::Uuid;
+
+const QUOTE_COLUMNS: &str = "id, merchant_id, payer_address, sell_asset, sell_amount, buy_asset, buy_amount, price, source, anchor_quote_id, path, expires_at, payment_id, created_at";
+
+/// SEP-38 context quotes are requested in: merchant balances leave through SEP-31
+const SEP38_CONTEXT: &str = "sep31";
+
+/// Prices payments to merchants in assets other than their settlement asset
+///
+/// Prices come from the anchor's SEP-38 server when one is configured and
+/// from Horizon path finding over the DEX otherwise, or when the anchor
+/// cannot price the pair. Firm quotes are stored with an expiry and redeemed
+/// once, by a payment that takes its `min_receive` from the quote.
+#[derive(Clone)]
+pub struct QuoteService {
+    db_pool: Arc<Pool>,
+    config: Config,
+    identity: IdentityService,
+    anchor: AnchorService,
+    horizon: Arc<HorizonClient>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuoteRequest {
+    pub merchant_id: String,
+    /// Asset the payer pays in
+    pub send_asset: String,
+    /// Exact amount the payer sends, in stroops; set this or `receive_amount`
+    pub send_amount: Option<i64>,
+    /// Exact amount the merchant receives in its settlement asset, in stroops
+    pub receive_amount: Option<i64>,
+}
+
+/// A price that is not locked and may have moved by the time a payment is made
+#[derive(Debug, Clone, Serialize)]
+pub struct IndicativePrice {
+    pub sell_asset: String,
+    pub sell_amount: i64,
+    pub buy_asset: String,
+    pub buy_amount: i64,
+    pub price: String,
+    pub source: QuoteSource,
+    pub path: Vec<String>,
+}
+
+/// The best route for a payment into a merchant's settlement asset
+#[derive(Debug, Clone, Serialize)]
+pub struct BestPath {
+    pub send_asset: String,
+    pub send_amount: i64,
+    pub settlement_asset: String,
+    /// What the merchant can expect to receive, in stroops
+    pub receive_amount: i64,
+    /// Intermediate assets, as `native` or `CODE:ISSUER`
+    pub path: Vec<String>,
+}
+
+/// The conversion a quote request asks for
+struct Pair {
+    merchant_id: String,
+    sell_asset: String,
+    buy_asset: String,
+    sell: Asset,
+    buy: Asset,
+    amount: FixedAmount,
+}
+
+/// Which side of the conversion is fixed
+#[derive(Clone, Copy)]
+enum FixedAmount {
+    Send(i64),
+    Receive(i64),
+}
+
+struct Priced {
+    sell_amount: i64,
+    buy_amount: i64,
+    price: String,
+    source: QuoteSource,
+    anchor_quote_id: Option<String>,
+    path: Vec<String>,
+    /// Set by the anchor on firm quotes
+    expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl QuoteService {
+    pub fn new(
+        db_pool: Arc<Pool>,
+        config: Config,
+        identity: IdentityService,
+        anchor: AnchorService,
+    ) -> Self {
+        let horizon = Arc::new(HorizonClient::new(
+            config.stellar_network.horizon_url.clone(),
+            Duration::from_secs(config.stellar_network.path_cache_secs),
+        ));
+        Self {
+            db_pool,
+            config,
+            identity,
+            anchor,
+            horizon,
+        }
+    }
+
+    /// Current price of a conversion, without locking it
+    pub async fn get_price(&self, request: &QuoteRequest) -> Result<IndicativePrice, ApiError> {
+        let pair = self.pair(request).await?;
+        let priced = self.price(&pair, None).await?;
+
+        Ok(IndicativePrice {
+            sell_asset: pair.sell_asset,
+            sell_amount: priced.sell_amount,
+            buy_asset: pair.buy_asset,
+            buy_amount: priced.buy_amount,
+            price: priced.price,
+            source: priced.source,
+            path: priced.path,
+        })
+    }
+
+    /// Lock a price for the user's wallet to pay the merchant with
+    pub async fn create_quote(
+        &self,
+        user_id: &str,
+        request: QuoteRequest,
+    ) -> Result<Quote, ApiError> {
+        let keypair = self.identity.get_user_keypair(user_id).await?;
+        let pair = self.pair(&request).await?;
+        let priced = self.price(&pair, Some(&keypair)).await?;
+        let expires_at = priced.expires_at.unwrap_or_else(|| {
+            chrono::Utc::now() + chrono::Duration::seconds(self.config.quotes.ttl_secs)
+        });
+
+        let client = self.db_pool.get().await?;
+        let row = client
+            .query_one(
+                &format!(
+                    r#"
+                    INSERT INTO quotes (
+                        id, merchant_id, payer_address, sell_asset, sell_amount, buy_asset,
+                        buy_amount, price, source, anchor_quote_id, path, expires_at
+                    )
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                    RETURNING {}
+                    "#,
+                    QUOTE_COLUMNS
+                ),
+                &[
+                    &Uuid::new_v4(),
+                    &pair.merchant_id,
+                    &keypair.address(),
+                    &pair.sell_asset,
+                    &priced.sell_amount,
+                    &pair.buy_asset,
+                    &priced.buy_amount,
+                    &priced.price,
+                    &priced.source.to_string(),
+                    &priced.anchor_quote_id,
+                    &serde_json::json!(priced.path),
+                    &expires_at,
+                ],
+            )
+            .await?;
+
+        quote_from_row(&row)
+    }
+
+    /// Best DEX path for paying the merchant, as Horizon currently prices it
+    ///
+    /// Unlike a quote this takes no margin and locks nothing; it tells the
+    /// payer roughly how much of their asset a payment needs or yields.
+    pub async fn find_path(&self, request: &QuoteRequest) -> Result<BestPath, ApiError> {
+        let pair = self.pair(request).await?;
+        let best = if pair.sell == pair.buy {
+            let amount = match pair.amount {
+                FixedAmount::Send(amount) | FixedAmount::Receive(amount) => amount,
+            };
+            PaymentPath {
+                source_amount: amount,
+                destination_amount: amount,
+                path: Vec::new(),
+            }
+        } else {
+            self.best_dex_path(&pair).await?
+        };
+
+        Ok(BestPath {
+            send_asset: pair.sell_asset,
+            send_amount: best.source_amount,
+            settlement_asset: pair.buy_asset,
+            receive_amount: best.destination_amount,
+            path: best.path,
+        })
+    }
+
+    /// A quote given to `payer_address`
+    pub async fn get_quote(&self, quote_id: Uuid, payer_address: &str) -> Result<Quote, ApiError> {
+        let client = self.db_pool.get().await?;
+        let row = client
+            .query_opt(
+                &format!(
+                    "SELECT {} FROM quotes WHERE id = $1 AND payer_address = $2",
+                    QUOTE_COLUMNS
+                ),
+                &[&quote_id, &payer_address],
+            )
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Quote not found".to_string()))?;
+
+        quote_from_row(&row)
+    }
+
+    async fn pair(&self, request: &QuoteRequest) -> Result<Pair, ApiError> {
+        let amount = match (request.send_amount, request.receive_amount) {
+            (Some(amount), None) => FixedAmount::Send(amount),
+            (None, Some(amount)) => FixedAmount::Receive(amount),
+            _ => {
+                return Err(ApiError::Validation(
+                    "Exactly one of send_amount and receive_amount is required".to_string(),
+                ))
+            }
+        };
+        if matches!(amount, FixedAmount::Send(a) | FixedAmount::Receive(a) if a <= 0) {
+            return Err(ApiError::Validation("Amount must be positive".to_string()));
+        }
+
+        let client = self.db_pool.get().await?;
+        let buy_asset: String = client
+            .query_opt(
+                "SELECT settlement_asset FROM merchants WHERE merchant_id = $1 AND active = true",
+                &[&request.merchant_id],
+            )
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Merchant not found or inactive".to_string()))?
+            .get(0);
+
+        let issuers = &self.config.stellar_network.assets;
+        Ok(Pair {
+            merchant_id: request.merchant_id.clone(),
+            sell: asset::resolve_asset(&request.send_asset, issuers)?,
+            buy: asset::resolve_asset(&buy_asset, issuers)?,
+            sell_asset: request.send_asset.clone(),
+            buy_asset,
+            amount,
+        })
+    }
+
+    /// Price `pair`, firmly with the anchor when `payer` is given
+    async fn price(&self, pair: &Pair, payer: Option<&Keypair>) -> Result<Priced, ApiError> {
+        if pair.sell == pair.buy {
+            let amount = match pair.amount {
+                FixedAmount::Send(amount) | FixedAmount::Receive(amount) => amount,
+            };
+            return Ok(Priced {
+                sell_amount: amount,
+                buy_amount: amount,
+                price: "1".to_string(),
+                source: QuoteSource::Direct,
+                anchor_quote_id: None,
+                path: Vec::new(),
+                expires_at: None,
+            });
+        }
+
+        if self.config.anchor_config.sep38_url.is_some() {
+            match self.anchor_price(pair, payer).await {
+                Ok(priced) => return Ok(priced),
+                Err(e) => tracing::warn!(
+                    "Anchor could not price {} to {}, using the DEX: {}",
+                    pair.sell_asset,
+                    pair.buy_asset,
+                    e
+                ),
+            }
+        }
+
+        self.dex_price(pair).await
+    }
+
+    async fn anchor_price(&self, pair: &Pair, payer: Option<&Keypair>) -> Result<Priced, ApiError> {
+        let (sell_amount, buy_amount) = match pair.amount {
+            FixedAmount::Send(amount) => (Some(format_stroops(amount)), None),
+            FixedAmount::Receive(amount) => (None, Some(format_stroops(amount))),
+        };
+        let request = Sep38QuoteRequest {
+            sell_asset: sep38_asset(&pair.sell),
+            buy_asset: sep38_asset(&pair.buy),
+            sell_amount,
+            buy_amount,
+            context: SEP38_CONTEXT.to_string(),
+        };
+
+        let (price, sell_amount, buy_amount, anchor_quote_id, expires_at) = match payer {
+            Some(keypair) => {
+                let token = self.anchor.authenticate(keypair).await?;
+                let quote = self.anchor.create_sep38_quote(&token, &request).await?;
+                (
+                    quote.total_price,
+                    quote.sell_amount,
+                    quote.buy_amount,
+                    Some(quote.id),
+                    Some(quote.expires_at),
+                )
+            }
+            None => {
+                let price = self.anchor.get_sep38_price(&request).await?;
+                (
+                    price.total_price,
+                    price.sell_amount,
+                    price.buy_amount,
+                    None,
+                    None,
+                )
+            }
+        };
+
+        let invalid = |e: ApiError| ApiError::Anchor(format!("Invalid SEP-38 amount: {}", e));
+        Ok(Priced {
+            sell_amount: parse_stroops(&sell_amount).map_err(invalid)?,
+            buy_amount: parse_stroops(&buy_amount).map_err(invalid)?,
+            price,
+            source: QuoteSource::Anchor,
+            anchor_quote_id,
+            path: Vec::new(),
+            expires_at,
+        })
+    }
+
+    /// Best DEX price for `pair`, less the configured slippage margin
+    async fn dex_price(&self, pair: &Pair) -> Result<Priced, ApiError> {
+        let slippage_bps = self.config.quotes.slippage_bps;
+        let best = self.best_dex_path(pair).await?;
+        let (sell_amount, buy_amount) = match pair.amount {
+            FixedAmount::Send(amount) => {
+                (amount, with_margin(best.destination_amount, -slippage_bps))
+            }
+            FixedAmount::Receive(amount) => (with_margin(best.source_amount, slippage_bps), amount),
+        };
+        if buy_amount <= 0 {
+            return Err(no_path(pair));
+        }
+
+        Ok(Priced {
+            sell_amount,
+            buy_amount,
+            price: unit_price(sell_amount, buy_amount),
+            source: QuoteSource::Dex,
+            anchor_quote_id: None,
+            path: best.path,
+            expires_at: None,
+        })
+    }
+
+    /// The DEX path that delivers the most for a fixed send, or costs the least for a fixed receive
+    async fn best_dex_path(&self, pair: &Pair) -> Result<PaymentPath, ApiError> {
+        let best = match pair.amount {
+            FixedAmount::Send(amount) => self
+                .horizon
+                .strict_send_paths(&pair.sell, amount, &pair.buy)
+                .await?
+                .into_iter()
+                .max_by_key(|path| path.destination_amount),
+            FixedAmount::Receive(amount) => self
+                .horizon
+                .strict_receive_paths(&pair.sell, &pair.buy, amount)
+                .await?
+                .into_iter()
+                .min_by_key(|path| path.source_amount),
+        };
+
+        best.ok_or_else(|| no_path(pair))
+    }
+}
+
+fn no_path(pair: &Pair) -> ApiError {
+    ApiError::Validation(format!(
+        "No DEX path from {} to {}",
+        pair.sell_asset, pair.buy_asset
+    ))
+}
+
+/// Lock a quote for a payment being created in `tx`, checking the payment matches it
+///
+/// The row stays locked until `tx` ends, so two payments cannot both redeem
+/// the same quote; the caller records the redemption with `mark_quote_used`.
+pub async fn lock_quote_for_payment(
+    tx: &Transaction<'_>,
+    quote_id: Uuid,
+    payer_address: &str,
+    merchant_id: &str,
+    send_asset: &str,
+    send_amount: i64,
+) -> Result<Quote, ApiError> {
+    let row = tx
+        .query_opt(
+            &format!(
+                "SELECT {} FROM quotes WHERE id = $1 AND payer_address = $2 FOR UPDATE",
+                QUOTE_COLUMNS
+            ),
+            &[&quote_id, &payer_address],
+        )
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Quote not found".to_string()))?;
+    let quote = quote_from_row(&row)?;
+
+    if quote.payment_id.is_some() {
+        return Err(ApiError::Conflict(
+            "Quote has already been used".to_string(),
+        ));
+    }
+    if quote.expires_at <= chrono::Utc::now() {
+        return Err(ApiError::Validation("Quote has expired".to_string()));
+    }
+    if quote.merchant_id != merchant_id {
+        return Err(ApiError::Validation(
+            "Quote was given for a different merchant".to_string(),
+        ));
+    }
+    if !quote.sell_asset.eq_ignore_ascii_case(send_asset) || quote.sell_amount != send_amount {
+        return Err(ApiError::Validation(format!(
+            "Payment must send the quoted {} {}",
+            format_stroops(quote.sell_amount),
+            quote.sell_asset
+        )));
+    }
+
+    Ok(quote)
+}
+
+/// Record that `payment_id` redeemed the quote
+pub async fn mark_quote_used(
+    tx: &Transaction<'_>,
+    quote_id: Uuid,
+    payment_id: Uuid,
+) -> Result<(), ApiError> {
+    tx.execute(
+        "UPDATE quotes SET payment_id = $1 WHERE id = $2",
+        &[&payment_id, &quote_id],
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// An asset in SEP-38's `stellar:CODE:ISSUER` form
+fn sep38_asset(asset: &Asset) -> String {
+    format!("stellar:{}", asset::canonical(asset))
+}
+
+/// `amount` moved by `bps` basis points, rounded away from the payer's favour
+fn with_margin(amount: i64, bps: i64) -> i64 {
+    let scaled = amount as i128 * (10_000 + bps) as i128;
+    let adjusted = if bps > 0 {
+        (scaled + 9_999) / 10_000
+    } else {
+        scaled / 10_000
+    };
+    adjusted.clamp(0, i64::MAX as i128) as i64
+}
+
+/// Cost of one unit of the bought asset in the sold one, to 7 decimals
+fn unit_price(sell_amount: i64, buy_amount: i64) -> String {
+    let stroops = sell_amount as i128 * 10_000_000 / buy_amount as i128;
+    format_stroops(stroops.min(i64::MAX as i128) as i64)
+}
+
+fn quote_from_row(row: &tokio_postgres::Row) -> Result<Quote, ApiError> {
+    let source: &str = row.get(8);
+    Ok(Quote {
+        id: row.get::<_, Uuid>(0).to_string(),
+        merchant_id: row.get(1),
+        payer_address: row.get(2),
+        sell_asset: row.get(3),
+        sell_amount: row.get(4),
+        buy_asset: row.get(5),
+        buy_amount: row.get(6),
+        price: row.get(7),
+        source: source.parse().map_err(|e| {
+            tracing::error!("{} in database", e);
+            ApiError::InternalServerError
+        })?,
+        anchor_quote_id: row.get(9),
+        path: serde_json::from_value(row.get(10))?,
+        expires_at: row.get::<_, chrono::DateTime<chrono::Utc>>(11),
+        payment_id: row.get::<_, Option<Uuid>>(12).map(|id| id.to_string()),
+        created_at: row.get::<_, chrono::DateTime<chrono::Utc>>(13),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_margin() {
+        assert_eq!(with_margin(10_000_000, -50), 9_950_000);
+        assert_eq!(with_margin(10_000_000, 50), 10_050_000);
+        // Rounds down what the merchant is promised and up what the payer sends
+        assert_eq!(with_margin(3, -50), 2);
+        assert_eq!(with_margin(3, 50), 4);
+        assert_eq!(with_margin(10_000_000, 0), 10_000_000);
+    }
+
+    #[test]
+    fn test_unit_price() {
+        assert_eq!(unit_price(80_000_000, 10_000_000), "8");
+        assert_eq!(unit_price(10_000_000, 80_000_000), "0.125");
+        assert_eq!(unit_price(10_000_000, 30_000_000), "0.3333333");
+    }
+
+    #[test]
+    fn test_sep38_asset() {
+        assert_eq!(sep38_asset(&Asset::Native), "stellar:native");
+    }
+}
@@ -0,0 +1,727 @@
+use crate::{
+    api_error::ApiError,
+    config::Config,
+    models::{
+        PaymentStatus, Refund, RefundReason, RefundStatus, StatusEvent, TransactionStatus,
+        WebhookEventType,
+    },
+    service::{
+        lifecycle::{self, LifecycleEntity},
+        soroban_service::Submission,
+        webhook_service, AnchorService, NotificationService, SorobanService,
+    },
+    stellar::asset,
+};
+use deadpool_postgres::{Pool, Transaction};
+use serde::{Deserialize, Serialize};
+use soroban_sdk::xdr::Memo;
+use std::sync::Arc;
+use uuid::Uuid;
+
+const REFUND_COLUMNS: &str = "id, payment_id, merchant_id, amount, asset, destination_address, reason, note, status, vault_tx_hash, tx_hash, requested_by, created_at, updated_at";
+
+/// Longest note a merchant can attach to a refund
+const MAX_NOTE_CHARS: usize = 500;
+
+/// Refunds of completed payments back to their payers
+///
+/// A payment can be refunded in full or in several parts, up to the amount
+/// the merchant received for it. Creating a refund debits the merchant's
+/// balance; `process_pending` then debits the merchant-vault contract and
+/// pays the payer from the platform account, each once, and completes the
+/// refund when the payment is confirmed on-chain.
+#[derive(Clone)]
+pub struct RefundService {
+    db_pool: Arc<Pool>,
+    config: Config,
+    anchor: AnchorService,
+    soroban: SorobanService,
+    notification: NotificationService,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateRefundRequest {
+    /// Amount in stroops of the merchant's settlement asset; the rest of the
+    /// refundable amount when omitted
+    pub amount: Option<i64>,
+    pub reason: RefundReason,
+    pub note: Option<String>,
+}
+
+impl RefundService {
+    pub fn new(
+        db_pool: Arc<Pool>,
+        config: Config,
+        anchor: AnchorService,
+        soroban: SorobanService,
+        notification: NotificationService,
+    ) -> Self {
+        Self {
+            db_pool,
+            config,
+            anchor,
+            soroban,
+            notification,
+        }
+    }
+
+    /// Refund part or all of a completed payment to its payer
+    ///
+    /// Refunds not yet failed count against the refundable amount, so
+    /// concurrent requests cannot refund more than was paid. The merchant is
+    /// told through its `refund.created` webhooks.
+    pub async fn create_refund(
+        &self,
+        payment_id: Uuid,
+        requested_by: &str,
+        request: CreateRefundRequest,
+    ) -> Result<Refund, ApiError> {
+        let note = request
+            .note
+            .map(|note| note.trim().to_string())
+            .filter(|note| !note.is_empty());
+        if note
+            .as_ref()
+            .is_some_and(|note| note.chars().count() > MAX_NOTE_CHARS)
+        {
+            return Err(ApiError::Validation(format!(
+                "note may be at most {} characters",
+                MAX_NOTE_CHARS
+            )));
+        }
+
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+
+        let payment = tx
+            .query_opt(
+                r#"
+                SELECT p.status, p.merchant_id, p.from_address, COALESCE(p.receive_amount, p.send_amount),
+                       m.settlement_asset
+                FROM payments p JOIN merchants m ON m.merchant_id = p.merchant_id
+                WHERE p.id = $1
+                FOR UPDATE OF p
+                "#,
+                &[&payment_id],
+            )
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Payment not found".to_string()))?;
+        let status: PaymentStatus =
+            lifecycle::parse_status(LifecycleEntity::Payment, payment.get(0))?;
+        let merchant_id: String = payment.get(1);
+        let destination_address: String = payment.get(2);
+        let settled: i64 = payment.get(3);
+        let settlement_asset: String = payment.get(4);
+
+        match status {
+            PaymentStatus::Completed => {}
+            PaymentStatus::Refunded => {
+                return Err(ApiError::Conflict(
+                    "Payment has already been fully refunded".to_string(),
+                ))
+            }
+            _ => {
+                return Err(ApiError::Conflict(format!(
+                    "Only completed payments can be refunded, this one is {}",
+                    status
+                )))
+            }
+        }
+
+        let reserved: i64 = tx
+            .query_one(
+                "SELECT COALESCE(SUM(amount), 0)::BIGINT FROM refunds WHERE payment_id = $1 AND status <> 'failed'",
+                &[&payment_id],
+            )
+            .await?
+            .get(0);
+        let amount = refund_amount(request.amount, settled, reserved)?;
+
+        let debited = tx
+            .execute(
+                "UPDATE balances SET amount = amount - $3, last_updated = NOW() WHERE owner_id = $1 AND asset = $2 AND amount >= $3",
+                &[&merchant_id, &settlement_asset, &amount],
+            )
+            .await?;
+        if debited == 0 {
+            return Err(ApiError::Validation(
+                "Merchant balance is insufficient for this refund".to_string(),
+            ));
+        }
+
+        let refund_id = Uuid::new_v4();
+        let row = tx
+            .query_one(
+                &format!(
+                    r#"
+                    INSERT INTO refunds (id, payment_id, merchant_id, amount, asset, destination_address, reason, note, status, requested_by)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                    RETURNING {}
+                    "#,
+                    REFUND_COLUMNS
+                ),
+                &[
+                    &refund_id,
+                    &payment_id,
+                    &merchant_id,
+                    &amount,
+                    &settlement_asset,
+                    &destination_address,
+                    &request.reason.to_string(),
+                    &note,
+                    &RefundStatus::Pending.to_string(),
+                    &requested_by,
+                ],
+            )
+            .await?;
+        lifecycle::record_created(
+            &tx,
+            LifecycleEntity::Refund,
+            refund_id,
+            RefundStatus::Pending,
+            None,
+        )
+        .await?;
+
+        let refund = refund_from_row(&row)?;
+        webhook_service::enqueue_event(
+            &tx,
+            &merchant_id,
+            WebhookEventType::RefundCreated,
+            serde_json::to_value(crate::http::refunds::refund_response(refund.clone()))?,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(refund)
+    }
+
+    pub async fn get_refund(&self, refund_id: Uuid) -> Result<Refund, ApiError> {
+        let client = self.db_pool.get().await?;
+        let row = client
+            .query_opt(
+                &format!("SELECT {} FROM refunds WHERE id = $1", REFUND_COLUMNS),
+                &[&refund_id],
+            )
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Refund not found".to_string()))?;
+
+        refund_from_row(&row)
+    }
+
+    /// A payment's refunds, oldest first
+    pub async fn list_refunds(&self, payment_id: Uuid) -> Result<Vec<Refund>, ApiError> {
+        let client = self.db_pool.get().await?;
+        let rows = client
+            .query(
+                &format!(
+                    "SELECT {} FROM refunds WHERE payment_id = $1 ORDER BY created_at",
+                    REFUND_COLUMNS
+                ),
+                &[&payment_id],
+            )
+            .await?;
+
+        rows.iter().map(refund_from_row).collect()
+    }
+
+    pub async fn get_refund_history(&self, refund_id: Uuid) -> Result<Vec<StatusEvent>, ApiError> {
+        let client = self.db_pool.get().await?;
+        lifecycle::history(&client, LifecycleEntity::Refund, refund_id).await
+    }
+
+    /// Send pending refunds and confirm sent ones
+    ///
+    /// Returns the number of refunds looked at. A failure on one refund is
+    /// logged and does not stop the others; it is retried on the next sweep.
+    pub async fn process_pending(&self) -> Result<usize, ApiError> {
+        let client = self.db_pool.get().await?;
+        let rows = client
+            .query(
+                &format!(
+                    "SELECT {} FROM refunds WHERE status IN ('pending', 'processing') ORDER BY created_at",
+                    REFUND_COLUMNS
+                ),
+                &[],
+            )
+            .await?;
+        drop(client);
+
+        for row in &rows {
+            let refund = refund_from_row(row)?;
+            let result = match refund.status {
+                RefundStatus::Pending => self.send(&refund).await,
+                _ => self.confirm(&refund).await,
+            };
+            if let Err(e) = result {
+                tracing::warn!("Failed to process refund {}: {}", refund.id, e);
+            }
+        }
+
+        Ok(rows.len())
+    }
+
+    /// Debit the merchant-vault, then pay the payer and mark the refund processing
+    ///
+    /// The debit and the payment are each signed and committed with their
+    /// hash before they are submitted, then resubmitted as they are until they
+    /// land or expire. A retried refund is never debited or paid a second time.
+    async fn send(&self, refund: &Refund) -> Result<(), ApiError> {
+        let refund_id = parse_id(&refund.id)?;
+
+        if self.soroban.vault_enabled() && !self.vault_debited(refund_id).await? {
+            return Ok(());
+        }
+
+        let Some(envelope) = self.signed_payment(refund_id).await? else {
+            return Ok(());
+        };
+        match self.soroban.reconcile(&envelope).await? {
+            Submission::Pending => Ok(()),
+            Submission::Confirmed => {
+                let mut client = self.db_pool.get().await?;
+                let tx = client.transaction().await?;
+                if lock_pending(&tx, refund_id).await?.is_none() {
+                    return Ok(());
+                }
+                lifecycle::transition(
+                    &tx,
+                    LifecycleEntity::Refund,
+                    refund_id,
+                    RefundStatus::Processing,
+                    "Refund payment to payer landed",
+                    None,
+                )
+                .await?;
+                tx.commit().await?;
+                Ok(())
+            }
+            Submission::Failed => {
+                if self.soroban.vault_enabled() {
+                    tracing::error!(
+                        "Refund {} payment failed after its merchant vault debit; the vault balance needs reconciling",
+                        refund_id
+                    );
+                }
+                self.fail_refund(refund_id, "Refund payment failed on-chain")
+                    .await
+            }
+            Submission::Expired => {
+                // It can no longer land, so the next sweep signs a new one
+                let client = self.db_pool.get().await?;
+                client
+                    .execute(
+                        "UPDATE refunds SET tx_hash = NULL, tx_envelope = NULL, updated_at = NOW() WHERE id = $1 AND tx_envelope = $2",
+                        &[&refund_id, &envelope],
+                    )
+                    .await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Whether the refund's merchant-vault debit has landed, signing and submitting it if need be
+    async fn vault_debited(&self, refund_id: Uuid) -> Result<bool, ApiError> {
+        let envelope = match self.signed_vault_debit(refund_id).await {
+            Ok(Some(envelope)) => envelope,
+            Ok(None) => return Ok(false),
+            // The vault refused the debit, e.g. for lack of balance: retrying will not help
+            Err(ApiError::Contract(e)) => {
+                self.fail_refund(
+                    refund_id,
+                    &format!("Merchant vault refused the debit: {}", e.message()),
+                )
+                .await?;
+                return Ok(false);
+            }
+            Err(e) => return Err(e),
+        };
+
+        match self.soroban.reconcile(&envelope).await? {
+            Submission::Confirmed => Ok(true),
+            Submission::Pending => Ok(false),
+            Submission::Failed => {
+                self.fail_refund(refund_id, "Merchant vault debit failed on-chain")
+                    .await?;
+                Ok(false)
+            }
+            Submission::Expired => {
+                let client = self.db_pool.get().await?;
+                client
+                    .execute(
+                        "UPDATE refunds SET vault_tx_hash = NULL, vault_tx_envelope = NULL, updated_at = NOW() WHERE id = $1 AND vault_tx_envelope = $2",
+                        &[&refund_id, &envelope],
+                    )
+                    .await?;
+                Ok(false)
+            }
+        }
+    }
+
+    /// The refund's signed merchant-vault debit, signed and recorded first if there is none
+    ///
+    /// Returns `None` once the refund is no longer pending or another worker holds it.
+    async fn signed_vault_debit(&self, refund_id: Uuid) -> Result<Option<String>, ApiError> {
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+        let Some(row) = tx
+            .query_opt(
+                r#"
+                SELECT r.vault_tx_envelope, r.amount, m.vault_address
+                FROM refunds r JOIN merchants m ON m.merchant_id = r.merchant_id
+                WHERE r.id = $1 AND r.status = 'pending'
+                FOR UPDATE OF r SKIP LOCKED
+                "#,
+                &[&refund_id],
+            )
+            .await?
+        else {
+            return Ok(None);
+        };
+        if let Some(envelope) = row.get::<_, Option<String>>(0) {
+            return Ok(Some(envelope));
+        }
+        let amount: i64 = row.get(1);
+        let vault_address: String = row.get(2);
+
+        let signed = self
+            .soroban
+            .sign_vault_debit(&vault_address, amount)
+            .await?;
+        tx.execute(
+            "UPDATE refunds SET vault_tx_hash = $1, vault_tx_envelope = $2, updated_at = NOW() WHERE id = $3",
+            &[&signed.tx_hash, &signed.envelope_xdr, &refund_id],
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(Some(signed.envelope_xdr))
+    }
+
+    /// The refund's signed payment to the payer, signed and recorded first if there is none
+    ///
+    /// Returns `None` once the refund is no longer pending or another worker holds it.
+    async fn signed_payment(&self, refund_id: Uuid) -> Result<Option<String>, ApiError> {
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+        let Some(row) = tx
+            .query_opt(
+                "SELECT tx_envelope, amount, asset, destination_address FROM refunds WHERE id = $1 AND status = 'pending' FOR UPDATE SKIP LOCKED",
+                &[&refund_id],
+            )
+            .await?
+        else {
+            return Ok(None);
+        };
+        if let Some(envelope) = row.get::<_, Option<String>>(0) {
+            return Ok(Some(envelope));
+        }
+        let amount: i64 = row.get(1);
+        let refund_asset: String = row.get(2);
+        let destination: String = row.get(3);
+
+        let stellar_asset =
+            asset::resolve_asset(&refund_asset, &self.config.stellar_network.assets)?;
+        let keypair = self.anchor.platform_keypair()?;
+        let signed = self
+            .soroban
+            .sign_payment(
+                &keypair,
+                &keypair.address(),
+                &destination,
+                stellar_asset,
+                amount,
+                Memo::None,
+            )
+            .await?;
+        tx.execute(
+            "UPDATE refunds SET tx_hash = $1, tx_envelope = $2, updated_at = NOW() WHERE id = $3",
+            &[&signed.tx_hash, &signed.envelope_xdr, &refund_id],
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(Some(signed.envelope_xdr))
+    }
+
+    /// Complete or fail a sent refund once its payment is final on-chain
+    async fn confirm(&self, refund: &Refund) -> Result<(), ApiError> {
+        let Some(tx_hash) = refund.tx_hash.as_deref() else {
+            return Ok(());
+        };
+        let refund_id = parse_id(&refund.id)?;
+
+        match self.soroban.get_transaction_status(tx_hash).await? {
+            TransactionStatus::CONFIRMED => self.complete_refund(refund, refund_id).await,
+            TransactionStatus::FAILED => {
+                if let Some(vault_tx_hash) = &refund.vault_tx_hash {
+                    tracing::error!(
+                        "Refund {} failed after merchant vault debit {}; the vault balance needs reconciling",
+                        refund_id,
+                        vault_tx_hash
+                    );
+                }
+                self.fail_refund(
+                    refund_id,
+                    &format!("Refund payment {} failed on-chain", tx_hash),
+                )
+                .await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Mark the refund completed, add it to the payment's refunded amount and tell the payer
+    ///
+    /// The payment moves to `refunded` once its refunds reach the settled amount.
+    async fn complete_refund(&self, refund: &Refund, refund_id: Uuid) -> Result<(), ApiError> {
+        let payment_id = Uuid::parse_str(&refund.payment_id).map_err(|_| {
+            tracing::error!(
+                "Invalid payment id of refund {}: {}",
+                refund_id,
+                refund.payment_id
+            );
+            ApiError::InternalServerError
+        })?;
+
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+
+        lifecycle::transition(
+            &tx,
+            LifecycleEntity::Refund,
+            refund_id,
+            RefundStatus::Completed,
+            "Refund payment confirmed",
+            None,
+        )
+        .await?;
+        let row = tx
+            .query_one(
+                r#"
+                UPDATE payments SET refunded_amount = refunded_amount + $1, updated_at = NOW()
+                WHERE id = $2
+                RETURNING refunded_amount, COALESCE(receive_amount, send_amount)
+                "#,
+                &[&refund.amount, &payment_id],
+            )
+            .await?;
+        let refunded: i64 = row.get(0);
+        let settled: i64 = row.get(1);
+        if refunded >= settled {
+            lifecycle::transition(
+                &tx,
+                LifecycleEntity::Payment,
+                payment_id,
+                PaymentStatus::Refunded,
+                "Fully refunded",
+                None,
+            )
+            .await?;
+        }
+        tx.commit().await?;
+
+        tracing::info!(
+            "Refund {} of {} {} for payment {} completed",
+            refund_id,
+            asset::format_stroops(refund.amount),
+            refund.asset,
+            payment_id
+        );
+        self.notify_payer(refund, refund_id).await;
+
+        Ok(())
+    }
+
+    /// Mark the refund failed and return its amount to the merchant's balance
+    async fn fail_refund(&self, refund_id: Uuid, reason: &str) -> Result<(), ApiError> {
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+
+        lifecycle::transition(
+            &tx,
+            LifecycleEntity::Refund,
+            refund_id,
+            RefundStatus::Failed,
+            reason,
+            None,
+        )
+        .await?;
+        let row = tx
+            .query_one(
+                "SELECT merchant_id, amount, asset FROM refunds WHERE id = $1",
+                &[&refund_id],
+            )
+            .await?;
+        let (merchant_id, amount, refund_asset): (String, i64, String) =
+            (row.get(0), row.get(1), row.get(2));
+        tx.execute(
+            "UPDATE balances SET amount = amount + $3, last_updated = NOW() WHERE owner_id = $1 AND asset = $2",
+            &[&merchant_id, &refund_asset, &amount],
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        tracing::warn!(
+            "Refund {} for merchant {} failed, {} {} returned: {}",
+            refund_id,
+            merchant_id,
+            asset::format_stroops(amount),
+            refund_asset,
+            reason
+        );
+
+        Ok(())
+    }
+
+    /// Tell the payer about the refund, if their wallet is on the platform
+    async fn notify_payer(&self, refund: &Refund, refund_id: Uuid) {
+        let owner: Result<Option<String>, ApiError> = async {
+            let client = self.db_pool.get().await?;
+            let owner = client
+                .query_opt(
+                    "SELECT user_id FROM users WHERE stellar_address = $1",
+                    &[&refund.destination_address],
+                )
+                .await?;
+            Ok(owner.map(|row| row.get(0)))
+        }
+        .await;
+        let user_id = match owner {
+            Ok(Some(user_id)) => user_id,
+            // Payers outside the platform have nobody to notify
+            Ok(None) => return,
+            Err(e) => {
+                tracing::warn!("Failed to notify the payer of refund {}: {}", refund_id, e);
+                return;
+            }
+        };
+
+        self.notification
+            .notify_best_effort(
+                &user_id,
+                "Refund received",
+                format!(
+                    "{} refunded {} {} to your wallet",
+                    refund.merchant_id,
+                    asset::format_stroops(refund.amount),
+                    refund.asset
+                ),
+                serde_json::json!({
+                    "refund_id": refund_id.to_string(),
+                    "payment_id": refund.payment_id,
+                }),
+            )
+            .await;
+    }
+}
+
+/// Lock a refund for sending, or `None` if it is no longer pending or another worker holds it
+async fn lock_pending(tx: &Transaction<'_>, refund_id: Uuid) -> Result<Option<Refund>, ApiError> {
+    tx.query_opt(
+        &format!(
+            "SELECT {} FROM refunds WHERE id = $1 AND status = 'pending' FOR UPDATE SKIP LOCKED",
+            REFUND_COLUMNS
+        ),
+        &[&refund_id],
+    )
+    .await?
+    .as_ref()
+    .map(refund_from_row)
+    .transpose()
+}
+
+/// The amount to refund, given what was settled and what other refunds already claim
+fn refund_amount(requested: Option<i64>, settled: i64, reserved: i64) -> Result<i64, ApiError> {
+    let refundable = settled - reserved;
+    if refundable <= 0 {
+        return Err(ApiError::Conflict(
+            "Payment has nothing left to refund".to_string(),
+        ));
+    }
+
+    match requested {
+        None => Ok(refundable),
+        Some(amount) if amount <= 0 => Err(ApiError::Validation(
+            "Amount must be greater than zero".to_string(),
+        )),
+        Some(amount) if amount > refundable => Err(ApiError::Validation(format!(
+            "Amount exceeds the {} stroops left to refund",
+            refundable
+        ))),
+        Some(amount) => Ok(amount),
+    }
+}
+
+fn parse_id(id: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(id).map_err(|_| {
+        tracing::error!("Invalid refund id in database: {}", id);
+        ApiError::InternalServerError
+    })
+}
+
+fn refund_from_row(row: &tokio_postgres::Row) -> Result<Refund, ApiError> {
+    Ok(Refund {
+        id: row.get::<_, Uuid>(0).to_string(),
+        payment_id: row.get::<_, Uuid>(1).to_string(),
+        merchant_id: row.get(2),
+        amount: row.get(3),
+        asset: row.get(4),
+        destination_address: row.get(5),
+        reason: row.get::<_, &str>(6).parse().map_err(|e| {
+            tracing::error!("{} in database", e);
+            ApiError::InternalServerError
+        })?,
+        note: row.get(7),
+        status: lifecycle::parse_status(LifecycleEntity::Refund, row.get(8))?,
+        vault_tx_hash: row.get(9),
+        tx_hash: row.get(10),
+        requested_by: row.get(11),
+        created_at: row.get::<_, chrono::DateTime<chrono::Utc>>(12),
+        updated_at: row.get::<_, chrono::DateTime<chrono::Utc>>(13),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refund_amount() {
+        assert_eq!(refund_amount(None, 1_000, 0).unwrap(), 1_000);
+        assert_eq!(refund_amount(None, 1_000, 400).unwrap(), 600);
+        assert_eq!(refund_amount(Some(600), 1_000, 400).unwrap(), 600);
+
+        assert!(matches!(
+            refund_amount(Some(601), 1_000, 400),
+            Err(ApiError::Validation(_))
+        ));
+        assert!(matches!(
+            refund_amount(Some(0), 1_000, 0),
+            Err(ApiError::Validation(_))
+        ));
+        assert!(matches!(
+            refund_amount(None, 1_000, 1_000),
+            Err(ApiError::Conflict(_))
+        ));
+    }
+
+    #[test]
+    fn test_refund_reasons_round_trip() {
+        for reason in [
+            RefundReason::RequestedByCustomer,
+            RefundReason::Duplicate,
+            RefundReason::Fraudulent,
+            RefundReason::ProductNotDelivered,
+            RefundReason::Other,
+        ] {
+            assert_eq!(reason.to_string().parse::<RefundReason>(), Ok(reason));
+            assert_eq!(
+                serde_json::to_value(reason).unwrap(),
+                serde_json::json!(reason.to_string())
+            );
+        }
+        assert!("changed_mind".parse::<RefundReason>().is_err());
+    }
+}
@@ -0,0 +1,903 @@
+use crate::{
+    api_error::ApiError,
+    config::Config,
+    models::{Lifecycle, Settlement, SettlementStatus, StatusEvent, WebhookEventType},
+    service::{
+        anchor_service::{
+            AnchorProgress, Sep31AssetInfo, Sep31Transaction, Sep31TransactionFields,
+            Sep31TransactionRequest,
+        },
+        lifecycle::{self, LifecycleEntity},
+        soroban_service::Submission,
+        webhook_service, AnchorService, SorobanService,
+    },
+    stellar::{asset, transaction},
+};
+use deadpool_postgres::{Pool, Transaction};
+use serde::{Deserialize, Serialize};
+use soroban_sdk::xdr::Memo;
+use std::{collections::HashMap, sync::Arc};
+use uuid::Uuid;
+
+const SETTLEMENT_COLUMNS: &str = "id, tx_hash, merchant_id, amount, asset, amount_out, status, sender_id, receiver_id, anchor_tx_id, anchor_status, anchor_message, stellar_account_id, stellar_memo, stellar_memo_type, created_at, updated_at";
+
+/// Pays merchants out in fiat through a SEP-31 receiving anchor
+///
+/// Merchant funds are held in the platform account and tracked in `balances`
+/// under the merchant id, in the merchant's `settlement_asset`. A settlement
+/// debits that balance, creates the payout with the anchor and, once the
+/// anchor is ready, sends the on-chain payment from the platform account.
+#[derive(Clone)]
+pub struct SettlementService {
+    db_pool: Arc<Pool>,
+    config: Config,
+    anchor: AnchorService,
+    soroban: SorobanService,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateSettlementRequest {
+    pub merchant_id: String,
+    /// Amount in stroops (1e-7 of the merchant's settlement asset)
+    pub amount: i64,
+    /// SEP-12 id of the sending customer, when the anchor requires one
+    pub sender_id: Option<String>,
+    /// SEP-12 id of the receiving customer, when the anchor requires one
+    pub receiver_id: Option<String>,
+    /// Transaction fields the anchor asks for in its `/info`, e.g. routing details
+    #[serde(default)]
+    pub fields: HashMap<String, String>,
+}
+
+impl SettlementService {
+    pub fn new(
+        db_pool: Arc<Pool>,
+        config: Config,
+        anchor: AnchorService,
+        soroban: SorobanService,
+    ) -> Self {
+        Self {
+            db_pool,
+            config,
+            anchor,
+            soroban,
+        }
+    }
+
+    /// Reserve `request.amount` of the merchant's balance and create the payout with the anchor
+    ///
+    /// The request is checked against what the anchor's `/info` requires for
+    /// the settlement asset before anything is reserved. The settlement then
+    /// stays `pending` until the anchor asks for the on-chain payment.
+    pub async fn create_settlement(
+        &self,
+        request: CreateSettlementRequest,
+    ) -> Result<Settlement, ApiError> {
+        if request.amount <= 0 {
+            return Err(ApiError::Validation(
+                "Amount must be greater than zero".to_string(),
+            ));
+        }
+
+        let settlement_asset = self.merchant_settlement_asset(&request.merchant_id).await?;
+        let asset_code = asset::asset_code(&settlement_asset).to_string();
+        let stellar_asset =
+            asset::resolve_asset(&settlement_asset, &self.config.stellar_network.assets)?;
+
+        let token = self.anchor.authenticate_platform().await?;
+        let info = self.anchor.get_sep31_info(&token).await?;
+        let asset_info = info
+            .receive
+            .get(&asset_code)
+            .filter(|asset_info| asset_info.enabled)
+            .ok_or_else(|| {
+                ApiError::Validation(format!(
+                    "The anchor does not pay out {} over SEP-31",
+                    asset_code
+                ))
+            })?;
+        check_requirements(asset_info, &request)?;
+
+        let settlement_id = self.reserve_settlement(&request, &settlement_asset).await?;
+
+        let created = self
+            .anchor
+            .create_sep31_transaction(
+                &token,
+                &Sep31TransactionRequest {
+                    amount: asset::format_stroops(request.amount),
+                    asset_code,
+                    asset_issuer: asset::issuer(&stellar_asset),
+                    sender_id: request.sender_id.clone(),
+                    receiver_id: request.receiver_id.clone(),
+                    fields: (!request.fields.is_empty()).then(|| Sep31TransactionFields {
+                        transaction: request.fields.clone(),
+                    }),
+                },
+            )
+            .await;
+
+        let created = match created {
+            Ok(created) => created,
+            Err(e) => {
+                self.fail_settlement(
+                    settlement_id,
+                    &format!("Anchor request failed: {}", e),
+                    None,
+                )
+                .await?;
+                return Err(e);
+            }
+        };
+
+        let client = self.db_pool.get().await?;
+        client
+            .execute(
+                r#"
+                UPDATE settlements
+                SET anchor_tx_id = $1, stellar_account_id = $2, stellar_memo = $3, stellar_memo_type = $4, updated_at = NOW()
+                WHERE id = $5
+                "#,
+                &[
+                    &created.id,
+                    &created.stellar_account_id,
+                    &created.stellar_memo,
+                    &created.stellar_memo_type,
+                    &settlement_id,
+                ],
+            )
+            .await?;
+        drop(client);
+
+        if let Some(url) = &self.config.anchor_config.sep31_callback_url {
+            if let Err(e) = self
+                .anchor
+                .register_sep31_callback(&token, &created.id, url)
+                .await
+            {
+                tracing::warn!(
+                    "Failed to register callback for settlement {}, relying on polling: {}",
+                    settlement_id,
+                    e
+                );
+            }
+        }
+
+        self.get_settlement(settlement_id).await
+    }
+
+    pub async fn get_settlement(&self, settlement_id: Uuid) -> Result<Settlement, ApiError> {
+        let client = self.db_pool.get().await?;
+
+        let row = client
+            .query_opt(
+                &format!(
+                    "SELECT {} FROM settlements WHERE id = $1",
+                    SETTLEMENT_COLUMNS
+                ),
+                &[&settlement_id],
+            )
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Settlement not found".to_string()))?;
+
+        settlement_from_row(&row)
+    }
+
+    pub async fn get_settlement_history(
+        &self,
+        settlement_id: Uuid,
+    ) -> Result<Vec<StatusEvent>, ApiError> {
+        let client = self.db_pool.get().await?;
+        lifecycle::history(&client, LifecycleEntity::Settlement, settlement_id).await
+    }
+
+    /// Poll the anchor for every open settlement and apply what it reports
+    ///
+    /// Returns the number of settlements checked. A failure on one settlement
+    /// is logged and does not stop the others.
+    pub async fn poll_pending(&self) -> Result<usize, ApiError> {
+        let client = self.db_pool.get().await?;
+        let rows = client
+            .query(
+                &format!(
+                    "SELECT {} FROM settlements WHERE status IN ('pending', 'processing') AND anchor_tx_id IS NOT NULL ORDER BY created_at",
+                    SETTLEMENT_COLUMNS
+                ),
+                &[],
+            )
+            .await?;
+        drop(client);
+
+        for row in &rows {
+            let settlement = settlement_from_row(row)?;
+            if let Err(e) = self.refresh(&settlement).await {
+                tracing::warn!("Failed to update settlement {}: {}", settlement.id, e);
+            }
+        }
+
+        Ok(rows.len())
+    }
+
+    /// Apply a signed callback from the anchor about one of our payouts
+    ///
+    /// The transaction in the callback is applied as if a poll had returned
+    /// it. Callbacks for settlements that already finished are ignored, so a
+    /// late or repeated status never moves a settlement twice.
+    pub async fn handle_callback(
+        &self,
+        anchor_tx: &Sep31Transaction,
+    ) -> Result<Settlement, ApiError> {
+        // A poll racing the callback can move the settlement under us, which
+        // surfaces as a conflicting transition; apply again from its new state
+        for _ in 0..2 {
+            let settlement = self.find_by_anchor_tx(&anchor_tx.id).await?;
+            if settlement.status.is_terminal() {
+                return Ok(settlement);
+            }
+            match self.apply(&settlement, anchor_tx).await {
+                Err(ApiError::Conflict(reason)) => {
+                    tracing::info!(
+                        "Settlement {} changed while applying a callback: {}",
+                        settlement.id,
+                        reason
+                    );
+                }
+                result => {
+                    result?;
+                    break;
+                }
+            }
+        }
+
+        self.find_by_anchor_tx(&anchor_tx.id).await
+    }
+
+    async fn find_by_anchor_tx(&self, anchor_tx_id: &str) -> Result<Settlement, ApiError> {
+        let client = self.db_pool.get().await?;
+        let row = client
+            .query_opt(
+                &format!(
+                    "SELECT {} FROM settlements WHERE anchor_tx_id = $1",
+                    SETTLEMENT_COLUMNS
+                ),
+                &[&anchor_tx_id],
+            )
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Settlement not found".to_string()))?;
+
+        settlement_from_row(&row)
+    }
+
+    async fn refresh(&self, settlement: &Settlement) -> Result<(), ApiError> {
+        let Some(anchor_tx_id) = settlement.anchor_tx_id.as_deref() else {
+            return Ok(());
+        };
+
+        let token = self.anchor.authenticate_platform().await?;
+        let anchor_tx = self
+            .anchor
+            .get_sep31_transaction(&token, anchor_tx_id)
+            .await?;
+
+        self.apply(settlement, &anchor_tx).await
+    }
+
+    /// Record what the anchor reports about a payout and move the settlement along
+    async fn apply(
+        &self,
+        settlement: &Settlement,
+        anchor_tx: &Sep31Transaction,
+    ) -> Result<(), ApiError> {
+        let settlement_id = parse_id(&settlement.id)?;
+
+        let client = self.db_pool.get().await?;
+        client
+            .execute(
+                r#"
+                UPDATE settlements
+                SET anchor_status = $1,
+                    anchor_message = COALESCE($2, anchor_message),
+                    stellar_account_id = COALESCE($3, stellar_account_id),
+                    stellar_memo = COALESCE($4, stellar_memo),
+                    stellar_memo_type = COALESCE($5, stellar_memo_type),
+                    updated_at = NOW()
+                WHERE id = $6
+                "#,
+                &[
+                    &anchor_tx.status,
+                    &anchor_tx.required_info_message,
+                    &anchor_tx.stellar_account_id,
+                    &anchor_tx.stellar_memo,
+                    &anchor_tx.stellar_memo_type,
+                    &settlement_id,
+                ],
+            )
+            .await?;
+        drop(client);
+
+        let reason = format!("Anchor reported {}", anchor_tx.status);
+        match AnchorProgress::from_sep31_status(&anchor_tx.status) {
+            AnchorProgress::Waiting => {}
+            AnchorProgress::AwaitingPayment => {
+                self.pay_anchor(settlement_id, anchor_tx).await?;
+            }
+            AnchorProgress::Processing => {
+                if settlement.status == SettlementStatus::Pending {
+                    self.transition(settlement_id, SettlementStatus::Processing, &reason)
+                        .await?;
+                }
+            }
+            AnchorProgress::Completed => {
+                self.complete_settlement(settlement, settlement_id, anchor_tx, &reason)
+                    .await?;
+            }
+            AnchorProgress::Failed => {
+                let reason = match &anchor_tx.required_info_message {
+                    Some(message) => format!("{}: {}", reason, message),
+                    None => reason,
+                };
+                self.fail_after_anchor(settlement_id, anchor_tx, &reason)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Pay the anchor once, and mark the settlement processing when the payment lands
+    ///
+    /// The signed payment is committed with its hash before it is submitted,
+    /// so a crash or failed submission is followed up by resubmitting the
+    /// same payment rather than signing a second one.
+    async fn pay_anchor(
+        &self,
+        settlement_id: Uuid,
+        anchor_tx: &Sep31Transaction,
+    ) -> Result<(), ApiError> {
+        let Some(envelope) = self.signed_payment(settlement_id, anchor_tx).await? else {
+            return Ok(());
+        };
+
+        match self.soroban.reconcile(&envelope).await? {
+            Submission::Pending => {}
+            Submission::Confirmed => {
+                self.transition(
+                    settlement_id,
+                    SettlementStatus::Processing,
+                    "Stellar payment to anchor confirmed",
+                )
+                .await?;
+            }
+            Submission::Failed => {
+                self.fail_settlement(settlement_id, "Stellar payment to anchor failed", None)
+                    .await?;
+            }
+            Submission::Expired => {
+                // It can no longer land, so the next poll signs a new one
+                let client = self.db_pool.get().await?;
+                client
+                    .execute(
+                        "UPDATE settlements SET tx_hash = NULL, tx_envelope = NULL, updated_at = NOW() WHERE id = $1 AND tx_envelope = $2",
+                        &[&settlement_id, &envelope],
+                    )
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The settlement's signed payment to the anchor, signed and recorded first if there is none
+    ///
+    /// Returns `None` once the settlement has moved on from `pending`.
+    async fn signed_payment(
+        &self,
+        settlement_id: Uuid,
+        anchor_tx: &Sep31Transaction,
+    ) -> Result<Option<String>, ApiError> {
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+
+        // Locked so a poll and a callback arriving together sign one payment
+        let row = tx
+            .query_one(
+                "SELECT status, tx_envelope, amount, asset, stellar_account_id, stellar_memo, stellar_memo_type FROM settlements WHERE id = $1 FOR UPDATE",
+                &[&settlement_id],
+            )
+            .await?;
+        let status: SettlementStatus =
+            lifecycle::parse_status(LifecycleEntity::Settlement, row.get(0))?;
+        if status != SettlementStatus::Pending {
+            return Ok(None);
+        }
+        if let Some(envelope) = row.get::<_, Option<String>>(1) {
+            return Ok(Some(envelope));
+        }
+        let amount: i64 = row.get(2);
+        let settlement_asset: String = row.get(3);
+        let destination: Option<String> = row.get(4);
+        let memo_value: Option<String> = row.get(5);
+        let memo_type: Option<String> = row.get(6);
+
+        let destination = destination.ok_or_else(|| {
+            ApiError::Anchor(format!(
+                "SEP-31 transaction {} has no stellar_account_id",
+                anchor_tx.id
+            ))
+        })?;
+        let memo = match &memo_value {
+            Some(memo) => transaction::memo(memo, memo_type.as_deref().unwrap_or("text"))?,
+            None => Memo::None,
+        };
+        let stellar_asset =
+            asset::resolve_asset(&settlement_asset, &self.config.stellar_network.assets)?;
+
+        let keypair = self.anchor.platform_keypair()?;
+        let signed = self
+            .soroban
+            .sign_payment(
+                &keypair,
+                &keypair.address(),
+                &destination,
+                stellar_asset,
+                amount,
+                memo,
+            )
+            .await?;
+        tx.execute(
+            "UPDATE settlements SET tx_hash = $1, tx_envelope = $2, updated_at = NOW() WHERE id = $3",
+            &[&signed.tx_hash, &signed.envelope_xdr, &settlement_id],
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(Some(signed.envelope_xdr))
+    }
+
+    /// Fail a settlement the anchor gave up on
+    ///
+    /// The merchant is credited in full only if our payment never reached the
+    /// anchor. Once it has, only what the anchor is seen to have paid back
+    /// on-chain is credited. While the payment may still land, nothing is
+    /// decided and the next poll looks again.
+    async fn fail_after_anchor(
+        &self,
+        settlement_id: Uuid,
+        anchor_tx: &Sep31Transaction,
+        reason: &str,
+    ) -> Result<(), ApiError> {
+        let client = self.db_pool.get().await?;
+        let row = client
+            .query_one(
+                "SELECT tx_envelope, amount, asset FROM settlements WHERE id = $1",
+                &[&settlement_id],
+            )
+            .await?;
+        drop(client);
+        let envelope: Option<String> = row.get(0);
+        let amount: i64 = row.get(1);
+        let settlement_asset: String = row.get(2);
+
+        let submission = match &envelope {
+            Some(envelope) => self.soroban.submission_status(envelope).await?,
+            None => Submission::Failed,
+        };
+        match submission {
+            Submission::Pending => {
+                tracing::info!(
+                    "Settlement {} failed at the anchor while its payment may still land",
+                    settlement_id
+                );
+                Ok(())
+            }
+            Submission::Failed | Submission::Expired => {
+                self.fail_settlement(settlement_id, reason, None).await
+            }
+            Submission::Confirmed => {
+                let platform = self.anchor.platform_keypair()?.address();
+                let stellar_asset =
+                    asset::resolve_asset(&settlement_asset, &self.config.stellar_network.assets)?;
+                let mut returned = 0;
+                if let Some(refunds) = &anchor_tx.refunds {
+                    for tx_hash in refunds.stellar_payment_ids() {
+                        returned += self
+                            .soroban
+                            .confirmed_payment_amount(tx_hash, &platform, &stellar_asset)
+                            .await?;
+                    }
+                }
+                self.fail_settlement(settlement_id, reason, Some(returned.min(amount)))
+                    .await
+            }
+        }
+    }
+
+    /// Mark the settlement completed with the amount the receiver got and tell the merchant
+    async fn complete_settlement(
+        &self,
+        settlement: &Settlement,
+        settlement_id: Uuid,
+        anchor_tx: &Sep31Transaction,
+        reason: &str,
+    ) -> Result<(), ApiError> {
+        let amount_out = anchor_tx
+            .amount_out
+            .as_deref()
+            .map(asset::parse_stroops)
+            .transpose()?;
+
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+
+        if settlement.status == SettlementStatus::Pending {
+            lifecycle::transition(
+                &tx,
+                LifecycleEntity::Settlement,
+                settlement_id,
+                SettlementStatus::Processing,
+                reason,
+                None,
+            )
+            .await?;
+        }
+        lifecycle::transition(
+            &tx,
+            LifecycleEntity::Settlement,
+            settlement_id,
+            SettlementStatus::Completed,
+            reason,
+            None,
+        )
+        .await?;
+        tx.execute(
+            "UPDATE settlements SET amount_out = COALESCE($1, amount_out) WHERE id = $2",
+            &[&amount_out, &settlement_id],
+        )
+        .await?;
+
+        let row = tx
+            .query_one(
+                &format!(
+                    "SELECT {} FROM settlements WHERE id = $1",
+                    SETTLEMENT_COLUMNS
+                ),
+                &[&settlement_id],
+            )
+            .await?;
+        webhook_service::enqueue_event(
+            &tx,
+            &settlement.merchant_id,
+            WebhookEventType::SettlementPaid,
+            serde_json::to_value(crate::http::settlements::settlement_response(
+                settlement_from_row(&row)?,
+            ))?,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        tracing::info!(
+            "Settlement {} of {} {} for merchant {} completed",
+            settlement_id,
+            asset::format_stroops(settlement.amount),
+            settlement.asset,
+            settlement.merchant_id
+        );
+
+        Ok(())
+    }
+
+    async fn merchant_settlement_asset(&self, merchant_id: &str) -> Result<String, ApiError> {
+        let client = self.db_pool.get().await?;
+        let row = client
+            .query_opt(
+                "SELECT settlement_asset FROM merchants WHERE merchant_id = $1 AND active = true",
+                &[&merchant_id],
+            )
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Merchant not found or inactive".to_string()))?;
+
+        Ok(row.get(0))
+    }
+
+    /// Debit the merchant and insert the pending settlement in one transaction
+    async fn reserve_settlement(
+        &self,
+        request: &CreateSettlementRequest,
+        settlement_asset: &str,
+    ) -> Result<Uuid, ApiError> {
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+
+        let debited = tx
+            .execute(
+                "UPDATE balances SET amount = amount - $3, last_updated = NOW() WHERE owner_id = $1 AND asset = $2 AND amount >= $3",
+                &[&request.merchant_id, &settlement_asset, &request.amount],
+            )
+            .await?;
+        if debited == 0 {
+            return Err(ApiError::Validation("Insufficient balance".to_string()));
+        }
+
+        let settlement_id = Uuid::new_v4();
+        tx.execute(
+            r#"
+            INSERT INTO settlements (id, merchant_id, amount, asset, status, sender_id, receiver_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            &[
+                &settlement_id,
+                &request.merchant_id,
+                &request.amount,
+                &settlement_asset,
+                &SettlementStatus::Pending.to_string(),
+                &request.sender_id,
+                &request.receiver_id,
+            ],
+        )
+        .await?;
+        lifecycle::record_created(
+            &tx,
+            LifecycleEntity::Settlement,
+            settlement_id,
+            SettlementStatus::Pending,
+            None,
+        )
+        .await?;
+
+        tx.commit().await?;
+        Ok(settlement_id)
+    }
+
+    async fn transition(
+        &self,
+        settlement_id: Uuid,
+        status: SettlementStatus,
+        reason: &str,
+    ) -> Result<(), ApiError> {
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+        lifecycle::transition(
+            &tx,
+            LifecycleEntity::Settlement,
+            settlement_id,
+            status,
+            reason,
+            None,
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Mark the settlement failed and return `returned` stroops to the merchant, or all of it
+    async fn fail_settlement(
+        &self,
+        settlement_id: Uuid,
+        reason: &str,
+        returned: Option<i64>,
+    ) -> Result<(), ApiError> {
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+
+        lifecycle::transition(
+            &tx,
+            LifecycleEntity::Settlement,
+            settlement_id,
+            SettlementStatus::Failed,
+            reason,
+            None,
+        )
+        .await?;
+        let (merchant_id, amount, settlement_asset) =
+            Self::settlement_owner(&tx, settlement_id).await?;
+        let returned = returned.unwrap_or(amount);
+        if returned > 0 {
+            tx.execute(
+                "UPDATE balances SET amount = amount + $3, last_updated = NOW() WHERE owner_id = $1 AND asset = $2",
+                &[&merchant_id, &settlement_asset, &returned],
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        tracing::warn!(
+            "Settlement {} of {} {} for merchant {} failed, {} returned: {}",
+            settlement_id,
+            asset::format_stroops(amount),
+            settlement_asset,
+            merchant_id,
+            asset::format_stroops(returned),
+            reason
+        );
+
+        Ok(())
+    }
+
+    async fn settlement_owner(
+        tx: &Transaction<'_>,
+        settlement_id: Uuid,
+    ) -> Result<(String, i64, String), ApiError> {
+        let row = tx
+            .query_one(
+                "SELECT merchant_id, amount, asset FROM settlements WHERE id = $1",
+                &[&settlement_id],
+            )
+            .await?;
+        Ok((row.get(0), row.get(1), row.get(2)))
+    }
+}
+
+/// Check a settlement against what the anchor requires for its asset
+///
+/// Every problem is reported at once so the caller can fix the request in
+/// one go.
+fn check_requirements(
+    info: &Sep31AssetInfo,
+    request: &CreateSettlementRequest,
+) -> Result<(), ApiError> {
+    let mut problems = Vec::new();
+
+    let amount = request.amount as f64 / 10_000_000.0;
+    if info.min_amount.is_some_and(|min| amount < min) {
+        problems.push(format!(
+            "amount is below the anchor's minimum of {}",
+            info.min_amount.unwrap_or_default()
+        ));
+    }
+    if info.max_amount.is_some_and(|max| amount > max) {
+        problems.push(format!(
+            "amount is above the anchor's maximum of {}",
+            info.max_amount.unwrap_or_default()
+        ));
+    }
+
+    if !info.sep12.sender.types.is_empty() && request.sender_id.is_none() {
+        problems.push("sender_id is required".to_string());
+    }
+    if !info.sep12.receiver.types.is_empty() && request.receiver_id.is_none() {
+        problems.push("receiver_id is required".to_string());
+    }
+
+    let mut names: Vec<&String> = info.fields.transaction.keys().collect();
+    names.sort();
+    for name in names {
+        let field = &info.fields.transaction[name];
+        match request.fields.get(name) {
+            None if !field.optional => problems.push(format!("field {} is required", name)),
+            Some(value) => {
+                if let Some(choices) = &field.choices {
+                    if !choices.contains(value) {
+                        problems.push(format!(
+                            "field {} must be one of {}",
+                            name,
+                            choices.join(", ")
+                        ));
+                    }
+                }
+            }
+            None => {}
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::Validation(format!(
+            "Settlement does not meet the anchor's requirements: {}",
+            problems.join("; ")
+        )))
+    }
+}
+
+fn parse_id(id: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(id).map_err(|_| {
+        tracing::error!("Invalid settlement id in database: {}", id);
+        ApiError::InternalServerError
+    })
+}
+
+fn settlement_from_row(row: &tokio_postgres::Row) -> Result<Settlement, ApiError> {
+    Ok(Settlement {
+        id: row.get::<_, Uuid>(0).to_string(),
+        tx_hash: row.get(1),
+        merchant_id: row.get(2),
+        amount: row.get(3),
+        asset: row.get(4),
+        amount_out: row.get(5),
+        status: lifecycle::parse_status(LifecycleEntity::Settlement, row.get(6))?,
+        sender_id: row.get(7),
+        receiver_id: row.get(8),
+        anchor_tx_id: row.get(9),
+        anchor_status: row.get(10),
+        anchor_message: row.get(11),
+        stellar_account_id: row.get(12),
+        stellar_memo: row.get(13),
+        stellar_memo_type: row.get(14),
+        created_at: row.get::<_, chrono::DateTime<chrono::Utc>>(15),
+        updated_at: row.get::<_, chrono::DateTime<chrono::Utc>>(16),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::anchor_service::{
+        Sep31CustomerTypes, Sep31Customers, Sep31Field, Sep31Fields,
+    };
+
+    fn asset_info() -> Sep31AssetInfo {
+        Sep31AssetInfo {
+            enabled: true,
+            min_amount: Some(10.0),
+            max_amount: Some(1000.0),
+            sep12: Sep31Customers {
+                sender: Sep31CustomerTypes {
+                    types: HashMap::from([("sep31-sender".to_string(), serde_json::json!({}))]),
+                },
+                receiver: Sep31CustomerTypes {
+                    types: HashMap::from([("sep31-receiver".to_string(), serde_json::json!({}))]),
+                },
+            },
+            fields: Sep31Fields {
+                transaction: HashMap::from([
+                    ("receiver_account_number".to_string(), Sep31Field::default()),
+                    (
+                        "type".to_string(),
+                        Sep31Field {
+                            choices: Some(vec!["SEPA".to_string(), "SWIFT".to_string()]),
+                            ..Default::default()
+                        },
+                    ),
+                    (
+                        "receiver_routing_number".to_string(),
+                        Sep31Field {
+                            optional: true,
+                            ..Default::default()
+                        },
+                    ),
+                ]),
+            },
+            ..Default::default()
+        }
+    }
+
+    fn request(amount: i64, fields: &[(&str, &str)]) -> CreateSettlementRequest {
+        CreateSettlementRequest {
+            merchant_id: "merchant_abc".to_string(),
+            amount,
+            sender_id: Some("sender-1".to_string()),
+            receiver_id: Some("receiver-1".to_string()),
+            fields: fields
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_accepts_complete_request() {
+        let request = request(
+            500_000_000,
+            &[("receiver_account_number", "123"), ("type", "SEPA")],
+        );
+        assert!(check_requirements(&asset_info(), &request).is_ok());
+    }
+
+    #[test]
+    fn test_reports_every_missing_requirement() {
+        let mut request = request(50_000_000, &[("type", "ACH")]);
+        request.receiver_id = None;
+
+        let Err(ApiError::Validation(message)) = check_requirements(&asset_info(), &request) else {
+            panic!("expected a validation error");
+        };
+        assert!(message.contains("below the anchor's minimum"));
+        assert!(message.contains("receiver_id is required"));
+        assert!(message.contains("field receiver_account_number is required"));
+        assert!(message.contains("field type must be one of SEPA, SWIFT"));
+        assert!(!message.contains("receiver_routing_number"));
+        assert!(!message.contains("sender_id"));
+    }
+}
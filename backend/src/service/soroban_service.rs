@@ -4,34 +4,52 @@ use crate::{
     api_error::ApiError,
     config::Config,
     models::{BuildTransactionDto, SignedTransactionResponse, TransactionStatus},
+    stellar::{
+        asset,
+        contract_error::{self, ContractError, ZapsContract},
+        rpc::{GetTransactionResponse, SorobanRpcClient},
+        scval, strkey, transaction, Keypair,
+    },
 };
-use std::sync::Arc;
+use soroban_sdk::xdr::{
+    AccountId, Asset, DiagnosticEvent, LedgerEntryData, LedgerKey, LedgerKeyAccount, Limits, Memo,
+    PublicKey, ReadXdr, ScVal, TransactionEnvelope, Uint256, WriteXdr,
+};
+use std::{sync::Arc, time::Duration};
 
-// Mocking Stellar SDK types for now as we don't have the full crate docs loaded
-// In a real scenario, these would be imports from stellar-sdk
-pub struct StellarClient {
-    pub network_passphrase: String,
-    pub rpc_url: String,
-}
+/// How long a built transaction stays valid, in seconds
+const TX_TIMEOUT_SECS: u64 = 300;
 
-impl StellarClient {
-    pub fn new(network_passphrase: String, rpc_url: String) -> Self {
-        Self {
-            network_passphrase,
-            rpc_url,
-        }
-    }
+/// Delay between `getTransaction` polls while a submission is pending
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
 
-    pub async fn submit_transaction(&self, _tx_envelope: &str) -> Result<String, String> {
-        // Mock submission
-        Ok("mock_tx_hash".to_string())
-    }
+/// How long past its time bound a missing transaction is still looked for, in seconds
+const EXPIRY_MARGIN_SECS: u64 = 60;
+
+/// A signed transaction, known by its hash before it is submitted
+#[derive(Debug, Clone)]
+pub struct SignedEnvelope {
+    pub tx_hash: String,
+    pub envelope_xdr: String,
+}
+
+/// Where a signed transaction that may have been submitted stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Submission {
+    /// Included in a ledger and successful
+    Confirmed,
+    /// Included in a ledger and failed, so none of its operations took effect
+    Failed,
+    /// Not included yet, and still could be
+    Pending,
+    /// Not included and past its time bound, so it never will be
+    Expired,
 }
 
 #[derive(Clone)]
 pub struct SorobanService {
     config: Config,
-    client: Arc<StellarClient>,
+    rpc: Arc<SorobanRpcClient>,
 }
 
 #[async_trait]
@@ -45,66 +63,483 @@ pub trait Signer {
 }
 
 pub struct CustodialSigner {
-    pub secret_key: String,
+    keypair: Keypair,
+    network_passphrase: String,
 }
 
 impl CustodialSigner {
-    pub fn new(secret_key: String) -> Self {
-        Self { secret_key }
+    pub fn new(secret_key: &str, network_passphrase: String) -> Result<Self, ApiError> {
+        Ok(Self {
+            keypair: Keypair::from_secret_seed(secret_key)?,
+            network_passphrase,
+        })
+    }
+
+    pub fn address(&self) -> String {
+        self.keypair.address()
     }
 }
 
 #[async_trait]
 impl Signer for CustodialSigner {
     async fn sign_transaction(&self, tx_xdr: &str) -> Result<String, ApiError> {
-        // Mock signing logic
-        // In reality: Parse XDR, sign with key, return new XDR
-        Ok(format!("{}_signed_by_custodial", tx_xdr))
+        let mut envelope = transaction::decode_envelope(tx_xdr)?;
+        transaction::sign_envelope(&mut envelope, &self.keypair, &self.network_passphrase)?;
+        transaction::encode_envelope(&envelope)
     }
 }
 
 impl SorobanService {
     pub fn new(config: Config) -> Self {
-        let client = Arc::new(StellarClient::new(
-            config.stellar_network.passphrase.clone(),
+        let rpc = Arc::new(SorobanRpcClient::new(
             config.stellar_network.rpc_url.clone(),
         ));
-        Self { config, client }
+        Self { config, rpc }
     }
 
     pub fn get_network_config(&self) -> &crate::config::StellarNetwork {
         &self.config.stellar_network
     }
 
+    pub fn rpc(&self) -> &SorobanRpcClient {
+        &self.rpc
+    }
+
+    /// Current sequence number of a `G...` account, read from the ledger
+    pub async fn get_account_sequence(&self, address: &str) -> Result<i64, ApiError> {
+        let account_id = strkey::decode_account_id(address)
+            .map_err(|e| ApiError::Validation(format!("Invalid account address: {}", e)))?;
+        let key = LedgerKey::Account(LedgerKeyAccount {
+            account_id: AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(account_id))),
+        })
+        .to_xdr_base64(Limits::none())
+        .map_err(|e| ApiError::Stellar(format!("Failed to encode ledger key: {}", e)))?;
+
+        let response = self.rpc.get_ledger_entries(&[key]).await?;
+        let entry = response.entries.first().ok_or_else(|| {
+            ApiError::Stellar(format!("Account {} does not exist on the network", address))
+        })?;
+
+        match LedgerEntryData::from_xdr_base64(&entry.xdr, Limits::none()) {
+            Ok(LedgerEntryData::Account(account)) => Ok(account.seq_num.0),
+            Ok(_) => Err(ApiError::Stellar(
+                "getLedgerEntries returned a non-account entry".to_string(),
+            )),
+            Err(e) => Err(ApiError::Stellar(format!("Invalid account entry: {}", e))),
+        }
+    }
+
+    /// Sign and submit a classic payment from `source`, returning its transaction hash
+    ///
+    /// The payment is not awaited; use `wait_for_transaction` for its outcome.
+    pub async fn send_payment(
+        &self,
+        keypair: &Keypair,
+        source: &str,
+        destination: &str,
+        asset: Asset,
+        amount: i64,
+        memo: Memo,
+    ) -> Result<String, ApiError> {
+        let signed = self
+            .sign_payment(keypair, source, destination, asset, amount, memo)
+            .await?;
+
+        let response = self.submit_transaction(signed.envelope_xdr).await?;
+        Ok(response.tx_hash)
+    }
+
+    /// Build and sign a classic payment from `source` without submitting it
+    pub async fn sign_payment(
+        &self,
+        keypair: &Keypair,
+        source: &str,
+        destination: &str,
+        asset: Asset,
+        amount: i64,
+        memo: Memo,
+    ) -> Result<SignedEnvelope, ApiError> {
+        let sequence = self.get_account_sequence(source).await?;
+        let envelope = transaction::build_payment(
+            source,
+            sequence,
+            destination,
+            asset,
+            amount,
+            memo,
+            transaction::BASE_FEE,
+            chrono::Utc::now().timestamp() as u64 + TX_TIMEOUT_SECS,
+        )?;
+        self.sign(envelope, keypair)
+    }
+
+    /// Simulate and sign a contract call as `keypair`'s account without submitting it
+    pub async fn sign_contract_call(
+        &self,
+        keypair: &Keypair,
+        contract_id: &str,
+        method: &str,
+        args: Vec<ScVal>,
+    ) -> Result<SignedEnvelope, ApiError> {
+        let source = keypair.address();
+        let sequence = self.get_account_sequence(&source).await?;
+        let envelope = transaction::build_invoke_contract(
+            &source,
+            sequence,
+            contract_id,
+            method,
+            args,
+            transaction::BASE_FEE,
+            chrono::Utc::now().timestamp() as u64 + TX_TIMEOUT_SECS,
+        )?;
+
+        let prepared = self
+            .prepare_transaction(&transaction::encode_envelope(&envelope)?)
+            .await?;
+        self.sign(transaction::decode_envelope(&prepared)?, keypair)
+    }
+
+    fn sign(
+        &self,
+        mut envelope: TransactionEnvelope,
+        keypair: &Keypair,
+    ) -> Result<SignedEnvelope, ApiError> {
+        let passphrase = &self.config.stellar_network.passphrase;
+        transaction::sign_envelope(&mut envelope, keypair, passphrase)?;
+
+        Ok(SignedEnvelope {
+            tx_hash: transaction::envelope_hash_hex(&envelope, passphrase)?,
+            envelope_xdr: transaction::encode_envelope(&envelope)?,
+        })
+    }
+
+    /// Find out what became of a signed transaction, submitting it while it can still land
+    ///
+    /// Submitting the same envelope again is harmless: it keeps its hash and
+    /// sequence number, so it is applied at most once.
+    pub async fn reconcile(&self, envelope_xdr: &str) -> Result<Submission, ApiError> {
+        let submission = self.submission_status(envelope_xdr).await?;
+        if submission == Submission::Pending {
+            self.submit_transaction(envelope_xdr.to_string()).await?;
+        }
+
+        Ok(submission)
+    }
+
+    /// What became of a signed transaction, without submitting it
+    pub async fn submission_status(&self, envelope_xdr: &str) -> Result<Submission, ApiError> {
+        let envelope = transaction::decode_envelope(envelope_xdr)?;
+        let tx_hash =
+            transaction::envelope_hash_hex(&envelope, &self.config.stellar_network.passphrase)?;
+
+        Ok(match self.get_transaction_status(&tx_hash).await? {
+            TransactionStatus::CONFIRMED => Submission::Confirmed,
+            TransactionStatus::FAILED => Submission::Failed,
+            TransactionStatus::PENDING => {
+                let now = chrono::Utc::now().timestamp() as u64;
+                match transaction::max_time(&envelope)? {
+                    Some(max_time) if now > max_time + EXPIRY_MARGIN_SECS => Submission::Expired,
+                    _ => Submission::Pending,
+                }
+            }
+        })
+    }
+
+    /// Stroops of `asset` a successful transaction paid to `destination`
+    ///
+    /// Zero when the transaction is unknown, pending or failed.
+    pub async fn confirmed_payment_amount(
+        &self,
+        tx_hash: &str,
+        destination: &str,
+        asset: &Asset,
+    ) -> Result<i64, ApiError> {
+        let response = self.rpc.get_transaction(tx_hash).await?;
+        if transaction_status(&response) != TransactionStatus::CONFIRMED {
+            return Ok(0);
+        }
+
+        let envelope = response
+            .envelope_xdr
+            .as_deref()
+            .ok_or_else(|| ApiError::Stellar(format!("Transaction {} has no envelope", tx_hash)))?;
+        transaction::paid_to(&transaction::decode_envelope(envelope)?, destination, asset)
+    }
+
+    /// Whether merchant changes are mirrored to the zaps-registry contract
+    pub fn registry_enabled(&self) -> bool {
+        !self.config.contracts.zaps_registry.is_empty()
+    }
+
+    /// Sign a registration of a merchant's vault and settlement asset with the zaps-registry contract
+    pub async fn sign_merchant_registration(
+        &self,
+        merchant_id: &str,
+        vault_address: &str,
+        settlement_asset: &Asset,
+    ) -> Result<SignedEnvelope, ApiError> {
+        let asset_contract =
+            asset::contract_address(settlement_asset, &self.config.stellar_network.passphrase)?;
+        let args = vec![
+            scval::bytes(merchant_id.as_bytes())?,
+            ScVal::Address(scval::sc_address(vault_address)?),
+            ScVal::Address(scval::sc_address(&asset_contract)?),
+        ];
+
+        self.sign_contract_call(
+            &self.registry_admin_keypair()?,
+            &self.config.contracts.zaps_registry,
+            "register_merchant",
+            args,
+        )
+        .await
+    }
+
+    /// Sign a deactivation of a merchant in the zaps-registry contract
+    pub async fn sign_merchant_deactivation(
+        &self,
+        merchant_id: &str,
+    ) -> Result<SignedEnvelope, ApiError> {
+        self.sign_contract_call(
+            &self.registry_admin_keypair()?,
+            &self.config.contracts.zaps_registry,
+            "deactivate_merchant",
+            vec![scval::bytes(merchant_id.as_bytes())?],
+        )
+        .await
+    }
+
+    /// Whether refunds are debited from the merchant-vault contract
+    pub fn vault_enabled(&self) -> bool {
+        !self.config.contracts.merchant_vault.is_empty()
+    }
+
+    /// Sign a debit of `amount` from a merchant's merchant-vault balance, keyed by its vault address
+    ///
+    /// The debit is simulated, so a vault that would refuse it is reported as
+    /// a contract error here, but it is not submitted.
+    pub async fn sign_vault_debit(
+        &self,
+        vault_address: &str,
+        amount: i64,
+    ) -> Result<SignedEnvelope, ApiError> {
+        let secret = self
+            .config
+            .contracts
+            .vault_payout_secret
+            .as_deref()
+            .filter(|secret| !secret.is_empty())
+            .ok_or_else(|| {
+                tracing::error!("contracts.vault_payout_secret is not configured");
+                ApiError::InternalServerError
+            })?;
+
+        self.sign_contract_call(
+            &Keypair::from_secret_seed(secret)?,
+            &self.config.contracts.merchant_vault,
+            "debit",
+            vec![
+                ScVal::Address(scval::sc_address(vault_address)?),
+                scval::i128_to_scval(amount as i128),
+            ],
+        )
+        .await
+    }
+
+    fn registry_admin_keypair(&self) -> Result<Keypair, ApiError> {
+        let secret = self
+            .config
+            .contracts
+            .registry_admin_secret
+            .as_deref()
+            .filter(|secret| !secret.is_empty())
+            .ok_or_else(|| {
+                tracing::error!("contracts.registry_admin_secret is not configured");
+                ApiError::InternalServerError
+            })?;
+
+        Keypair::from_secret_seed(secret)
+    }
+
+    /// Simulate an unsigned transaction and apply its footprint, resource fee and auth entries
+    pub async fn prepare_transaction(&self, unsigned_tx_xdr: &str) -> Result<String, ApiError> {
+        let mut envelope = transaction::decode_envelope(unsigned_tx_xdr)?;
+        let simulation = self.rpc.simulate_transaction(unsigned_tx_xdr).await?;
+
+        if let Some(error) = &simulation.error {
+            return Err(self.normalize_error(
+                transaction::invoked_contract(&envelope).as_deref(),
+                format!("Simulation failed: {}", error),
+                &contract_error::decode_diagnostic_events(&simulation.events),
+            ));
+        }
+
+        transaction::apply_simulation(&mut envelope, &simulation)?;
+        transaction::encode_envelope(&envelope)
+    }
+
+    /// Submit a signed transaction without waiting for it to be included in a ledger
     pub async fn submit_transaction(
         &self,
         signed_tx_xdr: String,
     ) -> Result<SignedTransactionResponse, ApiError> {
-        match self.client.submit_transaction(&signed_tx_xdr).await {
-            Ok(hash) => Ok(SignedTransactionResponse {
-                tx_hash: hash,
+        let response = self.rpc.send_transaction(&signed_tx_xdr).await?;
+
+        match response.status.as_str() {
+            "PENDING" | "DUPLICATE" => Ok(SignedTransactionResponse {
+                tx_hash: response.hash,
                 status: TransactionStatus::PENDING,
             }),
-            Err(e) => Err(self.normalize_error(e)),
+            "TRY_AGAIN_LATER" => Err(ApiError::Stellar(
+                "Transaction submission rejected, try again later".to_string(),
+            )),
+            _ => {
+                let invoked = transaction::decode_envelope(&signed_tx_xdr)
+                    .ok()
+                    .and_then(|envelope| transaction::invoked_contract(&envelope));
+                Err(self.normalize_error(
+                    invoked.as_deref(),
+                    format!(
+                        "Transaction {} rejected: {}",
+                        response.hash,
+                        response.error_result_xdr.unwrap_or_default()
+                    ),
+                    &contract_error::decode_diagnostic_events(&response.diagnostic_events_xdr),
+                ))
+            }
+        }
+    }
+
+    pub async fn get_transaction_status(
+        &self,
+        tx_hash: &str,
+    ) -> Result<TransactionStatus, ApiError> {
+        let response = self.rpc.get_transaction(tx_hash).await?;
+        Ok(transaction_status(&response))
+    }
+
+    /// Poll `getTransaction` until the transaction succeeds, fails or its time bound passes
+    pub async fn wait_for_transaction(
+        &self,
+        tx_hash: &str,
+    ) -> Result<SignedTransactionResponse, ApiError> {
+        let status = match self.poll_transaction(tx_hash).await? {
+            Some(response) => transaction_status(&response),
+            None => TransactionStatus::PENDING,
+        };
+
+        Ok(SignedTransactionResponse {
+            tx_hash: tx_hash.to_string(),
+            status,
+        })
+    }
+
+    /// Submit a signed transaction and wait for its final status
+    ///
+    /// A transaction that fails on-chain with a recognisable contract error is
+    /// reported as that error rather than as a `FAILED` status.
+    pub async fn submit_and_wait(
+        &self,
+        signed_tx_xdr: String,
+    ) -> Result<SignedTransactionResponse, ApiError> {
+        let submitted = self.submit_transaction(signed_tx_xdr.clone()).await?;
+        let Some(response) = self.poll_transaction(&submitted.tx_hash).await? else {
+            return Ok(submitted);
+        };
+
+        let status = transaction_status(&response);
+        if status == TransactionStatus::FAILED {
+            let events = response
+                .result_meta_xdr
+                .as_deref()
+                .map(contract_error::meta_diagnostic_events)
+                .unwrap_or_default();
+            if let Some((contract, code)) = contract_error::find_contract_error(&events) {
+                let invoked = transaction::decode_envelope(&signed_tx_xdr)
+                    .ok()
+                    .and_then(|envelope| transaction::invoked_contract(&envelope));
+                return Err(self.contract_error(contract.or(invoked).as_deref(), code));
+            }
+        }
+
+        Ok(SignedTransactionResponse {
+            tx_hash: submitted.tx_hash,
+            status,
+        })
+    }
+
+    /// Returns the final `getTransaction` response, or `None` if still pending at the timeout
+    async fn poll_transaction(
+        &self,
+        tx_hash: &str,
+    ) -> Result<Option<GetTransactionResponse>, ApiError> {
+        let attempts = TX_TIMEOUT_SECS / POLL_INTERVAL.as_secs().max(1);
+
+        for attempt in 0..attempts {
+            if attempt > 0 {
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+
+            let response = self.rpc.get_transaction(tx_hash).await?;
+            if transaction_status(&response) != TransactionStatus::PENDING {
+                return Ok(Some(response));
+            }
         }
+
+        Ok(None)
     }
 
-    fn normalize_error(&self, _: String) -> ApiError {
-        // Normalize Soroban/Stellar errors into ApiError
-        // This is a basic implementation
-        ApiError::InternalServerError
+    /// Normalize Soroban/Stellar errors into ApiError
+    ///
+    /// Contract errors are resolved against the contract that raised them when
+    /// diagnostic events identify it, and against the invoked contract otherwise.
+    fn normalize_error(
+        &self,
+        invoked_contract: Option<&str>,
+        message: String,
+        diagnostic_events: &[DiagnosticEvent],
+    ) -> ApiError {
+        if let Some((contract, code)) = contract_error::find_contract_error(diagnostic_events) {
+            return self.contract_error(contract.as_deref().or(invoked_contract), code);
+        }
+        if let Some(code) = contract_error::parse_contract_error_code(&message) {
+            return self.contract_error(invoked_contract, code);
+        }
+
+        ApiError::Stellar(message)
+    }
+
+    fn contract_error(&self, contract_address: Option<&str>, code: u32) -> ApiError {
+        let contract = contract_address
+            .and_then(|address| ZapsContract::from_address(&self.config.contracts, address));
+        ApiError::Contract(ContractError::new(contract, code))
+    }
+}
+
+fn transaction_status(response: &GetTransactionResponse) -> TransactionStatus {
+    match response.status.as_str() {
+        "SUCCESS" => TransactionStatus::CONFIRMED,
+        "FAILED" => TransactionStatus::FAILED,
+        _ => TransactionStatus::PENDING,
     }
 }
 
 #[async_trait]
 impl TransactionBuilder for SorobanService {
     async fn build_transaction(&self, dto: BuildTransactionDto) -> Result<String, ApiError> {
-        // Mock transaction building
-        // In reality: Use SDK to build InvokeHostFunctionOp
-        let tx_xdr = format!(
-            "mock_xdr_invoke_{}_{}_{:?}",
-            dto.contract_id, dto.method, dto.args
-        );
-        Ok(tx_xdr)
+        let args = scval::json_args_to_scvals(&dto.args)?;
+        let max_time = chrono::Utc::now().timestamp() as u64 + TX_TIMEOUT_SECS;
+
+        let envelope = transaction::build_invoke_contract(
+            &dto.source_account,
+            dto.sequence,
+            &dto.contract_id,
+            &dto.method,
+            args,
+            transaction::BASE_FEE,
+            max_time,
+        )?;
+
+        transaction::encode_envelope(&envelope)
     }
 }
@@ -0,0 +1,135 @@
+use crate::{
+    api_error::ApiError, config::Config, models::MerchantTerminal,
+    stellar::validate_account_address,
+};
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub(crate) const TERMINAL_COLUMNS: &str =
+    "id, merchant_id, public_key, label, active, created_at, revoked_at";
+
+/// Registry of the NFC terminals merchants accept tap payments with
+///
+/// Each terminal holds an ed25519 key and signs every tap payload it emits;
+/// `PaymentService::validate_nfc_payment` only accepts taps signed by an
+/// active terminal of the merchant being paid.
+#[derive(Clone)]
+#[allow(dead_code)]
+pub struct TerminalService {
+    db_pool: Arc<Pool>,
+    config: Config,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegisterTerminalRequest {
+    pub merchant_id: String,
+    /// The terminal's ed25519 public key, as a `G...` address
+    pub public_key: String,
+    pub label: Option<String>,
+}
+
+impl TerminalService {
+    pub fn new(db_pool: Arc<Pool>, config: Config) -> Self {
+        Self { db_pool, config }
+    }
+
+    pub async fn register_terminal(
+        &self,
+        request: RegisterTerminalRequest,
+    ) -> Result<MerchantTerminal, ApiError> {
+        validate_account_address("public_key", &request.public_key)?;
+
+        let client = self.db_pool.get().await?;
+        let merchant = client
+            .query_opt(
+                "SELECT 1 FROM merchants WHERE merchant_id = $1 AND active = true",
+                &[&request.merchant_id],
+            )
+            .await?;
+        if merchant.is_none() {
+            return Err(ApiError::NotFound(
+                "Merchant not found or inactive".to_string(),
+            ));
+        }
+
+        let row = client
+            .query_opt(
+                &format!(
+                    r#"
+                    INSERT INTO merchant_terminals (id, merchant_id, public_key, label)
+                    VALUES ($1, $2, $3, $4)
+                    ON CONFLICT (public_key) DO NOTHING
+                    RETURNING {}
+                    "#,
+                    TERMINAL_COLUMNS
+                ),
+                &[
+                    &Uuid::new_v4(),
+                    &request.merchant_id,
+                    &request.public_key,
+                    &request.label,
+                ],
+            )
+            .await?
+            .ok_or_else(|| {
+                ApiError::Conflict("A terminal with this key is already registered".to_string())
+            })?;
+
+        Ok(terminal_from_row(&row))
+    }
+
+    /// The merchant's terminals, revoked ones included, newest first
+    pub async fn list_terminals(
+        &self,
+        merchant_id: &str,
+    ) -> Result<Vec<MerchantTerminal>, ApiError> {
+        let client = self.db_pool.get().await?;
+        let rows = client
+            .query(
+                &format!(
+                    "SELECT {} FROM merchant_terminals WHERE merchant_id = $1 ORDER BY created_at DESC",
+                    TERMINAL_COLUMNS
+                ),
+                &[&merchant_id],
+            )
+            .await?;
+
+        Ok(rows.iter().map(terminal_from_row).collect())
+    }
+
+    /// Stop accepting the terminal's taps; revoking twice is a no-op
+    pub async fn revoke_terminal(&self, terminal_id: Uuid) -> Result<MerchantTerminal, ApiError> {
+        let client = self.db_pool.get().await?;
+        let row = client
+            .query_opt(
+                &format!(
+                    r#"
+                    UPDATE merchant_terminals
+                    SET active = false, revoked_at = COALESCE(revoked_at, NOW())
+                    WHERE id = $1
+                    RETURNING {}
+                    "#,
+                    TERMINAL_COLUMNS
+                ),
+                &[&terminal_id],
+            )
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Terminal not found".to_string()))?;
+
+        Ok(terminal_from_row(&row))
+    }
+}
+
+pub(crate) fn terminal_from_row(row: &tokio_postgres::Row) -> MerchantTerminal {
+    MerchantTerminal {
+        id: row.get::<_, Uuid>(0).to_string(),
+        merchant_id: row.get(1),
+        public_key: row.get(2),
+        label: row.get(3),
+        active: row.get(4),
+        created_at: row.get::<_, chrono::DateTime<chrono::Utc>>(5),
+        revoked_at: row.get::<_, Option<chrono::DateTime<chrono::Utc>>>(6),
+    }
+}
@@ -0,0 +1,426 @@
+use crate::{
+    api_error::ApiError,
+    config::Config,
+    models::{StatusEvent, Transfer, TransferStatus},
+    service::{
+        lifecycle::{self, LifecycleEntity},
+        soroban_service::Submission,
+        ComplianceService, IdentityService, NotificationService, SorobanService,
+    },
+    stellar::{asset, transaction},
+};
+use deadpool_postgres::{Pool, Transaction};
+use serde::{Deserialize, Serialize};
+use soroban_sdk::xdr::Memo;
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct TransferService {
+    db_pool: Arc<Pool>,
+    config: Config,
+    identity: IdentityService,
+    compliance: ComplianceService,
+    soroban: SorobanService,
+    notification: NotificationService,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateTransferRequest {
+    pub to_user_id: String,
+    /// Amount in stroops (1e-7 of the asset)
+    pub amount: i64,
+    /// `XLM`, a configured asset code such as `USDC`, or `CODE:ISSUER`
+    pub asset: String,
+    pub memo: Option<String>,
+}
+
+impl TransferService {
+    pub fn new(
+        db_pool: Arc<Pool>,
+        config: Config,
+        identity: IdentityService,
+        compliance: ComplianceService,
+        soroban: SorobanService,
+        notification: NotificationService,
+    ) -> Self {
+        Self {
+            db_pool,
+            config,
+            identity,
+            compliance,
+            soroban,
+            notification,
+        }
+    }
+
+    /// Send `request.amount` from the sender's wallet to the recipient's
+    ///
+    /// The amount is reserved from the sender's balance and the signed Stellar
+    /// payment recorded before it is submitted. The transfer is returned as
+    /// `processing`; `poll_processing` credits the recipient once the payment
+    /// confirms, or returns the amount to the sender if it fails or expires.
+    pub async fn create_transfer(
+        &self,
+        from_user_id: &str,
+        mut request: CreateTransferRequest,
+    ) -> Result<Transfer, ApiError> {
+        if request.amount <= 0 {
+            return Err(ApiError::Validation(
+                "Amount must be greater than zero".to_string(),
+            ));
+        }
+        if request.to_user_id == from_user_id {
+            return Err(ApiError::Validation(
+                "Cannot transfer to yourself".to_string(),
+            ));
+        }
+        if request.memo.as_ref().is_some_and(|memo| memo.len() > 28) {
+            return Err(ApiError::Validation(
+                "Memo must be at most 28 bytes".to_string(),
+            ));
+        }
+
+        let sender = self.identity.get_user_wallet(from_user_id).await?;
+        let recipient = self
+            .identity
+            .get_user_wallet(&request.to_user_id)
+            .await
+            .map_err(|e| match e {
+                ApiError::NotFound(_) => ApiError::NotFound("Recipient not found".to_string()),
+                other => other,
+            })?;
+
+        for user_id in [from_user_id, request.to_user_id.as_str()] {
+            if self.compliance.check_sanctions(user_id).await? {
+                return Err(ApiError::Compliance(
+                    "Transfer blocked by sanctions screening".to_string(),
+                ));
+            }
+        }
+        if !self
+            .compliance
+            .check_velocity_limits(from_user_id, request.amount)
+            .await?
+        {
+            return Err(ApiError::Compliance(
+                "Transfer exceeds velocity limits".to_string(),
+            ));
+        }
+
+        let stellar_asset =
+            asset::resolve_asset(&request.asset, &self.config.stellar_network.assets)?;
+        request.asset = asset::asset_key(&stellar_asset, &self.config.stellar_network.assets);
+
+        let transfer_id = self.reserve_transfer(from_user_id, &request).await?;
+
+        let signed = async {
+            let keypair = self.identity.get_user_keypair(from_user_id).await?;
+            let memo = match &request.memo {
+                Some(memo) => transaction::memo(memo, "text")?,
+                None => Memo::None,
+            };
+            self.soroban
+                .sign_payment(
+                    &keypair,
+                    &sender.address,
+                    &recipient.address,
+                    stellar_asset,
+                    request.amount,
+                    memo,
+                )
+                .await
+        }
+        .await;
+
+        let signed = match signed {
+            Ok(signed) => signed,
+            Err(e) => {
+                self.fail_transfer(transfer_id, &format!("Signing failed: {}", e))
+                    .await?;
+                return Err(e);
+            }
+        };
+
+        // Recorded before it is sent, so the status worker can follow it up whatever happens next
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+        tx.execute(
+            "UPDATE transfers SET tx_envelope = $1 WHERE id = $2",
+            &[&signed.envelope_xdr, &transfer_id],
+        )
+        .await?;
+        lifecycle::transition(
+            &tx,
+            LifecycleEntity::Transfer,
+            transfer_id,
+            TransferStatus::Processing,
+            "Stellar payment signed",
+            Some(&signed.tx_hash),
+        )
+        .await?;
+        tx.commit().await?;
+        drop(client);
+
+        if let Err(e) = self.soroban.submit_transaction(signed.envelope_xdr).await {
+            tracing::warn!(
+                "Could not submit transfer {} ({}), the status worker will retry: {}",
+                transfer_id,
+                signed.tx_hash,
+                e
+            );
+        }
+
+        self.get_transfer(transfer_id).await
+    }
+
+    pub async fn get_transfer(&self, transfer_id: Uuid) -> Result<Transfer, ApiError> {
+        let client = self.db_pool.get().await?;
+
+        let row = client
+            .query_opt(
+                r#"
+                SELECT id, tx_hash, from_user_id, to_user_id, amount, asset,
+                       status, memo, created_at, updated_at
+                FROM transfers WHERE id = $1
+                "#,
+                &[&transfer_id],
+            )
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Transfer not found".to_string()))?;
+
+        Ok(Transfer {
+            id: row.get::<_, Uuid>(0).to_string(),
+            tx_hash: row.get(1),
+            from_user_id: row.get(2),
+            to_user_id: row.get(3),
+            amount: row.get(4),
+            asset: row.get(5),
+            status: lifecycle::parse_status(LifecycleEntity::Transfer, row.get(6))?,
+            memo: row.get(7),
+            created_at: row.get::<_, chrono::DateTime<chrono::Utc>>(8),
+            updated_at: row.get::<_, chrono::DateTime<chrono::Utc>>(9),
+        })
+    }
+
+    pub async fn get_transfer_history(
+        &self,
+        transfer_id: Uuid,
+    ) -> Result<Vec<StatusEvent>, ApiError> {
+        let client = self.db_pool.get().await?;
+        lifecycle::history(&client, LifecycleEntity::Transfer, transfer_id).await
+    }
+
+    /// Resubmit processing transfers until their payment lands, then complete or fail them
+    ///
+    /// Returns the number of transfers looked at. A failure on one transfer
+    /// is logged and does not stop the others; it is retried on the next sweep.
+    pub async fn poll_processing(&self) -> Result<usize, ApiError> {
+        let client = self.db_pool.get().await?;
+        let rows = client
+            .query(
+                "SELECT id, tx_envelope FROM transfers WHERE status = 'processing' AND tx_envelope IS NOT NULL ORDER BY created_at",
+                &[],
+            )
+            .await?;
+        drop(client);
+
+        for row in &rows {
+            let transfer_id: Uuid = row.get(0);
+            let envelope: String = row.get(1);
+            if let Err(e) = self.reconcile(transfer_id, &envelope).await {
+                tracing::warn!("Failed to confirm transfer {}: {}", transfer_id, e);
+            }
+        }
+
+        Ok(rows.len())
+    }
+
+    async fn reconcile(&self, transfer_id: Uuid, envelope: &str) -> Result<(), ApiError> {
+        match self.soroban.reconcile(envelope).await? {
+            Submission::Pending => Ok(()),
+            Submission::Confirmed => self.complete_transfer(transfer_id).await,
+            Submission::Failed => {
+                self.fail_transfer(transfer_id, "Stellar payment failed")
+                    .await
+            }
+            Submission::Expired => {
+                self.fail_transfer(transfer_id, "Stellar payment expired before it landed")
+                    .await
+            }
+        }
+    }
+
+    /// Debit the sender and insert the pending transfer in one transaction
+    async fn reserve_transfer(
+        &self,
+        from_user_id: &str,
+        request: &CreateTransferRequest,
+    ) -> Result<Uuid, ApiError> {
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+
+        let debited = tx
+            .execute(
+                "UPDATE balances SET amount = amount - $3, last_updated = NOW() WHERE owner_id = $1 AND asset = $2 AND amount >= $3",
+                &[&from_user_id, &request.asset, &request.amount],
+            )
+            .await?;
+        if debited == 0 {
+            return Err(ApiError::Validation("Insufficient balance".to_string()));
+        }
+
+        let transfer_id = Uuid::new_v4();
+        tx.execute(
+            r#"
+            INSERT INTO transfers (id, from_user_id, to_user_id, amount, asset, status, memo)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            &[
+                &transfer_id,
+                &from_user_id,
+                &request.to_user_id,
+                &request.amount,
+                &request.asset,
+                &TransferStatus::Pending.to_string(),
+                &request.memo,
+            ],
+        )
+        .await?;
+        lifecycle::record_created(
+            &tx,
+            LifecycleEntity::Transfer,
+            transfer_id,
+            TransferStatus::Pending,
+            None,
+        )
+        .await?;
+
+        tx.commit().await?;
+        Ok(transfer_id)
+    }
+
+    /// Mark the transfer completed and credit the recipient
+    async fn complete_transfer(&self, transfer_id: Uuid) -> Result<(), ApiError> {
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+
+        lifecycle::transition(
+            &tx,
+            LifecycleEntity::Transfer,
+            transfer_id,
+            TransferStatus::Completed,
+            "Stellar payment confirmed",
+            None,
+        )
+        .await?;
+        let transfer = Self::transfer_parties(&tx, transfer_id).await?;
+        tx.execute(
+            r#"
+            INSERT INTO balances (owner_id, asset, amount)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (owner_id, asset)
+            DO UPDATE SET amount = balances.amount + EXCLUDED.amount, last_updated = NOW()
+            "#,
+            &[&transfer.to_user_id, &transfer.asset, &transfer.amount],
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        self.notification
+            .notify_best_effort(
+                &transfer.from_user_id,
+                "Transfer sent",
+                format!(
+                    "You sent {} {} to {}",
+                    asset::format_stroops(transfer.amount),
+                    transfer.asset,
+                    transfer.to_user_id
+                ),
+                serde_json::json!({ "transfer_id": transfer_id.to_string() }),
+            )
+            .await;
+        self.notification
+            .notify_best_effort(
+                &transfer.to_user_id,
+                "Transfer received",
+                format!(
+                    "You received {} {} from {}",
+                    asset::format_stroops(transfer.amount),
+                    transfer.asset,
+                    transfer.from_user_id
+                ),
+                serde_json::json!({ "transfer_id": transfer_id.to_string() }),
+            )
+            .await;
+
+        Ok(())
+    }
+
+    /// Mark the transfer failed and return the reserved amount to the sender
+    async fn fail_transfer(&self, transfer_id: Uuid, reason: &str) -> Result<(), ApiError> {
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+
+        lifecycle::transition(
+            &tx,
+            LifecycleEntity::Transfer,
+            transfer_id,
+            TransferStatus::Failed,
+            reason,
+            None,
+        )
+        .await?;
+        let transfer = Self::transfer_parties(&tx, transfer_id).await?;
+        tx.execute(
+            "UPDATE balances SET amount = amount + $3, last_updated = NOW() WHERE owner_id = $1 AND asset = $2",
+            &[&transfer.from_user_id, &transfer.asset, &transfer.amount],
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        self.notification
+            .notify_best_effort(
+                &transfer.from_user_id,
+                "Transfer failed",
+                format!(
+                    "Your transfer of {} {} to {} failed and the funds were returned",
+                    asset::format_stroops(transfer.amount),
+                    transfer.asset,
+                    transfer.to_user_id
+                ),
+                serde_json::json!({ "transfer_id": transfer_id.to_string() }),
+            )
+            .await;
+
+        Ok(())
+    }
+
+    async fn transfer_parties(
+        tx: &Transaction<'_>,
+        transfer_id: Uuid,
+    ) -> Result<TransferParties, ApiError> {
+        let row = tx
+            .query_one(
+                "SELECT from_user_id, to_user_id, amount, asset FROM transfers WHERE id = $1",
+                &[&transfer_id],
+            )
+            .await?;
+
+        Ok(TransferParties {
+            from_user_id: row.get(0),
+            to_user_id: row.get(1),
+            amount: row.get(2),
+            asset: row.get(3),
+        })
+    }
+}
+
+struct TransferParties {
+    from_user_id: String,
+    to_user_id: String,
+    amount: i64,
+    asset: String,
+}
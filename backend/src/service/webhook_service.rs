@@ -0,0 +1,788 @@
+use crate::{
+    api_error::ApiError,
+    config::{Config, EnvironmentType},
+    models::{WebhookDelivery, WebhookDeliveryStatus, WebhookEndpoint, WebhookEventType},
+    stellar::custody,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use deadpool_postgres::{Pool, Transaction};
+use ring::{
+    hmac,
+    rand::{SecureRandom, SystemRandom},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::task::JoinSet;
+use uuid::Uuid;
+
+const ENDPOINT_COLUMNS: &str = "id, merchant_id, url, event_types, active, created_at, disabled_at";
+
+const DELIVERY_COLUMNS: &str = "id, endpoint_id, event_id, event_type, payload, status, attempts, next_attempt_at, last_attempt_at, response_status, last_error, delivered_at, created_at";
+
+/// Start of every signing secret
+const SECRET_PREFIX: &str = "whsec_";
+
+/// Random bytes in a signing secret
+const SECRET_BYTES: usize = 32;
+
+/// Deliveries attempted per sweep
+const DELIVERY_BATCH_SIZE: i64 = 20;
+
+/// Deliveries kept in an endpoint's log listing
+const DELIVERY_LOG_LIMIT: i64 = 100;
+
+pub const SIGNATURE_HEADER: &str = "X-Zaps-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Zaps-Timestamp";
+pub const EVENT_HEADER: &str = "X-Zaps-Event";
+pub const DELIVERY_HEADER: &str = "X-Zaps-Delivery";
+
+/// Signed event notifications to merchant endpoints
+///
+/// Events are queued with [`enqueue_event`] inside the transaction that
+/// makes the change they report, so a committed change always has its
+/// deliveries and a rolled back one never does. `deliver_pending` then sends
+/// them, retrying failures with exponential backoff up to
+/// `webhooks.max_attempts` times.
+///
+/// Each request carries `X-Zaps-Timestamp` and `X-Zaps-Signature: v1=<hex>`,
+/// an HMAC-SHA256 under the endpoint's secret of `{timestamp}.{body}`.
+#[derive(Clone)]
+pub struct WebhookService {
+    db_pool: Arc<Pool>,
+    config: Config,
+    http: reqwest::Client,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateWebhookEndpointRequest {
+    pub url: String,
+    pub event_types: Vec<WebhookEventType>,
+}
+
+/// A newly created endpoint, the only time its signing secret is returned
+#[derive(Debug, Serialize)]
+pub struct CreatedWebhookEndpoint {
+    #[serde(flatten)]
+    pub endpoint: WebhookEndpoint,
+    pub secret: String,
+}
+
+/// A delivery claimed by a sweep, with what is needed to send it
+struct DueDelivery {
+    id: Uuid,
+    event_type: String,
+    payload: serde_json::Value,
+    attempts: i32,
+    url: String,
+    secret_encrypted: String,
+    endpoint_active: bool,
+}
+
+/// Outcome of one attempt at a delivery
+struct Attempt {
+    response_status: Option<i32>,
+    error: Option<String>,
+}
+
+impl WebhookService {
+    pub fn new(db_pool: Arc<Pool>, config: Config) -> Self {
+        let http = http_client(&config).build().unwrap_or_default();
+
+        Self {
+            db_pool,
+            config,
+            http,
+        }
+    }
+
+    pub async fn create_endpoint(
+        &self,
+        merchant_id: &str,
+        request: CreateWebhookEndpointRequest,
+    ) -> Result<CreatedWebhookEndpoint, ApiError> {
+        validate_url(
+            &request.url,
+            matches!(self.config.environment, EnvironmentType::Development),
+        )?;
+        if !self.config.webhooks.allow_private_networks {
+            resolve_public(&request.url)
+                .await
+                .map_err(ApiError::Validation)?;
+        }
+        let mut event_types = request.event_types;
+        event_types.sort_by_key(|event_type| event_type.to_string());
+        event_types.dedup();
+        if event_types.is_empty() {
+            return Err(ApiError::Validation(
+                "At least one event type is required".to_string(),
+            ));
+        }
+
+        let mut secret_bytes = [0u8; SECRET_BYTES];
+        SystemRandom::new()
+            .fill(&mut secret_bytes)
+            .map_err(|_| ApiError::InternalServerError)?;
+        let secret = format!("{}{}", SECRET_PREFIX, URL_SAFE_NO_PAD.encode(secret_bytes));
+        let secret_encrypted = custody::seal_secret(&self.config.custody.encryption_key, &secret)?;
+
+        let client = self.db_pool.get().await?;
+        let row = client
+            .query_one(
+                &format!(
+                    r#"
+                    INSERT INTO webhook_endpoints (id, merchant_id, url, secret_encrypted, event_types)
+                    VALUES ($1, $2, $3, $4, $5)
+                    RETURNING {}
+                    "#,
+                    ENDPOINT_COLUMNS
+                ),
+                &[
+                    &Uuid::new_v4(),
+                    &merchant_id,
+                    &request.url,
+                    &secret_encrypted,
+                    &event_types
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>(),
+                ],
+            )
+            .await?;
+
+        Ok(CreatedWebhookEndpoint {
+            endpoint: endpoint_from_row(&row)?,
+            secret,
+        })
+    }
+
+    /// The merchant's endpoints, disabled ones included, newest first
+    pub async fn list_endpoints(
+        &self,
+        merchant_id: &str,
+    ) -> Result<Vec<WebhookEndpoint>, ApiError> {
+        let client = self.db_pool.get().await?;
+        let rows = client
+            .query(
+                &format!(
+                    "SELECT {} FROM webhook_endpoints WHERE merchant_id = $1 ORDER BY created_at DESC",
+                    ENDPOINT_COLUMNS
+                ),
+                &[&merchant_id],
+            )
+            .await?;
+
+        rows.iter().map(endpoint_from_row).collect()
+    }
+
+    /// Stop sending to an endpoint; its pending deliveries are given up on
+    pub async fn disable_endpoint(
+        &self,
+        merchant_id: &str,
+        endpoint_id: Uuid,
+    ) -> Result<WebhookEndpoint, ApiError> {
+        let client = self.db_pool.get().await?;
+        let row = client
+            .query_opt(
+                &format!(
+                    r#"
+                    UPDATE webhook_endpoints
+                    SET active = false, disabled_at = COALESCE(disabled_at, NOW())
+                    WHERE id = $1 AND merchant_id = $2
+                    RETURNING {}
+                    "#,
+                    ENDPOINT_COLUMNS
+                ),
+                &[&endpoint_id, &merchant_id],
+            )
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Webhook endpoint not found".to_string()))?;
+
+        endpoint_from_row(&row)
+    }
+
+    /// The endpoint's most recent deliveries, newest first
+    pub async fn list_deliveries(
+        &self,
+        merchant_id: &str,
+        endpoint_id: Uuid,
+    ) -> Result<Vec<WebhookDelivery>, ApiError> {
+        let client = self.db_pool.get().await?;
+        self.find_endpoint(&client, merchant_id, endpoint_id)
+            .await?;
+
+        let rows = client
+            .query(
+                &format!(
+                    "SELECT {} FROM webhook_deliveries WHERE endpoint_id = $1 ORDER BY created_at DESC, id LIMIT $2",
+                    DELIVERY_COLUMNS
+                ),
+                &[&endpoint_id, &DELIVERY_LOG_LIMIT],
+            )
+            .await?;
+
+        rows.iter().map(delivery_from_row).collect()
+    }
+
+    /// Queue a fresh delivery of an earlier delivery's event, sent on the next sweep
+    ///
+    /// The original delivery and its log are left as they are; the new one
+    /// carries the same event id so receivers can deduplicate.
+    pub async fn redeliver(
+        &self,
+        merchant_id: &str,
+        endpoint_id: Uuid,
+        delivery_id: Uuid,
+    ) -> Result<WebhookDelivery, ApiError> {
+        let client = self.db_pool.get().await?;
+        let endpoint = self
+            .find_endpoint(&client, merchant_id, endpoint_id)
+            .await?;
+        if !endpoint.active {
+            return Err(ApiError::Conflict(
+                "Webhook endpoint is disabled".to_string(),
+            ));
+        }
+
+        let row = client
+            .query_opt(
+                &format!(
+                    r#"
+                    INSERT INTO webhook_deliveries (id, endpoint_id, event_id, event_type, payload)
+                    SELECT $1, endpoint_id, event_id, event_type, payload
+                    FROM webhook_deliveries WHERE id = $2 AND endpoint_id = $3
+                    RETURNING {}
+                    "#,
+                    DELIVERY_COLUMNS
+                ),
+                &[&Uuid::new_v4(), &delivery_id, &endpoint_id],
+            )
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Webhook delivery not found".to_string()))?;
+
+        delivery_from_row(&row)
+    }
+
+    /// Attempt every due delivery, returning how many were attempted
+    ///
+    /// Deliveries are claimed with `SKIP LOCKED` and pushed back by twice the
+    /// request timeout before being sent, so concurrent sweeps never send the
+    /// same delivery and one interrupted mid-send is retried later.
+    pub async fn deliver_pending(&self) -> Result<usize, ApiError> {
+        let lease_secs = (self.config.webhooks.timeout_secs.max(1) * 2) as f64;
+
+        let client = self.db_pool.get().await?;
+        let rows = client
+            .query(
+                r#"
+                WITH due AS (
+                    SELECT id FROM webhook_deliveries
+                    WHERE status = 'pending' AND next_attempt_at <= NOW()
+                    ORDER BY next_attempt_at
+                    LIMIT $1
+                    FOR UPDATE SKIP LOCKED
+                )
+                UPDATE webhook_deliveries d
+                SET next_attempt_at = NOW() + make_interval(secs => $2)
+                FROM due, webhook_endpoints e
+                WHERE d.id = due.id AND e.id = d.endpoint_id
+                RETURNING d.id, d.event_type, d.payload, d.attempts, e.url, e.secret_encrypted, e.active
+                "#,
+                &[&DELIVERY_BATCH_SIZE, &lease_secs],
+            )
+            .await?;
+        drop(client);
+
+        let mut sends = JoinSet::new();
+        for row in &rows {
+            let due = DueDelivery {
+                id: row.get(0),
+                event_type: row.get(1),
+                payload: row.get(2),
+                attempts: row.get(3),
+                url: row.get(4),
+                secret_encrypted: row.get(5),
+                endpoint_active: row.get(6),
+            };
+            let service = self.clone();
+            sends.spawn(async move {
+                let id = due.id;
+                if let Err(e) = service.deliver(due).await {
+                    tracing::warn!("Failed to record webhook delivery {}: {}", id, e);
+                }
+            });
+        }
+        while sends.join_next().await.is_some() {}
+
+        Ok(rows.len())
+    }
+
+    async fn deliver(&self, due: DueDelivery) -> Result<(), ApiError> {
+        let client = self.db_pool.get().await?;
+
+        if !due.endpoint_active {
+            client
+                .execute(
+                    "UPDATE webhook_deliveries SET status = $1, next_attempt_at = NULL, last_error = $2 WHERE id = $3",
+                    &[
+                        &WebhookDeliveryStatus::Failed.to_string(),
+                        &"Endpoint disabled",
+                        &due.id,
+                    ],
+                )
+                .await?;
+            return Ok(());
+        }
+
+        let attempt = self.send(&due).await;
+        let attempts = due.attempts + 1;
+
+        if attempt.error.is_none() {
+            client
+                .execute(
+                    r#"
+                    UPDATE webhook_deliveries
+                    SET status = $1, attempts = $2, last_attempt_at = NOW(), response_status = $3,
+                        last_error = NULL, delivered_at = NOW(), next_attempt_at = NULL
+                    WHERE id = $4
+                    "#,
+                    &[
+                        &WebhookDeliveryStatus::Delivered.to_string(),
+                        &attempts,
+                        &attempt.response_status,
+                        &due.id,
+                    ],
+                )
+                .await?;
+            return Ok(());
+        }
+
+        let webhooks = &self.config.webhooks;
+        let (status, retry_secs) = if attempts >= webhooks.max_attempts {
+            tracing::warn!(
+                "Webhook delivery {} to {} failed after {} attempts: {}",
+                due.id,
+                due.url,
+                attempts,
+                attempt.error.as_deref().unwrap_or_default()
+            );
+            (WebhookDeliveryStatus::Failed, None)
+        } else {
+            let delay =
+                retry_delay_secs(attempts, webhooks.retry_base_secs, webhooks.retry_max_secs);
+            (WebhookDeliveryStatus::Pending, Some(delay as f64))
+        };
+
+        client
+            .execute(
+                r#"
+                UPDATE webhook_deliveries
+                SET status = $1, attempts = $2, last_attempt_at = NOW(), response_status = $3, last_error = $4,
+                    next_attempt_at = NOW() + make_interval(secs => $5)
+                WHERE id = $6
+                "#,
+                &[
+                    &status.to_string(),
+                    &attempts,
+                    &attempt.response_status,
+                    &attempt.error,
+                    &retry_secs,
+                    &due.id,
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Sign and POST a delivery; any 2xx answer counts as delivered
+    async fn send(&self, due: &DueDelivery) -> Attempt {
+        let failed = |error: String| Attempt {
+            response_status: None,
+            error: Some(error),
+        };
+
+        let secret = match custody::open_secret(
+            &self.config.custody.encryption_key,
+            &due.secret_encrypted,
+        ) {
+            Ok(secret) => secret,
+            Err(_) => return failed("Signing secret could not be decrypted".to_string()),
+        };
+        let body = match serde_json::to_vec(&due.payload) {
+            Ok(body) => body,
+            Err(e) => return failed(format!("Payload could not be encoded: {}", e)),
+        };
+        let http = match self.delivery_client(&due.url).await {
+            Ok(http) => http,
+            Err(e) => return failed(e),
+        };
+        let timestamp = chrono::Utc::now().timestamp();
+
+        let response = http
+            .post(&due.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(SIGNATURE_HEADER, sign_payload(&secret, timestamp, &body))
+            .header(EVENT_HEADER, &due.event_type)
+            .header(DELIVERY_HEADER, due.id.to_string())
+            .body(body)
+            .send()
+            .await;
+
+        match response {
+            Ok(response) => {
+                let status = response.status();
+                Attempt {
+                    response_status: Some(i32::from(status.as_u16())),
+                    error: (!status.is_success()).then(|| format!("Endpoint answered {}", status)),
+                }
+            }
+            Err(e) => failed(format!("Request failed: {}", e)),
+        }
+    }
+
+    /// Client to deliver to `url` with, connecting only to its checked addresses
+    ///
+    /// Unless private networks are allowed, the host is resolved again before
+    /// every delivery and must still resolve to public addresses only. The
+    /// client is pinned to those addresses, so a different DNS answer at
+    /// connect time cannot redirect the delivery.
+    async fn delivery_client(&self, url: &str) -> Result<reqwest::Client, String> {
+        if self.config.webhooks.allow_private_networks {
+            return Ok(self.http.clone());
+        }
+
+        let addrs = resolve_public(url).await?;
+        let host = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_default();
+        if host.parse::<IpAddr>().is_ok() || host.starts_with('[') {
+            return Ok(self.http.clone());
+        }
+        http_client(&self.config)
+            .resolve_to_addrs(&host, &addrs)
+            .build()
+            .map_err(|e| format!("Client could not be built: {}", e))
+    }
+
+    async fn find_endpoint(
+        &self,
+        client: &deadpool_postgres::Client,
+        merchant_id: &str,
+        endpoint_id: Uuid,
+    ) -> Result<WebhookEndpoint, ApiError> {
+        let row = client
+            .query_opt(
+                &format!(
+                    "SELECT {} FROM webhook_endpoints WHERE id = $1 AND merchant_id = $2",
+                    ENDPOINT_COLUMNS
+                ),
+                &[&endpoint_id, &merchant_id],
+            )
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Webhook endpoint not found".to_string()))?;
+
+        endpoint_from_row(&row)
+    }
+}
+
+/// Queue `event_type` for every active endpoint of the merchant subscribed to it
+///
+/// Runs inside the caller's transaction. `data` is the object the event is
+/// about, e.g. the payment, sent as the body's `data` field.
+pub async fn enqueue_event(
+    tx: &Transaction<'_>,
+    merchant_id: &str,
+    event_type: WebhookEventType,
+    data: serde_json::Value,
+) -> Result<(), ApiError> {
+    let event_id = Uuid::new_v4();
+    let payload = serde_json::json!({
+        "id": event_id,
+        "type": event_type,
+        "created_at": chrono::Utc::now(),
+        "data": data,
+    });
+
+    tx.execute(
+        r#"
+        INSERT INTO webhook_deliveries (id, endpoint_id, event_id, event_type, payload)
+        SELECT gen_random_uuid(), id, $2, $3::text, $4
+        FROM webhook_endpoints
+        WHERE merchant_id = $1 AND active = true AND $3::text = ANY(event_types)
+        "#,
+        &[&merchant_id, &event_id, &event_type.to_string(), &payload],
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// `X-Zaps-Signature` value for a body sent at `timestamp`
+pub fn sign_payload(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let mut signed = format!("{}.", timestamp).into_bytes();
+    signed.extend_from_slice(body);
+
+    let tag = hmac::sign(&key, &signed);
+    let hex: String = tag.as_ref().iter().map(|b| format!("{:02x}", b)).collect();
+    format!("v1={}", hex)
+}
+
+/// Wait before the attempt after `attempts` failed ones: `base`, doubling each time, capped at `max`
+pub(crate) fn retry_delay_secs(attempts: i32, base: i64, max: i64) -> i64 {
+    let doublings = attempts.saturating_sub(1).clamp(0, 32) as u32;
+    base.max(1)
+        .saturating_mul(1i64 << doublings)
+        .min(max.max(1))
+}
+
+/// Endpoints must be absolute HTTPS URLs; plain HTTP is allowed in development
+fn validate_url(url: &str, allow_http: bool) -> Result<(), ApiError> {
+    let parsed = reqwest::Url::parse(url)
+        .map_err(|_| ApiError::Validation("url must be an absolute URL".to_string()))?;
+    match parsed.scheme() {
+        "https" if parsed.host().is_some() => Ok(()),
+        "http" if allow_http && parsed.host().is_some() => Ok(()),
+        _ => Err(ApiError::Validation("url must be an HTTPS URL".to_string())),
+    }
+}
+
+/// Addresses of the host of `url`, if every one of them is public
+///
+/// Endpoints on loopback, private, link-local and similar addresses would let
+/// merchants use deliveries to probe the platform's internal network.
+async fn resolve_public(url: &str) -> Result<Vec<SocketAddr>, String> {
+    let parsed = reqwest::Url::parse(url).map_err(|_| "url must be an absolute URL".to_string())?;
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| "url must have a host".to_string())?;
+    let port = parsed.port_or_known_default().unwrap_or(443);
+
+    let literal = host.trim_start_matches('[').trim_end_matches(']');
+    let addrs: Vec<SocketAddr> = if let Ok(ip) = literal.parse::<IpAddr>() {
+        vec![SocketAddr::new(ip, port)]
+    } else {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        if host == "localhost" || host.ends_with(".localhost") {
+            return Err("url must not point at a private or loopback address".to_string());
+        }
+        let resolved = tokio::net::lookup_host((host.as_str(), port))
+            .await
+            .map_err(|e| format!("url host could not be resolved: {}", e))?;
+        resolved.collect()
+    };
+
+    if addrs.is_empty() {
+        return Err("url host could not be resolved".to_string());
+    }
+    if addrs.iter().any(|addr| !is_public_ip(addr.ip())) {
+        return Err("url must not point at a private or loopback address".to_string());
+    }
+    Ok(addrs)
+}
+
+/// Whether `ip` is reachable on the public internet
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                // 0.0.0.0/8, shared address space 100.64.0.0/10 and 198.18.0.0/15 benchmarking
+                || a == 0
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 198 && (18..20).contains(&b)))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(v4));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local fc00::/7 and link-local fe80::/10
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+fn http_client(config: &Config) -> reqwest::ClientBuilder {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.webhooks.timeout_secs.max(1)))
+        .redirect(reqwest::redirect::Policy::none())
+}
+
+fn endpoint_from_row(row: &tokio_postgres::Row) -> Result<WebhookEndpoint, ApiError> {
+    let event_types = row
+        .get::<_, Vec<String>>(3)
+        .iter()
+        .map(|event_type| parse_event_type(event_type))
+        .collect::<Result<_, _>>()?;
+
+    Ok(WebhookEndpoint {
+        id: row.get::<_, Uuid>(0).to_string(),
+        merchant_id: row.get(1),
+        url: row.get(2),
+        event_types,
+        active: row.get(4),
+        created_at: row.get::<_, chrono::DateTime<chrono::Utc>>(5),
+        disabled_at: row.get::<_, Option<chrono::DateTime<chrono::Utc>>>(6),
+    })
+}
+
+fn delivery_from_row(row: &tokio_postgres::Row) -> Result<WebhookDelivery, ApiError> {
+    let status = row.get::<_, String>(5).parse().map_err(|e| {
+        tracing::error!("{} in database", e);
+        ApiError::InternalServerError
+    })?;
+
+    Ok(WebhookDelivery {
+        id: row.get::<_, Uuid>(0).to_string(),
+        endpoint_id: row.get::<_, Uuid>(1).to_string(),
+        event_id: row.get::<_, Uuid>(2).to_string(),
+        event_type: parse_event_type(row.get(3))?,
+        payload: row.get(4),
+        status,
+        attempts: row.get(6),
+        next_attempt_at: row.get::<_, Option<chrono::DateTime<chrono::Utc>>>(7),
+        last_attempt_at: row.get::<_, Option<chrono::DateTime<chrono::Utc>>>(8),
+        response_status: row.get(9),
+        last_error: row.get(10),
+        delivered_at: row.get::<_, Option<chrono::DateTime<chrono::Utc>>>(11),
+        created_at: row.get::<_, chrono::DateTime<chrono::Utc>>(12),
+    })
+}
+
+fn parse_event_type(value: &str) -> Result<WebhookEventType, ApiError> {
+    value.parse().map_err(|e| {
+        tracing::error!("{} in database", e);
+        ApiError::InternalServerError
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_payload() {
+        let signature = sign_payload("whsec_test", 1_700_000_000, br#"{"id":"1"}"#);
+        assert!(signature.starts_with("v1="));
+        assert_eq!(signature.len(), 3 + 64);
+
+        let key = hmac::Key::new(hmac::HMAC_SHA256, b"whsec_test");
+        let tag = hex_decode(&signature[3..]);
+        assert!(hmac::verify(&key, br#"1700000000.{"id":"1"}"#, &tag).is_ok());
+
+        assert_ne!(
+            signature,
+            sign_payload("whsec_test", 1_700_000_001, br#"{"id":"1"}"#)
+        );
+        assert_ne!(
+            signature,
+            sign_payload("whsec_other", 1_700_000_000, br#"{"id":"1"}"#)
+        );
+    }
+
+    fn hex_decode(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_up_to_the_cap() {
+        assert_eq!(retry_delay_secs(1, 30, 3600), 30);
+        assert_eq!(retry_delay_secs(2, 30, 3600), 60);
+        assert_eq!(retry_delay_secs(4, 30, 3600), 240);
+        assert_eq!(retry_delay_secs(8, 30, 3600), 3600);
+        assert_eq!(retry_delay_secs(1000, 30, 3600), 3600);
+    }
+
+    #[test]
+    fn test_validate_url() {
+        assert!(validate_url("https://merchant.example.com/hooks", false).is_ok());
+        assert!(validate_url("http://merchant.example.com/hooks", false).is_err());
+        assert!(validate_url("http://127.0.0.1:8080/hooks", true).is_ok());
+        assert!(validate_url("ftp://merchant.example.com", true).is_err());
+        assert!(validate_url("/hooks", true).is_err());
+    }
+
+    #[test]
+    fn test_public_ips() {
+        for ip in [
+            "93.184.216.34",
+            "8.8.8.8",
+            "2606:2800:220:1:248:1893:25c8:1946",
+        ] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:10.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_private_endpoints_are_rejected() {
+        for url in [
+            "https://127.0.0.1/hooks",
+            "https://10.0.0.5:8443/hooks",
+            "https://169.254.169.254/latest/meta-data",
+            "https://[::1]/hooks",
+            "https://localhost/hooks",
+            "https://api.localhost./hooks",
+        ] {
+            assert!(resolve_public(url).await.is_err(), "{}", url);
+        }
+        assert_eq!(
+            resolve_public("https://93.184.216.34/hooks").await,
+            Ok(vec!["93.184.216.34:443".parse().unwrap()])
+        );
+    }
+
+    #[test]
+    fn test_event_types_round_trip() {
+        for event_type in [
+            WebhookEventType::PaymentCompleted,
+            WebhookEventType::PaymentFailed,
+            WebhookEventType::RefundCreated,
+            WebhookEventType::SettlementPaid,
+        ] {
+            assert_eq!(
+                event_type.to_string().parse::<WebhookEventType>(),
+                Ok(event_type)
+            );
+            assert_eq!(
+                serde_json::to_value(event_type).unwrap(),
+                serde_json::json!(event_type.to_string())
+            );
+        }
+        assert!("payment.created".parse::<WebhookEventType>().is_err());
+    }
+}
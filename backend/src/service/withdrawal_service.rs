@@ -0,0 +1,769 @@
+use crate::{
+    api_error::ApiError,
+    config::Config,
+    models::{KycLevel, Lifecycle, StatusEvent, Withdrawal, WithdrawalStatus},
+    service::{
+        anchor_service::{AnchorProgress, AnchorTransaction, StartWithdrawalRequest},
+        lifecycle::{self, LifecycleEntity},
+        soroban_service::{SignedEnvelope, Submission},
+        AnchorService, ComplianceService, IdentityService, KycService, NotificationService,
+        SorobanService,
+    },
+    stellar::{asset, transaction, validate_account_address, Keypair},
+};
+use deadpool_postgres::{Pool, Transaction};
+use serde::{Deserialize, Serialize};
+use soroban_sdk::xdr::Memo;
+use std::sync::Arc;
+use uuid::Uuid;
+
+const WITHDRAWAL_COLUMNS: &str = "id, tx_hash, user_id, destination_address, amount, asset, status, anchor_tx_id, anchor_status, interactive_url, anchor_message, created_at, updated_at";
+
+#[derive(Clone)]
+pub struct WithdrawalService {
+    db_pool: Arc<Pool>,
+    config: Config,
+    identity: IdentityService,
+    compliance: ComplianceService,
+    kyc: KycService,
+    anchor: AnchorService,
+    soroban: SorobanService,
+    notification: NotificationService,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateWithdrawalRequest {
+    /// Amount in stroops (1e-7 of the asset)
+    pub amount: i64,
+    /// A configured asset code such as `USDC`, or `CODE:ISSUER`
+    pub asset: String,
+    /// Stellar account passed to the anchor as the destination; collected interactively when omitted
+    pub destination_address: Option<String>,
+    pub destination_extra: Option<String>,
+}
+
+impl WithdrawalService {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        db_pool: Arc<Pool>,
+        config: Config,
+        identity: IdentityService,
+        compliance: ComplianceService,
+        kyc: KycService,
+        anchor: AnchorService,
+        soroban: SorobanService,
+        notification: NotificationService,
+    ) -> Self {
+        Self {
+            db_pool,
+            config,
+            identity,
+            compliance,
+            kyc,
+            anchor,
+            soroban,
+            notification,
+        }
+    }
+
+    /// Reserve `request.amount` and start an interactive withdrawal with the anchor
+    ///
+    /// The withdrawal stays `pending` until the anchor asks for the Stellar
+    /// payment; `poll_pending` then sends it and follows the anchor to
+    /// completion. The reserved amount is returned if the anchor cannot be
+    /// reached or later fails the withdrawal.
+    pub async fn create_withdrawal(
+        &self,
+        user_id: &str,
+        mut request: CreateWithdrawalRequest,
+    ) -> Result<Withdrawal, ApiError> {
+        if request.amount <= 0 {
+            return Err(ApiError::Validation(
+                "Amount must be greater than zero".to_string(),
+            ));
+        }
+        let stellar_asset =
+            asset::resolve_asset(&request.asset, &self.config.stellar_network.assets)?;
+        request.asset = asset::asset_key(&stellar_asset, &self.config.stellar_network.assets);
+        if let Some(destination) = &request.destination_address {
+            validate_account_address("destination_address", destination)?;
+        }
+
+        let wallet = self.identity.get_user_wallet(user_id).await?;
+
+        if self.compliance.check_sanctions(user_id).await? {
+            return Err(ApiError::Compliance(
+                "Withdrawal blocked by sanctions screening".to_string(),
+            ));
+        }
+        if !self
+            .compliance
+            .check_velocity_limits(user_id, request.amount)
+            .await?
+        {
+            return Err(ApiError::Compliance(
+                "Withdrawal exceeds velocity limits".to_string(),
+            ));
+        }
+        self.kyc
+            .require_kyc(user_id, KycLevel::Full, "withdrawing")
+            .await?;
+
+        let keypair = self.identity.get_user_keypair(user_id).await?;
+        let token = self.anchor.authenticate(&keypair).await?;
+
+        let withdrawal_id = self.reserve_withdrawal(user_id, &request).await?;
+
+        let started = self
+            .anchor
+            .start_withdrawal(
+                &token,
+                &StartWithdrawalRequest {
+                    asset_code: asset::asset_code(&request.asset).to_string(),
+                    account: wallet.address,
+                    amount: Some(asset::format_stroops(request.amount)),
+                    dest: request.destination_address.clone(),
+                    dest_extra: request.destination_extra.clone(),
+                    on_change_callback: self.config.anchor_config.sep24_callback_url.clone(),
+                },
+            )
+            .await;
+
+        let interactive = match started {
+            Ok(interactive) => interactive,
+            Err(e) => {
+                self.fail_withdrawal(
+                    withdrawal_id,
+                    &format!("Anchor request failed: {}", e),
+                    None,
+                )
+                .await?;
+                return Err(e);
+            }
+        };
+
+        // Without the anchor's id nothing can follow the withdrawal up, so the reservation goes back
+        if let Err(e) = self
+            .record_anchor_tx(withdrawal_id, &interactive.id, &interactive.url)
+            .await
+        {
+            self.fail_withdrawal(
+                withdrawal_id,
+                &format!("Could not record anchor transaction: {}", e),
+                None,
+            )
+            .await?;
+            return Err(e);
+        }
+
+        self.get_withdrawal(withdrawal_id).await
+    }
+
+    async fn record_anchor_tx(
+        &self,
+        withdrawal_id: Uuid,
+        anchor_tx_id: &str,
+        interactive_url: &str,
+    ) -> Result<(), ApiError> {
+        let client = self.db_pool.get().await?;
+        client
+            .execute(
+                "UPDATE withdrawals SET anchor_tx_id = $1, interactive_url = $2, anchor_status = 'incomplete', updated_at = NOW() WHERE id = $3",
+                &[&anchor_tx_id, &interactive_url, &withdrawal_id],
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_withdrawal(&self, withdrawal_id: Uuid) -> Result<Withdrawal, ApiError> {
+        let client = self.db_pool.get().await?;
+
+        let row = client
+            .query_opt(
+                &format!(
+                    "SELECT {} FROM withdrawals WHERE id = $1",
+                    WITHDRAWAL_COLUMNS
+                ),
+                &[&withdrawal_id],
+            )
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Withdrawal not found".to_string()))?;
+
+        withdrawal_from_row(&row)
+    }
+
+    pub async fn get_withdrawal_history(
+        &self,
+        withdrawal_id: Uuid,
+    ) -> Result<Vec<StatusEvent>, ApiError> {
+        let client = self.db_pool.get().await?;
+        lifecycle::history(&client, LifecycleEntity::Withdrawal, withdrawal_id).await
+    }
+
+    /// Poll the anchor for every open withdrawal and apply what it reports
+    ///
+    /// Returns the number of withdrawals checked. A failure on one withdrawal
+    /// is logged and does not stop the others.
+    pub async fn poll_pending(&self) -> Result<usize, ApiError> {
+        let client = self.db_pool.get().await?;
+        let rows = client
+            .query(
+                &format!(
+                    "SELECT {} FROM withdrawals WHERE status IN ('pending', 'processing') AND anchor_tx_id IS NOT NULL ORDER BY created_at",
+                    WITHDRAWAL_COLUMNS
+                ),
+                &[],
+            )
+            .await?;
+        drop(client);
+
+        for row in &rows {
+            let withdrawal = withdrawal_from_row(row)?;
+            if let Err(e) = self.poll_withdrawal(&withdrawal).await {
+                tracing::warn!("Failed to update withdrawal {}: {}", withdrawal.id, e);
+            }
+        }
+
+        Ok(rows.len())
+    }
+
+    /// Apply a signed SEP-24 callback from the anchor about one of our withdrawals
+    ///
+    /// The transaction in the callback is applied as if a poll had returned
+    /// it. Callbacks for withdrawals that already finished are ignored, so a
+    /// late or repeated status never moves a withdrawal twice.
+    pub async fn handle_callback(
+        &self,
+        anchor_tx: &AnchorTransaction,
+    ) -> Result<Withdrawal, ApiError> {
+        // A poll racing the callback can move the withdrawal under us, which
+        // surfaces as a conflicting transition; apply again from its new state
+        for _ in 0..2 {
+            let withdrawal = self.find_by_anchor_tx(&anchor_tx.id).await?;
+            if withdrawal.status.is_terminal() {
+                return Ok(withdrawal);
+            }
+            match self.apply(&withdrawal, anchor_tx).await {
+                Err(ApiError::Conflict(reason)) => {
+                    tracing::info!(
+                        "Withdrawal {} changed while applying a callback: {}",
+                        withdrawal.id,
+                        reason
+                    );
+                }
+                result => {
+                    result?;
+                    break;
+                }
+            }
+        }
+
+        self.find_by_anchor_tx(&anchor_tx.id).await
+    }
+
+    async fn find_by_anchor_tx(&self, anchor_tx_id: &str) -> Result<Withdrawal, ApiError> {
+        let client = self.db_pool.get().await?;
+        let row = client
+            .query_opt(
+                &format!(
+                    "SELECT {} FROM withdrawals WHERE anchor_tx_id = $1",
+                    WITHDRAWAL_COLUMNS
+                ),
+                &[&anchor_tx_id],
+            )
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Withdrawal not found".to_string()))?;
+
+        withdrawal_from_row(&row)
+    }
+
+    async fn poll_withdrawal(&self, withdrawal: &Withdrawal) -> Result<(), ApiError> {
+        let Some(anchor_tx_id) = withdrawal.anchor_tx_id.as_deref() else {
+            return Ok(());
+        };
+
+        let keypair = self.identity.get_user_keypair(&withdrawal.user_id).await?;
+        let token = self.anchor.authenticate(&keypair).await?;
+        let anchor_tx = self.anchor.get_transaction(&token, anchor_tx_id).await?;
+
+        self.apply(withdrawal, &anchor_tx).await
+    }
+
+    /// Record what the anchor reports about a withdrawal and move it along
+    async fn apply(
+        &self,
+        withdrawal: &Withdrawal,
+        anchor_tx: &AnchorTransaction,
+    ) -> Result<(), ApiError> {
+        let withdrawal_id = Uuid::parse_str(&withdrawal.id).map_err(|_| {
+            tracing::error!("Invalid withdrawal id in database: {}", withdrawal.id);
+            ApiError::InternalServerError
+        })?;
+
+        let client = self.db_pool.get().await?;
+        client
+            .execute(
+                "UPDATE withdrawals SET anchor_status = $1, anchor_message = COALESCE($2, anchor_message), updated_at = NOW() WHERE id = $3",
+                &[&anchor_tx.status, &anchor_tx.message, &withdrawal_id],
+            )
+            .await?;
+        drop(client);
+
+        let reason = format!("Anchor reported {}", anchor_tx.status);
+        match AnchorProgress::from_status(&anchor_tx.status) {
+            AnchorProgress::Waiting => {}
+            AnchorProgress::AwaitingPayment => {
+                if withdrawal.status == WithdrawalStatus::Pending {
+                    self.pay_anchor(withdrawal, withdrawal_id, anchor_tx)
+                        .await?;
+                }
+            }
+            // The withdrawal is processing only once our payment to the anchor has landed
+            AnchorProgress::Processing => {
+                if withdrawal.status == WithdrawalStatus::Pending {
+                    self.follow_payment(withdrawal_id).await?;
+                }
+            }
+            AnchorProgress::Completed => {
+                if withdrawal.status == WithdrawalStatus::Pending {
+                    match self.follow_payment(withdrawal_id).await? {
+                        Some(Submission::Confirmed) => {}
+                        // It may still land; the next poll looks again
+                        Some(Submission::Pending) => return Ok(()),
+                        None | Some(Submission::Failed) | Some(Submission::Expired) => {
+                            return self
+                                .fail_withdrawal(
+                                    withdrawal_id,
+                                    "Anchor reported completed but no payment to it landed",
+                                    None,
+                                )
+                                .await;
+                        }
+                    }
+                }
+                self.transition(withdrawal_id, WithdrawalStatus::Completed, &reason, None)
+                    .await?;
+                self.notification
+                    .notify_best_effort(
+                        &withdrawal.user_id,
+                        "Withdrawal completed",
+                        format!(
+                            "Your withdrawal of {} {} has completed",
+                            asset::format_stroops(withdrawal.amount),
+                            withdrawal.asset
+                        ),
+                        serde_json::json!({ "withdrawal_id": withdrawal_id.to_string() }),
+                    )
+                    .await;
+            }
+            AnchorProgress::Failed => {
+                let reason = match &anchor_tx.message {
+                    Some(message) => format!("{}: {}", reason, message),
+                    None => reason,
+                };
+                self.fail_after_anchor(withdrawal, withdrawal_id, anchor_tx, &reason)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Pay the anchor once, and mark the withdrawal processing when the payment lands
+    ///
+    /// The signed payment is committed with its hash before it is submitted,
+    /// so a crash or failed submission is followed up by resubmitting the
+    /// same payment rather than signing a second one.
+    async fn pay_anchor(
+        &self,
+        withdrawal: &Withdrawal,
+        withdrawal_id: Uuid,
+        anchor_tx: &AnchorTransaction,
+    ) -> Result<(), ApiError> {
+        let Some(envelope) = self
+            .signed_payment(withdrawal, withdrawal_id, anchor_tx)
+            .await?
+        else {
+            return Ok(());
+        };
+
+        match self.soroban.reconcile(&envelope).await? {
+            Submission::Pending => {}
+            Submission::Confirmed => {
+                self.transition(
+                    withdrawal_id,
+                    WithdrawalStatus::Processing,
+                    "Stellar payment to anchor confirmed",
+                    None,
+                )
+                .await?;
+            }
+            Submission::Failed => {
+                self.fail_withdrawal(withdrawal_id, "Stellar payment to anchor failed", None)
+                    .await?;
+            }
+            Submission::Expired => {
+                // It can no longer land, so the next poll signs a new one
+                self.clear_payment(withdrawal_id, &envelope).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Follow up the recorded payment to the anchor, marking the withdrawal processing once it lands
+    ///
+    /// Returns `None` when no payment has been signed.
+    async fn follow_payment(&self, withdrawal_id: Uuid) -> Result<Option<Submission>, ApiError> {
+        let client = self.db_pool.get().await?;
+        let envelope: Option<String> = client
+            .query_one(
+                "SELECT tx_envelope FROM withdrawals WHERE id = $1",
+                &[&withdrawal_id],
+            )
+            .await?
+            .get(0);
+        drop(client);
+        let Some(envelope) = envelope else {
+            return Ok(None);
+        };
+
+        let submission = self.soroban.reconcile(&envelope).await?;
+        match submission {
+            Submission::Confirmed => {
+                self.transition(
+                    withdrawal_id,
+                    WithdrawalStatus::Processing,
+                    "Stellar payment to anchor confirmed",
+                    None,
+                )
+                .await?
+            }
+            Submission::Expired => self.clear_payment(withdrawal_id, &envelope).await?,
+            Submission::Pending | Submission::Failed => {}
+        }
+
+        Ok(Some(submission))
+    }
+
+    /// The withdrawal's signed payment to the anchor, signed and recorded first if there is none
+    ///
+    /// Returns `None` once the withdrawal has moved on from `pending`.
+    async fn signed_payment(
+        &self,
+        withdrawal: &Withdrawal,
+        withdrawal_id: Uuid,
+        anchor_tx: &AnchorTransaction,
+    ) -> Result<Option<String>, ApiError> {
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+
+        // Locked so a poll and a callback arriving together sign one payment
+        let row = tx
+            .query_one(
+                "SELECT status, tx_envelope FROM withdrawals WHERE id = $1 FOR UPDATE",
+                &[&withdrawal_id],
+            )
+            .await?;
+        let status: WithdrawalStatus =
+            lifecycle::parse_status(LifecycleEntity::Withdrawal, row.get(0))?;
+        if status != WithdrawalStatus::Pending {
+            return Ok(None);
+        }
+        if let Some(envelope) = row.get::<_, Option<String>>(1) {
+            return Ok(Some(envelope));
+        }
+
+        let keypair = self.identity.get_user_keypair(&withdrawal.user_id).await?;
+        let signed = self.sign_payment(withdrawal, &keypair, anchor_tx).await?;
+        tx.execute(
+            "UPDATE withdrawals SET tx_hash = $1, tx_envelope = $2, updated_at = NOW() WHERE id = $3",
+            &[&signed.tx_hash, &signed.envelope_xdr, &withdrawal_id],
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(Some(signed.envelope_xdr))
+    }
+
+    /// Forget a signed payment that can no longer land
+    async fn clear_payment(&self, withdrawal_id: Uuid, envelope: &str) -> Result<(), ApiError> {
+        let client = self.db_pool.get().await?;
+        client
+            .execute(
+                "UPDATE withdrawals SET tx_hash = NULL, tx_envelope = NULL, updated_at = NOW() WHERE id = $1 AND tx_envelope = $2",
+                &[&withdrawal_id, &envelope],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Fail a withdrawal the anchor gave up on
+    ///
+    /// The user is credited in full only if our payment never reached the
+    /// anchor. Once it has, only what the anchor is seen to have paid back
+    /// on-chain is credited. While the payment may still land, nothing is
+    /// decided and the next poll looks again.
+    async fn fail_after_anchor(
+        &self,
+        withdrawal: &Withdrawal,
+        withdrawal_id: Uuid,
+        anchor_tx: &AnchorTransaction,
+        reason: &str,
+    ) -> Result<(), ApiError> {
+        let client = self.db_pool.get().await?;
+        let envelope: Option<String> = client
+            .query_one(
+                "SELECT tx_envelope FROM withdrawals WHERE id = $1",
+                &[&withdrawal_id],
+            )
+            .await?
+            .get(0);
+        drop(client);
+
+        let submission = match &envelope {
+            Some(envelope) => self.soroban.submission_status(envelope).await?,
+            None => Submission::Failed,
+        };
+        match submission {
+            Submission::Pending => {
+                tracing::info!(
+                    "Withdrawal {} failed at the anchor while its payment may still land",
+                    withdrawal_id
+                );
+                Ok(())
+            }
+            Submission::Failed | Submission::Expired => {
+                self.fail_withdrawal(withdrawal_id, reason, None).await
+            }
+            Submission::Confirmed => {
+                let returned = self.returned_by_anchor(withdrawal, anchor_tx).await?;
+                self.fail_withdrawal(withdrawal_id, reason, Some(returned))
+                    .await
+            }
+        }
+    }
+
+    /// Stroops the anchor's reported refunds paid back to the user's wallet, as found on-chain
+    async fn returned_by_anchor(
+        &self,
+        withdrawal: &Withdrawal,
+        anchor_tx: &AnchorTransaction,
+    ) -> Result<i64, ApiError> {
+        let Some(refunds) = &anchor_tx.refunds else {
+            return Ok(0);
+        };
+
+        let wallet = self.identity.get_user_keypair(&withdrawal.user_id).await?;
+        let stellar_asset =
+            asset::resolve_asset(&withdrawal.asset, &self.config.stellar_network.assets)?;
+        let mut returned = 0;
+        for tx_hash in refunds.stellar_payment_ids() {
+            returned += self
+                .soroban
+                .confirmed_payment_amount(tx_hash, &wallet.address(), &stellar_asset)
+                .await?;
+        }
+
+        Ok(returned.min(withdrawal.amount))
+    }
+
+    /// Sign the payment of the withdrawn amount from the user's wallet to the anchor's account
+    async fn sign_payment(
+        &self,
+        withdrawal: &Withdrawal,
+        keypair: &Keypair,
+        anchor_tx: &AnchorTransaction,
+    ) -> Result<SignedEnvelope, ApiError> {
+        let destination = anchor_tx
+            .withdraw_anchor_account
+            .as_deref()
+            .ok_or_else(|| {
+                ApiError::Anchor(format!(
+                    "Anchor transaction {} has no withdraw_anchor_account",
+                    anchor_tx.id
+                ))
+            })?;
+        let memo = match &anchor_tx.withdraw_memo {
+            Some(memo) => transaction::memo(
+                memo,
+                anchor_tx.withdraw_memo_type.as_deref().unwrap_or("text"),
+            )?,
+            None => Memo::None,
+        };
+
+        let stellar_asset =
+            asset::resolve_asset(&withdrawal.asset, &self.config.stellar_network.assets)?;
+
+        self.soroban
+            .sign_payment(
+                keypair,
+                &keypair.address(),
+                destination,
+                stellar_asset,
+                withdrawal.amount,
+                memo,
+            )
+            .await
+    }
+
+    /// Debit the user and insert the pending withdrawal in one transaction
+    async fn reserve_withdrawal(
+        &self,
+        user_id: &str,
+        request: &CreateWithdrawalRequest,
+    ) -> Result<Uuid, ApiError> {
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+
+        let debited = tx
+            .execute(
+                "UPDATE balances SET amount = amount - $3, last_updated = NOW() WHERE owner_id = $1 AND asset = $2 AND amount >= $3",
+                &[&user_id, &request.asset, &request.amount],
+            )
+            .await?;
+        if debited == 0 {
+            return Err(ApiError::Validation("Insufficient balance".to_string()));
+        }
+
+        let withdrawal_id = Uuid::new_v4();
+        tx.execute(
+            r#"
+            INSERT INTO withdrawals (id, user_id, destination_address, amount, asset, status)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            &[
+                &withdrawal_id,
+                &user_id,
+                &request.destination_address,
+                &request.amount,
+                &request.asset,
+                &WithdrawalStatus::Pending.to_string(),
+            ],
+        )
+        .await?;
+        lifecycle::record_created(
+            &tx,
+            LifecycleEntity::Withdrawal,
+            withdrawal_id,
+            WithdrawalStatus::Pending,
+            None,
+        )
+        .await?;
+
+        tx.commit().await?;
+        Ok(withdrawal_id)
+    }
+
+    async fn transition(
+        &self,
+        withdrawal_id: Uuid,
+        status: WithdrawalStatus,
+        reason: &str,
+        tx_hash: Option<&str>,
+    ) -> Result<(), ApiError> {
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+        lifecycle::transition(
+            &tx,
+            LifecycleEntity::Withdrawal,
+            withdrawal_id,
+            status,
+            reason,
+            tx_hash,
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Mark the withdrawal failed and return `returned` stroops to the user, or all of it
+    async fn fail_withdrawal(
+        &self,
+        withdrawal_id: Uuid,
+        reason: &str,
+        returned: Option<i64>,
+    ) -> Result<(), ApiError> {
+        let mut client = self.db_pool.get().await?;
+        let tx = client.transaction().await?;
+
+        lifecycle::transition(
+            &tx,
+            LifecycleEntity::Withdrawal,
+            withdrawal_id,
+            WithdrawalStatus::Failed,
+            reason,
+            None,
+        )
+        .await?;
+        let (user_id, amount, asset_code) = Self::withdrawal_owner(&tx, withdrawal_id).await?;
+        let returned = returned.unwrap_or(amount);
+        if returned > 0 {
+            tx.execute(
+                "UPDATE balances SET amount = amount + $3, last_updated = NOW() WHERE owner_id = $1 AND asset = $2",
+                &[&user_id, &asset_code, &returned],
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        let message = if returned == amount {
+            format!(
+                "Your withdrawal of {} {} failed and the funds were returned",
+                asset::format_stroops(amount),
+                asset_code
+            )
+        } else {
+            format!(
+                "Your withdrawal of {} {} failed after it was paid to the anchor, which has returned {} {}",
+                asset::format_stroops(amount),
+                asset_code,
+                asset::format_stroops(returned),
+                asset_code
+            )
+        };
+        self.notification
+            .notify_best_effort(
+                &user_id,
+                "Withdrawal failed",
+                message,
+                serde_json::json!({ "withdrawal_id": withdrawal_id.to_string() }),
+            )
+            .await;
+
+        Ok(())
+    }
+
+    async fn withdrawal_owner(
+        tx: &Transaction<'_>,
+        withdrawal_id: Uuid,
+    ) -> Result<(String, i64, String), ApiError> {
+        let row = tx
+            .query_one(
+                "SELECT user_id, amount, asset FROM withdrawals WHERE id = $1",
+                &[&withdrawal_id],
+            )
+            .await?;
+        Ok((row.get(0), row.get(1), row.get(2)))
+    }
+}
+
+fn withdrawal_from_row(row: &tokio_postgres::Row) -> Result<Withdrawal, ApiError> {
+    Ok(Withdrawal {
+        id: row.get::<_, Uuid>(0).to_string(),
+        tx_hash: row.get(1),
+        user_id: row.get(2),
+        destination_address: row.get(3),
+        amount: row.get(4),
+        asset: row.get(5),
+        status: lifecycle::parse_status(LifecycleEntity::Withdrawal, row.get(6))?,
+        anchor_tx_id: row.get(7),
+        anchor_status: row.get(8),
+        interactive_url: row.get(9),
+        anchor_message: row.get(10),
+        created_at: row.get::<_, chrono::DateTime<chrono::Utc>>(11),
+        updated_at: row.get::<_, chrono::DateTime<chrono::Utc>>(12),
+    })
+}
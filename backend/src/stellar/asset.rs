@@ -0,0 +1,279 @@
+//! Resolution of asset codes used by the API into Stellar `Asset`s
+
+use ring::digest;
+use soroban_sdk::xdr::{
+    AccountId, AlphaNum12, AlphaNum4, Asset, AssetCode12, AssetCode4, ContractIdPreimage, Hash,
+    HashIdPreimage, HashIdPreimageContractId, Limits, PublicKey, Uint256, WriteXdr,
+};
+use std::collections::HashMap;
+
+use super::{strkey, transaction};
+use crate::api_error::ApiError;
+
+/// Resolve `XLM`/`native`, `CODE:ISSUER`, or a bare code with a configured issuer
+pub fn resolve_asset(asset: &str, issuers: &HashMap<String, String>) -> Result<Asset, ApiError> {
+    if asset.eq_ignore_ascii_case("xlm") || asset.eq_ignore_ascii_case("native") {
+        return Ok(Asset::Native);
+    }
+
+    let (code, issuer) = match asset.split_once(':') {
+        Some((code, issuer)) => (code.to_string(), issuer),
+        // Config keys are lowercased when loaded, so codes match case-insensitively;
+        // configured codes are taken to be upper case, as Stellar codes are case-sensitive
+        None => (
+            asset.to_ascii_uppercase(),
+            issuers
+                .iter()
+                .find(|(code, _)| code.eq_ignore_ascii_case(asset))
+                .map(|(_, issuer)| issuer.as_str())
+                .ok_or_else(|| ApiError::Validation(format!("Unsupported asset: {}", asset)))?,
+        ),
+    };
+
+    credit_asset(&code, issuer)
+}
+
+/// The one form an asset is stored under, in balances and the rows that move them
+///
+/// `XLM`, the bare code of a configured asset, or `CODE:ISSUER` for any other.
+pub fn asset_key(asset: &Asset, issuers: &HashMap<String, String>) -> String {
+    let Some((code, issuer)) = canonical(asset)
+        .split_once(':')
+        .map(|(code, issuer)| (code.to_string(), issuer.to_string()))
+    else {
+        return "XLM".to_string();
+    };
+    let configured = issuers.iter().any(|(configured, configured_issuer)| {
+        configured.eq_ignore_ascii_case(&code) && *configured_issuer == issuer
+    });
+    if configured && code == code.to_ascii_uppercase() {
+        code
+    } else {
+        format!("{}:{}", code, issuer)
+    }
+}
+
+/// The code part of `CODE` or `CODE:ISSUER`
+pub fn asset_code(asset: &str) -> &str {
+    asset.split_once(':').map_or(asset, |(code, _)| code)
+}
+
+/// Issuer (`G...`) of a credit asset, `None` for XLM
+pub fn issuer(asset: &Asset) -> Option<String> {
+    let AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(key))) = match asset {
+        Asset::Native => return None,
+        Asset::CreditAlphanum4(AlphaNum4 { issuer, .. }) => issuer,
+        Asset::CreditAlphanum12(AlphaNum12 { issuer, .. }) => issuer,
+    };
+    Some(strkey::encode_account_id(key))
+}
+
+/// `native` or `CODE:ISSUER`, the form Horizon takes assets in
+pub fn canonical(asset: &Asset) -> String {
+    let code = match asset {
+        Asset::Native => return "native".to_string(),
+        Asset::CreditAlphanum4(AlphaNum4 { asset_code, .. }) => &asset_code.0[..],
+        Asset::CreditAlphanum12(AlphaNum12 { asset_code, .. }) => &asset_code.0[..],
+    };
+    let code = String::from_utf8_lossy(code)
+        .trim_end_matches('\0')
+        .to_string();
+    format!("{}:{}", code, issuer(asset).unwrap_or_default())
+}
+
+/// Address (`C...`) of the asset's Stellar Asset Contract on the given network
+pub fn contract_address(asset: &Asset, network_passphrase: &str) -> Result<String, ApiError> {
+    let preimage = HashIdPreimage::ContractId(HashIdPreimageContractId {
+        network_id: Hash(transaction::network_id(network_passphrase)),
+        contract_id_preimage: ContractIdPreimage::Asset(asset.clone()),
+    })
+    .to_xdr(Limits::none())
+    .map_err(|e| ApiError::Stellar(format!("Failed to encode contract id preimage: {}", e)))?;
+
+    let mut id = [0u8; 32];
+    id.copy_from_slice(digest::digest(&digest::SHA256, &preimage).as_ref());
+    Ok(strkey::encode_contract(&id))
+}
+
+/// Format a stroop amount in whole units, e.g. `12500000` as `1.25`
+pub fn format_stroops(stroops: i64) -> String {
+    let units = format!(
+        "{}{}.{:07}",
+        if stroops < 0 { "-" } else { "" },
+        stroops.unsigned_abs() / 10_000_000,
+        stroops.unsigned_abs() % 10_000_000
+    );
+    units
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// Parse an amount in whole units, e.g. `1.25`, into stroops
+pub fn parse_stroops(amount: &str) -> Result<i64, ApiError> {
+    let invalid = || ApiError::Validation(format!("Invalid amount: {}", amount));
+    let (whole, fraction) = amount.trim().split_once('.').unwrap_or((amount.trim(), ""));
+    if whole.is_empty() && fraction.is_empty()
+        || fraction.len() > 7
+        || !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+
+    let whole: i64 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().map_err(|_| invalid())?
+    };
+    let fraction: i64 = format!("{:0<7}", fraction).parse().map_err(|_| invalid())?;
+    whole
+        .checked_mul(10_000_000)
+        .and_then(|stroops| stroops.checked_add(fraction))
+        .ok_or_else(invalid)
+}
+
+fn credit_asset(code: &str, issuer: &str) -> Result<Asset, ApiError> {
+    if code.is_empty() || code.len() > 12 || !code.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(ApiError::Validation(format!(
+            "Invalid asset code: {}",
+            code
+        )));
+    }
+
+    let issuer = AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(
+        strkey::decode_account_id(issuer)
+            .map_err(|e| ApiError::Validation(format!("Invalid asset issuer: {}", e)))?,
+    )));
+
+    if code.len() <= 4 {
+        let mut bytes = [0u8; 4];
+        bytes[..code.len()].copy_from_slice(code.as_bytes());
+        Ok(Asset::CreditAlphanum4(AlphaNum4 {
+            asset_code: AssetCode4(bytes),
+            issuer,
+        }))
+    } else {
+        let mut bytes = [0u8; 12];
+        bytes[..code.len()].copy_from_slice(code.as_bytes());
+        Ok(Asset::CreditAlphanum12(AlphaNum12 {
+            asset_code: AssetCode12(bytes),
+            issuer,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ISSUER: &str = "GA7QYNF7SOWQ3GLR2BGMZEHXAVIRZA4KVWLTJJFC7MGXUA74P7UJVSGZ";
+
+    #[test]
+    fn test_native_and_configured_assets() {
+        let issuers = HashMap::from([("usdc".to_string(), ISSUER.to_string())]);
+
+        assert_eq!(resolve_asset("XLM", &issuers).unwrap(), Asset::Native);
+        match resolve_asset("USDC", &issuers).unwrap() {
+            Asset::CreditAlphanum4(asset) => assert_eq!(&asset.asset_code.0, b"USDC"),
+            other => panic!("expected an alphanum4 asset, got {:?}", other),
+        }
+        assert!(resolve_asset("EURC", &issuers).is_err());
+    }
+
+    #[test]
+    fn test_bare_codes_are_upper_cased() {
+        let issuers = HashMap::from([("usdc".to_string(), ISSUER.to_string())]);
+
+        assert_eq!(
+            resolve_asset("usdc", &issuers).unwrap(),
+            resolve_asset(&format!("USDC:{}", ISSUER), &issuers).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_asset_key() {
+        let issuers = HashMap::from([("usdc".to_string(), ISSUER.to_string())]);
+        let key = |asset: &str| asset_key(&resolve_asset(asset, &issuers).unwrap(), &issuers);
+
+        assert_eq!(key("native"), "XLM");
+        assert_eq!(key("usdc"), "USDC");
+        assert_eq!(key(&format!("USDC:{}", ISSUER)), "USDC");
+        assert_eq!(key(&format!("usdc:{}", ISSUER)), format!("usdc:{}", ISSUER));
+        let other = format!(
+            "USDC:{}",
+            "GBBD47IF6LWK7P7MDEVSCWR7DPUWV3NY3DTQEVFL4NAT4AQH3ZLLFLA5"
+        );
+        assert_eq!(key(&other), other);
+    }
+
+    #[test]
+    fn test_explicit_issuer() {
+        let asset = resolve_asset(&format!("LONGERCODE:{}", ISSUER), &HashMap::new()).unwrap();
+        assert!(matches!(asset, Asset::CreditAlphanum12(_)));
+
+        assert!(resolve_asset("USDC:GINVALID", &HashMap::new()).is_err());
+        assert!(resolve_asset(&format!("BAD-CODE:{}", ISSUER), &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_asset_code() {
+        assert_eq!(asset_code("USDC"), "USDC");
+        assert_eq!(asset_code(&format!("USDC:{}", ISSUER)), "USDC");
+    }
+
+    #[test]
+    fn test_contract_address() {
+        // The native asset's well-known contract on testnet
+        assert_eq!(
+            contract_address(&Asset::Native, "Test SDF Network ; September 2015").unwrap(),
+            "CDLZFC3SYJYDZT7K67VZ75HPJVIEUVNIXF47ZG2FB2RMQQVU2HHGCYSC"
+        );
+
+        let usdc = resolve_asset(&format!("USDC:{}", ISSUER), &HashMap::new()).unwrap();
+        let address = contract_address(&usdc, "Test SDF Network ; September 2015").unwrap();
+        assert!(strkey::decode_contract(&address).is_ok());
+        assert_ne!(
+            address,
+            contract_address(&usdc, "Public Global Stellar Network ; September 2015").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_issuer() {
+        let asset = resolve_asset(&format!("USDC:{}", ISSUER), &HashMap::new()).unwrap();
+        assert_eq!(issuer(&asset).as_deref(), Some(ISSUER));
+        assert_eq!(issuer(&Asset::Native), None);
+    }
+
+    #[test]
+    fn test_canonical() {
+        let asset = resolve_asset(&format!("USDC:{}", ISSUER), &HashMap::new()).unwrap();
+        assert_eq!(canonical(&asset), format!("USDC:{}", ISSUER));
+        let asset = resolve_asset(&format!("LONGERCODE:{}", ISSUER), &HashMap::new()).unwrap();
+        assert_eq!(canonical(&asset), format!("LONGERCODE:{}", ISSUER));
+        assert_eq!(canonical(&Asset::Native), "native");
+    }
+
+    #[test]
+    fn test_format_stroops() {
+        assert_eq!(format_stroops(12_500_000), "1.25");
+        assert_eq!(format_stroops(10_000_000), "1");
+        assert_eq!(format_stroops(1), "0.0000001");
+        assert_eq!(format_stroops(0), "0");
+    }
+
+    #[test]
+    fn test_parse_stroops() {
+        assert_eq!(parse_stroops("1.25").unwrap(), 12_500_000);
+        assert_eq!(parse_stroops("100").unwrap(), 1_000_000_000);
+        assert_eq!(parse_stroops("0.0000001").unwrap(), 1);
+        assert_eq!(parse_stroops(".5").unwrap(), 5_000_000);
+        assert!(parse_stroops("1.00000001").is_err());
+        assert!(parse_stroops("-1").is_err());
+        assert!(parse_stroops("").is_err());
+        assert!(parse_stroops("1e5").is_err());
+    }
+}
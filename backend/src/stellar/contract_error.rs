@@ -0,0 +1,366 @@
+//! Decoding of Soroban contract errors into the error enums of the Zaps contracts
+//!
+//! A failed invocation reports `Error(Contract, #N)`, where `N` is the
+//! `#[contracterror]` discriminant of the contract that failed. The same code
+//! means different things in different contracts, so a code is only meaningful
+//! together with the contract that raised it.
+
+use serde::Serialize;
+use soroban_sdk::xdr::{
+    ContractEventBody, DiagnosticEvent, Hash, Limits, ReadXdr, ScError, ScVal, TransactionMeta,
+};
+
+use super::strkey;
+use crate::config::ContractsConfig;
+
+/// The deployed contracts whose errors the backend understands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ZapsContract {
+    PaymentRouter,
+    MerchantVault,
+    ZapsRegistry,
+    Escrow,
+}
+
+impl ZapsContract {
+    /// Identify a contract by its `C...` address using the configured deployments
+    pub fn from_address(contracts: &ContractsConfig, address: &str) -> Option<Self> {
+        if address.is_empty() {
+            return None;
+        }
+        [
+            (&contracts.payment_router, ZapsContract::PaymentRouter),
+            (&contracts.merchant_vault, ZapsContract::MerchantVault),
+            (&contracts.zaps_registry, ZapsContract::ZapsRegistry),
+            (&contracts.escrow, ZapsContract::Escrow),
+        ]
+        .into_iter()
+        .find(|(configured, _)| configured.as_str() == address)
+        .map(|(_, contract)| contract)
+    }
+}
+
+/// HTTP-level category of a contract error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContractErrorKind {
+    /// The request itself is invalid (bad amount, limits not met)
+    Validation,
+    /// The caller may not perform the operation
+    Authorization,
+    /// A referenced merchant, escrow or balance does not exist
+    NotFound,
+    /// The operation conflicts with the current on-chain state
+    Conflict,
+    /// Contract misconfiguration or a transient on-chain failure
+    Internal,
+}
+
+/// A contract error code resolved against the contract that raised it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractError {
+    pub contract: Option<ZapsContract>,
+    pub code: u32,
+}
+
+impl ContractError {
+    pub fn new(contract: Option<ZapsContract>, code: u32) -> Self {
+        Self { contract, code }
+    }
+
+    /// Stable machine-readable code returned to API clients
+    pub fn error_code(&self) -> &'static str {
+        self.describe().0
+    }
+
+    pub fn kind(&self) -> ContractErrorKind {
+        self.describe().1
+    }
+
+    pub fn message(&self) -> &'static str {
+        self.describe().2
+    }
+
+    fn describe(&self) -> (&'static str, ContractErrorKind, &'static str) {
+        use ContractErrorKind::*;
+
+        match (self.contract, self.code) {
+            // payment-router PaymentError
+            (Some(ZapsContract::PaymentRouter), 1) => (
+                "ROUTER_ALREADY_INITIALIZED",
+                Conflict,
+                "Payment router is already initialized",
+            ),
+            (Some(ZapsContract::PaymentRouter), 2) => (
+                "ROUTER_REGISTRY_NOT_SET",
+                Internal,
+                "Payment router has no merchant registry configured",
+            ),
+            (Some(ZapsContract::PaymentRouter), 3) => (
+                "INVALID_SEND_AMOUNT",
+                Validation,
+                "Send amount must be positive",
+            ),
+            (Some(ZapsContract::PaymentRouter), 4) => (
+                "INVALID_MIN_RECEIVE",
+                Validation,
+                "Minimum receive amount must be positive",
+            ),
+            (Some(ZapsContract::PaymentRouter), 5) => (
+                "PAYMENT_IN_PROGRESS",
+                Conflict,
+                "Another payment is being processed, retry shortly",
+            ),
+            (Some(ZapsContract::PaymentRouter), 6) => (
+                "MERCHANT_INACTIVE",
+                Conflict,
+                "Merchant is not accepting payments",
+            ),
+            (Some(ZapsContract::PaymentRouter), 7) => (
+                "FX_ROUTER_MISSING",
+                Internal,
+                "No FX router is configured for cross-asset payments",
+            ),
+            (Some(ZapsContract::PaymentRouter), 8) => (
+                "SETTLEMENT_BELOW_MIN",
+                Validation,
+                "Settled amount would be below the requested minimum",
+            ),
+            (Some(ZapsContract::PaymentRouter), 9) => {
+                ("FX_SWAP_FAILED", Internal, "Currency conversion failed")
+            }
+
+            // merchant-vault Error
+            (Some(ZapsContract::MerchantVault), 1) => {
+                ("NEGATIVE_AMOUNT", Validation, "Amount must not be negative")
+            }
+            (Some(ZapsContract::MerchantVault), 2) => (
+                "INSUFFICIENT_BALANCE",
+                Validation,
+                "Merchant balance is insufficient",
+            ),
+            (Some(ZapsContract::MerchantVault), 3) => (
+                "VAULT_UNAUTHORIZED_CALLER",
+                Authorization,
+                "Caller may not move merchant funds",
+            ),
+            (Some(ZapsContract::MerchantVault), 4) => (
+                "MERCHANT_NOT_INITIALIZED",
+                NotFound,
+                "Merchant has no vault balance",
+            ),
+            (Some(ZapsContract::MerchantVault), 5) => (
+                "VAULT_ALREADY_INITIALIZED",
+                Conflict,
+                "Merchant vault is already initialized",
+            ),
+            (Some(ZapsContract::MerchantVault), 6) => (
+                "VAULT_NOT_INITIALIZED",
+                Internal,
+                "Merchant vault is not initialized",
+            ),
+
+            // zaps-registry Error
+            (Some(ZapsContract::ZapsRegistry), 1) => (
+                "REGISTRY_ALREADY_INITIALIZED",
+                Conflict,
+                "Registry is already initialized",
+            ),
+            (Some(ZapsContract::ZapsRegistry), 2) => (
+                "REGISTRY_NOT_INITIALIZED",
+                Internal,
+                "Registry is not initialized",
+            ),
+            (Some(ZapsContract::ZapsRegistry), 3) => (
+                "REGISTRY_UNAUTHORIZED",
+                Authorization,
+                "Caller is not the registry admin",
+            ),
+            (Some(ZapsContract::ZapsRegistry), 4) => {
+                ("DUPLICATE_ID", Conflict, "Identifier is already registered")
+            }
+            (Some(ZapsContract::ZapsRegistry), 5) => {
+                ("MERCHANT_NOT_FOUND", NotFound, "Merchant is not registered")
+            }
+            (Some(ZapsContract::ZapsRegistry), 6) => (
+                "MERCHANT_INACTIVE",
+                Conflict,
+                "Merchant is not accepting payments",
+            ),
+            (Some(ZapsContract::ZapsRegistry), 7) => {
+                ("USER_NOT_FOUND", NotFound, "User is not registered")
+            }
+
+            // escrow-contract EscrowError
+            (Some(ZapsContract::Escrow), 1) => (
+                "ESCROW_NOT_AUTHORIZED",
+                Authorization,
+                "Caller is not a party to this escrow",
+            ),
+            (Some(ZapsContract::Escrow), 2) => (
+                "ESCROW_ALREADY_LOCKED",
+                Conflict,
+                "Escrow funds are already locked",
+            ),
+            (Some(ZapsContract::Escrow), 3) => {
+                ("ESCROW_NOT_LOCKED", Conflict, "Escrow holds no funds")
+            }
+            (Some(ZapsContract::Escrow), 4) => (
+                "ESCROW_ALREADY_FINALIZED",
+                Conflict,
+                "Escrow is already finalized",
+            ),
+            (Some(ZapsContract::Escrow), 5) => (
+                "ESCROW_INVALID_AMOUNT",
+                Validation,
+                "Escrow amount must be positive",
+            ),
+            (Some(ZapsContract::Escrow), 6) => (
+                "ESCROW_INVALID_STATE",
+                Conflict,
+                "Escrow is not in a state that allows this operation",
+            ),
+            (Some(ZapsContract::Escrow), 7) => (
+                "ESCROW_INVALID_ARBITRATOR",
+                Validation,
+                "Arbitrator must differ from payer and payee",
+            ),
+            (Some(ZapsContract::Escrow), 8) => (
+                "ESCROW_TIMEOUT_NOT_REACHED",
+                Conflict,
+                "Escrow timeout has not been reached",
+            ),
+
+            _ => ("CONTRACT_ERROR", Internal, "Contract invocation failed"),
+        }
+    }
+}
+
+impl std::fmt::Display for ContractError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (contract error #{})", self.message(), self.code)
+    }
+}
+
+/// Extract `N` from the first `Error(Contract, #N)` in an error or diagnostic string
+pub fn parse_contract_error_code(text: &str) -> Option<u32> {
+    let start = text.find("Error(Contract, #")? + "Error(Contract, #".len();
+    let digits: String = text[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+/// Decode base64 `DiagnosticEvent`s as returned by `simulateTransaction` and `sendTransaction`
+pub fn decode_diagnostic_events(encoded: &[String]) -> Vec<DiagnosticEvent> {
+    encoded
+        .iter()
+        .filter_map(|event| DiagnosticEvent::from_xdr_base64(event, Limits::none()).ok())
+        .collect()
+}
+
+/// Diagnostic events recorded in a transaction's result meta, as returned by `getTransaction`
+pub fn meta_diagnostic_events(result_meta_xdr: &str) -> Vec<DiagnosticEvent> {
+    match TransactionMeta::from_xdr_base64(result_meta_xdr, Limits::none()) {
+        Ok(TransactionMeta::V3(meta)) => meta
+            .soroban_meta
+            .map(|soroban| soroban.diagnostic_events.to_vec())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+/// Find the first contract error among diagnostic events, with the address of the contract that raised it
+///
+/// Diagnostic events are emitted innermost first, so for a cross-contract call
+/// this is the contract that actually failed rather than the one invoked.
+pub fn find_contract_error(events: &[DiagnosticEvent]) -> Option<(Option<String>, u32)> {
+    events.iter().find_map(|event| {
+        let ContractEventBody::V0(body) = &event.event.body;
+
+        std::iter::once(&body.data)
+            .chain(body.topics.iter())
+            .find_map(|value| match value {
+                ScVal::Error(ScError::Contract(code)) => Some(*code),
+                _ => None,
+            })
+            .map(|code| {
+                let contract = event
+                    .event
+                    .contract_id
+                    .as_ref()
+                    .map(|Hash(id)| strkey::encode_contract(id));
+                (contract, code)
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contracts() -> ContractsConfig {
+        ContractsConfig {
+            payment_router: "CROUTER".to_string(),
+            merchant_vault: "CVAULT".to_string(),
+            zaps_registry: "CREGISTRY".to_string(),
+            escrow: "CESCROW".to_string(),
+            registry_admin_secret: None,
+            vault_payout_secret: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_contract_error_code() {
+        assert_eq!(
+            parse_contract_error_code("HostError: Error(Contract, #6)\n\nEvent log:"),
+            Some(6)
+        );
+        assert_eq!(
+            parse_contract_error_code("Error(Contract, #12) in call"),
+            Some(12)
+        );
+        assert_eq!(
+            parse_contract_error_code("HostError: Error(Budget, ExceededLimit)"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_codes_depend_on_contract() {
+        let router = ZapsContract::from_address(&contracts(), "CROUTER");
+        let vault = ZapsContract::from_address(&contracts(), "CVAULT");
+
+        assert_eq!(
+            ContractError::new(router, 6).error_code(),
+            "MERCHANT_INACTIVE"
+        );
+        assert_eq!(
+            ContractError::new(router, 8).error_code(),
+            "SETTLEMENT_BELOW_MIN"
+        );
+        assert_eq!(
+            ContractError::new(vault, 6).error_code(),
+            "VAULT_NOT_INITIALIZED"
+        );
+        assert_eq!(
+            ContractError::new(vault, 2).kind(),
+            ContractErrorKind::Validation
+        );
+    }
+
+    #[test]
+    fn test_unknown_contract_or_code() {
+        assert_eq!(ZapsContract::from_address(&contracts(), "COTHER"), None);
+        assert_eq!(
+            ZapsContract::from_address(&ContractsConfig::default(), ""),
+            None
+        );
+        assert_eq!(ContractError::new(None, 6).error_code(), "CONTRACT_ERROR");
+        assert_eq!(
+            ContractError::new(Some(ZapsContract::Escrow), 99).error_code(),
+            "CONTRACT_ERROR"
+        );
+    }
+}
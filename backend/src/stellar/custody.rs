@@ -0,0 +1,83 @@
+//! Encryption at rest for custodial secret seeds
+//!
+//! Seeds are sealed with AES-256-GCM under a key derived from the configured
+//! custody secret and stored as `base64(nonce || ciphertext || tag)`.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use ring::{
+    aead::{self, Aad, LessSafeKey, Nonce, UnboundKey, NONCE_LEN},
+    digest,
+    rand::{SecureRandom, SystemRandom},
+};
+
+use crate::api_error::ApiError;
+
+fn sealing_key(encryption_key: &str) -> Result<LessSafeKey, ApiError> {
+    let key_bytes = digest::digest(&digest::SHA256, encryption_key.as_bytes());
+    let unbound = UnboundKey::new(&aead::AES_256_GCM, key_bytes.as_ref())
+        .map_err(|_| ApiError::InternalServerError)?;
+    Ok(LessSafeKey::new(unbound))
+}
+
+/// Encrypt a secret seed for storage
+pub fn seal_secret(encryption_key: &str, secret: &str) -> Result<String, ApiError> {
+    let key = sealing_key(encryption_key)?;
+
+    let mut nonce_bytes = [0u8; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce_bytes)
+        .map_err(|_| ApiError::InternalServerError)?;
+
+    let mut in_out = secret.as_bytes().to_vec();
+    key.seal_in_place_append_tag(
+        Nonce::assume_unique_for_key(nonce_bytes),
+        Aad::empty(),
+        &mut in_out,
+    )
+    .map_err(|_| ApiError::InternalServerError)?;
+
+    let mut sealed = nonce_bytes.to_vec();
+    sealed.extend_from_slice(&in_out);
+    Ok(BASE64.encode(sealed))
+}
+
+/// Decrypt a secret seed previously sealed with [`seal_secret`]
+pub fn open_secret(encryption_key: &str, sealed: &str) -> Result<String, ApiError> {
+    let key = sealing_key(encryption_key)?;
+
+    let data = BASE64
+        .decode(sealed)
+        .map_err(|_| ApiError::InternalServerError)?;
+    if data.len() <= NONCE_LEN {
+        return Err(ApiError::InternalServerError);
+    }
+
+    let (nonce_bytes, ciphertext) = data.split_at(NONCE_LEN);
+    let nonce =
+        Nonce::try_assume_unique_for_key(nonce_bytes).map_err(|_| ApiError::InternalServerError)?;
+
+    let mut in_out = ciphertext.to_vec();
+    let plaintext = key
+        .open_in_place(nonce, Aad::empty(), &mut in_out)
+        .map_err(|_| ApiError::InternalServerError)?;
+
+    String::from_utf8(plaintext.to_vec()).map_err(|_| ApiError::InternalServerError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let sealed = seal_secret("custody-key", "SSECRET").unwrap();
+        assert_ne!(sealed, "SSECRET");
+        assert_eq!(open_secret("custody-key", &sealed).unwrap(), "SSECRET");
+    }
+
+    #[test]
+    fn test_open_with_wrong_key_fails() {
+        let sealed = seal_secret("custody-key", "SSECRET").unwrap();
+        assert!(open_secret("other-key", &sealed).is_err());
+    }
+}
@@ -0,0 +1,276 @@
+//! Client for the Horizon endpoints the backend uses to price DEX trades
+//!
+//! Amounts cross this module's boundary in stroops; Horizon's decimal strings
+//! are converted on the way in and out. Path finding results are cached for a
+//! few seconds, since the same pair is typically priced several times while a
+//! payer checks out.
+
+use dashmap::DashMap;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use soroban_sdk::xdr::Asset;
+use std::time::{Duration, Instant};
+
+use super::asset::{canonical, format_stroops, parse_stroops};
+use crate::api_error::ApiError;
+
+pub struct HorizonClient {
+    http: reqwest::Client,
+    horizon_url: String,
+    /// Path finding results by request, kept for `cache_ttl`
+    paths_cache: DashMap<String, CachedPaths>,
+    cache_ttl: Duration,
+}
+
+struct CachedPaths {
+    fetched_at: Instant,
+    paths: Vec<PaymentPath>,
+}
+
+/// A conversion route found by Horizon's path finding
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaymentPath {
+    pub source_amount: i64,
+    pub destination_amount: i64,
+    /// Intermediate assets, as `native` or `CODE:ISSUER`
+    pub path: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Page<T> {
+    #[serde(rename = "_embedded")]
+    embedded: Records<T>,
+}
+
+#[derive(Deserialize)]
+struct Records<T> {
+    records: Vec<T>,
+}
+
+#[derive(Deserialize)]
+struct PathRecord {
+    source_amount: String,
+    destination_amount: String,
+    #[serde(default)]
+    path: Vec<PathAsset>,
+}
+
+#[derive(Deserialize)]
+struct PathAsset {
+    asset_type: String,
+    asset_code: Option<String>,
+    asset_issuer: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Problem {
+    title: String,
+    detail: Option<String>,
+}
+
+impl HorizonClient {
+    /// A client caching path finding results for `cache_ttl`; zero disables the cache
+    pub fn new(horizon_url: String, cache_ttl: Duration) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            horizon_url,
+            paths_cache: DashMap::new(),
+            cache_ttl,
+        }
+    }
+
+    /// Paths that sell exactly `source_amount` of `source` for `destination`
+    pub async fn strict_send_paths(
+        &self,
+        source: &Asset,
+        source_amount: i64,
+        destination: &Asset,
+    ) -> Result<Vec<PaymentPath>, ApiError> {
+        let mut query = asset_params("source", source);
+        query.push(("source_amount".to_string(), format_stroops(source_amount)));
+        query.push(("destination_assets".to_string(), canonical(destination)));
+
+        self.paths("paths/strict-send", &query).await
+    }
+
+    /// Paths that buy exactly `destination_amount` of `destination` with `source`
+    pub async fn strict_receive_paths(
+        &self,
+        source: &Asset,
+        destination: &Asset,
+        destination_amount: i64,
+    ) -> Result<Vec<PaymentPath>, ApiError> {
+        let mut query = asset_params("destination", destination);
+        query.push((
+            "destination_amount".to_string(),
+            format_stroops(destination_amount),
+        ));
+        query.push(("source_assets".to_string(), canonical(source)));
+
+        self.paths("paths/strict-receive", &query).await
+    }
+
+    async fn paths(
+        &self,
+        endpoint: &str,
+        query: &[(String, String)],
+    ) -> Result<Vec<PaymentPath>, ApiError> {
+        let key = cache_key(endpoint, query);
+        if let Some(cached) = self.paths_cache.get(&key) {
+            if cached.fetched_at.elapsed() < self.cache_ttl {
+                return Ok(cached.paths.clone());
+            }
+        }
+
+        let page: Page<PathRecord> = self.get(endpoint, query).await?;
+        let paths = page
+            .embedded
+            .records
+            .into_iter()
+            .map(|record| {
+                Ok(PaymentPath {
+                    source_amount: parse_stroops(&record.source_amount)?,
+                    destination_amount: parse_stroops(&record.destination_amount)?,
+                    path: record.path.into_iter().map(path_asset).collect(),
+                })
+            })
+            .collect::<Result<Vec<_>, ApiError>>()?;
+
+        if !self.cache_ttl.is_zero() {
+            self.paths_cache
+                .retain(|_, cached| cached.fetched_at.elapsed() < self.cache_ttl);
+            self.paths_cache.insert(
+                key,
+                CachedPaths {
+                    fetched_at: Instant::now(),
+                    paths: paths.clone(),
+                },
+            );
+        }
+
+        Ok(paths)
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        query: &[(String, String)],
+    ) -> Result<T, ApiError> {
+        let url = format!("{}/{}", self.horizon_url.trim_end_matches('/'), endpoint);
+        let response = self
+            .http
+            .get(&url)
+            .query(query)
+            .send()
+            .await
+            .map_err(|e| ApiError::Stellar(format!("{} request failed: {}", endpoint, e)))?;
+
+        let status = response.status();
+        let body = response.text().await.map_err(|e| {
+            ApiError::Stellar(format!("Failed to read {} response: {}", endpoint, e))
+        })?;
+
+        if !status.is_success() {
+            let message = serde_json::from_str::<Problem>(&body)
+                .map(|problem| match problem.detail {
+                    Some(detail) => format!("{}: {}", problem.title, detail),
+                    None => problem.title,
+                })
+                .unwrap_or(body);
+            return Err(ApiError::Stellar(format!(
+                "{} returned HTTP {}: {}",
+                endpoint, status, message
+            )));
+        }
+
+        serde_json::from_str(&body)
+            .map_err(|e| ApiError::Stellar(format!("Invalid {} response: {}", endpoint, e)))
+    }
+}
+
+fn cache_key(endpoint: &str, query: &[(String, String)]) -> String {
+    let mut params: Vec<String> = query
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect();
+    params.sort();
+    format!("{}?{}", endpoint, params.join("&"))
+}
+
+/// `{prefix}_asset_type`, `_asset_code` and `_asset_issuer` query parameters for one asset
+fn asset_params(prefix: &str, asset: &Asset) -> Vec<(String, String)> {
+    let asset_type = match asset {
+        Asset::Native => "native",
+        Asset::CreditAlphanum4(_) => "credit_alphanum4",
+        Asset::CreditAlphanum12(_) => "credit_alphanum12",
+    };
+    let mut params = vec![(format!("{}_asset_type", prefix), asset_type.to_string())];
+    if let Some((code, issuer)) = canonical(asset).split_once(':') {
+        params.push((format!("{}_asset_code", prefix), code.to_string()));
+        params.push((format!("{}_asset_issuer", prefix), issuer.to_string()));
+    }
+    params
+}
+
+fn path_asset(asset: PathAsset) -> String {
+    match (asset.asset_code, asset.asset_issuer) {
+        (Some(code), Some(issuer)) if asset.asset_type != "native" => {
+            format!("{}:{}", code, issuer)
+        }
+        _ => "native".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stellar::asset::resolve_asset;
+    use std::collections::HashMap;
+
+    const ISSUER: &str = "GA7QYNF7SOWQ3GLR2BGMZEHXAVIRZA4KVWLTJJFC7MGXUA74P7UJVSGZ";
+
+    #[test]
+    fn test_asset_params() {
+        assert_eq!(
+            asset_params("source", &Asset::Native),
+            vec![("source_asset_type".to_string(), "native".to_string())]
+        );
+
+        let usdc = resolve_asset(&format!("USDC:{}", ISSUER), &HashMap::new()).unwrap();
+        assert_eq!(
+            asset_params("destination", &usdc),
+            [
+                ("destination_asset_type", "credit_alphanum4"),
+                ("destination_asset_code", "USDC"),
+                ("destination_asset_issuer", ISSUER),
+            ]
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_paths_page() {
+        let body = serde_json::json!({
+            "_embedded": { "records": [{
+                "source_asset_type": "native",
+                "source_amount": "100.0000000",
+                "destination_asset_type": "credit_alphanum4",
+                "destination_asset_code": "USDC",
+                "destination_asset_issuer": ISSUER,
+                "destination_amount": "12.5000000",
+                "path": [{ "asset_type": "credit_alphanum4", "asset_code": "EURC", "asset_issuer": ISSUER }],
+            }]}
+        });
+        let page: Page<PathRecord> = serde_json::from_value(body).unwrap();
+        let record = page.embedded.records.into_iter().next().unwrap();
+
+        assert_eq!(parse_stroops(&record.source_amount).unwrap(), 1_000_000_000);
+        assert_eq!(
+            parse_stroops(&record.destination_amount).unwrap(),
+            125_000_000
+        );
+        assert_eq!(
+            record.path.into_iter().map(path_asset).collect::<Vec<_>>(),
+            vec![format!("EURC:{}", ISSUER)]
+        );
+    }
+}
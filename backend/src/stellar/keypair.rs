@@ -0,0 +1,117 @@
+//! Ed25519 keypairs for Stellar accounts
+
+use ring::{
+    rand::{SecureRandom, SystemRandom},
+    signature::{self, Ed25519KeyPair, KeyPair},
+};
+
+use super::strkey::{self, StrKeyError};
+use crate::api_error::ApiError;
+
+/// An ed25519 keypair identified by its raw 32-byte seed
+pub struct Keypair {
+    seed: [u8; 32],
+    key_pair: Ed25519KeyPair,
+}
+
+impl Keypair {
+    /// Generate a new random keypair
+    pub fn random() -> Result<Self, ApiError> {
+        let mut seed = [0u8; 32];
+        SystemRandom::new()
+            .fill(&mut seed)
+            .map_err(|_| ApiError::InternalServerError)?;
+        Self::from_seed(seed)
+    }
+
+    /// Build a keypair from a raw 32-byte seed
+    pub fn from_seed(seed: [u8; 32]) -> Result<Self, ApiError> {
+        let key_pair = Ed25519KeyPair::from_seed_unchecked(&seed)
+            .map_err(|_| ApiError::InternalServerError)?;
+        Ok(Self { seed, key_pair })
+    }
+
+    /// Build a keypair from an `S...` secret seed
+    pub fn from_secret_seed(secret: &str) -> Result<Self, ApiError> {
+        let seed = strkey::decode_secret_seed(secret).map_err(|e: StrKeyError| {
+            ApiError::Validation(format!("Invalid secret seed: {}", e))
+        })?;
+        Self::from_seed(seed)
+    }
+
+    pub fn public_key(&self) -> [u8; 32] {
+        let mut public_key = [0u8; 32];
+        public_key.copy_from_slice(self.key_pair.public_key().as_ref());
+        public_key
+    }
+
+    /// The `G...` account address for this keypair
+    pub fn address(&self) -> String {
+        strkey::encode_account_id(&self.public_key())
+    }
+
+    /// The `S...` secret seed for this keypair
+    pub fn secret_seed(&self) -> String {
+        strkey::encode_secret_seed(&self.seed)
+    }
+
+    /// Last four bytes of the public key, used as the hint on decorated signatures
+    pub fn signature_hint(&self) -> [u8; 4] {
+        let public_key = self.public_key();
+        let mut hint = [0u8; 4];
+        hint.copy_from_slice(&public_key[28..]);
+        hint
+    }
+
+    pub fn sign(&self, message: &[u8]) -> [u8; 64] {
+        let mut sig = [0u8; 64];
+        sig.copy_from_slice(self.key_pair.sign(message).as_ref());
+        sig
+    }
+}
+
+impl Clone for Keypair {
+    fn clone(&self) -> Self {
+        Self::from_seed(self.seed).expect("seed was already validated")
+    }
+}
+
+impl std::fmt::Debug for Keypair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Keypair")
+            .field("address", &self.address())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Verify an ed25519 signature against a raw public key
+pub fn verify_signature(public_key: &[u8; 32], message: &[u8], sig: &[u8]) -> bool {
+    signature::UnparsedPublicKey::new(&signature::ED25519, public_key)
+        .verify(message, sig)
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_keypair_round_trips_through_secret_seed() {
+        let keypair = Keypair::random().unwrap();
+        assert!(keypair.address().starts_with('G'));
+        assert!(keypair.secret_seed().starts_with('S'));
+
+        let restored = Keypair::from_secret_seed(&keypair.secret_seed()).unwrap();
+        assert_eq!(restored.address(), keypair.address());
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let keypair = Keypair::from_seed([7u8; 32]).unwrap();
+        let sig = keypair.sign(b"zaps");
+
+        assert!(verify_signature(&keypair.public_key(), b"zaps", &sig));
+        assert!(!verify_signature(&keypair.public_key(), b"zapz", &sig));
+        assert_eq!(keypair.signature_hint(), keypair.public_key()[28..]);
+    }
+}
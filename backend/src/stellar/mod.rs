@@ -0,0 +1,18 @@
+//! Stellar primitives shared by the services: StrKey addresses, keypairs,
+//! custodial key storage, transaction XDR, SEP-10 challenges, SEP-7 request
+//! URIs and the Soroban RPC and Horizon clients.
+
+pub mod asset;
+pub mod contract_error;
+pub mod custody;
+pub mod horizon;
+pub mod keypair;
+pub mod rpc;
+pub mod scval;
+pub mod sep10;
+pub mod sep7;
+pub mod strkey;
+pub mod transaction;
+
+pub use keypair::Keypair;
+pub use strkey::{validate_account_address, validate_account_or_contract_address, StrKeyError};
@@ -0,0 +1,250 @@
+//! JSON-RPC client for the Soroban RPC server
+//!
+//! Only the endpoints the backend needs are covered. Responses keep XDR fields
+//! as base64 strings; decoding is left to the callers that need them.
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::api_error::ApiError;
+
+pub struct SorobanRpcClient {
+    http: reqwest::Client,
+    rpc_url: String,
+    next_id: AtomicU64,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcErrorObject>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcErrorObject {
+    code: i64,
+    message: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulateHostFunctionResult {
+    #[serde(default)]
+    pub auth: Vec<String>,
+    pub xdr: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulateTransactionResponse {
+    pub latest_ledger: u32,
+    pub min_resource_fee: Option<String>,
+    pub transaction_data: Option<String>,
+    #[serde(default)]
+    pub results: Vec<SimulateHostFunctionResult>,
+    /// Set when the invocation failed during simulation
+    pub error: Option<String>,
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SendTransactionResponse {
+    /// `PENDING`, `DUPLICATE`, `TRY_AGAIN_LATER` or `ERROR`
+    pub status: String,
+    pub hash: String,
+    pub latest_ledger: u32,
+    pub error_result_xdr: Option<String>,
+    #[serde(default)]
+    pub diagnostic_events_xdr: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetTransactionResponse {
+    /// `SUCCESS`, `FAILED` or `NOT_FOUND`
+    pub status: String,
+    pub latest_ledger: u32,
+    pub ledger: Option<u32>,
+    pub created_at: Option<String>,
+    pub envelope_xdr: Option<String>,
+    pub result_xdr: Option<String>,
+    pub result_meta_xdr: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetLatestLedgerResponse {
+    pub id: String,
+    pub protocol_version: u32,
+    pub sequence: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerEntryResult {
+    /// Base64 `LedgerKey`
+    pub key: String,
+    /// Base64 `LedgerEntryData`
+    pub xdr: String,
+    pub last_modified_ledger_seq: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetLedgerEntriesResponse {
+    #[serde(default)]
+    pub entries: Vec<LedgerEntryResult>,
+    pub latest_ledger: u32,
+}
+
+/// Filter for `getEvents`; topics are base64 `ScVal`s, with `*` as a wildcard segment
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventFilter {
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub contract_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub topics: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventInfo {
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub ledger: u32,
+    pub ledger_closed_at: String,
+    pub contract_id: String,
+    pub id: String,
+    pub paging_token: Option<String>,
+    pub topic: Vec<String>,
+    pub value: String,
+    #[serde(default)]
+    pub in_successful_contract_call: bool,
+    pub tx_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetEventsResponse {
+    pub events: Vec<EventInfo>,
+    pub latest_ledger: u32,
+    pub cursor: Option<String>,
+}
+
+/// Where a `getEvents` page starts: a ledger for the first page, a cursor afterwards
+#[derive(Debug, Clone)]
+pub enum EventsStart {
+    Ledger(u32),
+    Cursor(String),
+}
+
+impl SorobanRpcClient {
+    pub fn new(rpc_url: String) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            rpc_url,
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    pub fn rpc_url(&self) -> &str {
+        &self.rpc_url
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, ApiError> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": self.next_id.fetch_add(1, Ordering::Relaxed),
+            "method": method,
+            "params": params,
+        });
+
+        let response = self
+            .http
+            .post(&self.rpc_url)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| ApiError::Stellar(format!("{} request failed: {}", method, e)))?;
+
+        if !response.status().is_success() {
+            return Err(ApiError::Stellar(format!(
+                "{} returned HTTP {}",
+                method,
+                response.status()
+            )));
+        }
+
+        let body: RpcResponse<T> = response
+            .json()
+            .await
+            .map_err(|e| ApiError::Stellar(format!("Invalid {} response: {}", method, e)))?;
+
+        match (body.result, body.error) {
+            (_, Some(err)) => Err(ApiError::Stellar(format!(
+                "{} failed ({}): {}",
+                method, err.code, err.message
+            ))),
+            (Some(result), None) => Ok(result),
+            (None, None) => Err(ApiError::Stellar(format!("{} returned no result", method))),
+        }
+    }
+
+    pub async fn simulate_transaction(
+        &self,
+        tx_xdr: &str,
+    ) -> Result<SimulateTransactionResponse, ApiError> {
+        self.call("simulateTransaction", json!({ "transaction": tx_xdr }))
+            .await
+    }
+
+    pub async fn send_transaction(
+        &self,
+        tx_xdr: &str,
+    ) -> Result<SendTransactionResponse, ApiError> {
+        self.call("sendTransaction", json!({ "transaction": tx_xdr }))
+            .await
+    }
+
+    pub async fn get_transaction(&self, hash: &str) -> Result<GetTransactionResponse, ApiError> {
+        self.call("getTransaction", json!({ "hash": hash })).await
+    }
+
+    pub async fn get_latest_ledger(&self) -> Result<GetLatestLedgerResponse, ApiError> {
+        self.call("getLatestLedger", json!({})).await
+    }
+
+    /// Fetch ledger entries by base64 `LedgerKey`; missing entries are simply absent
+    pub async fn get_ledger_entries(
+        &self,
+        keys: &[String],
+    ) -> Result<GetLedgerEntriesResponse, ApiError> {
+        self.call("getLedgerEntries", json!({ "keys": keys })).await
+    }
+
+    pub async fn get_events(
+        &self,
+        start: EventsStart,
+        filters: &[EventFilter],
+        limit: u32,
+    ) -> Result<GetEventsResponse, ApiError> {
+        let params = match start {
+            EventsStart::Ledger(ledger) => json!({
+                "startLedger": ledger,
+                "filters": filters,
+                "pagination": { "limit": limit },
+            }),
+            EventsStart::Cursor(cursor) => json!({
+                "filters": filters,
+                "pagination": { "cursor": cursor, "limit": limit },
+            }),
+        };
+
+        self.call("getEvents", params).await
+    }
+}
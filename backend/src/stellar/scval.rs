@@ -0,0 +1,294 @@
+//! Conversion of JSON contract arguments into Soroban `ScVal`s
+//!
+//! Arguments are tagged objects so the intended Soroban type is never guessed:
+//!
+//! ```json
+//! [
+//!   { "type": "address", "value": "GA7QYNF7SOWQ3GLR2BGMZEHXAVIRZA4KVWLTJJFC7MGXUA74P7UJVSGZ" },
+//!   { "type": "bytes", "value": "6d65726368616e745f616263" },
+//!   { "type": "i128", "value": "10000000" },
+//!   { "type": "vec", "value": [{ "type": "u32", "value": 1 }] },
+//!   { "type": "map", "value": [{ "key": { "type": "symbol", "value": "a" }, "value": { "type": "bool", "value": true } }] }
+//! ]
+//! ```
+
+use serde_json::Value;
+use soroban_sdk::xdr::{
+    AccountId, Hash, Int128Parts, Limits, PublicKey, ReadXdr, ScAddress, ScBytes, ScMap,
+    ScMapEntry, ScString, ScSymbol, ScVal, ScVec, UInt128Parts, Uint256,
+};
+
+use super::strkey;
+use crate::api_error::ApiError;
+
+fn invalid(message: impl Into<String>) -> ApiError {
+    ApiError::Validation(format!("Invalid contract argument: {}", message.into()))
+}
+
+/// Convert a `G...` or `C...` address into an `ScAddress`
+pub fn sc_address(address: &str) -> Result<ScAddress, ApiError> {
+    match address.chars().next() {
+        Some('C') => strkey::decode_contract(address)
+            .map(|id| ScAddress::Contract(Hash(id)))
+            .map_err(|e| invalid(format!("address {}: {}", address, e))),
+        _ => strkey::decode_account_id(address)
+            .map(|key| ScAddress::Account(AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(key)))))
+            .map_err(|e| invalid(format!("address {}: {}", address, e))),
+    }
+}
+
+/// Render an `ScAddress` back into its StrKey form
+pub fn sc_address_to_string(address: &ScAddress) -> String {
+    match address {
+        ScAddress::Account(AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(key)))) => {
+            strkey::encode_account_id(key)
+        }
+        ScAddress::Contract(Hash(id)) => strkey::encode_contract(id),
+    }
+}
+
+pub fn i128_to_scval(value: i128) -> ScVal {
+    ScVal::I128(Int128Parts {
+        hi: (value >> 64) as i64,
+        lo: value as u64,
+    })
+}
+
+pub fn scval_to_i128(value: &ScVal) -> Option<i128> {
+    match value {
+        ScVal::I128(parts) => Some(((parts.hi as i128) << 64) | parts.lo as i128),
+        _ => None,
+    }
+}
+
+pub fn scval_to_symbol(value: &ScVal) -> Option<String> {
+    match value {
+        ScVal::Symbol(ScSymbol(symbol)) => Some(symbol.to_utf8_string_lossy()),
+        _ => None,
+    }
+}
+
+pub fn scval_to_bytes(value: &ScVal) -> Option<&[u8]> {
+    match value {
+        ScVal::Bytes(ScBytes(bytes)) => Some(bytes.as_slice()),
+        _ => None,
+    }
+}
+
+pub fn scval_to_address(value: &ScVal) -> Option<String> {
+    match value {
+        ScVal::Address(address) => Some(sc_address_to_string(address)),
+        _ => None,
+    }
+}
+
+/// Field of a `#[contracttype]` struct, which is encoded as a map keyed by field-name symbols
+pub fn struct_field<'a>(value: &'a ScVal, name: &str) -> Option<&'a ScVal> {
+    let ScVal::Map(Some(ScMap(entries))) = value else {
+        return None;
+    };
+    entries
+        .iter()
+        .find(|entry| scval_to_symbol(&entry.key).as_deref() == Some(name))
+        .map(|entry| &entry.val)
+}
+
+/// Decode a base64 `ScVal`, as `getEvents` returns topics and values
+pub fn from_base64(encoded: &str) -> Option<ScVal> {
+    ScVal::from_xdr_base64(encoded, Limits::none()).ok()
+}
+
+pub fn symbol(value: &str) -> Result<ScVal, ApiError> {
+    Ok(ScVal::Symbol(ScSymbol(
+        value.try_into().map_err(|_| invalid("symbol too long"))?,
+    )))
+}
+
+pub fn bytes(value: &[u8]) -> Result<ScVal, ApiError> {
+    Ok(ScVal::Bytes(ScBytes(
+        value
+            .to_vec()
+            .try_into()
+            .map_err(|_| invalid("bytes too long"))?,
+    )))
+}
+
+/// Convert a list of tagged JSON arguments
+pub fn json_args_to_scvals(args: &[Value]) -> Result<Vec<ScVal>, ApiError> {
+    args.iter().map(json_to_scval).collect()
+}
+
+/// Convert one tagged JSON argument
+pub fn json_to_scval(arg: &Value) -> Result<ScVal, ApiError> {
+    let arg_type = arg
+        .get("type")
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid("missing \"type\""))?;
+    let value = arg.get("value").unwrap_or(&Value::Null);
+
+    let scval = match arg_type {
+        "void" => ScVal::Void,
+        "bool" => ScVal::Bool(value.as_bool().ok_or_else(|| invalid("expected bool"))?),
+        "u32" => ScVal::U32(
+            as_integer::<u64>(value)?
+                .try_into()
+                .map_err(|_| invalid("u32 out of range"))?,
+        ),
+        "i32" => ScVal::I32(
+            as_integer::<i64>(value)?
+                .try_into()
+                .map_err(|_| invalid("i32 out of range"))?,
+        ),
+        "u64" => ScVal::U64(as_integer(value)?),
+        "i64" => ScVal::I64(as_integer(value)?),
+        "u128" => {
+            let v: u128 = as_integer(value)?;
+            ScVal::U128(UInt128Parts {
+                hi: (v >> 64) as u64,
+                lo: v as u64,
+            })
+        }
+        "i128" => i128_to_scval(as_integer(value)?),
+        "symbol" => symbol(as_str(value)?)?,
+        "string" => ScVal::String(ScString(
+            as_str(value)?
+                .try_into()
+                .map_err(|_| invalid("string too long"))?,
+        )),
+        "bytes" => bytes(&hex_decode(as_str(value)?)?)?,
+        "address" => ScVal::Address(sc_address(as_str(value)?)?),
+        "vec" => {
+            let items = value
+                .as_array()
+                .ok_or_else(|| invalid("expected array for vec"))?
+                .iter()
+                .map(json_to_scval)
+                .collect::<Result<Vec<_>, _>>()?;
+            ScVal::Vec(Some(ScVec(
+                items.try_into().map_err(|_| invalid("vec too long"))?,
+            )))
+        }
+        "map" => {
+            let entries = value
+                .as_array()
+                .ok_or_else(|| invalid("expected array of entries for map"))?
+                .iter()
+                .map(|entry| {
+                    Ok(ScMapEntry {
+                        key: json_to_scval(entry.get("key").unwrap_or(&Value::Null))?,
+                        val: json_to_scval(entry.get("value").unwrap_or(&Value::Null))?,
+                    })
+                })
+                .collect::<Result<Vec<_>, ApiError>>()?;
+            ScVal::Map(Some(ScMap(
+                entries.try_into().map_err(|_| invalid("map too long"))?,
+            )))
+        }
+        other => return Err(invalid(format!("unsupported type \"{}\"", other))),
+    };
+
+    Ok(scval)
+}
+
+fn as_str(value: &Value) -> Result<&str, ApiError> {
+    value.as_str().ok_or_else(|| invalid("expected string"))
+}
+
+/// Integers may be JSON numbers or decimal strings (needed for 128-bit values)
+fn as_integer<T: std::str::FromStr>(value: &Value) -> Result<T, ApiError> {
+    let text = match value {
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.clone(),
+        _ => return Err(invalid("expected integer")),
+    };
+    text.parse()
+        .map_err(|_| invalid(format!("invalid integer {}", text)))
+}
+
+fn hex_decode(value: &str) -> Result<Vec<u8>, ApiError> {
+    if !value.len().is_multiple_of(2) {
+        return Err(invalid("hex string has odd length"));
+    }
+    // Decoded from bytes, as a multi-byte character would split a `str` slice
+    value
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| invalid("invalid hex"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_i128_round_trip() {
+        for v in [0i128, 1, -1, i128::MAX, i128::MIN, 10_000_000] {
+            assert_eq!(scval_to_i128(&i128_to_scval(v)), Some(v));
+        }
+    }
+
+    #[test]
+    fn test_json_args() {
+        let args = json_args_to_scvals(&[
+            json!({ "type": "address", "value": "GA7QYNF7SOWQ3GLR2BGMZEHXAVIRZA4KVWLTJJFC7MGXUA74P7UJVSGZ" }),
+            json!({ "type": "bytes", "value": "6d65726368616e74" }),
+            json!({ "type": "i128", "value": "-170141183460469231731687303715884105728" }),
+            json!({ "type": "u32", "value": 7 }),
+        ])
+        .unwrap();
+
+        assert!(matches!(args[0], ScVal::Address(ScAddress::Account(_))));
+        assert_eq!(args[1], bytes(b"merchant").unwrap());
+        assert_eq!(scval_to_i128(&args[2]), Some(i128::MIN));
+        assert_eq!(args[3], ScVal::U32(7));
+    }
+
+    #[test]
+    fn test_invalid_args_are_rejected() {
+        assert!(json_to_scval(&json!({ "value": 1 })).is_err());
+        assert!(json_to_scval(&json!({ "type": "u32", "value": -1 })).is_err());
+        assert!(json_to_scval(&json!({ "type": "address", "value": "GEXAMPLE" })).is_err());
+        assert!(json_to_scval(&json!({ "type": "bytes", "value": "abc" })).is_err());
+        assert!(json_to_scval(&json!({ "type": "bytes", "value": "aéa" })).is_err());
+        assert!(json_to_scval(&json!({ "type": "bytes", "value": "éé" })).is_err());
+        assert!(json_to_scval(&json!({ "type": "float", "value": 1.5 })).is_err());
+    }
+
+    #[test]
+    fn test_struct_fields() {
+        let event = json_to_scval(&json!({ "type": "map", "value": [
+            { "key": { "type": "symbol", "value": "amount" }, "value": { "type": "i128", "value": 250 } },
+            { "key": { "type": "symbol", "value": "merchant_id" }, "value": { "type": "bytes", "value": "6d65726368616e74" } },
+        ] }))
+        .unwrap();
+
+        assert_eq!(
+            struct_field(&event, "amount").and_then(scval_to_i128),
+            Some(250)
+        );
+        assert_eq!(
+            struct_field(&event, "merchant_id").and_then(scval_to_bytes),
+            Some(&b"merchant"[..])
+        );
+        assert!(struct_field(&event, "payer").is_none());
+        assert!(struct_field(&ScVal::Void, "amount").is_none());
+    }
+
+    #[test]
+    fn test_address_round_trip() {
+        let account = "GA7QYNF7SOWQ3GLR2BGMZEHXAVIRZA4KVWLTJJFC7MGXUA74P7UJVSGZ";
+        let contract = "CAAACAQDAQCQMBYIBEFAWDANBYHRAEISCMKBKFQXDAMRUGY4DUPB6N4O";
+        assert_eq!(sc_address_to_string(&sc_address(account).unwrap()), account);
+        assert_eq!(
+            sc_address_to_string(&sc_address(contract).unwrap()),
+            contract
+        );
+    }
+}
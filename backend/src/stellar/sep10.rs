@@ -0,0 +1,274 @@
+//! SEP-10 web authentication challenges
+//!
+//! A challenge is a transaction with sequence number 0, so it can never be
+//! submitted, signed by the anchor's `SIGNING_KEY`. The client checks who
+//! issued it and for which domain, then countersigns it to prove control of
+//! its account.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use ring::rand::{SecureRandom, SystemRandom};
+use soroban_sdk::xdr::{
+    DataValue, ManageDataOp, MuxedAccount, Operation, OperationBody, Preconditions, String64,
+    TimeBounds, TimePoint, TransactionEnvelope,
+};
+
+use super::{keypair::verify_signature, strkey, transaction, Keypair};
+use crate::api_error::ApiError;
+
+/// How far a challenge's `min_time` may be ahead of our clock, in seconds
+const CLOCK_SKEW_SECS: u64 = 300;
+
+/// What a challenge must match to be accepted
+pub struct ChallengeParams<'a> {
+    /// The anchor's `SIGNING_KEY` (`G...`)
+    pub server_key: &'a str,
+    /// Account the client is authenticating as
+    pub client_account: &'a str,
+    pub home_domain: &'a str,
+    /// Host serving the web authentication endpoint
+    pub web_auth_domain: &'a str,
+    pub network_passphrase: &'a str,
+}
+
+fn invalid(reason: impl std::fmt::Display) -> ApiError {
+    ApiError::Anchor(format!("Invalid SEP-10 challenge: {}", reason))
+}
+
+/// Decode and check a challenge, returning it ready to be countersigned
+///
+/// `now` is the current Unix time in seconds.
+pub fn verify_challenge(
+    xdr: &str,
+    params: &ChallengeParams<'_>,
+    now: u64,
+) -> Result<TransactionEnvelope, ApiError> {
+    let envelope = transaction::decode_envelope(xdr).map_err(invalid)?;
+    let TransactionEnvelope::Tx(v1) = &envelope else {
+        return Err(invalid("not a v1 transaction envelope"));
+    };
+    let tx = &v1.tx;
+
+    let server = transaction::muxed_account(params.server_key).map_err(invalid)?;
+    let client = transaction::muxed_account(params.client_account).map_err(invalid)?;
+
+    if tx.source_account != server {
+        return Err(invalid("source account is not the anchor's signing key"));
+    }
+    if tx.seq_num.0 != 0 {
+        return Err(invalid("sequence number is not zero"));
+    }
+
+    let time_bounds = match &tx.cond {
+        Preconditions::Time(bounds) => Some(bounds),
+        Preconditions::V2(conditions) => conditions.time_bounds.as_ref(),
+        Preconditions::None => None,
+    }
+    .ok_or_else(|| invalid("missing time bounds"))?;
+    if time_bounds.max_time.0 == 0
+        || now > time_bounds.max_time.0
+        || now + CLOCK_SKEW_SECS < time_bounds.min_time.0
+    {
+        return Err(invalid("expired or not yet valid"));
+    }
+
+    if tx.operations.is_empty() {
+        return Err(invalid("no operations"));
+    }
+    let auth_name = format!("{} auth", params.home_domain);
+    for (index, operation) in tx.operations.iter().enumerate() {
+        let OperationBody::ManageData(data) = &operation.body else {
+            return Err(invalid("operations must all be manage_data"));
+        };
+        let name = data.data_name.0.to_utf8_string_lossy();
+        let value = data.data_value.as_ref().map(|value| value.0.as_slice());
+
+        if index == 0 {
+            if operation.source_account.as_ref() != Some(&client) {
+                return Err(invalid(
+                    "first operation is not sourced by the client account",
+                ));
+            }
+            if name != auth_name {
+                return Err(invalid(format!("unexpected home domain key {}", name)));
+            }
+            if value.map_or(0, <[u8]>::len) != 64 {
+                return Err(invalid("nonce must be 64 bytes"));
+            }
+            continue;
+        }
+
+        if operation.source_account.as_ref() != Some(&server) {
+            return Err(invalid(
+                "operations after the first must be sourced by the anchor",
+            ));
+        }
+        if name == "web_auth_domain" && value != Some(params.web_auth_domain.as_bytes()) {
+            return Err(invalid("web_auth_domain does not match the endpoint"));
+        }
+    }
+
+    let server_key = strkey::decode_account_id(params.server_key).map_err(invalid)?;
+    let hash = transaction::transaction_hash(tx, params.network_passphrase)?;
+    let signed_by_server = v1.signatures.iter().any(|signature| {
+        signature.hint.0 == server_key[28..]
+            && verify_signature(&server_key, &hash, &signature.signature)
+    });
+    if !signed_by_server {
+        return Err(invalid("missing a valid signature from the anchor"));
+    }
+
+    Ok(envelope)
+}
+
+/// Build a signed challenge as a SEP-10 server would, valid for `valid_secs` from `now`
+pub fn build_challenge(
+    server: &Keypair,
+    client_account: &str,
+    home_domain: &str,
+    web_auth_domain: &str,
+    network_passphrase: &str,
+    now: u64,
+    valid_secs: u64,
+) -> Result<String, ApiError> {
+    // 48 random bytes, base64 encoded to the 64-byte nonce the spec requires
+    let mut random = [0u8; 48];
+    SystemRandom::new()
+        .fill(&mut random)
+        .map_err(|_| ApiError::InternalServerError)?;
+    let nonce = BASE64.encode(random);
+
+    let operations = vec![
+        manage_data(
+            transaction::muxed_account(client_account)?,
+            &format!("{} auth", home_domain),
+            nonce.as_bytes(),
+        )?,
+        manage_data(
+            transaction::muxed_account(&server.address())?,
+            "web_auth_domain",
+            web_auth_domain.as_bytes(),
+        )?,
+    ];
+
+    let mut envelope = transaction::build_transaction(
+        &server.address(),
+        -1,
+        transaction::BASE_FEE,
+        now + valid_secs,
+        operations,
+    )?;
+    if let TransactionEnvelope::Tx(v1) = &mut envelope {
+        v1.tx.cond = Preconditions::Time(TimeBounds {
+            min_time: TimePoint(now),
+            max_time: TimePoint(now + valid_secs),
+        });
+    }
+    transaction::sign_envelope(&mut envelope, server, network_passphrase)?;
+
+    transaction::encode_envelope(&envelope)
+}
+
+fn manage_data(source: MuxedAccount, name: &str, value: &[u8]) -> Result<Operation, ApiError> {
+    let invalid_data = |e| ApiError::Validation(format!("Invalid manage_data entry: {}", e));
+    Ok(Operation {
+        source_account: Some(source),
+        body: OperationBody::ManageData(ManageDataOp {
+            data_name: String64(name.try_into().map_err(invalid_data)?),
+            data_value: Some(DataValue(value.to_vec().try_into().map_err(invalid_data)?)),
+        }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TESTNET: &str = "Test SDF Network ; September 2015";
+    const NOW: u64 = 1_767_225_600;
+
+    fn params<'a>(server: &'a str, client: &'a str) -> ChallengeParams<'a> {
+        ChallengeParams {
+            server_key: server,
+            client_account: client,
+            home_domain: "anchor.example.com",
+            web_auth_domain: "auth.anchor.example.com",
+            network_passphrase: TESTNET,
+        }
+    }
+
+    fn challenge(server: &Keypair, client: &str) -> String {
+        build_challenge(
+            server,
+            client,
+            "anchor.example.com",
+            "auth.anchor.example.com",
+            TESTNET,
+            NOW,
+            900,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_accepts_valid_challenge() {
+        let server = Keypair::from_seed([1u8; 32]).unwrap();
+        let client = Keypair::from_seed([2u8; 32]).unwrap();
+        let xdr = challenge(&server, &client.address());
+
+        let envelope = verify_challenge(
+            &xdr,
+            &params(&server.address(), &client.address()),
+            NOW + 10,
+        )
+        .unwrap();
+        assert_eq!(transaction::envelope_tx(&envelope).unwrap().seq_num.0, 0);
+    }
+
+    #[test]
+    fn test_rejects_other_signing_key() {
+        let server = Keypair::from_seed([1u8; 32]).unwrap();
+        let impostor = Keypair::from_seed([3u8; 32]).unwrap();
+        let client = Keypair::from_seed([2u8; 32]).unwrap();
+        let xdr = challenge(&impostor, &client.address());
+
+        assert!(
+            verify_challenge(&xdr, &params(&server.address(), &client.address()), NOW).is_err()
+        );
+    }
+
+    #[test]
+    fn test_rejects_expired_challenge_and_wrong_domains() {
+        let server = Keypair::from_seed([1u8; 32]).unwrap();
+        let client = Keypair::from_seed([2u8; 32]).unwrap();
+        let (server_address, client_address) = (server.address(), client.address());
+        let xdr = challenge(&server, &client_address);
+        let expected = params(&server_address, &client_address);
+
+        assert!(verify_challenge(&xdr, &expected, NOW + 901).is_err());
+        assert!(verify_challenge(&xdr, &expected, NOW - CLOCK_SKEW_SECS - 1).is_err());
+
+        let other_home = ChallengeParams {
+            home_domain: "evil.example.com",
+            ..params(&server_address, &client_address)
+        };
+        assert!(verify_challenge(&xdr, &other_home, NOW).is_err());
+
+        let other_auth = ChallengeParams {
+            web_auth_domain: "evil.example.com",
+            ..params(&server_address, &client_address)
+        };
+        assert!(verify_challenge(&xdr, &other_auth, NOW).is_err());
+    }
+
+    #[test]
+    fn test_rejects_challenge_for_another_client() {
+        let server = Keypair::from_seed([1u8; 32]).unwrap();
+        let client = Keypair::from_seed([2u8; 32]).unwrap();
+        let other = Keypair::from_seed([4u8; 32]).unwrap();
+        let xdr = challenge(&server, &other.address());
+
+        assert!(
+            verify_challenge(&xdr, &params(&server.address(), &client.address()), NOW).is_err()
+        );
+    }
+}
@@ -0,0 +1,156 @@
+//! SEP-7 `web+stellar:` request URIs
+//!
+//! A signed URI ends with a `signature` parameter: the origin's
+//! `URI_REQUEST_SIGNING_KEY` signature over the rest of the URI, prefixed as
+//! SEP-7 describes so the signature cannot be mistaken for a transaction's.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use reqwest::Url;
+
+use super::{keypair::verify_signature, Keypair};
+use crate::api_error::ApiError;
+
+pub const SCHEME: &str = "web+stellar";
+
+/// Text signed together with the URI
+const SIGNATURE_PREFIX: &[u8] = b"stellar.sep.7 - URI Scheme";
+
+/// A request URI with its parameters decoded, in order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestUri {
+    /// `pay` or `tx`
+    pub operation: String,
+    pub params: Vec<(String, String)>,
+}
+
+impl RequestUri {
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Build an unsigned `web+stellar:{operation}` URI, percent-encoding the parameters
+pub fn build(operation: &str, params: &[(&str, String)]) -> String {
+    let mut url =
+        Url::parse(&format!("{}:{}", SCHEME, operation)).expect("a scheme and path always parse");
+    url.query_pairs_mut()
+        .extend_pairs(params.iter().map(|(key, value)| (*key, value.as_str())));
+    url.to_string()
+}
+
+/// Append the origin's `signature` to an unsigned URI
+pub fn sign(uri: &str, keypair: &Keypair) -> String {
+    let signature = BASE64.encode(keypair.sign(&signing_payload(uri)));
+    let mut url = Url::parse(uri).expect("built URIs parse");
+    url.query_pairs_mut().append_pair("signature", &signature);
+    url.to_string()
+}
+
+/// Decode a request URI
+pub fn parse(uri: &str) -> Result<RequestUri, ApiError> {
+    let invalid = |reason: &str| ApiError::Validation(format!("Invalid SEP-7 URI: {}", reason));
+
+    let url = Url::parse(uri.trim()).map_err(|e| invalid(&e.to_string()))?;
+    if url.scheme() != SCHEME {
+        return Err(invalid("scheme must be web+stellar"));
+    }
+    let operation = url.path().to_string();
+    if operation != "pay" && operation != "tx" {
+        return Err(invalid("operation must be pay or tx"));
+    }
+
+    Ok(RequestUri {
+        operation,
+        params: url
+            .query_pairs()
+            .map(|(key, value)| (key.into_owned(), value.into_owned()))
+            .collect(),
+    })
+}
+
+/// Check a URI's trailing `signature` was made with the key `signing_key`
+pub fn verify(uri: &str, signing_key: &[u8; 32]) -> Result<(), ApiError> {
+    let invalid = || ApiError::Validation("SEP-7 URI signature is invalid".to_string());
+
+    let uri = uri.trim();
+    let (unsigned, _) = uri
+        .rsplit_once("&signature=")
+        .ok_or_else(|| ApiError::Validation("SEP-7 URI is not signed".to_string()))?;
+    let signature = parse(uri)?
+        .param("signature")
+        .map(str::to_string)
+        .ok_or_else(invalid)?;
+    let signature = BASE64.decode(signature).map_err(|_| invalid())?;
+
+    if verify_signature(signing_key, &signing_payload(unsigned), &signature) {
+        Ok(())
+    } else {
+        Err(invalid())
+    }
+}
+
+/// 35 zero bytes, then 4, then the prefix and the URI
+fn signing_payload(uri: &str) -> Vec<u8> {
+    let mut payload = vec![0u8; 36];
+    payload[35] = 4;
+    payload.extend_from_slice(SIGNATURE_PREFIX);
+    payload.extend_from_slice(uri.as_bytes());
+    payload
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DESTINATION: &str = "GBBD47IF6LWK7P7MDEVSCWR7DPUWV3NY3DTQEVFL4NAT4AQH3ZLLFLA5";
+
+    fn pay_uri() -> String {
+        build(
+            "pay",
+            &[
+                ("destination", DESTINATION.to_string()),
+                ("amount", "12.5".to_string()),
+                ("memo", "order #42 & more".to_string()),
+                ("memo_type", "MEMO_TEXT".to_string()),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_build_encodes_params() {
+        let uri = pay_uri();
+        assert!(uri.starts_with("web+stellar:pay?destination=GBBD"));
+        assert!(uri.contains("memo=order+%2342+%26+more"), "{}", uri);
+
+        let parsed = parse(&uri).unwrap();
+        assert_eq!(parsed.operation, "pay");
+        assert_eq!(parsed.param("memo"), Some("order #42 & more"));
+        assert_eq!(parsed.param("amount"), Some("12.5"));
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let keypair = Keypair::from_seed([3u8; 32]).unwrap();
+        let signed = sign(&pay_uri(), &keypair);
+
+        assert!(signed.starts_with(&pay_uri()));
+        verify(&signed, &keypair.public_key()).unwrap();
+
+        let other = Keypair::from_seed([4u8; 32]).unwrap();
+        assert!(verify(&signed, &other.public_key()).is_err());
+
+        let tampered = signed.replace("amount=12.5", "amount=1.25");
+        assert!(verify(&tampered, &keypair.public_key()).is_err());
+        assert!(verify(&pay_uri(), &keypair.public_key()).is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_other_uris() {
+        assert!(parse("https://example.com/pay?destination=G").is_err());
+        assert!(parse("web+stellar:send?destination=G").is_err());
+        assert!(parse("not a uri").is_err());
+    }
+}
@@ -0,0 +1,315 @@
+//! StrKey encoding for Stellar keys and addresses (SEP-23)
+//!
+//! A StrKey is `base32(version_byte || payload || crc16_xmodem(version_byte || payload))`
+//! with the checksum appended little-endian and no padding. Decoding is strict:
+//! the input must re-encode to exactly the same string, which rejects lowercase,
+//! padding and non-canonical trailing bits.
+
+use thiserror::Error;
+
+use crate::api_error::ApiError;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Version bytes for the key types the backend deals with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    /// `G...` ed25519 public key (account ID)
+    AccountId,
+    /// `M...` multiplexed account (ed25519 public key + 64-bit ID)
+    MuxedAccount,
+    /// `S...` ed25519 secret seed
+    SecretSeed,
+    /// `C...` Soroban contract ID
+    Contract,
+}
+
+impl Version {
+    pub fn byte(&self) -> u8 {
+        match self {
+            Version::AccountId => 6 << 3,
+            Version::MuxedAccount => 12 << 3,
+            Version::SecretSeed => 18 << 3,
+            Version::Contract => 2 << 3,
+        }
+    }
+
+    fn payload_len(&self) -> usize {
+        match self {
+            Version::MuxedAccount => 40,
+            _ => 32,
+        }
+    }
+
+    fn prefix(&self) -> char {
+        match self {
+            Version::AccountId => 'G',
+            Version::MuxedAccount => 'M',
+            Version::SecretSeed => 'S',
+            Version::Contract => 'C',
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum StrKeyError {
+    #[error("invalid length")]
+    InvalidLength,
+
+    #[error("invalid base32 encoding")]
+    InvalidEncoding,
+
+    #[error("expected a key starting with '{expected}'")]
+    InvalidVersion { expected: char },
+
+    #[error("checksum mismatch")]
+    InvalidChecksum,
+}
+
+/// Encode a raw payload as a StrKey of the given version
+pub fn encode(version: Version, payload: &[u8]) -> String {
+    let mut data = Vec::with_capacity(payload.len() + 3);
+    data.push(version.byte());
+    data.extend_from_slice(payload);
+    let checksum = crc16_xmodem(&data);
+    data.extend_from_slice(&checksum.to_le_bytes());
+    base32_encode(&data)
+}
+
+/// Decode a StrKey, checking its version byte, length and checksum
+pub fn decode(version: Version, key: &str) -> Result<Vec<u8>, StrKeyError> {
+    let expected_chars = ((version.payload_len() + 3) * 8).div_ceil(5);
+    if key.len() != expected_chars {
+        return Err(StrKeyError::InvalidLength);
+    }
+
+    let data = base32_decode(key)?;
+    if base32_encode(&data) != key {
+        return Err(StrKeyError::InvalidEncoding);
+    }
+
+    let (body, checksum) = data.split_at(data.len() - 2);
+    if body[0] != version.byte() {
+        return Err(StrKeyError::InvalidVersion {
+            expected: version.prefix(),
+        });
+    }
+    if crc16_xmodem(body).to_le_bytes() != checksum {
+        return Err(StrKeyError::InvalidChecksum);
+    }
+
+    Ok(body[1..].to_vec())
+}
+
+pub fn encode_account_id(public_key: &[u8; 32]) -> String {
+    encode(Version::AccountId, public_key)
+}
+
+pub fn decode_account_id(address: &str) -> Result<[u8; 32], StrKeyError> {
+    to_array(decode(Version::AccountId, address)?)
+}
+
+pub fn encode_secret_seed(seed: &[u8; 32]) -> String {
+    encode(Version::SecretSeed, seed)
+}
+
+pub fn decode_secret_seed(secret: &str) -> Result<[u8; 32], StrKeyError> {
+    to_array(decode(Version::SecretSeed, secret)?)
+}
+
+pub fn encode_contract(contract_id: &[u8; 32]) -> String {
+    encode(Version::Contract, contract_id)
+}
+
+pub fn decode_contract(address: &str) -> Result<[u8; 32], StrKeyError> {
+    to_array(decode(Version::Contract, address)?)
+}
+
+/// Encode a multiplexed account; the payload is the ed25519 key followed by the big-endian ID
+pub fn encode_muxed_account(public_key: &[u8; 32], id: u64) -> String {
+    let mut payload = [0u8; 40];
+    payload[..32].copy_from_slice(public_key);
+    payload[32..].copy_from_slice(&id.to_be_bytes());
+    encode(Version::MuxedAccount, &payload)
+}
+
+pub fn decode_muxed_account(address: &str) -> Result<([u8; 32], u64), StrKeyError> {
+    let payload = decode(Version::MuxedAccount, address)?;
+    let mut public_key = [0u8; 32];
+    public_key.copy_from_slice(&payload[..32]);
+    let mut id = [0u8; 8];
+    id.copy_from_slice(&payload[32..]);
+    Ok((public_key, u64::from_be_bytes(id)))
+}
+
+/// Validate an address that must identify a Stellar account (`G...` or `M...`)
+pub fn validate_account_address(field: &str, address: &str) -> Result<(), ApiError> {
+    let result = match address.chars().next() {
+        Some('M') => decode_muxed_account(address).map(|_| ()),
+        _ => decode_account_id(address).map(|_| ()),
+    };
+
+    result.map_err(|e| ApiError::Validation(format!("Invalid {}: {}", field, e)))
+}
+
+/// Validate an address that may be either an account (`G...`) or a contract (`C...`)
+pub fn validate_account_or_contract_address(field: &str, address: &str) -> Result<(), ApiError> {
+    let result = match address.chars().next() {
+        Some('C') => decode_contract(address).map(|_| ()),
+        _ => decode_account_id(address).map(|_| ()),
+    };
+
+    result.map_err(|e| ApiError::Validation(format!("Invalid {}: {}", field, e)))
+}
+
+fn to_array(payload: Vec<u8>) -> Result<[u8; 32], StrKeyError> {
+    payload.try_into().map_err(|_| StrKeyError::InvalidLength)
+}
+
+/// CRC16-XModem (polynomial 0x1021, initial value 0)
+fn crc16_xmodem(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+fn base32_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity((data.len() * 8).div_ceil(5));
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for byte in data {
+        buffer = (buffer << 8) | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+
+    out
+}
+
+fn base32_decode(input: &str) -> Result<Vec<u8>, StrKeyError> {
+    let mut out = Vec::with_capacity(input.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for c in input.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'2'..=b'7' => c - b'2' + 26,
+            _ => return Err(StrKeyError::InvalidEncoding),
+        };
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test vectors from SEP-23
+    const ACCOUNT: &str = "GA7QYNF7SOWQ3GLR2BGMZEHXAVIRZA4KVWLTJJFC7MGXUA74P7UJVSGZ";
+    const ACCOUNT_RAW: [u8; 32] = [
+        0x3f, 0x0c, 0x34, 0xbf, 0x93, 0xad, 0x0d, 0x99, 0x71, 0xd0, 0x4c, 0xcc, 0x90, 0xf7, 0x05,
+        0x51, 0x1c, 0x83, 0x8a, 0xad, 0x97, 0x34, 0xa4, 0xa2, 0xfb, 0x0d, 0x7a, 0x03, 0xfc, 0x7f,
+        0xe8, 0x9a,
+    ];
+
+    #[test]
+    fn test_account_id_round_trip() {
+        assert_eq!(encode_account_id(&ACCOUNT_RAW), ACCOUNT);
+        assert_eq!(decode_account_id(ACCOUNT).unwrap(), ACCOUNT_RAW);
+    }
+
+    #[test]
+    fn test_muxed_account_round_trip() {
+        let muxed = encode_muxed_account(&ACCOUNT_RAW, 9_223_372_036_854_775_808);
+        assert!(muxed.starts_with('M'));
+        assert_eq!(muxed.len(), 69);
+
+        let (key, id) = decode_muxed_account(&muxed).unwrap();
+        assert_eq!(key, ACCOUNT_RAW);
+        assert_eq!(id, 9_223_372_036_854_775_808);
+    }
+
+    #[test]
+    fn test_seed_and_contract_prefixes() {
+        assert!(encode_secret_seed(&ACCOUNT_RAW).starts_with('S'));
+        assert!(encode_contract(&ACCOUNT_RAW).starts_with('C'));
+        assert_eq!(
+            decode_contract(&encode_contract(&ACCOUNT_RAW)).unwrap(),
+            ACCOUNT_RAW
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_keys() {
+        // Wrong version byte
+        assert_eq!(
+            decode_secret_seed(ACCOUNT),
+            Err(StrKeyError::InvalidVersion { expected: 'S' })
+        );
+
+        // Corrupted checksum
+        let mut corrupted = ACCOUNT.to_string();
+        corrupted.replace_range(55..56, "A");
+        assert_eq!(
+            decode_account_id(&corrupted),
+            Err(StrKeyError::InvalidChecksum)
+        );
+
+        // Lowercase, truncated and padded input
+        assert!(decode_account_id(&ACCOUNT.to_lowercase()).is_err());
+        assert_eq!(
+            decode_account_id(&ACCOUNT[..55]),
+            Err(StrKeyError::InvalidLength)
+        );
+        assert!(decode_account_id(&format!("{}=", &ACCOUNT[..55])).is_err());
+
+        // The old placeholder format is rejected
+        assert!(decode_account_id("G0123456789ABCDEF0123456789ABCDEF").is_err());
+    }
+
+    #[test]
+    fn test_validate_account_address() {
+        assert!(validate_account_address("from_address", ACCOUNT).is_ok());
+        assert!(
+            validate_account_address("from_address", &encode_muxed_account(&ACCOUNT_RAW, 1))
+                .is_ok()
+        );
+
+        match validate_account_address("destination_address", "GEXAMPLE_ADDRESS") {
+            Err(ApiError::Validation(msg)) => assert!(msg.contains("destination_address")),
+            other => panic!("expected validation error, got {:?}", other),
+        }
+
+        // Contracts are not accounts
+        assert!(validate_account_address("from_address", &encode_contract(&ACCOUNT_RAW)).is_err());
+        assert!(validate_account_or_contract_address(
+            "vault_address",
+            &encode_contract(&ACCOUNT_RAW)
+        )
+        .is_ok());
+    }
+}
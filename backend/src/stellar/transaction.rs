@@ -0,0 +1,364 @@
+//! Transaction envelope construction, hashing and signing
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use ring::digest;
+use soroban_sdk::xdr::{
+    Asset, DecoratedSignature, Hash, HostFunction, InvokeContractArgs, InvokeHostFunctionOp,
+    Limits, Memo, MuxedAccount, MuxedAccountMed25519, Operation, OperationBody, PaymentOp,
+    Preconditions, ReadXdr, ScSymbol, ScVal, SequenceNumber, Signature, SignatureHint,
+    SorobanAuthorizationEntry, SorobanTransactionData, TimeBounds, TimePoint, Transaction,
+    TransactionEnvelope, TransactionExt, TransactionSignaturePayload,
+    TransactionSignaturePayloadTaggedTransaction, TransactionV1Envelope, Uint256, WriteXdr,
+};
+
+use super::{rpc::SimulateTransactionResponse, scval, strkey, Keypair};
+use crate::api_error::ApiError;
+
+/// Minimum inclusion fee per operation, in stroops
+pub const BASE_FEE: u32 = 100;
+
+fn xdr_error(context: &str, err: impl std::fmt::Display) -> ApiError {
+    ApiError::Stellar(format!("{}: {}", context, err))
+}
+
+/// Convert a `G...` or `M...` address into a `MuxedAccount`
+pub fn muxed_account(address: &str) -> Result<MuxedAccount, ApiError> {
+    let invalid = |e| ApiError::Validation(format!("Invalid source account: {}", e));
+    match address.chars().next() {
+        Some('M') => {
+            let (key, id) = strkey::decode_muxed_account(address).map_err(invalid)?;
+            Ok(MuxedAccount::MuxedEd25519(MuxedAccountMed25519 {
+                id,
+                ed25519: Uint256(key),
+            }))
+        }
+        _ => Ok(MuxedAccount::Ed25519(Uint256(
+            strkey::decode_account_id(address).map_err(invalid)?,
+        ))),
+    }
+}
+
+/// Build an unsigned envelope with a single `InvokeHostFunctionOp` calling `method` on `contract_id`
+///
+/// `sequence` is the source account's current sequence number; the transaction
+/// consumes `sequence + 1`. A `max_time` of zero leaves the transaction unbounded.
+pub fn build_invoke_contract(
+    source_account: &str,
+    sequence: i64,
+    contract_id: &str,
+    method: &str,
+    args: Vec<ScVal>,
+    fee: u32,
+    max_time: u64,
+) -> Result<TransactionEnvelope, ApiError> {
+    let contract_address = match scval::sc_address(contract_id)? {
+        address @ soroban_sdk::xdr::ScAddress::Contract(_) => address,
+        _ => {
+            return Err(ApiError::Validation(format!(
+                "Invalid contract_id: {} is not a contract address",
+                contract_id
+            )))
+        }
+    };
+
+    let operation = Operation {
+        source_account: None,
+        body: OperationBody::InvokeHostFunction(InvokeHostFunctionOp {
+            host_function: HostFunction::InvokeContract(InvokeContractArgs {
+                contract_address,
+                function_name: ScSymbol(
+                    method
+                        .try_into()
+                        .map_err(|_| ApiError::Validation("Invalid method name".to_string()))?,
+                ),
+                args: args
+                    .try_into()
+                    .map_err(|e| xdr_error("Too many contract arguments", e))?,
+            }),
+            auth: Default::default(),
+        }),
+    };
+
+    build_transaction(source_account, sequence, fee, max_time, vec![operation])
+}
+
+/// Build a memo of the given SEP-style type: `text`, `id` or `hash` (base64)
+pub fn memo(value: &str, memo_type: &str) -> Result<Memo, ApiError> {
+    match memo_type {
+        "text" => Ok(Memo::Text(value.try_into().map_err(|_| {
+            ApiError::Validation("Memo must be at most 28 bytes".to_string())
+        })?)),
+        "id" => Ok(Memo::Id(value.parse().map_err(|_| {
+            ApiError::Validation(format!("Invalid id memo: {}", value))
+        })?)),
+        "hash" => {
+            let bytes: [u8; 32] = BASE64
+                .decode(value)
+                .ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or_else(|| ApiError::Validation(format!("Invalid hash memo: {}", value)))?;
+            Ok(Memo::Hash(Hash(bytes)))
+        }
+        other => Err(ApiError::Validation(format!(
+            "Unsupported memo type: {}",
+            other
+        ))),
+    }
+}
+
+/// Build an unsigned envelope with a single classic `Payment` of `amount` stroops to `destination`
+#[allow(clippy::too_many_arguments)]
+pub fn build_payment(
+    source_account: &str,
+    sequence: i64,
+    destination: &str,
+    asset: Asset,
+    amount: i64,
+    memo: Memo,
+    fee: u32,
+    max_time: u64,
+) -> Result<TransactionEnvelope, ApiError> {
+    if amount <= 0 {
+        return Err(ApiError::Validation(
+            "Payment amount must be positive".to_string(),
+        ));
+    }
+
+    let operation = Operation {
+        source_account: None,
+        body: OperationBody::Payment(PaymentOp {
+            destination: muxed_account(destination)?,
+            asset,
+            amount,
+        }),
+    };
+
+    let mut envelope = build_transaction(source_account, sequence, fee, max_time, vec![operation])?;
+    if let TransactionEnvelope::Tx(v1) = &mut envelope {
+        v1.tx.memo = memo;
+    }
+
+    Ok(envelope)
+}
+
+/// Build an unsigned envelope carrying the given operations
+pub fn build_transaction(
+    source_account: &str,
+    sequence: i64,
+    fee: u32,
+    max_time: u64,
+    operations: Vec<Operation>,
+) -> Result<TransactionEnvelope, ApiError> {
+    let op_count = operations.len() as u32;
+    let tx = Transaction {
+        source_account: muxed_account(source_account)?,
+        fee: fee.saturating_mul(op_count.max(1)),
+        seq_num: SequenceNumber(sequence + 1),
+        cond: Preconditions::Time(TimeBounds {
+            min_time: TimePoint(0),
+            max_time: TimePoint(max_time),
+        }),
+        memo: Memo::None,
+        operations: operations
+            .try_into()
+            .map_err(|e| xdr_error("Too many operations", e))?,
+        ext: TransactionExt::V0,
+    };
+
+    Ok(TransactionEnvelope::Tx(TransactionV1Envelope {
+        tx,
+        signatures: Default::default(),
+    }))
+}
+
+/// Apply the footprint, resource fee and authorization entries from a simulation
+///
+/// The envelope must be unsigned: changing the transaction invalidates any
+/// existing signature.
+pub fn apply_simulation(
+    envelope: &mut TransactionEnvelope,
+    simulation: &SimulateTransactionResponse,
+) -> Result<(), ApiError> {
+    if let Some(error) = &simulation.error {
+        return Err(ApiError::Stellar(format!("Simulation failed: {}", error)));
+    }
+
+    let TransactionEnvelope::Tx(v1) = envelope else {
+        return Err(ApiError::Stellar(
+            "Unsupported transaction envelope type".to_string(),
+        ));
+    };
+
+    let transaction_data = simulation
+        .transaction_data
+        .as_deref()
+        .ok_or_else(|| ApiError::Stellar("Simulation returned no transaction data".to_string()))?;
+    let transaction_data =
+        SorobanTransactionData::from_xdr_base64(transaction_data, Limits::none())
+            .map_err(|e| xdr_error("Invalid simulated transaction data", e))?;
+
+    let resource_fee: u32 = simulation
+        .min_resource_fee
+        .as_deref()
+        .unwrap_or("0")
+        .parse()
+        .map_err(|e| xdr_error("Invalid simulated resource fee", e))?;
+
+    if let Some(result) = simulation.results.first() {
+        let auth: Vec<SorobanAuthorizationEntry> = result
+            .auth
+            .iter()
+            .map(|entry| SorobanAuthorizationEntry::from_xdr_base64(entry, Limits::none()))
+            .collect::<Result<_, _>>()
+            .map_err(|e| xdr_error("Invalid simulated authorization entry", e))?;
+
+        let mut operations = v1.tx.operations.to_vec();
+        if let Some(OperationBody::InvokeHostFunction(op)) =
+            operations.first_mut().map(|operation| &mut operation.body)
+        {
+            op.auth = auth
+                .try_into()
+                .map_err(|e| xdr_error("Too many authorization entries", e))?;
+        }
+        v1.tx.operations = operations
+            .try_into()
+            .map_err(|e| xdr_error("Too many operations", e))?;
+    }
+
+    v1.tx.fee = v1.tx.fee.saturating_add(resource_fee);
+    v1.tx.ext = TransactionExt::V1(transaction_data);
+
+    Ok(())
+}
+
+/// Network ID: SHA-256 of the network passphrase
+pub fn network_id(network_passphrase: &str) -> [u8; 32] {
+    let mut id = [0u8; 32];
+    id.copy_from_slice(digest::digest(&digest::SHA256, network_passphrase.as_bytes()).as_ref());
+    id
+}
+
+/// Hash of a transaction as signed on the given network
+pub fn transaction_hash(tx: &Transaction, network_passphrase: &str) -> Result<[u8; 32], ApiError> {
+    let payload = TransactionSignaturePayload {
+        network_id: Hash(network_id(network_passphrase)),
+        tagged_transaction: TransactionSignaturePayloadTaggedTransaction::Tx(tx.clone()),
+    };
+    let bytes = payload
+        .to_xdr(Limits::none())
+        .map_err(|e| xdr_error("Failed to encode signature payload", e))?;
+
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(digest::digest(&digest::SHA256, &bytes).as_ref());
+    Ok(hash)
+}
+
+/// Hash of the transaction inside an envelope, hex encoded as Stellar reports it
+pub fn envelope_hash_hex(
+    envelope: &TransactionEnvelope,
+    network_passphrase: &str,
+) -> Result<String, ApiError> {
+    let hash = transaction_hash(envelope_tx(envelope)?, network_passphrase)?;
+    Ok(hash.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Append a decorated signature from `keypair` to the envelope
+pub fn sign_envelope(
+    envelope: &mut TransactionEnvelope,
+    keypair: &Keypair,
+    network_passphrase: &str,
+) -> Result<(), ApiError> {
+    let TransactionEnvelope::Tx(v1) = envelope else {
+        return Err(ApiError::Stellar(
+            "Only v1 transaction envelopes can be signed".to_string(),
+        ));
+    };
+
+    let hash = transaction_hash(&v1.tx, network_passphrase)?;
+    let signature = DecoratedSignature {
+        hint: SignatureHint(keypair.signature_hint()),
+        signature: Signature(
+            keypair
+                .sign(&hash)
+                .to_vec()
+                .try_into()
+                .map_err(|e| xdr_error("Invalid signature", e))?,
+        ),
+    };
+
+    let mut signatures = v1.signatures.to_vec();
+    signatures.push(signature);
+    v1.signatures = signatures
+        .try_into()
+        .map_err(|e| xdr_error("Too many signatures", e))?;
+
+    Ok(())
+}
+
+/// Address of the contract invoked by the envelope's first operation, if any
+pub fn invoked_contract(envelope: &TransactionEnvelope) -> Option<String> {
+    let operation = envelope_tx(envelope).ok()?.operations.first()?;
+    match &operation.body {
+        OperationBody::InvokeHostFunction(InvokeHostFunctionOp {
+            host_function: HostFunction::InvokeContract(invoke),
+            ..
+        }) => Some(scval::sc_address_to_string(&invoke.contract_address)),
+        _ => None,
+    }
+}
+
+pub fn envelope_tx(envelope: &TransactionEnvelope) -> Result<&Transaction, ApiError> {
+    match envelope {
+        TransactionEnvelope::Tx(v1) => Ok(&v1.tx),
+        _ => Err(ApiError::Stellar(
+            "Unsupported transaction envelope type".to_string(),
+        )),
+    }
+}
+
+/// Time after which the envelope's transaction can no longer be included in a ledger
+pub fn max_time(envelope: &TransactionEnvelope) -> Result<Option<u64>, ApiError> {
+    let bounds = match &envelope_tx(envelope)?.cond {
+        Preconditions::Time(bounds) => Some(bounds),
+        Preconditions::V2(conditions) => conditions.time_bounds.as_ref(),
+        Preconditions::None => None,
+    };
+
+    // A max_time of zero leaves the transaction unbounded
+    Ok(bounds
+        .map(|bounds| bounds.max_time.0)
+        .filter(|&max_time| max_time > 0))
+}
+
+/// Stroops of `asset` the envelope's `Payment` operations send to `destination`
+pub fn paid_to(
+    envelope: &TransactionEnvelope,
+    destination: &str,
+    asset: &Asset,
+) -> Result<i64, ApiError> {
+    let destination = muxed_account(destination)?;
+
+    Ok(envelope_tx(envelope)?
+        .operations
+        .iter()
+        .filter_map(|operation| match &operation.body {
+            OperationBody::Payment(payment)
+                if payment.destination == destination && &payment.asset == asset =>
+            {
+                Some(payment.amount)
+            }
+            _ => None,
+        })
+        .sum())
+}
+
+pub fn encode_envelope(envelope: &TransactionEnvelope) -> Result<String, ApiError> {
+    envelope
+        .to_xdr_base64(Limits::none())
+        .map_err(|e| xdr_error("Failed to encode transaction envelope", e))
+}
+
+pub fn decode_envelope(xdr: &str) -> Result<TransactionEnvelope, ApiError> {
+    TransactionEnvelope::from_xdr_base64(xdr, Limits::none())
+        .map_err(|e| ApiError::Validation(format!("Invalid transaction envelope XDR: {}", e)))
+}
@@ -0,0 +1,113 @@
+//! Background tasks started alongside the HTTP server
+
+use std::{future::Future, sync::Arc, time::Duration};
+use tokio::task::JoinHandle;
+
+use crate::{
+    api_error::ApiError,
+    service::{LeaderService, ServiceContainer},
+};
+
+/// Spawn the background workers, returning their handles
+///
+/// Every instance spawns them all, but each runs only while this instance
+/// leads it, so replicas do not duplicate work.
+pub fn spawn_workers(services: Arc<ServiceContainer>) -> Vec<JoinHandle<()>> {
+    let leader = &services.leader;
+    let anchor_interval =
+        Duration::from_secs(services.config.anchor_config.poll_interval_secs.max(1));
+
+    let withdrawals = services.withdrawal.clone();
+    let deposits = services.deposit.clone();
+    let settlements = services.settlement.clone();
+    let transfers = services.transfer.clone();
+    let transfer_interval =
+        Duration::from_secs(services.config.transfers.poll_interval_secs.max(1));
+    let merchants = services.merchant.clone();
+    let registry_interval =
+        Duration::from_secs(services.config.merchants.registry_poll_interval_secs.max(1));
+    let kyc = services.kyc.clone();
+    let webhooks = services.webhook.clone();
+    let webhook_interval = Duration::from_secs(services.config.webhooks.poll_interval_secs.max(1));
+    let refunds = services.refund.clone();
+    let refund_interval = Duration::from_secs(services.config.refunds.poll_interval_secs.max(1));
+    let indexer = services.indexer.clone();
+    let indexer_interval = Duration::from_secs(services.config.indexer.poll_interval_secs.max(1));
+    let jobs = services.job.clone();
+    let job_interval = Duration::from_secs(services.config.jobs.poll_interval_secs.max(1));
+    vec![
+        spawn_poller(leader, "withdrawal status", anchor_interval, move || {
+            let withdrawals = withdrawals.clone();
+            async move { withdrawals.poll_pending().await }
+        }),
+        spawn_poller(leader, "deposit status", anchor_interval, move || {
+            let deposits = deposits.clone();
+            async move { deposits.poll_pending().await }
+        }),
+        spawn_poller(leader, "settlement status", anchor_interval, move || {
+            let settlements = settlements.clone();
+            async move { settlements.poll_pending().await }
+        }),
+        spawn_poller(leader, "transfer status", transfer_interval, move || {
+            let transfers = transfers.clone();
+            async move { transfers.poll_processing().await }
+        }),
+        spawn_poller(leader, "merchant registry", registry_interval, move || {
+            let merchants = merchants.clone();
+            async move { merchants.sync_registry().await }
+        }),
+        spawn_poller(leader, "KYC status", anchor_interval, move || {
+            let kyc = kyc.clone();
+            async move { kyc.poll_pending().await }
+        }),
+        spawn_poller(leader, "webhook delivery", webhook_interval, move || {
+            let webhooks = webhooks.clone();
+            async move { webhooks.deliver_pending().await }
+        }),
+        spawn_poller(leader, "refund processing", refund_interval, move || {
+            let refunds = refunds.clone();
+            async move { refunds.process_pending().await }
+        }),
+        spawn_poller(
+            leader,
+            "contract event indexing",
+            indexer_interval,
+            move || {
+                let indexer = indexer.clone();
+                async move { indexer.index_events().await }
+            },
+        ),
+        spawn_poller(leader, "job queue", job_interval, move || {
+            let jobs = jobs.clone();
+            async move { jobs.run_due().await }
+        }),
+    ]
+}
+
+/// Run `poll` every `interval` for the life of the process, while this instance leads it
+fn spawn_poller<F, Fut>(
+    leader: &LeaderService,
+    name: &'static str,
+    interval: Duration,
+    poll: F,
+) -> JoinHandle<()>
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<usize, ApiError>> + Send,
+{
+    let mut lock = leader.lock(name);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            if !lock.acquire().await {
+                continue;
+            }
+            if let Err(e) = poll().await {
+                tracing::error!("{} poll failed: {}", name, e);
+            }
+        }
+    })
+}
@@ -0,0 +1,155 @@
+//! SEP-10 client tests against an httpmock stand-in for the anchor's web
+//! authentication endpoint. No database connection is made.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use httpmock::prelude::*;
+use serde_json::json;
+use std::sync::Arc;
+
+use zaps_backend::{
+    api_error::ApiError,
+    config::Config,
+    db,
+    service::AnchorService,
+    stellar::{sep10, Keypair},
+};
+
+const HOME_DOMAIN: &str = "anchor.example.com";
+
+struct TestContext {
+    server: MockServer,
+    anchor_key: Keypair,
+    config: Config,
+}
+
+fn setup() -> TestContext {
+    let server = MockServer::start();
+    let anchor_key = Keypair::from_seed([9u8; 32]).unwrap();
+
+    let mut config = Config::default();
+    config.anchor_config.web_auth_url = server.url("/auth");
+    config.anchor_config.home_domain = HOME_DOMAIN.to_string();
+    config.anchor_config.signing_key = anchor_key.address();
+
+    TestContext {
+        server,
+        anchor_key,
+        config,
+    }
+}
+
+fn jwt(account: &str, expires_in: i64) -> String {
+    let claims = json!({
+        "sub": account,
+        "exp": chrono::Utc::now().timestamp() + expires_in,
+    });
+    format!(
+        "eyJhbGciOiJIUzI1NiJ9.{}.signature",
+        URL_SAFE_NO_PAD.encode(claims.to_string())
+    )
+}
+
+impl TestContext {
+    async fn anchor(&self) -> AnchorService {
+        let pool = db::create_pool(&self.config.database.url).await.unwrap();
+        AnchorService::new(Arc::new(pool), self.config.clone())
+    }
+
+    /// Mock the challenge endpoint, signing challenges with `signer`
+    fn mock_challenge(&self, signer: &Keypair, account: &str) -> httpmock::Mock<'_> {
+        let web_auth_domain = self.server.address().to_string();
+        let challenge = sep10::build_challenge(
+            signer,
+            account,
+            HOME_DOMAIN,
+            &web_auth_domain,
+            &self.config.stellar_network.passphrase,
+            chrono::Utc::now().timestamp() as u64,
+            900,
+        )
+        .unwrap();
+        let passphrase = self.config.stellar_network.passphrase.clone();
+
+        self.server.mock(|when, then| {
+            when.method(GET)
+                .path("/auth")
+                .query_param("account", account)
+                .query_param("home_domain", HOME_DOMAIN);
+            then.status(200).json_body(json!({
+                "transaction": challenge,
+                "network_passphrase": passphrase,
+            }));
+        })
+    }
+
+    fn mock_token(&self, token: &str) -> httpmock::Mock<'_> {
+        self.server.mock(|when, then| {
+            when.method(POST).path("/auth");
+            then.status(200).json_body(json!({ "token": token }));
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_authenticate_caches_token_per_account() {
+    let ctx = setup();
+    let client = Keypair::from_seed([2u8; 32]).unwrap();
+    let token = jwt(&client.address(), 3600);
+    let challenge = ctx.mock_challenge(&ctx.anchor_key, &client.address());
+    let exchange = ctx.mock_token(&token);
+    let anchor = ctx.anchor().await;
+
+    assert_eq!(anchor.authenticate(&client).await.unwrap(), token);
+    assert_eq!(anchor.authenticate(&client).await.unwrap(), token);
+
+    challenge.assert_hits(1);
+    exchange.assert_hits(1);
+}
+
+#[tokio::test]
+async fn test_token_near_expiry_is_refreshed() {
+    let ctx = setup();
+    let client = Keypair::from_seed([2u8; 32]).unwrap();
+    let challenge = ctx.mock_challenge(&ctx.anchor_key, &client.address());
+    ctx.mock_token(&jwt(&client.address(), 30));
+    let anchor = ctx.anchor().await;
+
+    anchor.authenticate(&client).await.unwrap();
+    anchor.authenticate(&client).await.unwrap();
+
+    challenge.assert_hits(2);
+}
+
+#[tokio::test]
+async fn test_rejects_challenge_from_another_signing_key() {
+    let ctx = setup();
+    let client = Keypair::from_seed([2u8; 32]).unwrap();
+    let impostor = Keypair::from_seed([3u8; 32]).unwrap();
+    ctx.mock_challenge(&impostor, &client.address());
+    let exchange = ctx.mock_token(&jwt(&client.address(), 3600));
+    let anchor = ctx.anchor().await;
+
+    let result = anchor.authenticate(&client).await;
+
+    assert!(matches!(result, Err(ApiError::Anchor(_))), "{:?}", result);
+    exchange.assert_hits(0);
+}
+
+#[tokio::test]
+async fn test_platform_authentication() {
+    let mut ctx = setup();
+    let anchor = ctx.anchor().await;
+    assert!(matches!(
+        anchor.authenticate_platform().await,
+        Err(ApiError::Anchor(_))
+    ));
+
+    let platform = Keypair::from_seed([5u8; 32]).unwrap();
+    ctx.config.anchor_config.platform_secret = Some(platform.secret_seed());
+    let token = jwt(&platform.address(), 3600);
+    ctx.mock_challenge(&ctx.anchor_key, &platform.address());
+    ctx.mock_token(&token);
+    let anchor = ctx.anchor().await;
+
+    assert_eq!(anchor.authenticate_platform().await.unwrap(), token);
+}
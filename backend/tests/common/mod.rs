@@ -0,0 +1,251 @@
+//! Fixtures shared by the tests run against httpmock stand-ins for the
+//! anchor's SEP servers and the Soroban RPC server.
+//!
+//! Each test crate uses only some of them.
+#![allow(dead_code)]
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+    Router,
+};
+use httpmock::prelude::*;
+use serde_json::{json, Value};
+use soroban_sdk::xdr::{
+    AccountEntry, AccountEntryExt, AccountId, ExtensionPoint, LedgerEntryData, LedgerFootprint,
+    Limits, PublicKey, SequenceNumber, SorobanResources, SorobanTransactionData, Thresholds,
+    Uint256, WriteXdr,
+};
+use std::{net::SocketAddr, sync::Arc};
+use tower::util::ServiceExt; // for oneshot
+
+use zaps_backend::{
+    app::build_router,
+    auth,
+    config::Config,
+    db,
+    role::Role,
+    service::ServiceContainer,
+    stellar::{sep10, strkey, Keypair},
+};
+
+pub const USDC_ISSUER: &str = "GA7QYNF7SOWQ3GLR2BGMZEHXAVIRZA4KVWLTJJFC7MGXUA74P7UJVSGZ";
+pub const ANCHOR_TOKEN: &str = "eyJhbGciOiJIUzI1NiJ9.eyJleHAiOjQxMDI0NDQ4MDB9.signature";
+
+pub fn anchor_key() -> Keypair {
+    Keypair::from_seed([9u8; 32]).unwrap()
+}
+
+pub fn platform_key() -> Keypair {
+    Keypair::from_seed([7u8; 32]).unwrap()
+}
+
+pub struct TestContext {
+    pub app: Router,
+    pub services: Arc<ServiceContainer>,
+    pub pool: deadpool_postgres::Pool,
+    pub config: Config,
+    pub server: MockServer,
+}
+
+/// Set up with the anchor's SEP-10 server, the RPC server and USDC on the mock
+/// server, then let `configure` adjust the config
+pub async fn setup(configure: impl FnOnce(&mut Config, &MockServer)) -> TestContext {
+    let server = MockServer::start();
+
+    let mut config = Config::load().expect("Failed to load config");
+    config.stellar_network.rpc_url = server.url("/");
+    config.anchor_config.web_auth_url = server.url("/auth");
+    config.anchor_config.home_domain = "anchor.example.com".to_string();
+    config.anchor_config.signing_key = anchor_key().address();
+    config
+        .stellar_network
+        .assets
+        .insert("usdc".to_string(), USDC_ISSUER.to_string());
+    configure(&mut config, &server);
+
+    db::run_migrations(&config.database.url)
+        .await
+        .expect("Failed to run database migrations");
+
+    let pool = db::create_pool(&config.database.url)
+        .await
+        .expect("Failed to create pool");
+    let services = Arc::new(
+        ServiceContainer::new(pool.clone(), config.clone())
+            .await
+            .expect("Failed to create services"),
+    );
+    let app = build_router(services.clone());
+
+    TestContext {
+        app,
+        services,
+        pool,
+        config,
+        server,
+    }
+}
+
+impl TestContext {
+    pub fn token(&self, user_id: &str) -> String {
+        self.token_as(user_id, Role::User)
+    }
+
+    pub fn token_as(&self, user_id: &str, role: Role) -> String {
+        auth::generate_access_token(user_id, role, &self.config.jwt.secret, 1).unwrap()
+    }
+
+    pub async fn balance(&self, owner_id: &str) -> i64 {
+        let client = self.pool.get().await.unwrap();
+        client
+            .query_opt(
+                "SELECT amount FROM balances WHERE owner_id = $1 AND asset = 'USDC'",
+                &[&owner_id],
+            )
+            .await
+            .unwrap()
+            .map(|row| row.get(0))
+            .unwrap_or(0)
+    }
+
+    pub async fn notification_titles(&self, user_id: &str) -> Vec<String> {
+        let client = self.pool.get().await.unwrap();
+        client
+            .query(
+                "SELECT title FROM notifications WHERE user_id = $1 ORDER BY created_at",
+                &[&user_id],
+            )
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| row.get(0))
+            .collect()
+    }
+
+    /// Mock the anchor's SEP-10 endpoint for `account`
+    pub fn mock_web_auth(&self, account: &str) {
+        let challenge = sep10::build_challenge(
+            &anchor_key(),
+            account,
+            "anchor.example.com",
+            &self.server.address().to_string(),
+            &self.config.stellar_network.passphrase,
+            chrono::Utc::now().timestamp() as u64,
+            900,
+        )
+        .unwrap();
+        self.server.mock(|when, then| {
+            when.method(GET)
+                .path("/auth")
+                .query_param("account", account);
+            then.status(200)
+                .json_body(json!({ "transaction": challenge }));
+        });
+        self.server.mock(|when, then| {
+            when.method(POST).path("/auth");
+            then.status(200).json_body(json!({ "token": ANCHOR_TOKEN }));
+        });
+    }
+
+    /// Mock `getTransaction` reporting `status` (`SUCCESS`, `FAILED` or `NOT_FOUND`) for any hash
+    ///
+    /// The first matching mock answers, so mocks for one hash must be made before this one.
+    pub fn mock_transaction_status(&self, status: &str) -> httpmock::Mock<'_> {
+        let body = rpc_result(json!({
+            "status": status,
+            "latestLedger": 121,
+            "ledger": (status != "NOT_FOUND").then_some(121),
+        }));
+        self.server.mock(|when, then| {
+            when.method(POST)
+                .json_body_partial(r#"{"method":"getTransaction"}"#);
+            then.status(200).json_body(body);
+        })
+    }
+
+    pub async fn send(
+        &self,
+        method: &str,
+        uri: &str,
+        token: Option<&str>,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("Content-Type", "application/json")
+            .extension(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+        if let Some(token) = token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        let body = body.map_or_else(Body::empty, |body| Body::from(body.to_string()));
+        self.oneshot(request.body(body).unwrap()).await
+    }
+
+    pub async fn oneshot(&self, request: Request<Body>) -> (StatusCode, Value) {
+        let response = self.app.clone().oneshot(request).await.unwrap();
+
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (
+            status,
+            serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        )
+    }
+}
+
+pub fn rpc_result(result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": 1, "result": result })
+}
+
+pub fn account_entry(address: &str, sequence: i64) -> String {
+    LedgerEntryData::Account(AccountEntry {
+        account_id: AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(
+            strkey::decode_account_id(address).unwrap(),
+        ))),
+        balance: 100_000_000,
+        seq_num: SequenceNumber(sequence),
+        num_sub_entries: 0,
+        inflation_dest: None,
+        flags: 0,
+        home_domain: Default::default(),
+        thresholds: Thresholds([1, 0, 0, 0]),
+        signers: Default::default(),
+        ext: AccountEntryExt::V0,
+    })
+    .to_xdr_base64(Limits::none())
+    .unwrap()
+}
+
+/// Simulated resources of a contract call, as `simulateTransaction` returns them
+pub fn transaction_data() -> String {
+    SorobanTransactionData {
+        ext: ExtensionPoint::V0,
+        resources: SorobanResources {
+            footprint: LedgerFootprint {
+                read_only: Default::default(),
+                read_write: Default::default(),
+            },
+            instructions: 1_000_000,
+            read_bytes: 2_000,
+            write_bytes: 500,
+        },
+        resource_fee: 54_321,
+    }
+    .to_xdr_base64(Limits::none())
+    .unwrap()
+}
+
+/// The statuses a transaction detail's history moved through, in order
+pub fn statuses(detail: &Value) -> Vec<&str> {
+    detail["history"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|event| event["to_status"].as_str().unwrap())
+        .collect()
+}
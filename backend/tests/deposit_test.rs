@@ -0,0 +1,234 @@
+//! Deposit tests against the database and an httpmock stand-in for the
+//! anchor's SEP-10 and SEP-24 endpoints.
+//!
+//! These tests require a running database as defined in the config/env.
+//! Run with: cargo test --test deposit_test -- --ignored
+
+mod common;
+
+use axum::http::StatusCode;
+use httpmock::prelude::*;
+use serde_json::{json, Value};
+
+use common::{statuses, TestContext, ANCHOR_TOKEN};
+use zaps_backend::role::Role;
+
+async fn setup() -> TestContext {
+    common::setup(|config, server| {
+        config.anchor_config.sep24_url = server.url("/sep24");
+    })
+    .await
+}
+
+impl TestContext {
+    /// Register a user with accepted KYC and return its user_id and wallet address
+    async fn register_user(&self) -> (String, String) {
+        let user_id = format!("user_{}", uuid::Uuid::new_v4().simple());
+        let (status, _) = self
+            .send(
+                "POST",
+                "/auth/register",
+                None,
+                Some(json!({ "user_id": user_id, "pin": "1234" })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let client = self.pool.get().await.unwrap();
+        let row = client
+            .query_one(
+                "SELECT stellar_address FROM users WHERE user_id = $1",
+                &[&user_id],
+            )
+            .await
+            .unwrap();
+        client
+            .execute(
+                "INSERT INTO kyc_profiles (user_id, level, requested_level, status) VALUES ($1, 'full', 'full', 'accepted')",
+                &[&user_id],
+            )
+            .await
+            .unwrap();
+        (user_id, row.get(0))
+    }
+
+    /// Mock the anchor accepting an interactive withdrawal, returning its id
+    /// Mock the anchor accepting an interactive deposit, returning its id
+    fn mock_start(&self) -> String {
+        let anchor_tx_id = uuid::Uuid::new_v4().to_string();
+        let url = format!("https://anchor.example.com/deposit?id={}", anchor_tx_id);
+        let body = json!({
+            "type": "interactive_customer_info_needed",
+            "url": url,
+            "id": anchor_tx_id,
+        });
+        self.server.mock(|when, then| {
+            when.method(POST)
+                .path("/sep24/transactions/deposit/interactive")
+                .header("Authorization", format!("Bearer {}", ANCHOR_TOKEN));
+            then.status(200).json_body(body);
+        });
+        anchor_tx_id
+    }
+
+    /// Mock the anchor reporting `status` for a deposit
+    fn mock_anchor_status(&self, anchor_tx_id: &str, status: &str) -> httpmock::Mock<'_> {
+        let tx_hash = format!(
+            "{}{}",
+            uuid::Uuid::new_v4().simple(),
+            uuid::Uuid::new_v4().simple()
+        );
+        let body = json!({
+            "transaction": {
+                "id": anchor_tx_id,
+                "kind": "deposit",
+                "status": status,
+                "amount_in": "2.5",
+                "amount_out": "2.45",
+                "stellar_transaction_id": tx_hash,
+            }
+        });
+        self.server.mock(|when, then| {
+            when.method(GET)
+                .path("/sep24/transaction")
+                .query_param("id", anchor_tx_id)
+                .header("Authorization", format!("Bearer {}", ANCHOR_TOKEN));
+            then.status(200).json_body(body);
+        })
+    }
+
+    async fn create_deposit(&self, user_id: &str) -> (StatusCode, Value) {
+        self.send(
+            "POST",
+            "/deposits/deposits",
+            Some(&self.token(user_id)),
+            Some(json!({ "asset": "USDC" })),
+        )
+        .await
+    }
+
+    async fn deposit(&self, user_id: &str, id: &str) -> Value {
+        let (status, body) = self
+            .send(
+                "GET",
+                &format!("/deposits/deposits/{}", id),
+                Some(&self.token(user_id)),
+                None,
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK, "{:?}", body);
+        body
+    }
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_deposit_completes_and_credits_balance() {
+    let ctx = setup().await;
+    let (user, address) = ctx.register_user().await;
+    ctx.mock_web_auth(&address);
+    let anchor_tx_id = ctx.mock_start();
+
+    let (status, body) = ctx.create_deposit(&user).await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert_eq!(body["status"], "pending");
+    assert!(body["amount"].is_null());
+    assert!(body["interactive_url"]
+        .as_str()
+        .unwrap()
+        .contains(&anchor_tx_id));
+    let id = body["id"].as_str().unwrap().to_string();
+
+    // The user has sent funds to the anchor off-chain
+    let mut anchor = ctx.mock_anchor_status(&anchor_tx_id, "pending_anchor");
+    ctx.services.deposit.poll_pending().await.unwrap();
+    assert_eq!(ctx.deposit(&user, &id).await["status"], "processing");
+    assert_eq!(ctx.balance(&user).await, 0);
+
+    anchor.delete();
+    ctx.mock_anchor_status(&anchor_tx_id, "completed");
+    ctx.services.deposit.poll_pending().await.unwrap();
+    ctx.services.deposit.poll_pending().await.unwrap();
+
+    let detail = ctx.deposit(&user, &id).await;
+    assert_eq!(detail["status"], "completed");
+    assert_eq!(detail["amount"], 24_500_000);
+    assert!(detail["tx_hash"].is_string());
+    assert_eq!(
+        statuses(&detail),
+        vec!["pending", "processing", "completed"]
+    );
+    assert_eq!(ctx.balance(&user).await, 24_500_000);
+    assert_eq!(
+        ctx.notification_titles(&user).await,
+        vec!["Deposit completed"]
+    );
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_failed_deposit_credits_nothing() {
+    let ctx = setup().await;
+    let (user, address) = ctx.register_user().await;
+    ctx.mock_web_auth(&address);
+    let anchor_tx_id = ctx.mock_start();
+
+    let (_, body) = ctx.create_deposit(&user).await;
+    let id = body["id"].as_str().unwrap().to_string();
+
+    ctx.mock_anchor_status(&anchor_tx_id, "error");
+    ctx.services.deposit.poll_pending().await.unwrap();
+
+    let detail = ctx.deposit(&user, &id).await;
+    assert_eq!(detail["status"], "failed");
+    assert_eq!(statuses(&detail), vec!["pending", "failed"]);
+    assert_eq!(ctx.balance(&user).await, 0);
+    assert_eq!(ctx.notification_titles(&user).await, vec!["Deposit failed"]);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_deposit_hidden_from_other_users() {
+    let ctx = setup().await;
+    let (user, address) = ctx.register_user().await;
+    let (outsider, _) = ctx.register_user().await;
+    ctx.mock_web_auth(&address);
+    ctx.mock_start();
+
+    let (_, body) = ctx.create_deposit(&user).await;
+    let uri = format!("/deposits/deposits/{}/status", body["id"].as_str().unwrap());
+
+    let (status, _) = ctx
+        .send("GET", &uri, Some(&ctx.token(&outsider)), None)
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let admin = ctx.token_as(&outsider, Role::Admin);
+    let (status, _) = ctx.send("GET", &uri, Some(&admin), None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_anchor_info_is_exposed_to_the_app() {
+    let ctx = setup().await;
+    let (user, _) = ctx.register_user().await;
+    ctx.server.mock(|when, then| {
+        when.method(GET).path("/sep24/info");
+        then.status(200).json_body(json!({
+            "deposit": { "USDC": { "enabled": true, "min_amount": 1.0, "fee_fixed": 0.5 } },
+            "withdraw": { "USDC": { "enabled": true, "max_amount": 1000.0 } },
+            "fee": { "enabled": false },
+            "features": { "account_creation": true, "claimable_balances": false },
+        }));
+    });
+
+    let (status, body) = ctx
+        .send("GET", "/anchor/info", Some(&ctx.token(&user)), None)
+        .await;
+
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert_eq!(body["deposit"]["USDC"]["enabled"], true);
+    assert_eq!(body["deposit"]["USDC"]["fee_fixed"], 0.5);
+    assert_eq!(body["withdraw"]["USDC"]["max_amount"], 1000.0);
+}
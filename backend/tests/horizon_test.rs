@@ -0,0 +1,148 @@
+//! Horizon client tests against an httpmock stand-in for Horizon
+
+use httpmock::prelude::*;
+use serde_json::json;
+use soroban_sdk::xdr::Asset;
+use std::{collections::HashMap, time::Duration};
+use zaps_backend::{
+    api_error::ApiError,
+    stellar::{
+        asset::resolve_asset,
+        horizon::{HorizonClient, PaymentPath},
+    },
+};
+
+const ISSUER: &str = "GBBD47IF6LWK7P7MDEVSCWR7DPUWV3NY3DTQEVFL4NAT4AQH3ZLLFLA5";
+
+fn usdc() -> Asset {
+    resolve_asset(&format!("USDC:{}", ISSUER), &HashMap::new()).unwrap()
+}
+
+fn paths_page() -> serde_json::Value {
+    json!({
+        "_embedded": { "records": [{
+            "source_asset_type": "native",
+            "source_amount": "100.0000000",
+            "destination_asset_type": "credit_alphanum4",
+            "destination_asset_code": "USDC",
+            "destination_asset_issuer": ISSUER,
+            "destination_amount": "12.3456789",
+            "path": [{ "asset_type": "native" }],
+        }]}
+    })
+}
+
+#[tokio::test]
+async fn test_strict_send_paths() {
+    let server = MockServer::start();
+    let mock = server.mock(|when, then| {
+        when.method(GET)
+            .path("/paths/strict-send")
+            .query_param("source_asset_type", "native")
+            .query_param("source_amount", "100")
+            .query_param("destination_assets", format!("USDC:{}", ISSUER));
+        then.status(200).json_body(paths_page());
+    });
+
+    let client = HorizonClient::new(server.url(""), Duration::ZERO);
+    let paths = client
+        .strict_send_paths(&Asset::Native, 1_000_000_000, &usdc())
+        .await
+        .unwrap();
+
+    mock.assert();
+    assert_eq!(
+        paths,
+        vec![PaymentPath {
+            source_amount: 1_000_000_000,
+            destination_amount: 123_456_789,
+            path: vec!["native".to_string()],
+        }]
+    );
+}
+
+#[tokio::test]
+async fn test_strict_receive_paths() {
+    let server = MockServer::start();
+    let mock = server.mock(|when, then| {
+        when.method(GET)
+            .path("/paths/strict-receive")
+            .query_param("destination_asset_type", "credit_alphanum4")
+            .query_param("destination_asset_code", "USDC")
+            .query_param("destination_asset_issuer", ISSUER)
+            .query_param("destination_amount", "12.3456789")
+            .query_param("source_assets", "native");
+        then.status(200).json_body(paths_page());
+    });
+
+    let client = HorizonClient::new(server.url(""), Duration::ZERO);
+    let paths = client
+        .strict_receive_paths(&Asset::Native, &usdc(), 123_456_789)
+        .await
+        .unwrap();
+
+    mock.assert();
+    assert_eq!(paths[0].source_amount, 1_000_000_000);
+}
+
+#[tokio::test]
+async fn test_paths_are_cached_per_request() {
+    let server = MockServer::start();
+    let mock = server.mock(|when, then| {
+        when.method(GET).path("/paths/strict-send");
+        then.status(200).json_body(paths_page());
+    });
+
+    let client = HorizonClient::new(server.url(""), Duration::from_secs(60));
+    for _ in 0..3 {
+        client
+            .strict_send_paths(&Asset::Native, 1_000_000_000, &usdc())
+            .await
+            .unwrap();
+    }
+    mock.assert_hits(1);
+
+    // A different amount is a different request
+    client
+        .strict_send_paths(&Asset::Native, 2_000_000_000, &usdc())
+        .await
+        .unwrap();
+    mock.assert_hits(2);
+
+    // With caching disabled every call reaches Horizon
+    let uncached = HorizonClient::new(server.url(""), Duration::ZERO);
+    for _ in 0..2 {
+        uncached
+            .strict_send_paths(&Asset::Native, 1_000_000_000, &usdc())
+            .await
+            .unwrap();
+    }
+    mock.assert_hits(4);
+}
+
+#[tokio::test]
+async fn test_horizon_problem_is_surfaced() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/paths/strict-send");
+        then.status(400).json_body(json!({
+            "type": "https://stellar.org/horizon-errors/bad_request",
+            "title": "Bad Request",
+            "detail": "The request you sent was invalid in some way.",
+        }));
+    });
+
+    let client = HorizonClient::new(server.url(""), Duration::from_secs(60));
+    let err = client
+        .strict_send_paths(&Asset::Native, 1_000_000_000, &usdc())
+        .await
+        .unwrap_err();
+
+    match err {
+        ApiError::Stellar(message) => {
+            assert!(message.contains("HTTP 400"), "{}", message);
+            assert!(message.contains("invalid in some way"), "{}", message);
+        }
+        other => panic!("expected a Stellar error, got {:?}", other),
+    }
+}
@@ -0,0 +1,306 @@
+//! Idempotency-Key tests: a retried payment replays the stored response
+//! instead of creating a second payment.
+//!
+//! These tests require a running database as defined in the config/env.
+//! Run with: cargo test --test idempotency_test -- --ignored
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{HeaderMap, Request, StatusCode},
+    Router,
+};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use tower::util::ServiceExt; // for oneshot
+
+use zaps_backend::{
+    app::create_app, auth, config::Config, db, role::Role, service::IdempotencyService,
+};
+
+const VAULT: &str = "CAAACAQDAQCQMBYIBEFAWDANBYHRAEISCMKBKFQXDAMRUGY4DUPB6N4O";
+
+struct TestContext {
+    app: Router,
+    pool: deadpool_postgres::Pool,
+    config: Config,
+}
+
+async fn setup() -> TestContext {
+    let config = Config::load().expect("Failed to load config");
+    db::run_migrations(&config.database.url)
+        .await
+        .expect("Failed to run database migrations");
+
+    let pool = db::create_pool(&config.database.url)
+        .await
+        .expect("Failed to create pool");
+    let app = create_app(pool.clone(), config.clone())
+        .await
+        .expect("Failed to create app");
+
+    TestContext { app, pool, config }
+}
+
+impl TestContext {
+    async fn create_merchant(&self) -> String {
+        let merchant_id = format!("merchant_{}", uuid::Uuid::new_v4().simple());
+        let client = self.pool.get().await.unwrap();
+        client
+            .execute(
+                "INSERT INTO merchants (merchant_id, vault_address, settlement_asset) VALUES ($1, $2, 'USDC')",
+                &[&merchant_id, &VAULT],
+            )
+            .await
+            .unwrap();
+        merchant_id
+    }
+
+    /// Register a user and return a bearer token for it
+    async fn register_user(&self) -> String {
+        let user_id = format!("payer_{}", uuid::Uuid::new_v4().simple());
+        let (status, _, _) = self
+            .post(
+                "/auth/register",
+                None,
+                None,
+                json!({ "user_id": user_id, "pin": "1234" }),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+
+        auth::generate_access_token(&user_id, Role::User, &self.config.jwt.secret, 1).unwrap()
+    }
+
+    /// Reserve `key` as if an identical request were in flight, holding the lease for `lease_secs`
+    async fn reserve(&self, token: &str, key: &str, body: &Value, lease_secs: f64) {
+        let user_id = auth::validate_jwt(token, &self.config.jwt.secret)
+            .unwrap()
+            .sub;
+        let fingerprint = IdempotencyService::fingerprint(
+            "POST",
+            "/payments/payments",
+            body.to_string().as_bytes(),
+        );
+        let client = self.pool.get().await.unwrap();
+        client
+            .execute(
+                r#"
+                INSERT INTO idempotency_keys (
+                    user_id, idempotency_key, request_method, request_path,
+                    request_fingerprint, expires_at, locked_until
+                )
+                VALUES ($1, $2, 'POST', '/payments/payments', $3,
+                        NOW() + INTERVAL '1 day', NOW() + make_interval(secs => $4))
+                "#,
+                &[&user_id, &key, &fingerprint, &lease_secs],
+            )
+            .await
+            .unwrap();
+    }
+
+    async fn payment_count(&self, merchant_id: &str) -> i64 {
+        let client = self.pool.get().await.unwrap();
+        client
+            .query_one(
+                "SELECT COUNT(*) FROM payments WHERE merchant_id = $1",
+                &[&merchant_id],
+            )
+            .await
+            .unwrap()
+            .get(0)
+    }
+
+    async fn post(
+        &self,
+        uri: &str,
+        token: Option<&str>,
+        idempotency_key: Option<&str>,
+        body: Value,
+    ) -> (StatusCode, HeaderMap, Value) {
+        let mut request = Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header("Content-Type", "application/json")
+            .extension(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+        if let Some(token) = token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        if let Some(key) = idempotency_key {
+            request = request.header("Idempotency-Key", key);
+        }
+
+        let response = self
+            .app
+            .clone()
+            .oneshot(request.body(Body::from(body.to_string())).unwrap())
+            .await
+            .unwrap();
+
+        let status = response.status();
+        let headers = response.headers().clone();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (
+            status,
+            headers,
+            serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        )
+    }
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_retried_payment_is_replayed() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant().await;
+    let token = ctx.register_user().await;
+    let key = uuid::Uuid::new_v4().to_string();
+    let body = json!({ "merchant_id": merchant_id, "send_asset": "USDC", "send_amount": 1000 });
+
+    let (status, headers, first) = ctx
+        .post("/payments/payments", Some(&token), Some(&key), body.clone())
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", first);
+    assert!(headers.get("idempotent-replayed").is_none());
+
+    let (status, headers, second) = ctx
+        .post("/payments/payments", Some(&token), Some(&key), body)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers.get("idempotent-replayed").unwrap(), "true");
+    assert_eq!(second["id"], first["id"]);
+    assert_eq!(ctx.payment_count(&merchant_id).await, 1);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_key_reused_with_different_body_conflicts() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant().await;
+    let token = ctx.register_user().await;
+    let key = uuid::Uuid::new_v4().to_string();
+
+    let (status, _, _) = ctx
+        .post(
+            "/payments/payments",
+            Some(&token),
+            Some(&key),
+            json!({ "merchant_id": merchant_id, "send_asset": "USDC", "send_amount": 1000 }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _, body) = ctx
+        .post(
+            "/payments/payments",
+            Some(&token),
+            Some(&key),
+            json!({ "merchant_id": merchant_id, "send_asset": "USDC", "send_amount": 2000 }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT, "{:?}", body);
+    assert_eq!(ctx.payment_count(&merchant_id).await, 1);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_keys_are_scoped_per_user() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant().await;
+    let key = uuid::Uuid::new_v4().to_string();
+    let body = json!({ "merchant_id": merchant_id, "send_asset": "USDC", "send_amount": 1000 });
+
+    for _ in 0..2 {
+        let token = ctx.register_user().await;
+        let (status, headers, _) = ctx
+            .post("/payments/payments", Some(&token), Some(&key), body.clone())
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(headers.get("idempotent-replayed").is_none());
+    }
+    assert_eq!(ctx.payment_count(&merchant_id).await, 2);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_requests_without_key_are_not_deduplicated() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant().await;
+    let token = ctx.register_user().await;
+    let body = json!({ "merchant_id": merchant_id, "send_asset": "USDC", "send_amount": 1000 });
+
+    for _ in 0..2 {
+        let (status, _, _) = ctx
+            .post("/payments/payments", Some(&token), None, body.clone())
+            .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    assert_eq!(ctx.payment_count(&merchant_id).await, 2);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_unfinished_key_is_never_run_again() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant().await;
+    let token = ctx.register_user().await;
+    let body = json!({ "merchant_id": merchant_id, "send_asset": "USDC", "send_amount": 1000 });
+
+    let live = uuid::Uuid::new_v4().to_string();
+    ctx.reserve(&token, &live, &body, 60.0).await;
+    let (status, _, response) = ctx
+        .post(
+            "/payments/payments",
+            Some(&token),
+            Some(&live),
+            body.clone(),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert!(response["message"]
+        .as_str()
+        .unwrap()
+        .contains("still being processed"));
+
+    // Its holder may have committed before it died, so the key stays refused
+    let abandoned = uuid::Uuid::new_v4().to_string();
+    ctx.reserve(&token, &abandoned, &body, -1.0).await;
+    let (status, _, response) = ctx
+        .post("/payments/payments", Some(&token), Some(&abandoned), body)
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert!(response["message"]
+        .as_str()
+        .unwrap()
+        .contains("outcome is unknown"));
+    assert_eq!(ctx.payment_count(&merchant_id).await, 0);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_abandoned_key_is_not_taken_over_by_a_different_request() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant().await;
+    let token = ctx.register_user().await;
+    let key = uuid::Uuid::new_v4().to_string();
+
+    ctx.reserve(
+        &token,
+        &key,
+        &json!({ "merchant_id": merchant_id, "send_asset": "USDC", "send_amount": 1000 }),
+        -1.0,
+    )
+    .await;
+    let (status, _, _) = ctx
+        .post(
+            "/payments/payments",
+            Some(&token),
+            Some(&key),
+            json!({ "merchant_id": merchant_id, "send_asset": "USDC", "send_amount": 2000 }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(ctx.payment_count(&merchant_id).await, 0);
+}
@@ -0,0 +1,374 @@
+//! Contract event indexer tests against the database and an httpmock stand-in
+//! for the Soroban RPC server: payment-router events moving payments through
+//! their lifecycle, merchant-vault events moving merchant balances, and events
+//! read again after a restart being applied once.
+//!
+//! These tests require a running database as defined in the config/env.
+//! Run with: cargo test --test indexer_test -- --ignored
+
+use httpmock::prelude::*;
+use serde_json::{json, Value};
+use soroban_sdk::xdr::{Limits, WriteXdr};
+use std::sync::Arc;
+
+use zaps_backend::{
+    config::Config,
+    db,
+    service::ServiceContainer,
+    stellar::{asset, scval, Keypair},
+};
+
+const ROUTER: &str = "CAAACAQDAQCQMBYIBEFAWDANBYHRAEISCMKBKFQXDAMRUGY4DUPB6N4O";
+const VAULT: &str = "CBDRHDQCGK5OM3LHJOCGUAQTCLA3LNTKB5XK5I5EL6VVWDPB2AVDKEON";
+
+struct TestContext {
+    pool: deadpool_postgres::Pool,
+    config: Config,
+    server: MockServer,
+}
+
+async fn setup() -> TestContext {
+    let server = MockServer::start();
+
+    let mut config = Config::load().expect("Failed to load config");
+    config.stellar_network.rpc_url = server.url("/");
+    config.contracts.payment_router = ROUTER.to_string();
+    config.contracts.merchant_vault = VAULT.to_string();
+
+    db::run_migrations(&config.database.url)
+        .await
+        .expect("Failed to run database migrations");
+
+    let pool = db::create_pool(&config.database.url)
+        .await
+        .expect("Failed to create pool");
+
+    TestContext {
+        pool,
+        config,
+        server,
+    }
+}
+
+fn rpc_result(result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": 1, "result": result })
+}
+
+fn random_address() -> String {
+    let mut seed = [0u8; 32];
+    seed[..16].copy_from_slice(uuid::Uuid::new_v4().as_bytes());
+    Keypair::from_seed(seed).unwrap().address()
+}
+
+fn random_hash() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+fn encode(value: Value) -> String {
+    scval::json_to_scval(&value)
+        .unwrap()
+        .to_xdr_base64(Limits::none())
+        .unwrap()
+}
+
+fn symbol(value: &str) -> String {
+    encode(json!({ "type": "symbol", "value": value }))
+}
+
+fn entry(key: &str, value: Value) -> Value {
+    json!({ "key": { "type": "symbol", "value": key }, "value": value })
+}
+
+fn i128_value(amount: i64) -> Value {
+    json!({ "type": "i128", "value": amount.to_string() })
+}
+
+/// A `getEvents` event with an id unique to this test run
+fn rpc_event(contract_id: &str, topic: Vec<String>, value: Value, tx_hash: &str) -> Value {
+    let id = format!(
+        "{:019}-{:010}",
+        uuid::Uuid::new_v4().as_u64_pair().0 >> 1,
+        1
+    );
+    json!({
+        "type": "contract",
+        "ledger": 2000,
+        "ledgerClosedAt": "2026-02-16T09:00:00Z",
+        "contractId": contract_id,
+        "id": id,
+        "pagingToken": id,
+        "topic": topic,
+        "value": encode(value),
+        "inSuccessfulContractCall": true,
+        "txHash": tx_hash,
+    })
+}
+
+fn vault_event(name: &str, vault_address: &str, amount: i64, tx_hash: &str) -> Value {
+    rpc_event(
+        VAULT,
+        vec![
+            symbol(name),
+            encode(json!({ "type": "address", "value": vault_address })),
+        ],
+        json!({ "type": "map", "value": [
+            entry("amount", i128_value(amount)),
+            entry("merchant_id", json!({ "type": "address", "value": vault_address })),
+            entry("resulting_balance", i128_value(0)),
+        ] }),
+        tx_hash,
+    )
+}
+
+impl TestContext {
+    /// A fresh service container, as after a restart
+    async fn services(&self) -> Arc<ServiceContainer> {
+        Arc::new(
+            ServiceContainer::new(self.pool.clone(), self.config.clone())
+                .await
+                .expect("Failed to create services"),
+        )
+    }
+
+    /// Serve `events` to every `getEvents` request
+    fn serve_events(&self, events: Vec<Value>) {
+        self.server.mock(|when, then| {
+            when.method(POST).body_contains("getLatestLedger");
+            then.status(200).json_body(rpc_result(json!({
+                "id": "ledger",
+                "protocolVersion": 22,
+                "sequence": 2000,
+            })));
+        });
+        self.server.mock(|when, then| {
+            when.method(POST).body_contains("getEvents");
+            then.status(200).json_body(rpc_result(json!({
+                "events": events,
+                "latestLedger": 2001,
+                "cursor": "0000008594229559296-0000000000",
+            })));
+        });
+    }
+
+    /// A router event for a payment of 5 XLM by `payer`
+    fn payment_event(
+        &self,
+        kind: &str,
+        payer: &str,
+        merchant_id: &str,
+        settled_amount: i64,
+        tx_hash: &str,
+    ) -> Value {
+        let xlm = asset::resolve_asset("XLM", &Default::default()).unwrap();
+        let xlm_contract =
+            asset::contract_address(&xlm, &self.config.stellar_network.passphrase).unwrap();
+        let merchant_hex: String = merchant_id.bytes().map(|b| format!("{:02x}", b)).collect();
+
+        rpc_event(
+            ROUTER,
+            vec![symbol("payment"), symbol(kind)],
+            json!({ "type": "map", "value": [
+                entry("merchant_id", json!({ "type": "bytes", "value": merchant_hex })),
+                entry("payer", json!({ "type": "address", "value": payer })),
+                entry("send_amount", i128_value(50_000_000)),
+                entry("send_asset", json!({ "type": "address", "value": xlm_contract })),
+                entry("settled_amount", i128_value(settled_amount)),
+                entry("settlement_asset", json!({ "type": "address", "value": xlm_contract })),
+            ] }),
+            tx_hash,
+        )
+    }
+
+    /// A USDC merchant holding `balance` stroops in its own vault
+    async fn create_merchant(&self, balance: i64) -> (String, String) {
+        let merchant_id = format!("merchant_{}", uuid::Uuid::new_v4().simple());
+        let vault_address = random_address();
+        let client = self.pool.get().await.unwrap();
+        client
+            .execute(
+                "INSERT INTO merchants (merchant_id, vault_address, settlement_asset) VALUES ($1, $2, 'USDC')",
+                &[&merchant_id, &vault_address],
+            )
+            .await
+            .unwrap();
+        client
+            .execute(
+                "INSERT INTO balances (owner_id, asset, amount) VALUES ($1, 'USDC', $2)",
+                &[&merchant_id, &balance],
+            )
+            .await
+            .unwrap();
+        (merchant_id, vault_address)
+    }
+
+    /// A pending 5 XLM payment from `payer` to `merchant_id`
+    async fn create_payment(&self, payer: &str, merchant_id: &str) -> uuid::Uuid {
+        let client = self.pool.get().await.unwrap();
+        client
+            .query_one(
+                r#"
+                INSERT INTO payments (tx_hash, from_address, merchant_id, send_asset, send_amount, receive_amount, status)
+                VALUES ($1, $2, $3, 'XLM', 50000000, 4000000, 'pending')
+                RETURNING id
+                "#,
+                &[
+                    &format!("tx_{}", uuid::Uuid::new_v4().simple()),
+                    &payer,
+                    &merchant_id,
+                ],
+            )
+            .await
+            .unwrap()
+            .get(0)
+    }
+
+    async fn payment(&self, payment_id: uuid::Uuid) -> (String, Option<i64>, Option<String>) {
+        let client = self.pool.get().await.unwrap();
+        let row = client
+            .query_one(
+                "SELECT status, receive_amount, tx_hash FROM payments WHERE id = $1",
+                &[&payment_id],
+            )
+            .await
+            .unwrap();
+        (row.get(0), row.get(1), row.get(2))
+    }
+
+    async fn payment_history(&self, payment_id: uuid::Uuid) -> Vec<String> {
+        let client = self.pool.get().await.unwrap();
+        client
+            .query(
+                "SELECT to_status FROM payment_events WHERE payment_id = $1 ORDER BY created_at, id",
+                &[&payment_id],
+            )
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| row.get(0))
+            .collect()
+    }
+
+    async fn balance(&self, merchant_id: &str) -> i64 {
+        let client = self.pool.get().await.unwrap();
+        client
+            .query_one(
+                "SELECT amount FROM balances WHERE owner_id = $1 AND asset = 'USDC'",
+                &[&merchant_id],
+            )
+            .await
+            .unwrap()
+            .get(0)
+    }
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_router_events_settle_and_fail_payments() {
+    let ctx = setup().await;
+    let (merchant_id, _) = ctx.create_merchant(0).await;
+    let payer = random_address();
+    let settled = ctx.create_payment(&payer, &merchant_id).await;
+    let failed_payer = random_address();
+    let failed = ctx.create_payment(&failed_payer, &merchant_id).await;
+
+    let settle_hash = random_hash();
+    ctx.serve_events(vec![
+        ctx.payment_event("PaymentInitiated", &payer, &merchant_id, 0, &settle_hash),
+        ctx.payment_event(
+            "PaymentSettled",
+            &payer,
+            &merchant_id,
+            4_900_000,
+            &settle_hash,
+        ),
+        ctx.payment_event(
+            "PaymentFailed",
+            &failed_payer,
+            &merchant_id,
+            0,
+            &random_hash(),
+        ),
+        // A payment the backend never created
+        ctx.payment_event(
+            "PaymentSettled",
+            &random_address(),
+            &merchant_id,
+            1,
+            &random_hash(),
+        ),
+    ]);
+
+    let services = ctx.services().await;
+    assert_eq!(services.indexer.index_events().await.unwrap(), 3);
+
+    assert_eq!(
+        ctx.payment(settled).await,
+        (
+            "completed".to_string(),
+            Some(4_900_000),
+            Some(settle_hash.clone())
+        )
+    );
+    assert_eq!(
+        ctx.payment_history(settled).await,
+        vec!["processing", "completed"]
+    );
+    assert_eq!(ctx.payment(failed).await.0, "failed");
+
+    // Reading the same events again after a restart changes nothing
+    let restarted = ctx.services().await;
+    assert_eq!(restarted.indexer.index_events().await.unwrap(), 0);
+    assert_eq!(
+        ctx.payment_history(settled).await,
+        vec!["processing", "completed"]
+    );
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_vault_events_move_merchant_balances() {
+    let ctx = setup().await;
+    let (merchant_id, vault_address) = ctx.create_merchant(1_000).await;
+
+    // A refund whose vault debit was already taken from the balance
+    let payment_id = ctx.create_payment(&random_address(), &merchant_id).await;
+    let refund_hash = random_hash();
+    ctx.pool
+        .get()
+        .await
+        .unwrap()
+        .execute(
+            r#"
+            INSERT INTO refunds (id, payment_id, merchant_id, amount, asset, destination_address,
+                                 reason, status, vault_tx_hash, requested_by)
+            VALUES ($1, $2, $3, 300, 'USDC', $4, 'other', 'processing', $5, 'operator')
+            "#,
+            &[
+                &uuid::Uuid::new_v4(),
+                &payment_id,
+                &merchant_id,
+                &random_address(),
+                &refund_hash,
+            ],
+        )
+        .await
+        .unwrap();
+
+    ctx.serve_events(vec![
+        vault_event("balance_credited", &vault_address, 500, &random_hash()),
+        vault_event("balance_debited", &vault_address, 200, &random_hash()),
+        vault_event("balance_debited", &vault_address, 300, &refund_hash),
+    ]);
+
+    let services = ctx.services().await;
+    assert_eq!(services.indexer.index_events().await.unwrap(), 2);
+    assert_eq!(ctx.balance(&merchant_id).await, 1_300);
+
+    let restarted = ctx.services().await;
+    assert_eq!(restarted.indexer.index_events().await.unwrap(), 0);
+    assert_eq!(ctx.balance(&merchant_id).await, 1_300);
+}
@@ -0,0 +1,354 @@
+//! Background job queue tests against the database: failing jobs are retried
+//! then dead-lettered, admins can list and retry dead jobs, scheduled jobs
+//! wait for their time, and audit log and notification email side effects run
+//! as jobs.
+//!
+//! These tests require a running database as defined in the config/env.
+//! Run with: cargo test --test job_test -- --ignored
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+    Router,
+};
+use chrono::{Duration, Utc};
+use serde_json::{json, Value};
+use std::{net::SocketAddr, sync::Arc};
+use tokio::sync::Mutex;
+use tower::util::ServiceExt; // for oneshot
+use uuid::Uuid;
+
+use zaps_backend::{
+    app::build_router,
+    auth,
+    config::Config,
+    db,
+    models::{CreateAuditLogParams, JobStatus, NotificationType},
+    role::Role,
+    service::{
+        job_service::Job, notification_service::CreateNotificationRequest, ServiceContainer,
+    },
+};
+
+/// Sweeps run every due job in the database, so tests take turns
+static SWEEPS: Mutex<()> = Mutex::const_new(());
+
+struct TestContext {
+    app: Router,
+    services: Arc<ServiceContainer>,
+    pool: deadpool_postgres::Pool,
+    config: Config,
+}
+
+async fn setup() -> TestContext {
+    let mut config = Config::load().expect("Failed to load config");
+    config.jobs.max_attempts = 2;
+
+    db::run_migrations(&config.database.url)
+        .await
+        .expect("Failed to run database migrations");
+
+    let pool = db::create_pool(&config.database.url)
+        .await
+        .expect("Failed to create pool");
+    let services = Arc::new(
+        ServiceContainer::new(pool.clone(), config.clone())
+            .await
+            .expect("Failed to create services"),
+    );
+
+    TestContext {
+        app: build_router(services.clone()),
+        services,
+        pool,
+        config,
+    }
+}
+
+impl TestContext {
+    async fn send(&self, method: &str, uri: &str, actor: &str) -> (StatusCode, Value) {
+        let token =
+            auth::generate_access_token(actor, Role::Admin, &self.config.jwt.secret, 1).unwrap();
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("Authorization", format!("Bearer {}", token))
+            .extension(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))))
+            .body(Body::empty())
+            .unwrap();
+        let response = self.app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (
+            status,
+            serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        )
+    }
+
+    /// Sweep until no job is due
+    async fn run_jobs(&self) {
+        while self.services.job.run_due().await.unwrap() > 0 {}
+    }
+
+    /// Make a pending job due now rather than after its backoff or schedule
+    async fn make_due(&self, job_id: Uuid) {
+        let client = self.pool.get().await.unwrap();
+        client
+            .execute("UPDATE jobs SET run_at = NOW() WHERE id = $1", &[&job_id])
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_failing_jobs_are_retried_then_dead_lettered_and_retried_by_admins() {
+    let _turn = SWEEPS.lock().await;
+    let ctx = setup().await;
+
+    // No such notification, so the email job fails every time
+    let job_id = ctx
+        .services
+        .job
+        .enqueue(
+            &Job::NotificationEmail {
+                notification_id: Uuid::new_v4(),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    ctx.run_jobs().await;
+    let job = ctx.services.job.get_job(job_id).await.unwrap();
+    assert_eq!(job.status, JobStatus::Pending);
+    assert_eq!(job.attempts, 1);
+    assert!(job.last_error.unwrap().contains("Notification not found"));
+    assert!(job.run_at > Utc::now(), "the retry was not backed off");
+
+    ctx.make_due(job_id).await;
+    ctx.run_jobs().await;
+    let job = ctx.services.job.get_job(job_id).await.unwrap();
+    assert_eq!(job.status, JobStatus::Dead);
+    assert_eq!(job.attempts, 2);
+
+    let (status, body) = ctx
+        .send(
+            "GET",
+            "/admin/jobs?status=dead&job_type=notification_email",
+            "admin",
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|job| job["id"] == job_id.to_string() && job["status"] == "dead"));
+
+    let (status, body) = ctx
+        .send("POST", &format!("/admin/jobs/{}/retry", job_id), "admin")
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "pending");
+    assert_eq!(body["attempts"], 0);
+
+    // Only dead jobs can be retried
+    let (status, _) = ctx
+        .send("POST", &format!("/admin/jobs/{}/retry", job_id), "admin")
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, _) = ctx
+        .send(
+            "POST",
+            &format!("/admin/jobs/{}/retry", Uuid::new_v4()),
+            "admin",
+        )
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Keep the job out of later sweeps
+    let client = ctx.pool.get().await.unwrap();
+    client
+        .execute("DELETE FROM jobs WHERE id = $1", &[&job_id])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_invalid_jobs_are_dead_lettered_at_once() {
+    let _turn = SWEEPS.lock().await;
+    let ctx = setup().await;
+
+    let job_id = Uuid::new_v4();
+    let client = ctx.pool.get().await.unwrap();
+    client
+        .execute(
+            "INSERT INTO jobs (id, job_type, payload) VALUES ($1, 'notification_email', $2)",
+            &[&job_id, &json!({ "id": "not a notification id" })],
+        )
+        .await
+        .unwrap();
+
+    ctx.run_jobs().await;
+    let job = ctx.services.job.get_job(job_id).await.unwrap();
+    assert_eq!(job.status, JobStatus::Dead);
+    assert_eq!(job.attempts, 1);
+    assert!(job
+        .last_error
+        .unwrap()
+        .starts_with("Invalid notification_email job"));
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_scheduled_jobs_wait_for_their_time() {
+    let _turn = SWEEPS.lock().await;
+    let ctx = setup().await;
+
+    let actor_id = format!("actor_{}", Uuid::new_v4().simple());
+    let occurred_at = Utc::now() - Duration::minutes(5);
+    let job_id = ctx
+        .services
+        .job
+        .enqueue(
+            &Job::AuditLog(CreateAuditLogParams {
+                actor_id: actor_id.clone(),
+                action: "create_payments".to_string(),
+                resource: "payments".to_string(),
+                resource_id: None,
+                metadata: None,
+                ip_address: None,
+                user_agent: None,
+                occurred_at: Some(occurred_at),
+            }),
+            Some(Utc::now() + Duration::hours(1)),
+        )
+        .await
+        .unwrap();
+
+    ctx.run_jobs().await;
+    let job = ctx.services.job.get_job(job_id).await.unwrap();
+    assert_eq!(job.status, JobStatus::Pending);
+    assert_eq!(job.attempts, 0);
+
+    ctx.make_due(job_id).await;
+    ctx.run_jobs().await;
+    let job = ctx.services.job.get_job(job_id).await.unwrap();
+    assert_eq!(job.status, JobStatus::Completed);
+    assert_eq!(job.attempts, 1);
+    assert!(job.completed_at.is_some());
+
+    // The entry keeps the time of the audited request, not of the job
+    let client = ctx.pool.get().await.unwrap();
+    let row = client
+        .query_one(
+            "SELECT timestamp FROM audit_logs WHERE actor_id = $1",
+            &[&actor_id],
+        )
+        .await
+        .unwrap();
+    let timestamp: chrono::DateTime<Utc> = row.get(0);
+    assert!((timestamp - occurred_at).num_milliseconds().abs() < 1);
+
+    // Running the job again, e.g. after its completion was lost, writes no second entry
+    client
+        .execute(
+            "UPDATE jobs SET status = 'pending', run_at = NOW() WHERE id = $1",
+            &[&job_id],
+        )
+        .await
+        .unwrap();
+    ctx.run_jobs().await;
+    let job = ctx.services.job.get_job(job_id).await.unwrap();
+    assert_eq!(job.status, JobStatus::Completed);
+    assert_eq!(job.attempts, 2);
+    let entries: i64 = client
+        .query_one(
+            "SELECT COUNT(*) FROM audit_logs WHERE actor_id = $1",
+            &[&actor_id],
+        )
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(entries, 1);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_audit_logs_and_notification_emails_run_as_jobs() {
+    let _turn = SWEEPS.lock().await;
+    let ctx = setup().await;
+
+    // Audited requests queue their entry rather than writing it
+    let actor_id = format!("admin_{}", Uuid::new_v4().simple());
+    let (status, _) = ctx.send("GET", "/admin/jobs", &actor_id).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let client = ctx.pool.get().await.unwrap();
+    let audit_job: Uuid = client
+        .query_one(
+            "SELECT id FROM jobs WHERE job_type = 'audit_log' AND payload->>'actor_id' = $1",
+            &[&actor_id],
+        )
+        .await
+        .unwrap()
+        .get(0);
+    let logged: i64 = client
+        .query_one(
+            "SELECT COUNT(*) FROM audit_logs WHERE actor_id = $1",
+            &[&actor_id],
+        )
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(logged, 0);
+
+    // Notifications queue their email alongside them
+    let notification = ctx
+        .services
+        .notification
+        .create_notification(CreateNotificationRequest {
+            user_id: format!("user_{}", Uuid::new_v4().simple()),
+            notification_type: NotificationType::SYSTEM,
+            title: "Payment received".to_string(),
+            message: "You received 10 USDC".to_string(),
+            metadata: None,
+        })
+        .await
+        .unwrap();
+    let email_job: Uuid = client
+        .query_one(
+            "SELECT id FROM jobs WHERE job_type = 'notification_email' AND payload->>'notification_id' = $1",
+            &[&notification.id],
+        )
+        .await
+        .unwrap()
+        .get(0);
+
+    ctx.run_jobs().await;
+
+    for job_id in [audit_job, email_job] {
+        let job = ctx.services.job.get_job(job_id).await.unwrap();
+        assert_eq!(
+            job.status,
+            JobStatus::Completed,
+            "{} did not run",
+            job.job_type
+        );
+    }
+    let row = client
+        .query_one(
+            "SELECT action, resource FROM audit_logs WHERE actor_id = $1",
+            &[&actor_id],
+        )
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, String>(0), "view_admin");
+    assert_eq!(row.get::<_, String>(1), "admin");
+}
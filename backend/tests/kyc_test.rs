@@ -0,0 +1,261 @@
+//! KYC tests against the database and an httpmock stand-in for the anchor's
+//! SEP-10 and SEP-12 servers: submissions, status updates by polling and
+//! callback, and the checks gated on the accepted level.
+//!
+//! These tests require a running database as defined in the config/env.
+//! Run with: cargo test --test kyc_test -- --ignored
+
+mod common;
+
+use axum::http::StatusCode;
+use httpmock::prelude::*;
+use serde_json::{json, Value};
+
+use common::{TestContext, ANCHOR_TOKEN};
+
+const VAULT: &str = "CAAACAQDAQCQMBYIBEFAWDANBYHRAEISCMKBKFQXDAMRUGY4DUPB6N4O";
+/// Payments of this many stroops or more need basic KYC in these tests
+const THRESHOLD: i64 = 1_000_000_000;
+
+async fn setup() -> TestContext {
+    common::setup(|config, server| {
+        config.anchor_config.kyc_required = true;
+        config.anchor_config.kyc_payment_threshold = THRESHOLD;
+        config.anchor_config.sep12_url = server.url("/kyc");
+        config.anchor_config.sep12_callback_url =
+            Some("https://api.example.com/callbacks/sep12".to_string());
+    })
+    .await
+}
+
+fn basic_fields() -> Value {
+    json!({
+        "first_name": "Ada",
+        "last_name": "Lovelace",
+        "email_address": "ada@example.com",
+    })
+}
+
+impl TestContext {
+    /// Register a user and return its user_id and wallet address
+    async fn register_user(&self) -> (String, String) {
+        let user_id = format!("user_{}", uuid::Uuid::new_v4().simple());
+        let (status, _) = self
+            .send(
+                "POST",
+                "/auth/register",
+                None,
+                Some(json!({ "user_id": user_id, "pin": "1234" })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let client = self.pool.get().await.unwrap();
+        let row = client
+            .query_one(
+                "SELECT stellar_address FROM users WHERE user_id = $1",
+                &[&user_id],
+            )
+            .await
+            .unwrap();
+        (user_id, row.get(0))
+    }
+
+    async fn create_merchant(&self) -> String {
+        let merchant_id = format!("merchant_{}", uuid::Uuid::new_v4().simple());
+        let client = self.pool.get().await.unwrap();
+        client
+            .execute(
+                "INSERT INTO merchants (merchant_id, vault_address, settlement_asset) VALUES ($1, $2, 'USDC')",
+                &[&merchant_id, &VAULT],
+            )
+            .await
+            .unwrap();
+        merchant_id
+    }
+
+    /// Mock the anchor accepting a submission for `account`, returning the customer id
+    fn mock_put_customer(&self, account: &str) -> String {
+        let customer_id = uuid::Uuid::new_v4().to_string();
+        let body = json!({ "id": customer_id });
+        self.server.mock(|when, then| {
+            when.method(PUT)
+                .path("/kyc/customer")
+                .header("Authorization", format!("Bearer {}", ANCHOR_TOKEN))
+                .json_body_partial(json!({ "account": account }).to_string());
+            then.status(202).json_body(body);
+        });
+        self.server.mock(|when, then| {
+            when.method(PUT).path("/kyc/customer/callback");
+            then.status(200);
+        });
+        customer_id
+    }
+
+    /// Mock the anchor reporting `status` for a customer
+    fn mock_customer_status(&self, customer_id: &str, status: &str) -> httpmock::Mock<'_> {
+        let mut body = json!({ "id": customer_id, "status": status });
+        if status == "REJECTED" {
+            body["message"] = json!("Document could not be verified");
+        }
+        self.server.mock(|when, then| {
+            when.method(GET)
+                .path("/kyc/customer")
+                .query_param("id", customer_id);
+            then.status(200).json_body(body);
+        })
+    }
+
+    async fn submit(&self, user_id: &str, level: &str, fields: Value) -> (StatusCode, Value) {
+        self.send(
+            "PUT",
+            "/kyc/profile",
+            Some(&self.token(user_id)),
+            Some(json!({ "level": level, "fields": fields })),
+        )
+        .await
+    }
+
+    async fn pay(&self, user_id: &str, merchant_id: &str, amount: i64) -> StatusCode {
+        let (status, _) = self
+            .send(
+                "POST",
+                "/payments/payments",
+                Some(&self.token(user_id)),
+                Some(json!({ "merchant_id": merchant_id, "send_asset": "USDC", "send_amount": amount })),
+            )
+            .await;
+        status
+    }
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_large_payments_wait_for_accepted_kyc() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant().await;
+    let (user_id, address) = ctx.register_user().await;
+    ctx.mock_web_auth(&address);
+
+    // No profile yet: small payments go through, large ones do not
+    assert_eq!(ctx.pay(&user_id, &merchant_id, 1000).await, StatusCode::OK);
+    assert_eq!(
+        ctx.pay(&user_id, &merchant_id, THRESHOLD).await,
+        StatusCode::FORBIDDEN
+    );
+
+    let customer_id = ctx.mock_put_customer(&address);
+    let mut processing = ctx.mock_customer_status(&customer_id, "PROCESSING");
+    let (status, body) = ctx.submit(&user_id, "basic", basic_fields()).await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert_eq!(body["status"], "processing");
+    assert_eq!(body["level"], "none");
+    assert_eq!(body["requested_level"], "basic");
+    assert_eq!(
+        ctx.pay(&user_id, &merchant_id, THRESHOLD).await,
+        StatusCode::FORBIDDEN
+    );
+
+    processing.delete();
+    ctx.mock_customer_status(&customer_id, "ACCEPTED");
+    let (status, body) = ctx
+        .send(
+            "POST",
+            "/callbacks/sep12",
+            None,
+            Some(json!({ "id": customer_id, "status": "ACCEPTED" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert_eq!(body["level"], "basic");
+
+    assert_eq!(
+        ctx.pay(&user_id, &merchant_id, THRESHOLD).await,
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_withdrawals_need_full_kyc() {
+    let ctx = setup().await;
+    let (user_id, address) = ctx.register_user().await;
+    ctx.mock_web_auth(&address);
+
+    let customer_id = ctx.mock_put_customer(&address);
+    ctx.mock_customer_status(&customer_id, "ACCEPTED");
+    let (status, body) = ctx.submit(&user_id, "basic", basic_fields()).await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert_eq!(body["level"], "basic");
+
+    let (status, body) = ctx
+        .send(
+            "POST",
+            "/withdrawals/withdrawals",
+            Some(&ctx.token(&user_id)),
+            Some(json!({ "amount": 1000, "asset": "XLM" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN, "{:?}", body);
+    assert!(body["message"].as_str().unwrap().contains("Full KYC"));
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_rejection_found_by_polling_revokes_level() {
+    let ctx = setup().await;
+    let (user_id, address) = ctx.register_user().await;
+    ctx.mock_web_auth(&address);
+
+    let customer_id = ctx.mock_put_customer(&address);
+    let mut accepted = ctx.mock_customer_status(&customer_id, "ACCEPTED");
+    ctx.submit(&user_id, "basic", basic_fields()).await;
+
+    // Upgrading to full keeps basic while the anchor reviews it
+    accepted.delete();
+    let mut needs_info = ctx.mock_customer_status(&customer_id, "NEEDS_INFO");
+    let mut fields = basic_fields();
+    for (name, value) in [
+        ("birth_date", "1815-12-10"),
+        ("address", "12 St James's Square, London"),
+        ("id_type", "passport"),
+        ("id_number", "123456789"),
+    ] {
+        fields[name] = json!(value);
+    }
+    let (status, body) = ctx.submit(&user_id, "full", fields).await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert_eq!(body["status"], "needs_info");
+    assert_eq!(body["level"], "basic");
+
+    needs_info.delete();
+    ctx.mock_customer_status(&customer_id, "REJECTED");
+    ctx.services.kyc.poll_pending().await.unwrap();
+
+    let (status, body) = ctx
+        .send("GET", "/kyc/profile", Some(&ctx.token(&user_id)), None)
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert_eq!(body["status"], "rejected");
+    assert_eq!(body["level"], "none");
+    assert_eq!(body["message"], "Document could not be verified");
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_submission_must_cover_the_level() {
+    let ctx = setup().await;
+    let (user_id, _) = ctx.register_user().await;
+
+    let (status, body) = ctx.submit(&user_id, "full", basic_fields()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["message"]
+        .as_str()
+        .unwrap()
+        .contains("birth_date, address, id_type, id_number"));
+
+    let (status, _) = ctx
+        .send("GET", "/kyc/profile", Some(&ctx.token(&user_id)), None)
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
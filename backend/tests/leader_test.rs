@@ -0,0 +1,149 @@
+//! Worker leader election tests against the database: one instance leads a
+//! worker at a time, leadership passes on when the leader goes away, and the
+//! admin system health endpoint reports who leads.
+//!
+//! These tests require a running database as defined in the config/env.
+//! Run with: cargo test --test leader_test -- --ignored
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+    Router,
+};
+use serde_json::Value;
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+use tower::util::ServiceExt; // for oneshot
+
+use zaps_backend::{
+    app::build_router, auth, config::Config, db, role::Role, service::ServiceContainer,
+};
+
+/// One backend instance
+struct Instance {
+    app: Router,
+    services: Arc<ServiceContainer>,
+    config: Config,
+}
+
+async fn start_instance(name: &str) -> Instance {
+    let mut config = Config::load().expect("Failed to load config");
+    config.workers.instance_id = format!("{}_{}", name, uuid::Uuid::new_v4().simple());
+
+    db::run_migrations(&config.database.url)
+        .await
+        .expect("Failed to run database migrations");
+
+    let pool = db::create_pool(&config.database.url)
+        .await
+        .expect("Failed to create pool");
+    let services = Arc::new(
+        ServiceContainer::new(pool, config.clone())
+            .await
+            .expect("Failed to create services"),
+    );
+
+    Instance {
+        app: build_router(services.clone()),
+        services,
+        config,
+    }
+}
+
+/// A worker name no other test run competes for
+fn worker_name() -> &'static str {
+    Box::leak(format!("test worker {}", uuid::Uuid::new_v4().simple()).into_boxed_str())
+}
+
+impl Instance {
+    fn id(&self) -> &str {
+        &self.config.workers.instance_id
+    }
+
+    /// The system health entry of `worker`
+    async fn health_of(&self, worker: &str) -> Value {
+        let token =
+            auth::generate_access_token("admin", Role::Admin, &self.config.jwt.secret, 1).unwrap();
+        let request = Request::builder()
+            .uri("/admin/system/health")
+            .header("Authorization", format!("Bearer {}", token))
+            .extension(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))))
+            .body(Body::empty())
+            .unwrap();
+        let response = self.app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let health: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(health["instance_id"], self.id());
+        health["workers"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|entry| entry["worker"] == worker)
+            .cloned()
+            .unwrap_or(Value::Null)
+    }
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_one_instance_leads_a_worker_until_it_goes_away() {
+    let first = start_instance("first").await;
+    let second = start_instance("second").await;
+    let worker = worker_name();
+
+    let mut first_lock = first.services.leader.lock(worker);
+    let mut second_lock = second.services.leader.lock(worker);
+
+    assert!(first_lock.acquire().await);
+    assert!(!second_lock.acquire().await);
+    // The leader keeps leading on later ticks
+    assert!(first_lock.acquire().await);
+    assert!(!second_lock.acquire().await);
+
+    let entry = first.health_of(worker).await;
+    assert_eq!(entry["leader"], first.id());
+    assert_eq!(entry["is_this_instance"], true);
+    let entry = second.health_of(worker).await;
+    assert_eq!(entry["leader"], first.id());
+    assert_eq!(entry["is_this_instance"], false);
+
+    // The leader crashing closes its lock session, freeing the lock
+    drop(first_lock);
+    let mut taken_over = false;
+    for _ in 0..50 {
+        if second_lock.acquire().await {
+            taken_over = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(taken_over, "the second instance never took over");
+
+    let entry = second.health_of(worker).await;
+    assert_eq!(entry["leader"], second.id());
+    assert_eq!(entry["is_this_instance"], true);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_workers_are_led_independently() {
+    let first = start_instance("first").await;
+    let second = start_instance("second").await;
+    let indexing = worker_name();
+    let webhooks = worker_name();
+
+    let mut first_indexing = first.services.leader.lock(indexing);
+    let mut second_webhooks = second.services.leader.lock(webhooks);
+    let mut first_webhooks = first.services.leader.lock(webhooks);
+
+    assert!(first_indexing.acquire().await);
+    assert!(second_webhooks.acquire().await);
+    assert!(!first_webhooks.acquire().await);
+
+    assert_eq!(first.health_of(indexing).await["leader"], first.id());
+    assert_eq!(first.health_of(webhooks).await["leader"], second.id());
+}
@@ -0,0 +1,691 @@
+//! Merchant management tests: operators onboard and manage their own
+//! merchants, admins manage every merchant, and changes the registry contract
+//! cannot mirror are refused while it is configured. Merchant API keys are
+//! scoped, tied to their merchant, and stop working once revoked. New and
+//! deactivated merchants reach the registry through its worker.
+//!
+//! These tests require a running database as defined in the config/env.
+//! Run with: cargo test --test merchant_test -- --ignored
+
+mod common;
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+    Router,
+};
+use httpmock::prelude::*;
+use serde_json::{json, Value};
+use std::{net::SocketAddr, sync::Arc};
+use tower::util::ServiceExt; // for oneshot
+
+use common::{account_entry, rpc_result, transaction_data};
+use zaps_backend::{
+    app::build_router,
+    auth,
+    config::Config,
+    db,
+    role::Role,
+    service::ServiceContainer,
+    stellar::{strkey, Keypair},
+};
+
+const VAULT: &str = "CAAACAQDAQCQMBYIBEFAWDANBYHRAEISCMKBKFQXDAMRUGY4DUPB6N4O";
+const OTHER_VAULT: &str = "GA7QYNF7SOWQ3GLR2BGMZEHXAVIRZA4KVWLTJJFC7MGXUA74P7UJVSGZ";
+const USDC_ISSUER: &str = "GBBD47IF6LWK7P7MDEVSCWR7DPUWV3NY3DTQEVFL4NAT4AQH3ZLLFLA5";
+
+struct TestContext {
+    app: Router,
+    services: Arc<ServiceContainer>,
+    config: Config,
+}
+
+/// Set up with merchant changes mirrored to a registry contract, or without one
+async fn setup(registry: Option<&str>) -> TestContext {
+    setup_with(|config| config.contracts.zaps_registry = registry.unwrap_or_default().to_string())
+        .await
+}
+
+async fn setup_with(configure: impl FnOnce(&mut Config)) -> TestContext {
+    let mut config = Config::load().expect("Failed to load config");
+    config.stellar_network.assets = [("usdc".to_string(), USDC_ISSUER.to_string())]
+        .into_iter()
+        .collect();
+    configure(&mut config);
+
+    db::run_migrations(&config.database.url)
+        .await
+        .expect("Failed to run database migrations");
+
+    let pool = db::create_pool(&config.database.url)
+        .await
+        .expect("Failed to create pool");
+    let services = Arc::new(
+        ServiceContainer::new(pool, config.clone())
+            .await
+            .expect("Failed to create services"),
+    );
+    let app = build_router(services.clone());
+
+    TestContext {
+        app,
+        services,
+        config,
+    }
+}
+
+impl TestContext {
+    fn token(&self, user_id: &str, role: Role) -> String {
+        auth::generate_access_token(user_id, role, &self.config.jwt.secret, 1).unwrap()
+    }
+
+    /// Register a user and return a token for it with `role`
+    async fn register_user(&self, role: Role) -> (String, String) {
+        let user_id = format!("operator_{}", uuid::Uuid::new_v4().simple());
+        let (status, _) = self
+            .send(
+                "POST",
+                "/auth/register",
+                None,
+                json!({ "user_id": user_id, "pin": "1234" }),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let token = self.token(&user_id, role);
+        (user_id, token)
+    }
+
+    async fn send(
+        &self,
+        method: &str,
+        uri: &str,
+        token: Option<&str>,
+        body: Value,
+    ) -> (StatusCode, Value) {
+        let auth = token.map(|token| ("Authorization", format!("Bearer {}", token)));
+        self.send_with(method, uri, auth, body).await
+    }
+
+    /// Send a request to the merchant API with an `X-API-KEY`
+    async fn send_with_key(
+        &self,
+        method: &str,
+        uri: &str,
+        api_key: &str,
+        body: Value,
+    ) -> (StatusCode, Value) {
+        self.send_with(method, uri, Some(("X-API-KEY", api_key.to_string())), body)
+            .await
+    }
+
+    async fn send_with(
+        &self,
+        method: &str,
+        uri: &str,
+        credential: Option<(&str, String)>,
+        body: Value,
+    ) -> (StatusCode, Value) {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("Content-Type", "application/json")
+            .extension(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+        if let Some((header, value)) = credential {
+            request = request.header(header, value);
+        }
+        let body = match body {
+            Value::Null => Body::empty(),
+            body => Body::from(body.to_string()),
+        };
+
+        let response = self
+            .app
+            .clone()
+            .oneshot(request.body(body).unwrap())
+            .await
+            .unwrap();
+
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (
+            status,
+            serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        )
+    }
+}
+
+fn new_merchant() -> Value {
+    json!({
+        "merchant_id": format!("shop_{}", uuid::Uuid::new_v4().simple()),
+        "business_name": "Corner Coffee",
+        "contact_email": "owner@corner.example",
+        "vault_address": VAULT,
+        "settlement_asset": "usdc",
+    })
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_merchant_onboards_and_manages_itself() {
+    let ctx = setup(None).await;
+    let (_, token) = ctx.register_user(Role::Merchant).await;
+    let (_, stranger) = ctx.register_user(Role::Merchant).await;
+
+    let request = new_merchant();
+    let merchant_id = request["merchant_id"].as_str().unwrap().to_string();
+    let (status, body) = ctx
+        .send("POST", "/merchants", Some(&token), request.clone())
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert_eq!(body["settlement_asset"], "USDC");
+    assert_eq!(body["business_name"], "Corner Coffee");
+    assert_eq!(body["active"], true);
+
+    let (status, _) = ctx.send("POST", "/merchants", Some(&token), request).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, body) = ctx
+        .send("GET", "/merchants", Some(&token), Value::Null)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.as_array().unwrap().len(), 1);
+    assert_eq!(body[0]["merchant_id"], merchant_id.as_str());
+
+    // Other operators cannot see or change it
+    let path = format!("/merchants/{}", merchant_id);
+    let (status, _) = ctx.send("GET", &path, Some(&stranger), Value::Null).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = ctx
+        .send(
+            "PATCH",
+            &path,
+            Some(&stranger),
+            json!({ "business_name": "Mine" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, body) = ctx
+        .send(
+            "PATCH",
+            &path,
+            Some(&token),
+            json!({ "website": "https://corner.example", "vault_address": OTHER_VAULT }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert_eq!(body["website"], "https://corner.example");
+    assert_eq!(body["vault_address"], OTHER_VAULT);
+    assert_eq!(body["business_name"], "Corner Coffee");
+
+    let (status, _) = ctx
+        .send(
+            "PATCH",
+            &path,
+            Some(&token),
+            json!({ "settlement_asset": "EURC" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = ctx
+        .send(
+            "POST",
+            &format!("{}/deactivate", path),
+            Some(&token),
+            Value::Null,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["active"], false);
+
+    // Only admins can bring it back
+    let reactivate = format!("{}/reactivate", path);
+    let (status, _) = ctx
+        .send("POST", &reactivate, Some(&token), Value::Null)
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let admin = ctx.token("admin", Role::Admin);
+    let (status, body) = ctx
+        .send("POST", &reactivate, Some(&admin), Value::Null)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["active"], true);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_admin_onboards_merchant_for_operator() {
+    let ctx = setup(None).await;
+    let admin = ctx.token("admin", Role::Admin);
+    let (operator_id, operator) = ctx.register_user(Role::Merchant).await;
+    let (user_id, user) = ctx.register_user(Role::User).await;
+
+    // Plain users cannot onboard merchants
+    let (status, _) = ctx
+        .send("POST", "/merchants", Some(&user), new_merchant())
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let mut request = new_merchant();
+    request["operator_user_id"] = json!(operator_id);
+    let merchant_id = request["merchant_id"].as_str().unwrap().to_string();
+    let (status, body) = ctx.send("POST", "/merchants", Some(&admin), request).await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+
+    let path = format!("/merchants/{}", merchant_id);
+    let (status, _) = ctx.send("GET", &path, Some(&operator), Value::Null).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // The operator can bring in a colleague
+    let (status, _) = ctx.send("GET", &path, Some(&user), Value::Null).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = ctx
+        .send(
+            "POST",
+            &format!("{}/operators", path),
+            Some(&operator),
+            json!({ "user_id": user_id }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = ctx.send("GET", &path, Some(&user), Value::Null).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = ctx
+        .send("GET", "/merchants", Some(&admin), Value::Null)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|merchant| merchant["merchant_id"] == merchant_id.as_str()));
+
+    let mut invalid = new_merchant();
+    invalid["merchant_id"] = json!("has spaces");
+    let (status, _) = ctx.send("POST", "/merchants", Some(&admin), invalid).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_registry_blocks_changes_it_cannot_mirror() {
+    let ctx = setup(Some(VAULT)).await;
+    let admin = ctx.token("admin", Role::Admin);
+
+    // Merchants that predate the API are only in the database
+    let merchant_id = format!("shop_{}", uuid::Uuid::new_v4().simple());
+    let config = ctx.config.clone();
+    let pool = db::create_pool(&config.database.url).await.unwrap();
+    pool.get()
+        .await
+        .unwrap()
+        .execute(
+            "INSERT INTO merchants (merchant_id, vault_address, settlement_asset, active) VALUES ($1, $2, 'USDC', false)",
+            &[&merchant_id, &VAULT],
+        )
+        .await
+        .unwrap();
+
+    let path = format!("/merchants/{}", merchant_id);
+    let (status, body) = ctx
+        .send(
+            "PATCH",
+            &path,
+            Some(&admin),
+            json!({ "vault_address": OTHER_VAULT }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT, "{:?}", body);
+
+    // Profile changes do not touch the registry
+    let (status, body) = ctx
+        .send(
+            "PATCH",
+            &path,
+            Some(&admin),
+            json!({ "business_name": "Renamed" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert_eq!(body["business_name"], "Renamed");
+
+    let (status, _) = ctx
+        .send(
+            "POST",
+            &format!("{}/reactivate", path),
+            Some(&admin),
+            Value::Null,
+        )
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_registry_worker_registers_and_deactivates() {
+    let server = MockServer::start();
+    let admin_key = Keypair::from_seed([5u8; 32]).unwrap();
+    let ctx = setup_with(|config| {
+        config.stellar_network.rpc_url = server.url("/");
+        config.contracts.zaps_registry = strkey::encode_contract(&[7u8; 32]);
+        config.contracts.registry_admin_secret = Some(admin_key.secret_seed());
+    })
+    .await;
+    let admin = ctx.token("admin", Role::Admin);
+
+    let entry = account_entry(&admin_key.address(), 41);
+    let mut sign = server.mock(|when, then| {
+        when.method(POST)
+            .json_body_partial(r#"{"method":"getLedgerEntries"}"#);
+        then.status(200).json_body(rpc_result(json!({
+            "entries": [{ "key": "", "xdr": entry, "lastModifiedLedgerSeq": 100 }],
+            "latestLedger": 120,
+        })));
+    });
+    let mut simulate = server.mock(|when, then| {
+        when.method(POST)
+            .json_body_partial(r#"{"method":"simulateTransaction"}"#);
+        then.status(200).json_body(rpc_result(json!({
+            "latestLedger": 120,
+            "minResourceFee": "54321",
+            "transactionData": transaction_data(),
+            "results": [{ "auth": [], "xdr": "AAAAAQ==" }],
+        })));
+    });
+    let mut send = server.mock(|when, then| {
+        when.method(POST)
+            .json_body_partial(r#"{"method":"sendTransaction"}"#);
+        then.status(200).json_body(rpc_result(json!({
+            "status": "PENDING",
+            "hash": "00".repeat(32),
+            "latestLedger": 120,
+        })));
+    });
+    let mut landed = transaction_status(&server, "NOT_FOUND");
+
+    // The merchant is committed first and takes no payments until registered
+    let (status, merchant) = ctx
+        .send("POST", "/merchants", Some(&admin), new_merchant())
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", merchant);
+    assert_eq!(merchant["active"], false);
+    assert_eq!(merchant["registry_status"], "registering");
+    let path = format!("/merchants/{}", merchant["merchant_id"].as_str().unwrap());
+    sign.assert_hits(0);
+
+    let (status, _) = ctx
+        .send(
+            "POST",
+            &format!("{}/deactivate", path),
+            Some(&admin),
+            Value::Null,
+        )
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // Resent as signed until it lands
+    ctx.services.merchant.sync_registry().await.unwrap();
+    ctx.services.merchant.sync_registry().await.unwrap();
+    sign.assert_hits(1);
+    send.assert_hits(2);
+    let (_, merchant) = ctx.send("GET", &path, Some(&admin), Value::Null).await;
+    assert_eq!(merchant["registry_status"], "registering");
+
+    landed.delete();
+    landed = transaction_status(&server, "SUCCESS");
+    ctx.services.merchant.sync_registry().await.unwrap();
+    sign.assert_hits(1);
+    send.assert_hits(2);
+    let (_, merchant) = ctx.send("GET", &path, Some(&admin), Value::Null).await;
+    assert_eq!(merchant["active"], true);
+    assert_eq!(merchant["registry_status"], "registered");
+
+    // Deactivation takes effect here at once and reaches the registry afterwards
+    let (status, merchant) = ctx
+        .send(
+            "POST",
+            &format!("{}/deactivate", path),
+            Some(&admin),
+            Value::Null,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", merchant);
+    assert_eq!(merchant["active"], false);
+    assert_eq!(merchant["registry_status"], "deactivating");
+
+    ctx.services.merchant.sync_registry().await.unwrap();
+    sign.assert_hits(2);
+    simulate.assert_hits(2);
+    let (_, merchant) = ctx.send("GET", &path, Some(&admin), Value::Null).await;
+    assert_eq!(merchant["active"], false);
+    assert_eq!(merchant["registry_status"], "deactivated");
+
+    sign.delete();
+    simulate.delete();
+    send.delete();
+    landed.delete();
+}
+
+/// Mock `getTransaction` reporting `status` for any hash
+fn transaction_status<'a>(server: &'a MockServer, status: &str) -> httpmock::Mock<'a> {
+    let body = rpc_result(json!({
+        "status": status,
+        "latestLedger": 121,
+        "ledger": (status != "NOT_FOUND").then_some(121),
+    }));
+    server.mock(|when, then| {
+        when.method(POST)
+            .json_body_partial(r#"{"method":"getTransaction"}"#);
+        then.status(200).json_body(body);
+    })
+}
+
+impl TestContext {
+    /// Onboard a merchant operated by a new merchant user, returning its id and the operator's token
+    async fn onboard_merchant(&self) -> (String, String) {
+        let (_, token) = self.register_user(Role::Merchant).await;
+        let request = new_merchant();
+        let (status, body) = self
+            .send("POST", "/merchants", Some(&token), request.clone())
+            .await;
+        assert_eq!(status, StatusCode::OK, "{:?}", body);
+        (request["merchant_id"].as_str().unwrap().to_string(), token)
+    }
+
+    async fn create_api_key(&self, merchant_id: &str, token: &str, scopes: Value) -> Value {
+        let (status, body) = self
+            .send(
+                "POST",
+                &format!("/merchants/{}/api-keys", merchant_id),
+                Some(token),
+                json!({ "name": "Checkout server", "scopes": scopes }),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK, "{:?}", body);
+        body
+    }
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_api_key_creates_and_reads_payments() {
+    let ctx = setup(None).await;
+    let (merchant_id, token) = ctx.onboard_merchant().await;
+    let (customer_id, _) = ctx.register_user(Role::User).await;
+
+    let created = ctx
+        .create_api_key(
+            &merchant_id,
+            &token,
+            json!(["payments:write", "payments:read"]),
+        )
+        .await;
+    let key = created["key"].as_str().unwrap().to_string();
+    assert!(key.starts_with(created["prefix"].as_str().unwrap()));
+    assert!(key.starts_with("zk_"));
+
+    // Listings never show the key again
+    let (status, body) = ctx
+        .send(
+            "GET",
+            &format!("/merchants/{}/api-keys", merchant_id),
+            Some(&token),
+            Value::Null,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.as_array().unwrap().len(), 1);
+    assert!(body[0].get("key").is_none());
+    assert!(body[0].get("key_hash").is_none());
+
+    let payment = json!({
+        "customer_user_id": customer_id,
+        "reason": "Order 1042",
+        "merchant_id": merchant_id,
+        "send_asset": "USDC",
+        "send_amount": 1000,
+    });
+    let (status, body) = ctx
+        .send_with_key("POST", "/merchant-api/payments", &key, payment.clone())
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    let payment_id = body["id"].as_str().unwrap().to_string();
+
+    let (status, body) = ctx
+        .send_with_key(
+            "GET",
+            &format!("/merchant-api/payments/{}", payment_id),
+            &key,
+            Value::Null,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert_eq!(body["merchant_id"], merchant_id.as_str());
+
+    // A key only acts for its own merchant
+    let (other_merchant, _) = ctx.onboard_merchant().await;
+    let mut elsewhere = payment;
+    elsewhere["merchant_id"] = json!(other_merchant);
+    let (status, _) = ctx
+        .send_with_key("POST", "/merchant-api/payments", &key, elsewhere)
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_api_key_scopes_and_revocation() {
+    let ctx = setup(None).await;
+    let (merchant_id, token) = ctx.onboard_merchant().await;
+    let (_, stranger) = ctx.register_user(Role::Merchant).await;
+
+    // Only the merchant's operators manage its keys
+    let (status, _) = ctx
+        .send(
+            "POST",
+            &format!("/merchants/{}/api-keys", merchant_id),
+            Some(&stranger),
+            json!({ "name": "Mine", "scopes": ["payments:read"] }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = ctx
+        .send(
+            "POST",
+            &format!("/merchants/{}/api-keys", merchant_id),
+            Some(&token),
+            json!({ "name": "Nothing", "scopes": [] }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let created = ctx
+        .create_api_key(&merchant_id, &token, json!(["payments:read"]))
+        .await;
+    let key = created["key"].as_str().unwrap().to_string();
+
+    let (status, body) = ctx
+        .send_with_key("POST", "/merchant-api/payments", &key, json!({}))
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{:?}", body);
+    let (status, body) = ctx
+        .send_with_key(
+            "POST",
+            "/merchant-api/payments",
+            &key,
+            json!({
+                "customer_user_id": "nobody",
+                "reason": "Order",
+                "merchant_id": merchant_id,
+                "send_asset": "USDC",
+                "send_amount": 1000,
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN, "{:?}", body);
+
+    // Keys are not JWTs and JWTs are not keys
+    let (status, _) = ctx
+        .send(
+            "GET",
+            &format!("/merchant-api/payments/{}", uuid::Uuid::new_v4()),
+            Some(&token),
+            Value::Null,
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = ctx
+        .send_with_key("GET", "/merchants", &key, Value::Null)
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = ctx
+        .send_with_key(
+            "GET",
+            &format!("/merchant-api/payments/{}", uuid::Uuid::new_v4()),
+            &format!("{}x", key),
+            Value::Null,
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = ctx
+        .send_with_key(
+            "GET",
+            &format!("/merchant-api/payments/{}", uuid::Uuid::new_v4()),
+            &key,
+            Value::Null,
+        )
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, body) = ctx
+        .send(
+            "DELETE",
+            &format!(
+                "/merchants/{}/api-keys/{}",
+                merchant_id,
+                created["id"].as_str().unwrap()
+            ),
+            Some(&token),
+            Value::Null,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["revoked_at"].is_string());
+
+    let (status, _) = ctx
+        .send_with_key(
+            "GET",
+            &format!("/merchant-api/payments/{}", uuid::Uuid::new_v4()),
+            &key,
+            Value::Null,
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,788 @@
+//! Payment handler tests: payer identity comes from the JWT, and paying on
+//! behalf of a customer is restricted to merchants/admins and audited, and
+//! status changes follow the payment lifecycle and are recorded. Payments are
+//! visible to their payer, their merchant's operators and admins. QR payment
+//! requests are signed SEP-7 URIs whose nonce the payment redeems once, and NFC
+//! taps must be signed by an active terminal of the merchant, recently, and
+//! are spent by the payment made with them.
+//!
+//! These tests require a running database as defined in the config/env.
+//! Run with: cargo test --test payment_handler_test -- --ignored
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+    Router,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use tower::util::ServiceExt; // for oneshot
+
+use zaps_backend::{
+    app::create_app,
+    auth,
+    config::Config,
+    db,
+    http::payments::NfcPaymentRequest,
+    models::PaymentStatus,
+    role::Role,
+    service::{payment_service::nfc_signing_payload, PaymentService, ServiceContainer},
+    stellar::{sep7, Keypair},
+};
+
+const OTHER_ACCOUNT: &str = "GA7QYNF7SOWQ3GLR2BGMZEHXAVIRZA4KVWLTJJFC7MGXUA74P7UJVSGZ";
+const VAULT: &str = "CAAACAQDAQCQMBYIBEFAWDANBYHRAEISCMKBKFQXDAMRUGY4DUPB6N4O";
+
+struct TestContext {
+    app: Router,
+    pool: deadpool_postgres::Pool,
+    config: Config,
+}
+
+fn uri_signing_key() -> Keypair {
+    Keypair::from_seed([5u8; 32]).unwrap()
+}
+
+async fn setup() -> TestContext {
+    let mut config = Config::load().expect("Failed to load config");
+    config.sep7.signing_secret = Some(uri_signing_key().secret_seed());
+    db::run_migrations(&config.database.url)
+        .await
+        .expect("Failed to run database migrations");
+
+    let pool = db::create_pool(&config.database.url)
+        .await
+        .expect("Failed to create pool");
+    let app = create_app(pool.clone(), config.clone())
+        .await
+        .expect("Failed to create app");
+
+    TestContext { app, pool, config }
+}
+
+impl TestContext {
+    async fn payment_service(&self) -> PaymentService {
+        ServiceContainer::new(self.pool.clone(), self.config.clone())
+            .await
+            .expect("Failed to create services")
+            .payment
+    }
+
+    fn token(&self, user_id: &str, role: Role) -> String {
+        auth::generate_access_token(user_id, role, &self.config.jwt.secret, 1).unwrap()
+    }
+
+    async fn create_merchant(&self) -> String {
+        self.create_merchant_with_vault(VAULT).await
+    }
+
+    async fn create_merchant_with_vault(&self, vault_address: &str) -> String {
+        let merchant_id = format!("merchant_{}", uuid::Uuid::new_v4().simple());
+        let client = self.pool.get().await.unwrap();
+        client
+            .execute(
+                "INSERT INTO merchants (merchant_id, vault_address, settlement_asset) VALUES ($1, $2, 'USDC')",
+                &[&merchant_id, &vault_address],
+            )
+            .await
+            .unwrap();
+        merchant_id
+    }
+
+    /// A merchant paid into an account rather than a contract vault, and a token of its operator
+    async fn create_account_merchant(&self) -> (String, String) {
+        let merchant_id = self.create_merchant_with_vault(OTHER_ACCOUNT).await;
+        let (operator, _) = self.register_user().await;
+        let client = self.pool.get().await.unwrap();
+        client
+            .execute(
+                "INSERT INTO merchant_users (merchant_id, user_id) VALUES ($1, $2)",
+                &[&merchant_id, &operator],
+            )
+            .await
+            .unwrap();
+        (merchant_id, self.token(&operator, Role::Merchant))
+    }
+
+    /// Register a user and return its user_id and wallet address
+    async fn register_user(&self) -> (String, String) {
+        let user_id = format!("payer_{}", uuid::Uuid::new_v4().simple());
+        let (status, _) = self
+            .post(
+                "/auth/register",
+                None,
+                json!({ "user_id": user_id, "pin": "1234" }),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let client = self.pool.get().await.unwrap();
+        let row = client
+            .query_one(
+                "SELECT stellar_address FROM users WHERE user_id = $1",
+                &[&user_id],
+            )
+            .await
+            .unwrap();
+        (user_id, row.get(0))
+    }
+
+    async fn post(&self, uri: &str, token: Option<&str>, body: Value) -> (StatusCode, Value) {
+        self.send("POST", uri, token, Body::from(body.to_string()))
+            .await
+    }
+
+    async fn get(&self, uri: &str, token: &str) -> (StatusCode, Value) {
+        self.send("GET", uri, Some(token), Body::empty()).await
+    }
+
+    async fn send(
+        &self,
+        method: &str,
+        uri: &str,
+        token: Option<&str>,
+        body: Body,
+    ) -> (StatusCode, Value) {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("Content-Type", "application/json")
+            .extension(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+        if let Some(token) = token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+
+        let response = self
+            .app
+            .clone()
+            .oneshot(request.body(body).unwrap())
+            .await
+            .unwrap();
+
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (
+            status,
+            serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        )
+    }
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_create_payment_uses_caller_wallet() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant().await;
+    let (user_id, address) = ctx.register_user().await;
+
+    let (status, body) = ctx
+        .post(
+            "/payments/payments",
+            Some(&ctx.token(&user_id, Role::User)),
+            json!({ "merchant_id": merchant_id, "send_asset": "USDC", "send_amount": 1000 }),
+        )
+        .await;
+
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert_eq!(body["from_address"], address);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_create_payment_rejects_other_wallet() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant().await;
+    let (user_id, _) = ctx.register_user().await;
+
+    let (status, body) = ctx
+        .post(
+            "/payments/payments",
+            Some(&ctx.token(&user_id, Role::User)),
+            json!({
+                "from_address": OTHER_ACCOUNT,
+                "merchant_id": merchant_id,
+                "send_asset": "USDC",
+                "send_amount": 1000
+            }),
+        )
+        .await;
+
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "AUTHORIZATION_FAILED");
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_on_behalf_requires_merchant_or_admin() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant().await;
+    let (user_id, _) = ctx.register_user().await;
+    let (customer_id, _) = ctx.register_user().await;
+
+    let (status, _) = ctx
+        .post(
+            "/payments/payments/on-behalf",
+            Some(&ctx.token(&user_id, Role::User)),
+            json!({
+                "customer_user_id": customer_id,
+                "reason": "in-store checkout",
+                "merchant_id": merchant_id,
+                "send_asset": "USDC",
+                "send_amount": 1000
+            }),
+        )
+        .await;
+
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_on_behalf_payment_is_audited() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant().await;
+    let (customer_id, customer_address) = ctx.register_user().await;
+    let actor_id = format!("merchant_user_{}", uuid::Uuid::new_v4().simple());
+
+    let (status, body) = ctx
+        .post(
+            "/payments/payments/on-behalf",
+            Some(&ctx.token(&actor_id, Role::Merchant)),
+            json!({
+                "customer_user_id": customer_id,
+                "reason": "in-store checkout",
+                "merchant_id": merchant_id,
+                "send_asset": "USDC",
+                "send_amount": 1000
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert_eq!(body["from_address"], customer_address);
+
+    let client = ctx.pool.get().await.unwrap();
+    let row = client
+        .query_one(
+            "SELECT actor_id, metadata FROM audit_logs WHERE action = 'create_payment_on_behalf' AND resource_id = $1",
+            &[&body["id"].as_str().unwrap()],
+        )
+        .await
+        .unwrap();
+    let metadata: Value = row.get(1);
+    assert_eq!(row.get::<_, String>(0), actor_id);
+    assert_eq!(metadata["customer_user_id"], customer_id);
+    assert_eq!(metadata["reason"], "in-store checkout");
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_on_behalf_requires_reason() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant().await;
+    let (customer_id, _) = ctx.register_user().await;
+
+    let (status, _) = ctx
+        .post(
+            "/payments/payments/on-behalf",
+            Some(&ctx.token("admin", Role::Admin)),
+            json!({
+                "customer_user_id": customer_id,
+                "reason": " ",
+                "merchant_id": merchant_id,
+                "send_asset": "USDC",
+                "send_amount": 1000
+            }),
+        )
+        .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_get_payment_includes_status_history() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant().await;
+    let (user_id, _) = ctx.register_user().await;
+    let token = ctx.token(&user_id, Role::User);
+
+    let (status, body) = ctx
+        .post(
+            "/payments/payments",
+            Some(&token),
+            json!({ "merchant_id": merchant_id, "send_asset": "USDC", "send_amount": 1000 }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    let payment_id = uuid::Uuid::parse_str(body["id"].as_str().unwrap()).unwrap();
+
+    let service = ctx.payment_service().await;
+    service
+        .update_payment_status(payment_id, PaymentStatus::Processing, "submitted", None)
+        .await
+        .unwrap();
+
+    let (status, body) = ctx
+        .get(&format!("/payments/payments/{}", payment_id), &token)
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert_eq!(body["status"], "processing");
+
+    let history = body["history"].as_array().unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0]["from_status"], Value::Null);
+    assert_eq!(history[0]["to_status"], "pending");
+    assert_eq!(history[1]["from_status"], "pending");
+    assert_eq!(history[1]["to_status"], "processing");
+    assert_eq!(history[1]["reason"], "submitted");
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_payment_visible_to_payer_operators_and_admins_only() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant().await;
+    let (user_id, _) = ctx.register_user().await;
+    let (operator, _) = ctx.register_user().await;
+    let (outsider, _) = ctx.register_user().await;
+    let client = ctx.pool.get().await.unwrap();
+    client
+        .execute(
+            "INSERT INTO merchant_users (merchant_id, user_id) VALUES ($1, $2)",
+            &[&merchant_id, &operator],
+        )
+        .await
+        .unwrap();
+
+    let (status, body) = ctx
+        .post(
+            "/payments/payments",
+            Some(&ctx.token(&user_id, Role::User)),
+            json!({ "merchant_id": merchant_id, "send_asset": "USDC", "send_amount": 1000 }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    let payment_id = body["id"].as_str().unwrap().to_string();
+
+    for (viewer, role, expected) in [
+        (&user_id, Role::User, StatusCode::OK),
+        (&operator, Role::Merchant, StatusCode::OK),
+        (&outsider, Role::User, StatusCode::NOT_FOUND),
+        (&outsider, Role::Admin, StatusCode::OK),
+    ] {
+        let token = ctx.token(viewer, role);
+        for uri in [
+            format!("/payments/payments/{}", payment_id),
+            format!("/payments/payments/{}/status", payment_id),
+        ] {
+            let (status, _) = ctx.get(&uri, &token).await;
+            assert_eq!(status, expected, "{} as {:?}", uri, role);
+        }
+    }
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_illegal_payment_transition_is_rejected() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant().await;
+    let (_, address) = ctx.register_user().await;
+
+    let service = ctx.payment_service().await;
+    let payment = service
+        .create_payment(
+            address,
+            serde_json::from_value(json!({
+                "merchant_id": merchant_id,
+                "send_asset": "USDC",
+                "send_amount": 1000
+            }))
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+    let payment_id = uuid::Uuid::parse_str(&payment.id).unwrap();
+
+    let err = service
+        .update_payment_status(payment_id, PaymentStatus::Refunded, "refund", None)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, zaps_backend::ApiError::Conflict(_)),
+        "{:?}",
+        err
+    );
+
+    let payment = service.get_payment(payment_id).await.unwrap();
+    assert_eq!(payment.status, PaymentStatus::Pending);
+    assert_eq!(
+        service.get_payment_history(payment_id).await.unwrap().len(),
+        1
+    );
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_qr_payment_request_is_signed_and_redeemed_once() {
+    let ctx = setup().await;
+    let (merchant_id, operator) = ctx.create_account_merchant().await;
+    let (user_id, _) = ctx.register_user().await;
+    let token = ctx.token(&user_id, Role::User);
+    let expiry = chrono::Utc::now().timestamp() + 300;
+    let qr = json!({
+        "merchant_id": merchant_id,
+        "amount": 12_500_000,
+        "asset": "USDC",
+        "memo": "order #42",
+        "expiry": expiry,
+    });
+
+    // Only the merchant's operators and admins sign requests for it
+    let (status, _) = ctx
+        .post("/payments/qr/generate", Some(&token), qr.clone())
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let outsider = ctx.token("operator_elsewhere", Role::Merchant);
+    let (status, _) = ctx
+        .post("/payments/qr/generate", Some(&outsider), qr.clone())
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, body) = ctx.post("/payments/qr/generate", Some(&operator), qr).await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    let uri = body["qr_data"].as_str().unwrap().to_string();
+
+    assert!(uri.starts_with(&format!("web+stellar:pay?destination={}", OTHER_ACCOUNT)));
+    sep7::verify(&uri, &uri_signing_key().public_key()).unwrap();
+    let request = sep7::parse(&uri).unwrap();
+    assert_eq!(request.param("amount"), Some("1.25"));
+    assert_eq!(request.param("asset_code"), Some("USDC"));
+    assert_eq!(request.param("memo"), Some("order #42"));
+    assert_eq!(request.param("memo_type"), Some("MEMO_TEXT"));
+    assert_eq!(request.param("expires"), Some(expiry.to_string().as_str()));
+
+    // Tampering breaks the signature
+    let (status, _) = ctx
+        .post(
+            "/payments/qr/verify",
+            Some(&token),
+            json!({ "uri": uri.replace("amount=1.25", "amount=0.25") }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Verifying does not use the request up
+    for _ in 0..2 {
+        let (status, body) = ctx
+            .post("/payments/qr/verify", Some(&token), json!({ "uri": uri }))
+            .await;
+        assert_eq!(status, StatusCode::OK, "{:?}", body);
+        assert_eq!(body["merchant_id"], merchant_id);
+        assert_eq!(body["amount"], 12_500_000);
+        assert_eq!(body["asset"], "USDC");
+        assert_eq!(body["memo"], "order #42");
+    }
+
+    // The payment must be the one requested
+    let payment = |amount: i64| {
+        json!({
+            "merchant_id": merchant_id,
+            "send_asset": "USDC",
+            "send_amount": amount,
+            "memo": "order #42",
+            "payment_uri": uri,
+        })
+    };
+    let (status, body) = ctx
+        .post("/payments/payments", Some(&token), payment(2_500_000))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["message"].as_str().unwrap().contains("does not match"));
+
+    let (status, body) = ctx
+        .post("/payments/payments", Some(&token), payment(12_500_000))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+
+    // Paying it redeems it
+    let (status, _) = ctx
+        .post("/payments/qr/verify", Some(&token), json!({ "uri": uri }))
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = ctx
+        .post("/payments/payments", Some(&token), payment(12_500_000))
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_qr_payment_request_expiry() {
+    let ctx = setup().await;
+    let (merchant_id, operator) = ctx.create_account_merchant().await;
+    let (user_id, _) = ctx.register_user().await;
+    let token = ctx.token(&user_id, Role::User);
+    let now = chrono::Utc::now().timestamp();
+    let request = |expiry: i64| json!({ "merchant_id": merchant_id, "amount": 1000, "asset": "XLM", "expiry": expiry });
+
+    let (status, _) = ctx
+        .post("/payments/qr/generate", Some(&operator), request(now - 1))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = ctx
+        .post(
+            "/payments/qr/generate",
+            Some(&operator),
+            request(now + ctx.config.sep7.max_ttl_secs + 60),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = ctx
+        .post("/payments/qr/generate", Some(&operator), request(now + 60))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    let uri = body["qr_data"].as_str().unwrap().to_string();
+    assert_eq!(sep7::parse(&uri).unwrap().param("asset_code"), None);
+
+    let client = ctx.pool.get().await.unwrap();
+    client
+        .execute(
+            "UPDATE payment_uri_nonces SET expires_at = NOW() - INTERVAL '1 second' WHERE nonce = $1",
+            &[&sep7::parse(&uri).unwrap().param("nonce").unwrap()],
+        )
+        .await
+        .unwrap();
+
+    let (status, body) = ctx
+        .post("/payments/qr/verify", Some(&token), json!({ "uri": uri }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["message"].as_str().unwrap().contains("expired"));
+    let (status, body) = ctx
+        .post(
+            "/payments/payments",
+            Some(&token),
+            json!({ "merchant_id": merchant_id, "send_asset": "XLM", "send_amount": 1000, "payment_uri": uri }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["message"].as_str().unwrap().contains("expired"));
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_qr_payment_requests_need_an_account_vault() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant().await;
+    let admin = ctx.token("admin", Role::Admin);
+
+    let (status, body) = ctx
+        .post(
+            "/payments/qr/generate",
+            Some(&admin),
+            json!({
+                "merchant_id": merchant_id,
+                "amount": 1000,
+                "asset": "USDC",
+                "expiry": chrono::Utc::now().timestamp() + 60,
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["message"].as_str().unwrap().contains("contract vault"));
+}
+
+/// A tap for 2.5 USDC signed by `key`, as the terminal would emit it
+fn signed_tap(terminal_id: &str, merchant_id: &str, key: &Keypair, timestamp: i64) -> Value {
+    let mut tap = NfcPaymentRequest {
+        terminal_id: terminal_id.parse().unwrap(),
+        merchant_id: merchant_id.to_string(),
+        amount: 25_000_000,
+        asset: "USDC".to_string(),
+        memo: Some("table 7".to_string()),
+        timestamp,
+        nonce: uuid::Uuid::new_v4().simple().to_string(),
+        signature: String::new(),
+    };
+    tap.signature = BASE64.encode(key.sign(&nfc_signing_payload(&tap).unwrap()));
+
+    json!({
+        "terminal_id": tap.terminal_id,
+        "merchant_id": tap.merchant_id,
+        "amount": tap.amount,
+        "asset": tap.asset,
+        "memo": tap.memo,
+        "timestamp": tap.timestamp,
+        "nonce": tap.nonce,
+        "signature": tap.signature,
+    })
+}
+
+impl TestContext {
+    /// Register a fresh terminal key for the merchant, returning its id and key
+    async fn register_terminal(&self, merchant_id: &str) -> (String, Keypair) {
+        let key = Keypair::random().unwrap();
+        let admin = self.token("admin", Role::Admin);
+        let (status, body) = self
+            .post(
+                "/terminals",
+                Some(&admin),
+                json!({ "merchant_id": merchant_id, "public_key": key.address(), "label": "Till 1" }),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK, "{:?}", body);
+        (body["id"].as_str().unwrap().to_string(), key)
+    }
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_nfc_tap_signed_by_terminal_is_paid_once() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant().await;
+    let (terminal_id, key) = ctx.register_terminal(&merchant_id).await;
+    let (user_id, _) = ctx.register_user().await;
+    let token = ctx.token(&user_id, Role::User);
+    let now = chrono::Utc::now().timestamp();
+
+    // Only admins manage terminal keys
+    let (status, _) = ctx
+        .post(
+            "/terminals",
+            Some(&token),
+            json!({ "merchant_id": merchant_id, "public_key": OTHER_ACCOUNT }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Validating does not use the tap up
+    let tap = signed_tap(&terminal_id, &merchant_id, &key, now);
+    for _ in 0..2 {
+        let (status, body) = ctx
+            .post("/payments/nfc/validate", Some(&token), tap.clone())
+            .await;
+        assert_eq!(status, StatusCode::OK, "{:?}", body);
+        assert_eq!(body["valid"], true);
+        assert_eq!(body["terminal_id"], terminal_id.as_str());
+        assert_eq!(body["amount"], 25_000_000);
+        assert_eq!(body["memo"], "table 7");
+    }
+
+    // The payment must be the one tapped
+    let payment = |amount: i64| {
+        json!({
+            "merchant_id": merchant_id,
+            "send_asset": "USDC",
+            "send_amount": amount,
+            "memo": "table 7",
+            "nfc_tap": tap,
+        })
+    };
+    let (status, body) = ctx
+        .post("/payments/payments", Some(&token), payment(1))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["message"].as_str().unwrap().contains("does not match"));
+    let (status, body) = ctx
+        .post("/payments/payments", Some(&token), payment(25_000_000))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+
+    // Paying it spends it
+    let (status, _) = ctx
+        .post("/payments/nfc/validate", Some(&token), tap.clone())
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = ctx
+        .post("/payments/payments", Some(&token), payment(25_000_000))
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // The amount is bound by the signature
+    let mut tampered = signed_tap(&terminal_id, &merchant_id, &key, now);
+    tampered["amount"] = json!(1);
+    let (status, body) = ctx
+        .post("/payments/nfc/validate", Some(&token), tampered)
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["message"].as_str().unwrap().contains("signature"));
+
+    // So is the signing key
+    let forged = signed_tap(&terminal_id, &merchant_id, &Keypair::random().unwrap(), now);
+    let (status, _) = ctx
+        .post("/payments/nfc/validate", Some(&token), forged)
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // A terminal can only sign for its own merchant
+    let other_merchant = ctx.create_merchant().await;
+    let misrouted = signed_tap(&terminal_id, &other_merchant, &key, now);
+    let (status, _) = ctx
+        .post("/payments/nfc/validate", Some(&token), misrouted)
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_nfc_tap_window_and_revoked_terminals() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant().await;
+    let (terminal_id, key) = ctx.register_terminal(&merchant_id).await;
+    let (user_id, _) = ctx.register_user().await;
+    let token = ctx.token(&user_id, Role::User);
+    let now = chrono::Utc::now().timestamp();
+    let nfc = &ctx.config.nfc;
+
+    let stale = signed_tap(
+        &terminal_id,
+        &merchant_id,
+        &key,
+        now - nfc.max_age_secs - 10,
+    );
+    let (status, body) = ctx
+        .post("/payments/nfc/validate", Some(&token), stale)
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["message"].as_str().unwrap().contains("expired"));
+
+    let early = signed_tap(
+        &terminal_id,
+        &merchant_id,
+        &key,
+        now + nfc.max_clock_skew_secs + 10,
+    );
+    let (status, body) = ctx
+        .post("/payments/nfc/validate", Some(&token), early)
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["message"].as_str().unwrap().contains("future"));
+
+    let admin = ctx.token("admin", Role::Admin);
+    let (status, body) = ctx
+        .send(
+            "DELETE",
+            &format!("/terminals/{}", terminal_id),
+            Some(&admin),
+            Body::empty(),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["active"], false);
+
+    let (status, body) = ctx
+        .get(&format!("/terminals?merchant_id={}", merchant_id), &admin)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.as_array().unwrap().len(), 1);
+
+    let tap = signed_tap(&terminal_id, &merchant_id, &key, now);
+    let (status, body) = ctx.post("/payments/nfc/validate", Some(&token), tap).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["message"].as_str().unwrap().contains("revoked"));
+}
@@ -0,0 +1,369 @@
+//! Quote tests against the database and an httpmock stand-in for the anchor's
+//! SEP-10 and SEP-38 servers and for Horizon: firm quotes from the anchor, the
+//! DEX fallback, payments redeeming quotes, and DEX path finding.
+//!
+//! These tests require a running database as defined in the config/env.
+//! Run with: cargo test --test quote_test -- --ignored
+
+mod common;
+
+use axum::http::StatusCode;
+use httpmock::prelude::*;
+use serde_json::{json, Value};
+
+use common::{TestContext, ANCHOR_TOKEN, USDC_ISSUER};
+
+const VAULT: &str = "CAAACAQDAQCQMBYIBEFAWDANBYHRAEISCMKBKFQXDAMRUGY4DUPB6N4O";
+
+/// Set up against the mock server, with the anchor's SEP-38 server or without one
+async fn setup(with_sep38: bool) -> TestContext {
+    common::setup(|config, server| {
+        config.stellar_network.horizon_url = server.url("/horizon");
+        config.stellar_network.assets = [("usdc".to_string(), USDC_ISSUER.to_string())]
+            .into_iter()
+            .collect();
+        config.anchor_config.sep38_url = with_sep38.then(|| server.url("/sep38"));
+        config.quotes.slippage_bps = 100;
+    })
+    .await
+}
+
+impl TestContext {
+    /// Register a user and return its user_id and wallet address
+    async fn register_user(&self) -> (String, String) {
+        let user_id = format!("user_{}", uuid::Uuid::new_v4().simple());
+        let (status, _) = self
+            .send(
+                "POST",
+                "/auth/register",
+                None,
+                Some(json!({ "user_id": user_id, "pin": "1234" })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let client = self.pool.get().await.unwrap();
+        let row = client
+            .query_one(
+                "SELECT stellar_address FROM users WHERE user_id = $1",
+                &[&user_id],
+            )
+            .await
+            .unwrap();
+        (user_id, row.get(0))
+    }
+
+    async fn create_merchant(&self) -> String {
+        let merchant_id = format!("merchant_{}", uuid::Uuid::new_v4().simple());
+        let client = self.pool.get().await.unwrap();
+        client
+            .execute(
+                "INSERT INTO merchants (merchant_id, vault_address, settlement_asset) VALUES ($1, $2, 'USDC')",
+                &[&merchant_id, &VAULT],
+            )
+            .await
+            .unwrap();
+        merchant_id
+    }
+
+    /// Mock Horizon finding two XLM to USDC paths for selling 100 XLM
+    fn mock_strict_send(&self) -> httpmock::Mock<'_> {
+        self.server.mock(|when, then| {
+            when.method(GET)
+                .path("/horizon/paths/strict-send")
+                .query_param("source_asset_type", "native")
+                .query_param("source_amount", "100")
+                .query_param("destination_assets", format!("USDC:{}", USDC_ISSUER));
+            then.status(200).json_body(json!({
+                "_embedded": { "records": [
+                    path_record("100.0000000", "11.0000000", json!([])),
+                    path_record("100.0000000", "12.0000000", json!([{
+                        "asset_type": "credit_alphanum4",
+                        "asset_code": "EURC",
+                        "asset_issuer": USDC_ISSUER,
+                    }])),
+                ]}
+            }));
+        })
+    }
+
+    async fn pay(&self, user_id: &str, body: Value) -> (StatusCode, Value) {
+        self.send(
+            "POST",
+            "/payments/payments",
+            Some(&self.token(user_id)),
+            Some(body),
+        )
+        .await
+    }
+}
+
+fn path_record(source_amount: &str, destination_amount: &str, path: Value) -> Value {
+    json!({
+        "source_asset_type": "native",
+        "source_amount": source_amount,
+        "destination_asset_type": "credit_alphanum4",
+        "destination_asset_code": "USDC",
+        "destination_asset_issuer": USDC_ISSUER,
+        "destination_amount": destination_amount,
+        "path": path,
+    })
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_anchor_quote_locks_min_receive() {
+    let ctx = setup(true).await;
+    let merchant_id = ctx.create_merchant().await;
+    let (user_id, address) = ctx.register_user().await;
+    ctx.mock_web_auth(&address);
+
+    let expires_at = chrono::Utc::now() + chrono::Duration::minutes(5);
+    ctx.server.mock(|when, then| {
+        when.method(POST)
+            .path("/sep38/quote")
+            .header("Authorization", format!("Bearer {}", ANCHOR_TOKEN))
+            .json_body_partial(
+                json!({
+                    "sell_asset": "stellar:native",
+                    "buy_asset": format!("stellar:USDC:{}", USDC_ISSUER),
+                    "sell_amount": "100",
+                })
+                .to_string(),
+            );
+        then.status(201).json_body(json!({
+            "id": "anchor-quote-1",
+            "expires_at": expires_at.to_rfc3339(),
+            "total_price": "8",
+            "price": "7.9",
+            "sell_asset": "stellar:native",
+            "sell_amount": "100",
+            "buy_asset": format!("stellar:USDC:{}", USDC_ISSUER),
+            "buy_amount": "12.5",
+        }));
+    });
+
+    let (status, quote) = ctx
+        .send(
+            "POST",
+            "/quotes/quotes",
+            Some(&ctx.token(&user_id)),
+            Some(json!({ "merchant_id": merchant_id, "send_asset": "XLM", "send_amount": 1_000_000_000 })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", quote);
+    assert_eq!(quote["source"], "anchor");
+    assert_eq!(quote["anchor_quote_id"], "anchor-quote-1");
+    assert_eq!(quote["buy_amount"], 125_000_000);
+    assert_eq!(quote["price"], "8");
+    let quote_id = quote["id"].as_str().unwrap();
+
+    // The payment must send what was quoted, and cannot name its own min_receive
+    let (status, _) = ctx
+        .pay(
+            &user_id,
+            json!({ "merchant_id": merchant_id, "send_asset": "XLM", "send_amount": 500_000_000, "quote_id": quote_id }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = ctx
+        .pay(
+            &user_id,
+            json!({ "merchant_id": merchant_id, "send_asset": "XLM", "send_amount": 1_000_000_000, "min_receive": 1, "quote_id": quote_id }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, payment) = ctx
+        .pay(
+            &user_id,
+            json!({ "merchant_id": merchant_id, "send_asset": "XLM", "send_amount": 1_000_000_000, "quote_id": quote_id }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", payment);
+    assert_eq!(payment["receive_amount"], 125_000_000);
+
+    let (status, quote) = ctx
+        .send(
+            "GET",
+            &format!("/quotes/quotes/{}", quote_id),
+            Some(&ctx.token(&user_id)),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(quote["payment_id"], payment["id"]);
+
+    // A quote pays for one payment only
+    let (status, _) = ctx
+        .pay(
+            &user_id,
+            json!({ "merchant_id": merchant_id, "send_asset": "XLM", "send_amount": 1_000_000_000, "quote_id": quote_id }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_dex_quote_when_the_anchor_cannot_price() {
+    let ctx = setup(true).await;
+    let merchant_id = ctx.create_merchant().await;
+    let (user_id, address) = ctx.register_user().await;
+    ctx.mock_web_auth(&address);
+    ctx.mock_strict_send();
+    ctx.server.mock(|when, then| {
+        when.method(POST).path("/sep38/quote");
+        then.status(400)
+            .json_body(json!({ "error": "unsupported asset pair" }));
+    });
+
+    let (status, quote) = ctx
+        .send(
+            "POST",
+            "/quotes/quotes",
+            Some(&ctx.token(&user_id)),
+            Some(json!({ "merchant_id": merchant_id, "send_asset": "XLM", "send_amount": 1_000_000_000 })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", quote);
+    assert_eq!(quote["source"], "dex");
+    // The better of the two paths, less 1% slippage
+    assert_eq!(quote["buy_amount"], 118_800_000);
+    assert_eq!(quote["path"], json!([format!("EURC:{}", USDC_ISSUER)]));
+
+    // Expired quotes cannot be redeemed
+    let client = ctx.pool.get().await.unwrap();
+    client
+        .execute(
+            "UPDATE quotes SET expires_at = NOW() - INTERVAL '1 second' WHERE id = $1::text::uuid",
+            &[&quote["id"].as_str().unwrap()],
+        )
+        .await
+        .unwrap();
+    let (status, body) = ctx
+        .pay(
+            &user_id,
+            json!({ "merchant_id": merchant_id, "send_asset": "XLM", "send_amount": 1_000_000_000, "quote_id": quote["id"] }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["message"].as_str().unwrap().contains("expired"));
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_indicative_prices() {
+    let ctx = setup(false).await;
+    let merchant_id = ctx.create_merchant().await;
+    let (user_id, _) = ctx.register_user().await;
+    let token = ctx.token(&user_id);
+
+    // Without a SEP-38 server, a fixed receive amount is priced by strict-receive
+    ctx.server.mock(|when, then| {
+        when.method(GET)
+            .path("/horizon/paths/strict-receive")
+            .query_param("destination_asset_code", "USDC")
+            .query_param("destination_amount", "10")
+            .query_param("source_assets", "native");
+        then.status(200).json_body(json!({
+            "_embedded": { "records": [
+                path_record("90.0000000", "10.0000000", json!([])),
+                path_record("80.0000000", "10.0000000", json!([])),
+            ]}
+        }));
+    });
+    let (status, price) = ctx
+        .send(
+            "GET",
+            &format!(
+                "/quotes/price?merchant_id={}&send_asset=XLM&receive_amount=100000000",
+                merchant_id
+            ),
+            Some(&token),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", price);
+    assert_eq!(price["source"], "dex");
+    assert_eq!(price["sell_amount"], 808_000_000);
+    assert_eq!(price["buy_amount"], 100_000_000);
+    assert_eq!(price["price"], "8.08");
+
+    // Paying in the settlement asset needs no conversion
+    let (status, price) = ctx
+        .send(
+            "GET",
+            &format!(
+                "/quotes/price?merchant_id={}&send_asset=USDC&send_amount=5000",
+                merchant_id
+            ),
+            Some(&token),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", price);
+    assert_eq!(price["source"], "direct");
+    assert_eq!(price["buy_amount"], 5000);
+
+    let (status, _) = ctx
+        .send(
+            "GET",
+            &format!(
+                "/quotes/price?merchant_id={}&send_asset=XLM&send_amount=1&receive_amount=1",
+                merchant_id
+            ),
+            Some(&token),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_best_path_into_settlement_asset() {
+    let ctx = setup(false).await;
+    let merchant_id = ctx.create_merchant().await;
+    let (user_id, _) = ctx.register_user().await;
+    let token = ctx.token(&user_id);
+    let horizon = ctx.mock_strict_send();
+
+    let uri = format!(
+        "/quotes/paths?merchant_id={}&send_asset=XLM&send_amount=1000000000",
+        merchant_id
+    );
+    for _ in 0..2 {
+        let (status, path) = ctx.send("GET", &uri, Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK, "{:?}", path);
+        assert_eq!(path["settlement_asset"], "USDC");
+        assert_eq!(path["send_amount"], 1_000_000_000);
+        // The best path as Horizon prices it, with no margin taken
+        assert_eq!(path["receive_amount"], 120_000_000);
+        assert_eq!(path["path"], json!([format!("EURC:{}", USDC_ISSUER)]));
+    }
+    // The second request is answered from the cache
+    horizon.assert_hits(1);
+
+    // No liquidity for tiny amounts
+    ctx.server.mock(|when, then| {
+        when.method(GET)
+            .path("/horizon/paths/strict-send")
+            .query_param("source_amount", "0.0000005");
+        then.status(200)
+            .json_body(json!({ "_embedded": { "records": [] } }));
+    });
+    let (status, body) = ctx
+        .send(
+            "GET",
+            &format!(
+                "/quotes/paths?merchant_id={}&send_asset=XLM&send_amount=5",
+                merchant_id
+            ),
+            Some(&token),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{:?}", body);
+    assert!(body["message"].as_str().unwrap().contains("No DEX path"));
+}
@@ -0,0 +1,365 @@
+//! Merchant refund tests against the database and an httpmock stand-in for
+//! the Soroban RPC server: partial and full refunds of a payment, the limit
+//! of its settled amount, and who may refund it.
+//!
+//! These tests require a running database as defined in the config/env.
+//! Run with: cargo test --test refund_test -- --ignored
+
+mod common;
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+};
+use httpmock::prelude::*;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use tokio::sync::Mutex;
+
+use common::{account_entry, platform_key, rpc_result, TestContext, USDC_ISSUER};
+use zaps_backend::role::Role;
+
+/// Sweeps send every pending refund in the database, so tests take turns
+static SWEEPS: Mutex<()> = Mutex::const_new(());
+
+async fn setup() -> TestContext {
+    common::setup(|config, _| {
+        config.anchor_config.platform_secret = Some(platform_key().secret_seed());
+        config.contracts.merchant_vault = String::new();
+    })
+    .await
+}
+
+/// A merchant with an operator, and a customer who paid it
+struct Fixture {
+    merchant_id: String,
+    operator: String,
+    customer: String,
+}
+
+impl TestContext {
+    async fn create_user(&self, prefix: &str) -> String {
+        let user_id = format!("{}_{}", prefix, uuid::Uuid::new_v4().simple());
+        let (status, _) = self
+            .send(
+                "POST",
+                "/auth/register",
+                None,
+                Some(json!({ "user_id": user_id, "pin": "1234" })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        user_id
+    }
+
+    /// A USDC merchant holding `balance` stroops, operated by a new user
+    async fn create_merchant(&self, balance: i64) -> Fixture {
+        let merchant_id = format!("merchant_{}", uuid::Uuid::new_v4().simple());
+        let operator = self.create_user("operator").await;
+        let customer = self.create_user("customer").await;
+        let client = self.pool.get().await.unwrap();
+        client
+            .execute(
+                "INSERT INTO merchants (merchant_id, vault_address, settlement_asset) VALUES ($1, $2, 'USDC')",
+                &[&merchant_id, &USDC_ISSUER],
+            )
+            .await
+            .unwrap();
+        client
+            .execute(
+                "INSERT INTO merchant_users (merchant_id, user_id) VALUES ($1, $2)",
+                &[&merchant_id, &operator],
+            )
+            .await
+            .unwrap();
+        client
+            .execute(
+                "INSERT INTO balances (owner_id, asset, amount) VALUES ($1, 'USDC', $2)",
+                &[&merchant_id, &balance],
+            )
+            .await
+            .unwrap();
+        Fixture {
+            merchant_id,
+            operator,
+            customer,
+        }
+    }
+
+    /// A payment from the customer that settled `amount` stroops with the merchant
+    async fn create_payment(&self, fixture: &Fixture, status: &str, amount: i64) -> String {
+        let client = self.pool.get().await.unwrap();
+        let row = client
+            .query_one(
+                r#"
+                INSERT INTO payments (from_address, merchant_id, send_asset, send_amount, receive_amount, status)
+                SELECT stellar_address, $2, 'XLM', $3::BIGINT * 10, $3::BIGINT, $4 FROM users WHERE user_id = $1
+                RETURNING id
+                "#,
+                &[&fixture.customer, &fixture.merchant_id, &amount, &status],
+            )
+            .await
+            .unwrap();
+        row.get::<_, uuid::Uuid>(0).to_string()
+    }
+
+    async fn payment(&self, payment_id: &str) -> Value {
+        let client = self.pool.get().await.unwrap();
+        let row = client
+            .query_one(
+                "SELECT status, refunded_amount FROM payments WHERE id = $1::text::uuid",
+                &[&payment_id],
+            )
+            .await
+            .unwrap();
+        json!({ "status": row.get::<_, String>(0), "refunded_amount": row.get::<_, i64>(1) })
+    }
+
+    /// Mock the RPC calls of the refund payments, returning the mocks
+    /// signing (`getLedgerEntries`) and sending (`sendTransaction`) them
+    fn mock_network(&self) -> (httpmock::Mock<'_>, httpmock::Mock<'_>) {
+        let tx_hash = format!(
+            "{}{}",
+            uuid::Uuid::new_v4().simple(),
+            uuid::Uuid::new_v4().simple()
+        );
+        let entry = account_entry(&platform_key().address(), 41);
+        let sign = self.server.mock(|when, then| {
+            when.method(POST)
+                .json_body_partial(r#"{"method":"getLedgerEntries"}"#);
+            then.status(200).json_body(rpc_result(json!({
+                "entries": [{ "key": "", "xdr": entry, "lastModifiedLedgerSeq": 100 }],
+                "latestLedger": 120,
+            })));
+        });
+        let send = self.server.mock(|when, then| {
+            when.method(POST)
+                .json_body_partial(r#"{"method":"sendTransaction"}"#);
+            then.status(200).json_body(rpc_result(json!({
+                "status": "PENDING",
+                "hash": tx_hash,
+                "latestLedger": 120,
+            })));
+        });
+        (sign, send)
+    }
+
+    /// Send the pending refund, resending it until it lands, then confirm it
+    async fn process_refunds(&self) {
+        let (mut sign, mut send) = self.mock_network();
+        let mut landed = self.mock_transaction_status("NOT_FOUND");
+        self.services.refund.process_pending().await.unwrap();
+        self.services.refund.process_pending().await.unwrap();
+        sign.assert_hits(1);
+        send.assert_hits(2);
+
+        landed.delete();
+        let mut landed = self.mock_transaction_status("SUCCESS");
+        self.services.refund.process_pending().await.unwrap();
+        self.services.refund.process_pending().await.unwrap();
+        sign.assert_hits(1);
+        send.assert_hits(2);
+
+        sign.delete();
+        send.delete();
+        landed.delete();
+    }
+
+    async fn refund(&self, payment_id: &str, token: &str, body: Value) -> (StatusCode, Value) {
+        self.send(
+            "POST",
+            &format!("/payments/payments/{}/refunds", payment_id),
+            Some(token),
+            Some(body),
+        )
+        .await
+    }
+
+    /// POST to the merchant API with an API key
+    async fn send_with_key(&self, uri: &str, key: &str, body: Value) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header("Content-Type", "application/json")
+            .header("X-API-KEY", key)
+            .extension(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))))
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        self.oneshot(request).await
+    }
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_partial_refunds_up_to_the_settled_amount() {
+    let _turn = SWEEPS.lock().await;
+    let ctx = setup().await;
+    let fixture = ctx.create_merchant(5_000_000).await;
+    let payment_id = ctx.create_payment(&fixture, "completed", 1_000_000).await;
+    let token = ctx.token_as(&fixture.operator, Role::Merchant);
+
+    let (status, body) = ctx
+        .refund(
+            &payment_id,
+            &token,
+            json!({ "amount": 400_000, "reason": "requested_by_customer", "note": "Size too small" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert_eq!(body["status"], "pending");
+    assert_eq!(body["amount"], 400_000);
+    assert_eq!(body["reason"], "requested_by_customer");
+    assert_eq!(ctx.balance(&fixture.merchant_id).await, 4_600_000);
+    let refund_id = body["id"].as_str().unwrap().to_string();
+
+    // Pending refunds count against what is left to refund
+    let (status, _) = ctx
+        .refund(
+            &payment_id,
+            &token,
+            json!({ "amount": 600_001, "reason": "other" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = ctx
+        .refund(
+            &payment_id,
+            &token,
+            json!({ "amount": 100, "reason": "changed_mind" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    ctx.process_refunds().await;
+    let (status, detail) = ctx
+        .send(
+            "GET",
+            &format!("/payments/payments/{}/refunds/{}", payment_id, refund_id),
+            Some(&token),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", detail);
+    assert_eq!(detail["status"], "completed");
+    assert!(detail["tx_hash"].is_string());
+    let history: Vec<&str> = detail["history"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|event| event["to_status"].as_str().unwrap())
+        .collect();
+    assert_eq!(history, vec!["pending", "processing", "completed"]);
+    assert_eq!(
+        ctx.payment(&payment_id).await,
+        json!({ "status": "completed", "refunded_amount": 400_000 })
+    );
+
+    // Without an amount the rest is refunded, which refunds the payment in full
+    let (status, body) = ctx
+        .refund(&payment_id, &token, json!({ "reason": "duplicate" }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert_eq!(body["amount"], 600_000);
+    ctx.process_refunds().await;
+    assert_eq!(
+        ctx.payment(&payment_id).await,
+        json!({ "status": "refunded", "refunded_amount": 1_000_000 })
+    );
+    assert_eq!(ctx.balance(&fixture.merchant_id).await, 4_000_000);
+    assert_eq!(
+        ctx.notification_titles(&fixture.customer).await,
+        vec!["Refund received", "Refund received"]
+    );
+
+    let (status, _) = ctx
+        .refund(&payment_id, &token, json!({ "reason": "other" }))
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, refunds) = ctx
+        .send(
+            "GET",
+            &format!("/payments/payments/{}/refunds", payment_id),
+            Some(&token),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(refunds.as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_refunds_are_limited_to_the_merchant() {
+    let _turn = SWEEPS.lock().await;
+    let ctx = setup().await;
+    let fixture = ctx.create_merchant(5_000_000).await;
+    let other = ctx.create_merchant(5_000_000).await;
+    let payment_id = ctx.create_payment(&fixture, "completed", 1_000_000).await;
+    let refund = json!({ "amount": 1_000, "reason": "other" });
+
+    let (status, _) = ctx
+        .refund(
+            &payment_id,
+            &ctx.token_as(&other.operator, Role::Merchant),
+            refund.clone(),
+        )
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = ctx
+        .refund(
+            &payment_id,
+            &ctx.token_as(&fixture.customer, Role::User),
+            refund.clone(),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // API keys need the refunds:write scope and their own merchant's payment
+    let token = ctx.token_as(&fixture.operator, Role::Merchant);
+    let mut keys = Vec::new();
+    for scopes in [json!(["payments:read"]), json!(["refunds:write"])] {
+        let (status, body) = ctx
+            .send(
+                "POST",
+                &format!("/merchants/{}/api-keys", fixture.merchant_id),
+                Some(&token),
+                Some(json!({ "name": "Shop", "scopes": scopes })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK, "{:?}", body);
+        keys.push(body["key"].as_str().unwrap().to_string());
+    }
+    let uri = format!("/merchant-api/payments/{}/refunds", payment_id);
+    let (status, _) = ctx.send_with_key(&uri, &keys[0], refund.clone()).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, body) = ctx.send_with_key(&uri, &keys[1], refund.clone()).await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert!(body["id"].is_string());
+
+    let other_payment = ctx.create_payment(&other, "completed", 1_000_000).await;
+    let (status, _) = ctx
+        .send_with_key(
+            &format!("/merchant-api/payments/{}/refunds", other_payment),
+            &keys[1],
+            refund.clone(),
+        )
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Only completed payments can be refunded
+    let pending = ctx.create_payment(&fixture, "pending", 1_000_000).await;
+    let (status, _) = ctx.refund(&pending, &token, refund).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // Keep the refund out of later sweeps
+    let client = ctx.pool.get().await.unwrap();
+    client
+        .execute(
+            "DELETE FROM refunds WHERE merchant_id = $1",
+            &[&fixture.merchant_id],
+        )
+        .await
+        .unwrap();
+}
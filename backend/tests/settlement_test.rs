@@ -0,0 +1,454 @@
+//! Merchant settlement tests against the database and httpmock stand-ins for
+//! the receiving anchor's SEP-31 server and the Soroban RPC server.
+//!
+//! These tests require a running database as defined in the config/env.
+//! Run with: cargo test --test settlement_test -- --ignored
+
+mod common;
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+};
+use httpmock::prelude::*;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+
+use common::{
+    account_entry, anchor_key, platform_key, rpc_result, statuses, TestContext, ANCHOR_TOKEN,
+    USDC_ISSUER,
+};
+use zaps_backend::{role::Role, service::anchor_service::sign_callback};
+
+async fn setup() -> TestContext {
+    let ctx = common::setup(|config, server| {
+        config.anchor_config.sep31_url = server.url("/sep31");
+        config.anchor_config.platform_secret = Some(platform_key().secret_seed());
+        config.anchor_config.sep31_callback_url =
+            Some("https://api.example.com/callbacks/sep31".to_string());
+    })
+    .await;
+    ctx.mock_web_auth(&platform_key().address());
+    ctx
+}
+
+impl TestContext {
+    fn admin_token(&self) -> String {
+        self.token_as("admin_settlements", Role::Admin)
+    }
+
+    /// Create an active merchant settling in USDC with `balance` stroops available
+    async fn create_merchant(&self, balance: i64) -> String {
+        let merchant_id = format!("merchant_{}", uuid::Uuid::new_v4().simple());
+        let client = self.pool.get().await.unwrap();
+        client
+            .execute(
+                "INSERT INTO merchants (merchant_id, vault_address, settlement_asset) VALUES ($1, $2, 'USDC')",
+                &[&merchant_id, &USDC_ISSUER],
+            )
+            .await
+            .unwrap();
+        client
+            .execute(
+                "INSERT INTO balances (owner_id, asset, amount) VALUES ($1, 'USDC', $2)",
+                &[&merchant_id, &balance],
+            )
+            .await
+            .unwrap();
+        merchant_id
+    }
+
+    /// Mock the anchor's SEP-31 `/info`, requiring SEP-12 customers and a bank account number
+    fn mock_info(&self) {
+        self.server.mock(|when, then| {
+            when.method(GET)
+                .path("/sep31/info")
+                .header("Authorization", format!("Bearer {}", ANCHOR_TOKEN));
+            then.status(200).json_body(json!({
+                "receive": {
+                    "USDC": {
+                        "enabled": true,
+                        "min_amount": 1,
+                        "max_amount": 10000,
+                        "sep12": {
+                            "sender": { "types": { "sep31-sender": { "description": "Sender" } } },
+                            "receiver": { "types": { "sep31-receiver": { "description": "Receiver" } } }
+                        },
+                        "fields": {
+                            "transaction": {
+                                "receiver_account_number": { "description": "Bank account number" },
+                                "type": { "description": "Rail", "choices": ["SEPA", "SWIFT"] }
+                            }
+                        }
+                    }
+                }
+            }));
+        });
+    }
+
+    /// Mock the anchor accepting a payout and its callback registration, returning its id
+    fn mock_create(&self) -> String {
+        let anchor_tx_id = uuid::Uuid::new_v4().to_string();
+        let body = json!({ "id": anchor_tx_id });
+        self.server.mock(|when, then| {
+            when.method(POST)
+                .path("/sep31/transactions")
+                .json_body_partial(
+                    r#"{"asset_code":"USDC","sender_id":"sender-1","receiver_id":"receiver-1","fields":{"transaction":{"receiver_account_number":"12345678","type":"SEPA"}}}"#,
+                );
+            then.status(201).json_body(body);
+        });
+        self.server.mock(|when, then| {
+            when.method(PUT)
+                .path(format!("/sep31/transactions/{}/callback", anchor_tx_id));
+            then.status(204);
+        });
+        anchor_tx_id
+    }
+
+    /// Mock the anchor reporting `status` for a payout
+    fn mock_anchor_status(&self, anchor_tx_id: &str, status: &str) -> httpmock::Mock<'_> {
+        let body = anchor_transaction(anchor_tx_id, status);
+        self.server.mock(|when, then| {
+            when.method(GET)
+                .path(format!("/sep31/transactions/{}", anchor_tx_id))
+                .header("Authorization", format!("Bearer {}", ANCHOR_TOKEN));
+            then.status(200).json_body(body);
+        })
+    }
+
+    /// Mock the RPC calls of the payment to the anchor, returning the mocks
+    /// signing (`getLedgerEntries`) and sending (`sendTransaction`) it
+    fn mock_network(&self, sender_address: &str) -> (httpmock::Mock<'_>, httpmock::Mock<'_>) {
+        let tx_hash = format!(
+            "{}{}",
+            uuid::Uuid::new_v4().simple(),
+            uuid::Uuid::new_v4().simple()
+        );
+        let entry = account_entry(sender_address, 41);
+        let sign = self.server.mock(|when, then| {
+            when.method(POST)
+                .json_body_partial(r#"{"method":"getLedgerEntries"}"#);
+            then.status(200).json_body(rpc_result(json!({
+                "entries": [{ "key": "", "xdr": entry, "lastModifiedLedgerSeq": 100 }],
+                "latestLedger": 120,
+            })));
+        });
+        let send = self.server.mock(|when, then| {
+            when.method(POST)
+                .json_body_partial(r#"{"method":"sendTransaction"}"#);
+            then.status(200).json_body(rpc_result(json!({
+                "status": "PENDING",
+                "hash": tx_hash,
+                "latestLedger": 120,
+            })));
+        });
+        (sign, send)
+    }
+
+    async fn create_settlement(&self, merchant_id: &str, body: Value) -> (StatusCode, Value) {
+        let mut body = body;
+        body["merchant_id"] = json!(merchant_id);
+        self.send(
+            "POST",
+            "/settlements/settlements",
+            Some(&self.admin_token()),
+            Some(body),
+        )
+        .await
+    }
+
+    async fn settlement(&self, id: &str) -> Value {
+        let (status, body) = self
+            .send(
+                "GET",
+                &format!("/settlements/settlements/{}", id),
+                Some(&self.admin_token()),
+                None,
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK, "{:?}", body);
+        body
+    }
+
+    /// POST an anchor callback, signed at `timestamp` unless `signature` overrides it
+    async fn callback(
+        &self,
+        uri: &str,
+        body: &Value,
+        timestamp: i64,
+        signature: Option<&str>,
+    ) -> (StatusCode, Value) {
+        let body = body.to_string();
+        let signature = signature.map_or_else(
+            || sign_callback(&anchor_key(), timestamp, "api.example.com", body.as_bytes()),
+            str::to_string,
+        );
+        let request = Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header("Content-Type", "application/json")
+            .header("Signature", signature)
+            .extension(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))))
+            .body(Body::from(body))
+            .unwrap();
+        self.oneshot(request).await
+    }
+}
+
+fn anchor_transaction(anchor_tx_id: &str, status: &str) -> Value {
+    json!({
+        "transaction": {
+            "id": anchor_tx_id,
+            "status": status,
+            "amount_in": "50",
+            "amount_out": "49.5",
+            "amount_fee": "0.5",
+            "stellar_account_id": USDC_ISSUER,
+            "stellar_memo": "42",
+            "stellar_memo_type": "id",
+        }
+    })
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+fn complete_request() -> Value {
+    json!({
+        "amount": 500_000_000,
+        "sender_id": "sender-1",
+        "receiver_id": "receiver-1",
+        "fields": { "receiver_account_number": "12345678", "type": "SEPA" },
+    })
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_settlement_pays_anchor_and_completes_on_callback() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant(1_000_000_000).await;
+    ctx.mock_info();
+    let anchor_tx_id = ctx.mock_create();
+
+    let (status, body) = ctx
+        .create_settlement(&merchant_id, complete_request())
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert_eq!(body["status"], "pending");
+    assert_eq!(body["anchor_tx_id"], anchor_tx_id.as_str());
+    assert_eq!(ctx.balance(&merchant_id).await, 500_000_000);
+    let id = body["id"].as_str().unwrap().to_string();
+
+    // The anchor asks for the payment: until it lands the same payment is resubmitted
+    let mut pending = ctx.mock_anchor_status(&anchor_tx_id, "pending_sender");
+    let (sign, send) = ctx.mock_network(&platform_key().address());
+    let mut landed = ctx.mock_transaction_status("NOT_FOUND");
+    ctx.services.settlement.poll_pending().await.unwrap();
+    let tx_hash = ctx.settlement(&id).await["tx_hash"].clone();
+    assert!(tx_hash.is_string());
+    ctx.services.settlement.poll_pending().await.unwrap();
+    sign.assert_hits(1);
+    send.assert_hits(2);
+
+    let detail = ctx.settlement(&id).await;
+    assert_eq!(detail["status"], "pending");
+    assert_eq!(detail["tx_hash"], tx_hash);
+
+    // Once it lands the settlement moves on, and nothing more is sent
+    landed.delete();
+    ctx.mock_transaction_status("SUCCESS");
+    ctx.services.settlement.poll_pending().await.unwrap();
+    ctx.services.settlement.poll_pending().await.unwrap();
+    sign.assert_hits(1);
+    send.assert_hits(2);
+
+    let detail = ctx.settlement(&id).await;
+    assert_eq!(detail["status"], "processing");
+    assert_eq!(detail["tx_hash"], tx_hash);
+
+    // The callback carries the status itself, and repeating it changes nothing
+    pending.delete();
+    let completed = anchor_transaction(&anchor_tx_id, "completed");
+    let (status, body) = ctx
+        .callback("/callbacks/sep31", &completed, now(), None)
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert_eq!(body["status"], "completed");
+    let (status, body) = ctx
+        .callback("/callbacks/sep31", &completed, now() - 1, None)
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert_eq!(body["status"], "completed");
+
+    let detail = ctx.settlement(&id).await;
+    assert_eq!(detail["amount_out"], 495_000_000);
+    assert_eq!(detail["anchor_status"], "completed");
+    assert_eq!(
+        statuses(&detail),
+        vec!["pending", "processing", "completed"]
+    );
+    assert_eq!(ctx.balance(&merchant_id).await, 500_000_000);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_refunded_settlement_returns_balance() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant(1_000_000_000).await;
+    ctx.mock_info();
+    let anchor_tx_id = ctx.mock_create();
+
+    let (status, body) = ctx
+        .create_settlement(&merchant_id, complete_request())
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    let id = body["id"].as_str().unwrap().to_string();
+
+    ctx.mock_anchor_status(&anchor_tx_id, "expired");
+    ctx.services.settlement.poll_pending().await.unwrap();
+
+    let detail = ctx.settlement(&id).await;
+    assert_eq!(detail["status"], "failed");
+    assert_eq!(statuses(&detail), vec!["pending", "failed"]);
+    assert_eq!(ctx.balance(&merchant_id).await, 1_000_000_000);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_failure_after_payment_does_not_return_balance() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant(1_000_000_000).await;
+    ctx.mock_info();
+    let anchor_tx_id = ctx.mock_create();
+
+    let (_, body) = ctx
+        .create_settlement(&merchant_id, complete_request())
+        .await;
+    let id = body["id"].as_str().unwrap().to_string();
+
+    // Our payment to the anchor lands
+    let mut pending = ctx.mock_anchor_status(&anchor_tx_id, "pending_sender");
+    ctx.mock_network(&platform_key().address());
+    ctx.mock_transaction_status("SUCCESS");
+    ctx.services.settlement.poll_pending().await.unwrap();
+    assert_eq!(ctx.settlement(&id).await["status"], "processing");
+
+    // The anchor gives up without paying anything back on-chain
+    pending.delete();
+    ctx.mock_anchor_status(&anchor_tx_id, "error");
+    ctx.services.settlement.poll_pending().await.unwrap();
+
+    let detail = ctx.settlement(&id).await;
+    assert_eq!(detail["status"], "failed");
+    assert_eq!(ctx.balance(&merchant_id).await, 500_000_000);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_settlement_missing_anchor_fields_is_rejected() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant(1_000_000_000).await;
+    ctx.mock_info();
+
+    let (status, body) = ctx
+        .create_settlement(
+            &merchant_id,
+            json!({ "amount": 500_000_000, "sender_id": "sender-1", "fields": { "type": "ACH" } }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{:?}", body);
+    let message = body["message"].as_str().unwrap();
+    assert!(message.contains("receiver_id is required"), "{}", message);
+    assert!(message.contains("receiver_account_number"), "{}", message);
+    assert_eq!(ctx.balance(&merchant_id).await, 1_000_000_000);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_settlement_requires_sufficient_balance() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant(100_000_000).await;
+    ctx.mock_info();
+
+    let (status, _) = ctx
+        .create_settlement(&merchant_id, complete_request())
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(ctx.balance(&merchant_id).await, 100_000_000);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_settlements_are_admin_only() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant(1_000_000_000).await;
+
+    let (status, _) = ctx
+        .send(
+            "POST",
+            "/settlements/settlements",
+            Some(&ctx.token_as(&merchant_id, Role::Merchant)),
+            Some(json!({ "merchant_id": merchant_id, "amount": 1 })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = ctx
+        .callback(
+            "/callbacks/sep31",
+            &anchor_transaction("unknown", "completed"),
+            now(),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_callbacks_require_a_fresh_anchor_signature() {
+    let ctx = setup().await;
+    let body = anchor_transaction("unknown", "completed");
+
+    let (status, _) = ctx
+        .send("POST", "/callbacks/sep31", None, Some(body.clone()))
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let forged = sign_callback(
+        &platform_key(),
+        now(),
+        "api.example.com",
+        body.to_string().as_bytes(),
+    );
+    let (status, _) = ctx
+        .callback("/callbacks/sep31", &body, now(), Some(&forged))
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let max_age = ctx.config.anchor_config.callback_max_age_secs;
+    let (status, _) = ctx
+        .callback("/callbacks/sep24", &body, now() - max_age - 60, None)
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // A signature is only accepted once, even when the callback is rejected
+    let timestamp = now();
+    let signature = sign_callback(
+        &anchor_key(),
+        timestamp,
+        "api.example.com",
+        body.to_string().as_bytes(),
+    );
+    let (status, _) = ctx
+        .callback("/callbacks/sep31", &body, timestamp, Some(&signature))
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = ctx
+        .callback("/callbacks/sep31", &body, timestamp, Some(&signature))
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
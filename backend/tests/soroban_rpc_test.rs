@@ -0,0 +1,406 @@
+//! Soroban RPC client tests against an httpmock stand-in for the RPC server
+
+use axum::{http::StatusCode, response::IntoResponse};
+use httpmock::prelude::*;
+use serde_json::{json, Value};
+use soroban_sdk::xdr::{
+    ContractEvent, ContractEventBody, ContractEventType, ContractEventV0, DiagnosticEvent,
+    ExtensionPoint, Hash, InvokeContractArgs, LedgerFootprint, Limits, OperationBody, ScError,
+    ScVal, SorobanAuthorizationEntry, SorobanAuthorizedFunction, SorobanAuthorizedInvocation,
+    SorobanCredentials, SorobanResources, SorobanTransactionData, TransactionEnvelope,
+    TransactionExt, WriteXdr,
+};
+use zaps_backend::{
+    api_error::ApiError,
+    config::Config,
+    models::{BuildTransactionDto, TransactionStatus},
+    service::{soroban_service::TransactionBuilder, SorobanService},
+    stellar::{
+        rpc::{EventFilter, EventsStart},
+        scval, strkey, transaction, Keypair,
+    },
+};
+
+const ROUTER: &str = "CAAACAQDAQCQMBYIBEFAWDANBYHRAEISCMKBKFQXDAMRUGY4DUPB6N4O";
+const TX_HASH: &str = "09b6edbd61cb5253177bae001b6a9763f19c9fd1e461d8721046531714539fe9";
+
+const REGISTRY_ID: [u8; 32] = [7u8; 32];
+
+fn service_for(server: &MockServer) -> SorobanService {
+    let mut config = Config::default();
+    config.stellar_network.rpc_url = server.url("/");
+    config.contracts.payment_router = ROUTER.to_string();
+    config.contracts.zaps_registry = strkey::encode_contract(&REGISTRY_ID);
+    SorobanService::new(config)
+}
+
+/// The diagnostic event the host emits when `contract_id` fails with `Error(Contract, #code)`
+fn contract_error_event(contract_id: [u8; 32], code: u32) -> String {
+    DiagnosticEvent {
+        in_successful_contract_call: false,
+        event: ContractEvent {
+            ext: ExtensionPoint::V0,
+            contract_id: Some(Hash(contract_id)),
+            type_: ContractEventType::Diagnostic,
+            body: ContractEventBody::V0(ContractEventV0 {
+                topics: vec![
+                    scval::symbol("error").unwrap(),
+                    ScVal::Error(ScError::Contract(code)),
+                ]
+                .try_into()
+                .unwrap(),
+                data: ScVal::Void,
+            }),
+        },
+    }
+    .to_xdr_base64(Limits::none())
+    .unwrap()
+}
+
+fn rpc_result(result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": 1, "result": result })
+}
+
+fn transaction_data() -> String {
+    SorobanTransactionData {
+        ext: ExtensionPoint::V0,
+        resources: SorobanResources {
+            footprint: LedgerFootprint {
+                read_only: Default::default(),
+                read_write: Default::default(),
+            },
+            instructions: 1_000_000,
+            read_bytes: 2_000,
+            write_bytes: 500,
+        },
+        resource_fee: 54_321,
+    }
+    .to_xdr_base64(Limits::none())
+    .unwrap()
+}
+
+fn auth_entry() -> String {
+    SorobanAuthorizationEntry {
+        credentials: SorobanCredentials::SourceAccount,
+        root_invocation: SorobanAuthorizedInvocation {
+            function: SorobanAuthorizedFunction::ContractFn(InvokeContractArgs {
+                contract_address: scval::sc_address(ROUTER).unwrap(),
+                function_name: "pay".try_into().unwrap(),
+                args: Default::default(),
+            }),
+            sub_invocations: Default::default(),
+        },
+    }
+    .to_xdr_base64(Limits::none())
+    .unwrap()
+}
+
+async fn unsigned_invocation(service: &SorobanService) -> String {
+    let keypair = Keypair::from_seed([1u8; 32]).unwrap();
+    service
+        .build_transaction(BuildTransactionDto {
+            source_account: keypair.address(),
+            sequence: 7,
+            contract_id: ROUTER.to_string(),
+            method: "pay".to_string(),
+            args: vec![json!({ "type": "i128", "value": "10000000" })],
+        })
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_prepare_applies_simulation() {
+    let server = MockServer::start();
+    let simulate = server.mock(|when, then| {
+        when.method(POST)
+            .json_body_partial(r#"{"jsonrpc":"2.0","method":"simulateTransaction"}"#);
+        then.status(200).json_body(rpc_result(json!({
+            "latestLedger": 1200,
+            "minResourceFee": "54321",
+            "transactionData": transaction_data(),
+            "results": [{ "auth": [auth_entry()], "xdr": "AAAAAQ==" }],
+        })));
+    });
+
+    let service = service_for(&server);
+    let unsigned = unsigned_invocation(&service).await;
+    let prepared = service.prepare_transaction(&unsigned).await.unwrap();
+    simulate.assert();
+
+    let TransactionEnvelope::Tx(v1) = transaction::decode_envelope(&prepared).unwrap() else {
+        panic!("expected a v1 envelope");
+    };
+    assert_eq!(v1.tx.fee, transaction::BASE_FEE + 54_321);
+    let TransactionExt::V1(data) = &v1.tx.ext else {
+        panic!("expected soroban transaction data");
+    };
+    assert_eq!(data.resources.instructions, 1_000_000);
+
+    let OperationBody::InvokeHostFunction(op) = &v1.tx.operations[0].body else {
+        panic!("expected an InvokeHostFunction operation");
+    };
+    assert_eq!(op.auth.len(), 1);
+}
+
+#[tokio::test]
+async fn test_simulation_error_maps_to_contract_error() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(POST)
+            .json_body_partial(r#"{"method":"simulateTransaction"}"#);
+        then.status(200).json_body(rpc_result(json!({
+            "latestLedger": 1200,
+            "error": "HostError: Error(Contract, #6)",
+        })));
+    });
+
+    let service = service_for(&server);
+    let unsigned = unsigned_invocation(&service).await;
+    let err = service.prepare_transaction(&unsigned).await.unwrap_err();
+
+    match &err {
+        ApiError::Contract(contract_err) => {
+            assert_eq!(contract_err.error_code(), "MERCHANT_INACTIVE")
+        }
+        other => panic!("expected a contract error, got {:?}", other),
+    }
+    assert_eq!(err.into_response().status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_diagnostic_events_identify_failing_contract() {
+    // The router invokes the registry, which fails with MerchantNotFound (#5);
+    // as a router error #5 would mean Reentrancy.
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(POST)
+            .json_body_partial(r#"{"method":"simulateTransaction"}"#);
+        then.status(200).json_body(rpc_result(json!({
+            "latestLedger": 1200,
+            "error": "HostError: Error(Contract, #5)",
+            "events": [contract_error_event(REGISTRY_ID, 5)],
+        })));
+    });
+
+    let service = service_for(&server);
+    let unsigned = unsigned_invocation(&service).await;
+    let err = service.prepare_transaction(&unsigned).await.unwrap_err();
+
+    match &err {
+        ApiError::Contract(contract_err) => {
+            assert_eq!(contract_err.error_code(), "MERCHANT_NOT_FOUND")
+        }
+        other => panic!("expected a contract error, got {:?}", other),
+    }
+    assert_eq!(err.into_response().status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_non_contract_simulation_error() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(POST)
+            .json_body_partial(r#"{"method":"simulateTransaction"}"#);
+        then.status(200).json_body(rpc_result(json!({
+            "latestLedger": 1200,
+            "error": "HostError: Error(Budget, ExceededLimit)",
+        })));
+    });
+
+    let service = service_for(&server);
+    let unsigned = unsigned_invocation(&service).await;
+    let err = service.prepare_transaction(&unsigned).await.unwrap_err();
+    assert!(matches!(err, ApiError::Stellar(_)));
+}
+
+#[tokio::test]
+async fn test_submit_and_wait_success() {
+    let server = MockServer::start();
+    let send = server.mock(|when, then| {
+        when.method(POST)
+            .json_body_partial(r#"{"method":"sendTransaction","params":{"transaction":"AAAA"}}"#);
+        then.status(200).json_body(rpc_result(json!({
+            "status": "PENDING",
+            "hash": TX_HASH,
+            "latestLedger": 1201,
+            "latestLedgerCloseTime": "1767225000",
+        })));
+    });
+    let get = server.mock(|when, then| {
+        when.method(POST)
+            .json_body_partial(r#"{"method":"getTransaction"}"#)
+            .body_contains(TX_HASH);
+        then.status(200).json_body(rpc_result(json!({
+            "status": "SUCCESS",
+            "latestLedger": 1203,
+            "ledger": 1202,
+            "createdAt": "1767225005",
+            "resultXdr": "AAAAAAAAAGQAAAAAAAAAAQAAAAAAAAAYAAAAAAAAAAA=",
+        })));
+    });
+
+    let service = service_for(&server);
+    let response = service.submit_and_wait("AAAA".to_string()).await.unwrap();
+
+    send.assert();
+    get.assert();
+    assert_eq!(response.tx_hash, TX_HASH);
+    assert_eq!(response.status, TransactionStatus::CONFIRMED);
+}
+
+#[tokio::test]
+async fn test_transaction_status_mapping() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(POST)
+            .json_body_partial(r#"{"method":"getTransaction","params":{"hash":"failed"}}"#);
+        then.status(200).json_body(rpc_result(
+            json!({ "status": "FAILED", "latestLedger": 10 }),
+        ));
+    });
+    server.mock(|when, then| {
+        when.method(POST)
+            .json_body_partial(r#"{"method":"getTransaction","params":{"hash":"unknown"}}"#);
+        then.status(200).json_body(rpc_result(
+            json!({ "status": "NOT_FOUND", "latestLedger": 10 }),
+        ));
+    });
+
+    let service = service_for(&server);
+    assert_eq!(
+        service.get_transaction_status("failed").await.unwrap(),
+        TransactionStatus::FAILED
+    );
+    assert_eq!(
+        service.get_transaction_status("unknown").await.unwrap(),
+        TransactionStatus::PENDING
+    );
+}
+
+#[tokio::test]
+async fn test_submit_rejected_transaction() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(POST)
+            .json_body_partial(r#"{"method":"sendTransaction"}"#);
+        then.status(200).json_body(rpc_result(json!({
+            "status": "ERROR",
+            "hash": TX_HASH,
+            "latestLedger": 1201,
+            "errorResultXdr": "AAAAAAAAAGT////7AAAAAA==",
+        })));
+    });
+
+    let service = service_for(&server);
+    assert!(matches!(
+        service.submit_transaction("AAAA".to_string()).await,
+        Err(ApiError::Stellar(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_submit_rejection_with_contract_error() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(POST)
+            .json_body_partial(r#"{"method":"sendTransaction"}"#);
+        then.status(200).json_body(rpc_result(json!({
+            "status": "ERROR",
+            "hash": TX_HASH,
+            "latestLedger": 1201,
+            "errorResultXdr": "AAAAAAAAAGT////7AAAAAA==",
+            "diagnosticEventsXdr": [contract_error_event(
+                strkey::decode_contract(ROUTER).unwrap(),
+                8
+            )],
+        })));
+    });
+
+    let service = service_for(&server);
+    match service.submit_transaction("AAAA".to_string()).await {
+        Err(ApiError::Contract(err)) => {
+            assert_eq!(err.error_code(), "SETTLEMENT_BELOW_MIN");
+            assert_eq!(
+                ApiError::Contract(err).into_response().status(),
+                StatusCode::UNPROCESSABLE_ENTITY
+            );
+        }
+        other => panic!("expected a contract error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_rpc_error_object() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(POST)
+            .json_body_partial(r#"{"method":"getLatestLedger"}"#);
+        then.status(200).json_body(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "error": { "code": -32601, "message": "method not found" },
+        }));
+    });
+
+    let service = service_for(&server);
+    let err = service.rpc().get_latest_ledger().await.unwrap_err();
+    assert!(err.to_string().contains("method not found"));
+}
+
+#[tokio::test]
+async fn test_get_latest_ledger_and_events() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(POST)
+            .json_body_partial(r#"{"method":"getLatestLedger"}"#);
+        then.status(200).json_body(rpc_result(json!({
+            "id": "abcd",
+            "protocolVersion": 21,
+            "sequence": 1500,
+        })));
+    });
+    let events = server.mock(|when, then| {
+        when.method(POST).json_body_partial(format!(
+            r#"{{"method":"getEvents","params":{{"startLedger":1400,"filters":[{{"type":"contract","contractIds":["{}"]}}],"pagination":{{"limit":50}}}}}}"#,
+            ROUTER
+        ));
+        then.status(200).json_body(rpc_result(json!({
+            "latestLedger": 1500,
+            "cursor": "0006000-0000000001",
+            "events": [{
+                "type": "contract",
+                "ledger": 1450,
+                "ledgerClosedAt": "2026-01-01T00:00:00Z",
+                "contractId": ROUTER,
+                "id": "0006000-0000000001",
+                "pagingToken": "0006000-0000000001",
+                "topic": ["AAAADwAAAAdwYXltZW50AA=="],
+                "value": "AAAAAQ==",
+                "inSuccessfulContractCall": true,
+                "txHash": TX_HASH,
+            }],
+        })));
+    });
+
+    let service = service_for(&server);
+    let ledger = service.rpc().get_latest_ledger().await.unwrap();
+    assert_eq!(ledger.sequence, 1500);
+
+    let filter = EventFilter {
+        event_type: "contract".to_string(),
+        contract_ids: vec![ROUTER.to_string()],
+        topics: vec![],
+    };
+    let page = service
+        .rpc()
+        .get_events(EventsStart::Ledger(1400), &[filter], 50)
+        .await
+        .unwrap();
+
+    events.assert();
+    assert_eq!(page.events.len(), 1);
+    assert_eq!(page.events[0].contract_id, ROUTER);
+    assert_eq!(page.events[0].tx_hash.as_deref(), Some(TX_HASH));
+    assert_eq!(page.cursor.as_deref(), Some("0006000-0000000001"));
+}
@@ -0,0 +1,204 @@
+//! Transaction envelope building and signing tests
+//!
+//! The fixtures are a `payment-router::pay` invocation built for a fixed source
+//! account, sequence and time bound on testnet. Their transaction hash was
+//! cross-checked independently as SHA-256(network_id || ENVELOPE_TYPE_TX || tx).
+
+use serde_json::json;
+use soroban_sdk::xdr::{Asset, HostFunction, Memo, OperationBody, ScVal, TransactionEnvelope};
+use zaps_backend::{
+    config::Config,
+    models::BuildTransactionDto,
+    service::{
+        soroban_service::{CustodialSigner, Signer, TransactionBuilder},
+        SorobanService,
+    },
+    stellar::{keypair::verify_signature, scval, transaction, Keypair},
+};
+
+const TESTNET: &str = "Test SDF Network ; September 2015";
+const ROUTER: &str = "CAAACAQDAQCQMBYIBEFAWDANBYHRAEISCMKBKFQXDAMRUGY4DUPB6N4O";
+const UNSIGNED_FIXTURE: &str = include_str!("fixtures/invoke_pay_unsigned.xdr");
+const SIGNED_FIXTURE: &str = include_str!("fixtures/invoke_pay_signed.xdr");
+const FIXTURE_TX_HASH: &str = "09b6edbd61cb5253177bae001b6a9763f19c9fd1e461d8721046531714539fe9";
+
+fn fixture_keypair() -> Keypair {
+    Keypair::from_seed([1u8; 32]).unwrap()
+}
+
+fn pay_args(payer: &str) -> Vec<serde_json::Value> {
+    vec![
+        json!({ "type": "address", "value": payer }),
+        json!({ "type": "bytes", "value": "6d65726368616e745f616263" }),
+        json!({ "type": "address", "value": ROUTER }),
+        json!({ "type": "i128", "value": "10000000" }),
+        json!({ "type": "i128", "value": "9900000" }),
+    ]
+}
+
+fn build_fixture_envelope() -> TransactionEnvelope {
+    let keypair = fixture_keypair();
+    let args = scval::json_args_to_scvals(&pay_args(&keypair.address())).unwrap();
+
+    transaction::build_invoke_contract(
+        &keypair.address(),
+        4_294_967_296,
+        ROUTER,
+        "pay",
+        args,
+        transaction::BASE_FEE,
+        1_767_225_600,
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_invoke_envelope_matches_fixture() {
+    let envelope = build_fixture_envelope();
+
+    assert_eq!(
+        transaction::encode_envelope(&envelope).unwrap(),
+        UNSIGNED_FIXTURE.trim()
+    );
+    assert_eq!(
+        transaction::envelope_hash_hex(&envelope, TESTNET).unwrap(),
+        FIXTURE_TX_HASH
+    );
+}
+
+#[test]
+fn test_signed_envelope_matches_fixture() {
+    let mut envelope = build_fixture_envelope();
+    transaction::sign_envelope(&mut envelope, &fixture_keypair(), TESTNET).unwrap();
+
+    assert_eq!(
+        transaction::encode_envelope(&envelope).unwrap(),
+        SIGNED_FIXTURE.trim()
+    );
+}
+
+#[test]
+fn test_fixture_round_trip() {
+    let envelope = transaction::decode_envelope(SIGNED_FIXTURE.trim()).unwrap();
+    assert_eq!(
+        transaction::encode_envelope(&envelope).unwrap(),
+        SIGNED_FIXTURE.trim()
+    );
+
+    let TransactionEnvelope::Tx(v1) = &envelope else {
+        panic!("expected a v1 envelope");
+    };
+    assert_eq!(v1.tx.seq_num.0, 4_294_967_297);
+    assert_eq!(v1.tx.fee, transaction::BASE_FEE);
+
+    let OperationBody::InvokeHostFunction(op) = &v1.tx.operations[0].body else {
+        panic!("expected an InvokeHostFunction operation");
+    };
+    let HostFunction::InvokeContract(invoke) = &op.host_function else {
+        panic!("expected a contract invocation");
+    };
+    assert_eq!(invoke.function_name.0.to_utf8_string_lossy(), "pay");
+    assert_eq!(
+        scval::sc_address_to_string(&invoke.contract_address),
+        ROUTER
+    );
+    assert_eq!(scval::scval_to_i128(&invoke.args[3]), Some(10_000_000));
+    assert!(matches!(invoke.args[1], ScVal::Bytes(_)));
+
+    // The decorated signature verifies against the transaction hash
+    let keypair = fixture_keypair();
+    let signature = &v1.signatures[0];
+    assert_eq!(signature.hint.0, keypair.signature_hint());
+    let hash = transaction::transaction_hash(&v1.tx, TESTNET).unwrap();
+    assert!(verify_signature(
+        &keypair.public_key(),
+        &hash,
+        signature.signature.0.as_slice()
+    ));
+
+    // ...but not on another network
+    let mainnet_hash =
+        transaction::transaction_hash(&v1.tx, "Public Global Stellar Network ; September 2015")
+            .unwrap();
+    assert!(!verify_signature(
+        &keypair.public_key(),
+        &mainnet_hash,
+        signature.signature.0.as_slice()
+    ));
+}
+
+#[tokio::test]
+async fn test_soroban_service_build_and_custodial_sign() {
+    let service = SorobanService::new(Config::default());
+    let keypair = fixture_keypair();
+
+    let unsigned = service
+        .build_transaction(BuildTransactionDto {
+            source_account: keypair.address(),
+            sequence: 41,
+            contract_id: ROUTER.to_string(),
+            method: "pay".to_string(),
+            args: pay_args(&keypair.address()),
+        })
+        .await
+        .unwrap();
+
+    let signer = CustodialSigner::new(&keypair.secret_seed(), TESTNET.to_string()).unwrap();
+    let signed = signer.sign_transaction(&unsigned).await.unwrap();
+
+    let TransactionEnvelope::Tx(v1) = transaction::decode_envelope(&signed).unwrap() else {
+        panic!("expected a v1 envelope");
+    };
+    assert_eq!(v1.tx.seq_num.0, 42);
+    assert_eq!(v1.signatures.len(), 1);
+}
+
+#[tokio::test]
+async fn test_build_rejects_non_contract_target() {
+    let service = SorobanService::new(Config::default());
+    let keypair = fixture_keypair();
+
+    let result = service
+        .build_transaction(BuildTransactionDto {
+            source_account: keypair.address(),
+            sequence: 1,
+            contract_id: keypair.address(),
+            method: "pay".to_string(),
+            args: vec![],
+        })
+        .await;
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_payment_memo_types() {
+    let keypair = fixture_keypair();
+    let build = |memo| {
+        transaction::build_payment(
+            &keypair.address(),
+            1,
+            &keypair.address(),
+            Asset::Native,
+            10_000_000,
+            memo,
+            transaction::BASE_FEE,
+            1_767_225_600,
+        )
+        .unwrap()
+    };
+
+    let envelope = build(transaction::memo("12345", "id").unwrap());
+    assert_eq!(
+        transaction::envelope_tx(&envelope).unwrap().memo,
+        Memo::Id(12345)
+    );
+
+    let hash = transaction::memo("AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=", "hash").unwrap();
+    assert!(matches!(hash, Memo::Hash(bytes) if bytes.0 == [1u8; 32]));
+
+    assert!(transaction::memo("not a number", "id").is_err());
+    assert!(transaction::memo("dG9vIHNob3J0", "hash").is_err());
+    assert!(transaction::memo("a memo that is longer than 28 bytes", "text").is_err());
+    assert!(transaction::memo("x", "return").is_err());
+}
@@ -0,0 +1,333 @@
+//! Peer-to-peer transfer tests against the database and an httpmock stand-in
+//! for the Soroban RPC server.
+//!
+//! These tests require a running database as defined in the config/env.
+//! Run with: cargo test --test transfer_test -- --ignored
+
+mod common;
+
+use axum::http::StatusCode;
+use httpmock::prelude::*;
+use serde_json::json;
+use tokio::sync::Mutex;
+
+use common::{account_entry, rpc_result, statuses, TestContext, USDC_ISSUER};
+
+/// Sweeps confirm every processing transfer in the database, so tests take turns
+static SWEEPS: Mutex<()> = Mutex::const_new(());
+
+async fn setup() -> TestContext {
+    common::setup(|_, _| {}).await
+}
+
+impl TestContext {
+    /// Register a user and return its user_id and wallet address
+    async fn register_user(&self) -> (String, String) {
+        let user_id = format!("user_{}", uuid::Uuid::new_v4().simple());
+        let (status, _) = self
+            .send(
+                "POST",
+                "/auth/register",
+                None,
+                Some(json!({ "user_id": user_id, "pin": "1234" })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let client = self.pool.get().await.unwrap();
+        let row = client
+            .query_one(
+                "SELECT stellar_address FROM users WHERE user_id = $1",
+                &[&user_id],
+            )
+            .await
+            .unwrap();
+        (user_id, row.get(0))
+    }
+
+    async fn fund(&self, user_id: &str, amount: i64) {
+        let client = self.pool.get().await.unwrap();
+        client
+            .execute(
+                "INSERT INTO balances (owner_id, asset, amount) VALUES ($1, 'USDC', $2)",
+                &[&user_id, &amount],
+            )
+            .await
+            .unwrap();
+    }
+
+    /// Mock the RPC calls of signing and sending a transfer, returning the
+    /// mocks signing (`getLedgerEntries`) and sending (`sendTransaction`) it
+    fn mock_network(&self, sender_address: &str) -> (httpmock::Mock<'_>, httpmock::Mock<'_>) {
+        let tx_hash = format!(
+            "{}{}",
+            uuid::Uuid::new_v4().simple(),
+            uuid::Uuid::new_v4().simple()
+        );
+        let entry = account_entry(sender_address, 41);
+        let sign = self.server.mock(|when, then| {
+            when.method(POST)
+                .json_body_partial(r#"{"method":"getLedgerEntries"}"#);
+            then.status(200).json_body(rpc_result(json!({
+                "entries": [{ "key": "", "xdr": entry, "lastModifiedLedgerSeq": 100 }],
+                "latestLedger": 120,
+            })));
+        });
+        let send = self.server.mock(|when, then| {
+            when.method(POST)
+                .json_body_partial(r#"{"method":"sendTransaction"}"#);
+            then.status(200).json_body(rpc_result(json!({
+                "status": "PENDING",
+                "hash": tx_hash,
+                "latestLedger": 120,
+            })));
+        });
+        (sign, send)
+    }
+
+    async fn transfer(&self, user_id: &str, id: &str) -> serde_json::Value {
+        let (status, body) = self
+            .send(
+                "GET",
+                &format!("/transfers/transfers/{}", id),
+                Some(&self.token(user_id)),
+                None,
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK, "{:?}", body);
+        body
+    }
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_transfer_completes_and_moves_balances() {
+    let _turn = SWEEPS.lock().await;
+    let ctx = setup().await;
+    let (sender, sender_address) = ctx.register_user().await;
+    let (recipient, _) = ctx.register_user().await;
+    ctx.fund(&sender, 10_000).await;
+    let (sign, send) = ctx.mock_network(&sender_address);
+
+    let (status, body) = ctx
+        .send(
+            "POST",
+            "/transfers/transfers",
+            Some(&ctx.token(&sender)),
+            Some(json!({ "to_user_id": recipient, "amount": 1_000, "asset": "USDC", "memo": "lunch" })),
+        )
+        .await;
+
+    // The request returns once the payment is sent, without waiting for it
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert_eq!(body["status"], "processing");
+    let tx_hash = body["tx_hash"].clone();
+    assert!(tx_hash.is_string());
+    assert_eq!(ctx.balance(&sender).await, 9_000);
+    assert_eq!(ctx.balance(&recipient).await, 0);
+    let id = body["id"].as_str().unwrap().to_string();
+
+    // Until it lands the same payment is resubmitted
+    let mut landed = ctx.mock_transaction_status("NOT_FOUND");
+    ctx.services.transfer.poll_processing().await.unwrap();
+    sign.assert_hits(1);
+    send.assert_hits(2);
+    let detail = ctx.transfer(&sender, &id).await;
+    assert_eq!(detail["status"], "processing");
+    assert_eq!(detail["tx_hash"], tx_hash);
+
+    landed.delete();
+    let mut landed = ctx.mock_transaction_status("SUCCESS");
+    ctx.services.transfer.poll_processing().await.unwrap();
+    landed.delete();
+    sign.assert_hits(1);
+
+    let detail = ctx.transfer(&recipient, &id).await;
+    assert_eq!(detail["status"], "completed");
+    assert_eq!(detail["tx_hash"], tx_hash);
+    assert_eq!(
+        statuses(&detail),
+        vec!["pending", "processing", "completed"]
+    );
+    assert_eq!(ctx.balance(&sender).await, 9_000);
+    assert_eq!(ctx.balance(&recipient).await, 1_000);
+    assert_eq!(
+        ctx.notification_titles(&sender).await,
+        vec!["Transfer sent"]
+    );
+    assert_eq!(
+        ctx.notification_titles(&recipient).await,
+        vec!["Transfer received"]
+    );
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_every_spelling_of_an_asset_moves_one_balance() {
+    let _turn = SWEEPS.lock().await;
+    let ctx = setup().await;
+    let (sender, sender_address) = ctx.register_user().await;
+    let (recipient, _) = ctx.register_user().await;
+    ctx.fund(&sender, 10_000).await;
+    let (sign, send) = ctx.mock_network(&sender_address);
+
+    for (asset, amount) in [
+        ("usdc".to_string(), 1_000),
+        (format!("USDC:{}", USDC_ISSUER), 1_500),
+    ] {
+        let (status, body) = ctx
+            .send(
+                "POST",
+                "/transfers/transfers",
+                Some(&ctx.token(&sender)),
+                Some(json!({ "to_user_id": recipient, "amount": amount, "asset": asset })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK, "{:?}", body);
+        assert_eq!(body["asset"], "USDC");
+    }
+    assert_eq!(ctx.balance(&sender).await, 7_500);
+
+    let mut landed = ctx.mock_transaction_status("SUCCESS");
+    ctx.services.transfer.poll_processing().await.unwrap();
+    landed.delete();
+    sign.assert_hits(2);
+    send.assert_hits(2);
+
+    assert_eq!(ctx.balance(&sender).await, 7_500);
+    assert_eq!(ctx.balance(&recipient).await, 2_500);
+    let client = ctx.pool.get().await.unwrap();
+    let rows: i64 = client
+        .query_one(
+            "SELECT COUNT(*) FROM balances WHERE owner_id = ANY($1)",
+            &[&vec![sender.clone(), recipient.clone()]],
+        )
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(rows, 2);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_failed_transfer_refunds_sender() {
+    let _turn = SWEEPS.lock().await;
+    let ctx = setup().await;
+    let (sender, sender_address) = ctx.register_user().await;
+    let (recipient, _) = ctx.register_user().await;
+    ctx.fund(&sender, 10_000).await;
+    ctx.mock_network(&sender_address);
+
+    let (status, body) = ctx
+        .send(
+            "POST",
+            "/transfers/transfers",
+            Some(&ctx.token(&sender)),
+            Some(json!({ "to_user_id": recipient, "amount": 1_000, "asset": "USDC" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert_eq!(body["status"], "processing");
+
+    let mut failed = ctx.mock_transaction_status("FAILED");
+    ctx.services.transfer.poll_processing().await.unwrap();
+    failed.delete();
+
+    let detail = ctx.transfer(&sender, body["id"].as_str().unwrap()).await;
+    assert_eq!(detail["status"], "failed");
+    assert_eq!(ctx.balance(&sender).await, 10_000);
+    assert_eq!(ctx.balance(&recipient).await, 0);
+    assert_eq!(
+        ctx.notification_titles(&sender).await,
+        vec!["Transfer failed"]
+    );
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_transfer_requires_sufficient_balance() {
+    let ctx = setup().await;
+    let (sender, _) = ctx.register_user().await;
+    let (recipient, _) = ctx.register_user().await;
+    ctx.fund(&sender, 500).await;
+
+    let (status, body) = ctx
+        .send(
+            "POST",
+            "/transfers/transfers",
+            Some(&ctx.token(&sender)),
+            Some(json!({ "to_user_id": recipient, "amount": 1_000, "asset": "USDC" })),
+        )
+        .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{:?}", body);
+    assert_eq!(ctx.balance(&sender).await, 500);
+
+    let client = ctx.pool.get().await.unwrap();
+    let count: i64 = client
+        .query_one(
+            "SELECT COUNT(*) FROM transfers WHERE from_user_id = $1",
+            &[&sender],
+        )
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(count, 0);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_transfer_to_unknown_user() {
+    let ctx = setup().await;
+    let (sender, _) = ctx.register_user().await;
+    ctx.fund(&sender, 10_000).await;
+
+    let (status, _) = ctx
+        .send(
+            "POST",
+            "/transfers/transfers",
+            Some(&ctx.token(&sender)),
+            Some(json!({ "to_user_id": "nobody_here", "amount": 1_000, "asset": "USDC" })),
+        )
+        .await;
+
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_transfer_hidden_from_other_users() {
+    let _turn = SWEEPS.lock().await;
+    let ctx = setup().await;
+    let (sender, sender_address) = ctx.register_user().await;
+    let (recipient, _) = ctx.register_user().await;
+    let (outsider, _) = ctx.register_user().await;
+    ctx.fund(&sender, 10_000).await;
+    ctx.mock_network(&sender_address);
+
+    let (_, body) = ctx
+        .send(
+            "POST",
+            "/transfers/transfers",
+            Some(&ctx.token(&sender)),
+            Some(json!({ "to_user_id": recipient, "amount": 1_000, "asset": "USDC" })),
+        )
+        .await;
+
+    let (status, _) = ctx
+        .send(
+            "GET",
+            &format!(
+                "/transfers/transfers/{}/status",
+                body["id"].as_str().unwrap()
+            ),
+            Some(&ctx.token(&outsider)),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    // Keep the transfer out of later sweeps
+    let mut landed = ctx.mock_transaction_status("SUCCESS");
+    ctx.services.transfer.poll_processing().await.unwrap();
+    landed.delete();
+}
@@ -0,0 +1,433 @@
+//! Merchant webhook tests: payment status changes are delivered, signed, to
+//! the endpoints subscribed to them, failed deliveries are logged and retried,
+//! and deliveries can be sent again by hand.
+//!
+//! These tests require a running database as defined in the config/env.
+//! Run with: cargo test --test webhook_test -- --ignored
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+    Router,
+};
+use httpmock::prelude::*;
+use serde_json::{json, Value};
+use std::{net::SocketAddr, sync::Arc};
+use tower::util::ServiceExt; // for oneshot
+
+use zaps_backend::{
+    api_error::ApiError,
+    app::build_router,
+    auth,
+    config::Config,
+    db,
+    models::{PaymentStatus, WebhookEventType},
+    role::Role,
+    service::{
+        payment_service::CreatePaymentRequest, webhook_service::CreateWebhookEndpointRequest,
+        ServiceContainer, WebhookService,
+    },
+};
+
+const PAYER: &str = "GA7QYNF7SOWQ3GLR2BGMZEHXAVIRZA4KVWLTJJFC7MGXUA74P7UJVSGZ";
+const VAULT: &str = "CAAACAQDAQCQMBYIBEFAWDANBYHRAEISCMKBKFQXDAMRUGY4DUPB6N4O";
+
+struct TestContext {
+    app: Router,
+    services: Arc<ServiceContainer>,
+    pool: deadpool_postgres::Pool,
+    config: Config,
+    server: MockServer,
+}
+
+async fn setup() -> TestContext {
+    let server = MockServer::start();
+
+    let mut config = Config::load().expect("Failed to load config");
+    // The mock merchant endpoints listen on localhost
+    config.webhooks.allow_private_networks = true;
+    db::run_migrations(&config.database.url)
+        .await
+        .expect("Failed to run database migrations");
+
+    let pool = db::create_pool(&config.database.url)
+        .await
+        .expect("Failed to create pool");
+    let services = Arc::new(
+        ServiceContainer::new(pool.clone(), config.clone())
+            .await
+            .expect("Failed to create services"),
+    );
+    let app = build_router(services.clone());
+
+    TestContext {
+        app,
+        services,
+        pool,
+        config,
+        server,
+    }
+}
+
+impl TestContext {
+    fn admin_token(&self) -> String {
+        auth::generate_access_token("admin", Role::Admin, &self.config.jwt.secret, 1).unwrap()
+    }
+
+    async fn create_merchant(&self) -> String {
+        let merchant_id = format!("merchant_{}", uuid::Uuid::new_v4().simple());
+        let client = self.pool.get().await.unwrap();
+        client
+            .execute(
+                "INSERT INTO merchants (merchant_id, vault_address, settlement_asset) VALUES ($1, $2, 'USDC')",
+                &[&merchant_id, &VAULT],
+            )
+            .await
+            .unwrap();
+        merchant_id
+    }
+
+    async fn create_endpoint(&self, merchant_id: &str, path: &str, event_types: Value) -> Value {
+        let (status, body) = self
+            .send(
+                "POST",
+                &format!("/merchants/{}/webhooks", merchant_id),
+                json!({ "url": self.server.url(path), "event_types": event_types }),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK, "{:?}", body);
+        body
+    }
+
+    /// Create a payment to the merchant and move it to `status`
+    async fn settle_payment(&self, merchant_id: &str, status: PaymentStatus) -> String {
+        let payment = self
+            .services
+            .payment
+            .create_payment(
+                PAYER.to_string(),
+                CreatePaymentRequest {
+                    from_address: None,
+                    merchant_id: merchant_id.to_string(),
+                    send_asset: "USDC".to_string(),
+                    send_amount: 1000,
+                    min_receive: None,
+                    quote_id: None,
+                    memo: None,
+                    payment_uri: None,
+                    nfc_tap: None,
+                },
+            )
+            .await
+            .unwrap();
+        let id = uuid::Uuid::parse_str(&payment.id).unwrap();
+        if status == PaymentStatus::Completed {
+            self.services
+                .payment
+                .update_payment_status(id, PaymentStatus::Processing, "Submitted", None)
+                .await
+                .unwrap();
+        }
+        self.services
+            .payment
+            .update_payment_status(id, status, "Settled on chain", None)
+            .await
+            .unwrap();
+        payment.id
+    }
+
+    async fn deliveries(&self, merchant_id: &str, endpoint_id: &str) -> Vec<Value> {
+        let (status, body) = self
+            .send(
+                "GET",
+                &format!(
+                    "/merchants/{}/webhooks/{}/deliveries",
+                    merchant_id, endpoint_id
+                ),
+                Value::Null,
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK, "{:?}", body);
+        body.as_array().unwrap().clone()
+    }
+
+    /// Sweep until none of the endpoint's deliveries are due any more
+    async fn deliver(&self, merchant_id: &str, endpoint_id: &str) {
+        for _ in 0..10 {
+            self.services.webhook.deliver_pending().await.unwrap();
+            let due = self
+                .deliveries(merchant_id, endpoint_id)
+                .await
+                .into_iter()
+                .any(|delivery| delivery["status"] == "pending" && delivery["attempts"] == 0);
+            if !due {
+                return;
+            }
+        }
+        panic!(
+            "Deliveries of endpoint {} were never attempted",
+            endpoint_id
+        );
+    }
+
+    async fn send(&self, method: &str, uri: &str, body: Value) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", self.admin_token()))
+            .extension(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+        let body = match body {
+            Value::Null => Body::empty(),
+            body => Body::from(body.to_string()),
+        };
+
+        let response = self
+            .app
+            .clone()
+            .oneshot(request.body(body).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+        (status, body)
+    }
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_completed_payment_is_delivered_signed() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant().await;
+
+    let completed = ctx.server.mock(|when, then| {
+        when.method(POST)
+            .path("/hooks/completed")
+            .header("X-Zaps-Event", "payment.completed")
+            .header_exists("X-Zaps-Timestamp")
+            .header_exists("X-Zaps-Signature")
+            .json_body_partial(r#"{ "type": "payment.completed" }"#);
+        then.status(204);
+    });
+    let failed_only = ctx.server.mock(|when, then| {
+        when.method(POST).path("/hooks/failed");
+        then.status(200);
+    });
+
+    let endpoint = ctx
+        .create_endpoint(
+            &merchant_id,
+            "/hooks/completed",
+            json!(["payment.completed"]),
+        )
+        .await;
+    assert!(endpoint["secret"].as_str().unwrap().starts_with("whsec_"));
+    let other = ctx
+        .create_endpoint(&merchant_id, "/hooks/failed", json!(["payment.failed"]))
+        .await;
+
+    // Secrets are only returned when an endpoint is created
+    let (status, body) = ctx
+        .send(
+            "GET",
+            &format!("/merchants/{}/webhooks", merchant_id),
+            Value::Null,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.as_array().unwrap().len(), 2);
+    assert!(body[0].get("secret").is_none());
+
+    let payment_id = ctx
+        .settle_payment(&merchant_id, PaymentStatus::Completed)
+        .await;
+    let endpoint_id = endpoint["id"].as_str().unwrap();
+    ctx.deliver(&merchant_id, endpoint_id).await;
+
+    completed.assert_hits(1);
+    failed_only.assert_hits(0);
+    let deliveries = ctx.deliveries(&merchant_id, endpoint_id).await;
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0]["status"], "delivered");
+    assert_eq!(deliveries[0]["attempts"], 1);
+    assert_eq!(deliveries[0]["response_status"], 204);
+    assert_eq!(deliveries[0]["payload"]["data"]["id"], payment_id.as_str());
+    assert_eq!(deliveries[0]["payload"]["data"]["status"], "completed");
+    assert!(ctx
+        .deliveries(&merchant_id, other["id"].as_str().unwrap())
+        .await
+        .is_empty());
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_failed_delivery_is_retried_and_can_be_redelivered() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant().await;
+
+    let mut down = ctx.server.mock(|when, then| {
+        when.method(POST).path("/hooks/flaky");
+        then.status(503);
+    });
+    let endpoint = ctx
+        .create_endpoint(&merchant_id, "/hooks/flaky", json!(["payment.failed"]))
+        .await;
+    let endpoint_id = endpoint["id"].as_str().unwrap();
+
+    ctx.settle_payment(&merchant_id, PaymentStatus::Failed)
+        .await;
+    ctx.deliver(&merchant_id, endpoint_id).await;
+
+    down.assert_hits(1);
+    let deliveries = ctx.deliveries(&merchant_id, endpoint_id).await;
+    assert_eq!(deliveries[0]["status"], "pending");
+    assert_eq!(deliveries[0]["attempts"], 1);
+    assert_eq!(deliveries[0]["response_status"], 503);
+    assert!(deliveries[0]["last_error"].is_string());
+    let retry_at: chrono::DateTime<chrono::Utc> =
+        serde_json::from_value(deliveries[0]["next_attempt_at"].clone()).unwrap();
+    assert!(retry_at > chrono::Utc::now());
+
+    // The retry is not due yet, but a redelivery goes out on the next sweep
+    down.delete();
+    let up = ctx.server.mock(|when, then| {
+        when.method(POST).path("/hooks/flaky");
+        then.status(200);
+    });
+    let (status, redelivery) = ctx
+        .send(
+            "POST",
+            &format!(
+                "/merchants/{}/webhooks/{}/deliveries/{}/redeliver",
+                merchant_id,
+                endpoint_id,
+                deliveries[0]["id"].as_str().unwrap()
+            ),
+            Value::Null,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", redelivery);
+    assert_eq!(redelivery["event_id"], deliveries[0]["event_id"]);
+    assert_ne!(redelivery["id"], deliveries[0]["id"]);
+
+    ctx.deliver(&merchant_id, endpoint_id).await;
+    up.assert_hits(1);
+    let deliveries = ctx.deliveries(&merchant_id, endpoint_id).await;
+    assert_eq!(deliveries.len(), 2);
+    let statuses: Vec<&str> = deliveries
+        .iter()
+        .map(|delivery| delivery["status"].as_str().unwrap())
+        .collect();
+    assert!(statuses.contains(&"delivered"));
+    assert!(statuses.contains(&"pending"));
+
+    // Disabled endpoints get no new events and cannot be redelivered to
+    let (status, body) = ctx
+        .send(
+            "DELETE",
+            &format!("/merchants/{}/webhooks/{}", merchant_id, endpoint_id),
+            Value::Null,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["active"], false);
+    ctx.settle_payment(&merchant_id, PaymentStatus::Failed)
+        .await;
+    assert_eq!(ctx.deliveries(&merchant_id, endpoint_id).await.len(), 2);
+    let (status, _) = ctx
+        .send(
+            "POST",
+            &format!(
+                "/merchants/{}/webhooks/{}/deliveries/{}/redeliver",
+                merchant_id,
+                endpoint_id,
+                redelivery["id"].as_str().unwrap()
+            ),
+            Value::Null,
+        )
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_endpoint_validation_and_ownership() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant().await;
+    let other_merchant = ctx.create_merchant().await;
+
+    for body in [
+        json!({ "url": "not a url", "event_types": ["payment.completed"] }),
+        json!({ "url": "ftp://merchant.example.com", "event_types": ["payment.completed"] }),
+        json!({ "url": "https://merchant.example.com/hooks", "event_types": [] }),
+    ] {
+        let (status, _) = ctx
+            .send(
+                "POST",
+                &format!("/merchants/{}/webhooks", merchant_id),
+                body,
+            )
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+    let (status, _) = ctx
+        .send(
+            "POST",
+            &format!("/merchants/{}/webhooks", merchant_id),
+            json!({ "url": "https://merchant.example.com/hooks", "event_types": ["payment.created"] }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let endpoint = ctx
+        .create_endpoint(&merchant_id, "/hooks", json!(["settlement.paid"]))
+        .await;
+    let (status, _) = ctx
+        .send(
+            "GET",
+            &format!(
+                "/merchants/{}/webhooks/{}/deliveries",
+                other_merchant,
+                endpoint["id"].as_str().unwrap()
+            ),
+            Value::Null,
+        )
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_private_endpoints_are_refused() {
+    let ctx = setup().await;
+    let merchant_id = ctx.create_merchant().await;
+    let mut config = ctx.config.clone();
+    config.webhooks.allow_private_networks = false;
+    let webhooks = WebhookService::new(Arc::new(ctx.pool.clone()), config);
+
+    for url in [
+        "https://169.254.169.254/latest/meta-data".to_string(),
+        "https://10.0.0.5/hooks".to_string(),
+        "https://localhost/hooks".to_string(),
+        ctx.server.url("/hooks"),
+    ] {
+        let result = webhooks
+            .create_endpoint(
+                &merchant_id,
+                CreateWebhookEndpointRequest {
+                    url: url.clone(),
+                    event_types: vec![WebhookEventType::PaymentCompleted],
+                },
+            )
+            .await;
+        assert!(
+            matches!(result, Err(ApiError::Validation(_))),
+            "{} was accepted",
+            url
+        );
+    }
+}
@@ -0,0 +1,578 @@
+//! Withdrawal tests against the database and httpmock stand-ins for the
+//! anchor's SEP-24 server and the Soroban RPC server.
+//!
+//! These tests require a running database as defined in the config/env.
+//! Run with: cargo test --test withdrawal_test -- --ignored
+
+mod common;
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+};
+use httpmock::prelude::*;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+
+use common::{
+    account_entry, anchor_key, rpc_result, statuses, TestContext, ANCHOR_TOKEN, USDC_ISSUER,
+};
+use soroban_sdk::xdr::Memo;
+use zaps_backend::{
+    role::Role,
+    service::anchor_service::sign_callback,
+    stellar::{asset, transaction},
+};
+
+async fn setup() -> TestContext {
+    common::setup(|config, server| {
+        config.anchor_config.sep24_url = server.url("/sep24");
+        config.anchor_config.sep24_callback_url =
+            Some("https://api.example.com/callbacks/sep24".to_string());
+    })
+    .await
+}
+
+impl TestContext {
+    /// Register a user with accepted KYC and return its user_id and wallet address
+    async fn register_user(&self) -> (String, String) {
+        let user_id = format!("user_{}", uuid::Uuid::new_v4().simple());
+        let (status, _) = self
+            .send(
+                "POST",
+                "/auth/register",
+                None,
+                Some(json!({ "user_id": user_id, "pin": "1234" })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let client = self.pool.get().await.unwrap();
+        let row = client
+            .query_one(
+                "SELECT stellar_address FROM users WHERE user_id = $1",
+                &[&user_id],
+            )
+            .await
+            .unwrap();
+        client
+            .execute(
+                "INSERT INTO kyc_profiles (user_id, level, requested_level, status) VALUES ($1, 'full', 'full', 'accepted')",
+                &[&user_id],
+            )
+            .await
+            .unwrap();
+        (user_id, row.get(0))
+    }
+
+    async fn fund(&self, user_id: &str, amount: i64) {
+        let client = self.pool.get().await.unwrap();
+        client
+            .execute(
+                "INSERT INTO balances (owner_id, asset, amount) VALUES ($1, 'USDC', $2)",
+                &[&user_id, &amount],
+            )
+            .await
+            .unwrap();
+    }
+
+    /// Mock the anchor accepting an interactive withdrawal, returning its id
+    fn mock_start(&self) -> String {
+        let anchor_tx_id = uuid::Uuid::new_v4().to_string();
+        let url = format!("https://anchor.example.com/withdraw?id={}", anchor_tx_id);
+        let body = json!({
+            "type": "interactive_customer_info_needed",
+            "url": url,
+            "id": anchor_tx_id,
+        });
+        self.server.mock(|when, then| {
+            when.method(POST)
+                .path("/sep24/transactions/withdraw/interactive");
+            then.status(200).json_body(body);
+        });
+        anchor_tx_id
+    }
+
+    /// Mock the anchor reporting `status` for a transaction
+    fn mock_anchor_status(&self, anchor_tx_id: &str, status: &str) -> httpmock::Mock<'_> {
+        let body = anchor_transaction(anchor_tx_id, status);
+        self.server.mock(|when, then| {
+            when.method(GET)
+                .path("/sep24/transaction")
+                .query_param("id", anchor_tx_id)
+                .header("Authorization", format!("Bearer {}", ANCHOR_TOKEN));
+            then.status(200).json_body(body);
+        })
+    }
+
+    /// Mock the RPC calls of the payment to the anchor, returning the
+    /// `getLedgerEntries` mock hit when it is signed and the `sendTransaction` mock
+    fn mock_network(&self, sender_address: &str) -> (httpmock::Mock<'_>, httpmock::Mock<'_>) {
+        let tx_hash = format!(
+            "{}{}",
+            uuid::Uuid::new_v4().simple(),
+            uuid::Uuid::new_v4().simple()
+        );
+        let entry = account_entry(sender_address, 41);
+        let sign = self.server.mock(|when, then| {
+            when.method(POST)
+                .json_body_partial(r#"{"method":"getLedgerEntries"}"#);
+            then.status(200).json_body(rpc_result(json!({
+                "entries": [{ "key": "", "xdr": entry, "lastModifiedLedgerSeq": 100 }],
+                "latestLedger": 120,
+            })));
+        });
+        let send = self.server.mock(|when, then| {
+            when.method(POST)
+                .json_body_partial(r#"{"method":"sendTransaction"}"#);
+            then.status(200).json_body(rpc_result(json!({
+                "status": "PENDING",
+                "hash": tx_hash,
+                "latestLedger": 120,
+            })));
+        });
+        (sign, send)
+    }
+
+    async fn create_withdrawal(&self, user_id: &str, amount: i64) -> (StatusCode, Value) {
+        self.send(
+            "POST",
+            "/withdrawals/withdrawals",
+            Some(&self.token(user_id)),
+            Some(json!({ "amount": amount, "asset": "USDC" })),
+        )
+        .await
+    }
+
+    async fn withdrawal(&self, user_id: &str, id: &str) -> Value {
+        let (status, body) = self
+            .send(
+                "GET",
+                &format!("/withdrawals/withdrawals/{}", id),
+                Some(&self.token(user_id)),
+                None,
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK, "{:?}", body);
+        body
+    }
+
+    /// POST a SEP-24 callback signed with the anchor's signing key
+    async fn callback(&self, body: &Value) -> (StatusCode, Value) {
+        let body = body.to_string();
+        let signature = sign_callback(
+            &anchor_key(),
+            chrono::Utc::now().timestamp(),
+            "api.example.com",
+            body.as_bytes(),
+        );
+        let request = Request::builder()
+            .method("POST")
+            .uri("/callbacks/sep24")
+            .header("Content-Type", "application/json")
+            .header("Signature", signature)
+            .extension(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))))
+            .body(Body::from(body))
+            .unwrap();
+        self.oneshot(request).await
+    }
+}
+
+fn anchor_transaction(anchor_tx_id: &str, status: &str) -> Value {
+    json!({
+        "transaction": {
+            "id": anchor_tx_id,
+            "kind": "withdrawal",
+            "status": status,
+            "amount_in": "0.0001",
+            "withdraw_anchor_account": USDC_ISSUER,
+            "withdraw_memo": "12345",
+            "withdraw_memo_type": "id",
+        }
+    })
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_withdrawal_follows_anchor_to_completion() {
+    let ctx = setup().await;
+    let (user, address) = ctx.register_user().await;
+    ctx.mock_web_auth(&address);
+    ctx.fund(&user, 10_000).await;
+    let anchor_tx_id = ctx.mock_start();
+
+    let (status, body) = ctx.create_withdrawal(&user, 1_000).await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert_eq!(body["status"], "pending");
+    assert_eq!(body["anchor_tx_id"], anchor_tx_id.as_str());
+    assert!(body["interactive_url"]
+        .as_str()
+        .unwrap()
+        .contains(&anchor_tx_id));
+    assert_eq!(ctx.balance(&user).await, 9_000);
+    let id = body["id"].as_str().unwrap().to_string();
+
+    // The user is still filling in the anchor's form
+    let mut anchor = ctx.mock_anchor_status(&anchor_tx_id, "incomplete");
+    ctx.services.withdrawal.poll_pending().await.unwrap();
+    assert_eq!(ctx.withdrawal(&user, &id).await["status"], "pending");
+
+    // The anchor asks for the funds: the payment is signed once, and
+    // submitted again while it has not landed
+    anchor.delete();
+    let mut anchor = ctx.mock_anchor_status(&anchor_tx_id, "pending_user_transfer_start");
+    let (sign, send) = ctx.mock_network(&address);
+    let mut landed = ctx.mock_transaction_status("NOT_FOUND");
+    ctx.services.withdrawal.poll_pending().await.unwrap();
+    let tx_hash = ctx.withdrawal(&user, &id).await["tx_hash"].clone();
+    assert!(tx_hash.is_string());
+    ctx.services.withdrawal.poll_pending().await.unwrap();
+    sign.assert_hits(1);
+    send.assert_hits(2);
+
+    let detail = ctx.withdrawal(&user, &id).await;
+    assert_eq!(detail["status"], "pending");
+    assert_eq!(detail["tx_hash"], tx_hash);
+
+    // Once it lands the withdrawal moves on, and nothing more is sent
+    landed.delete();
+    ctx.mock_transaction_status("SUCCESS");
+    ctx.services.withdrawal.poll_pending().await.unwrap();
+    ctx.services.withdrawal.poll_pending().await.unwrap();
+    sign.assert_hits(1);
+    send.assert_hits(2);
+
+    let detail = ctx.withdrawal(&user, &id).await;
+    assert_eq!(detail["status"], "processing");
+    assert_eq!(detail["anchor_status"], "pending_user_transfer_start");
+    assert_eq!(detail["tx_hash"], tx_hash);
+
+    anchor.delete();
+    ctx.mock_anchor_status(&anchor_tx_id, "completed");
+    ctx.services.withdrawal.poll_pending().await.unwrap();
+
+    let detail = ctx.withdrawal(&user, &id).await;
+    assert_eq!(detail["status"], "completed");
+    assert_eq!(
+        statuses(&detail),
+        vec!["pending", "processing", "completed"]
+    );
+    assert_eq!(ctx.balance(&user).await, 9_000);
+    assert_eq!(
+        ctx.notification_titles(&user).await,
+        vec!["Withdrawal completed"]
+    );
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_withdrawal_follows_anchor_callbacks() {
+    let ctx = setup().await;
+    let (user, address) = ctx.register_user().await;
+    ctx.mock_web_auth(&address);
+    ctx.fund(&user, 10_000).await;
+    let anchor_tx_id = ctx.mock_start();
+
+    let (_, body) = ctx.create_withdrawal(&user, 1_000).await;
+    let id = body["id"].as_str().unwrap().to_string();
+
+    // Repeated callbacks asking for the funds send them once
+    let (sign, send) = ctx.mock_network(&address);
+    let mut landed = ctx.mock_transaction_status("NOT_FOUND");
+    let awaiting = anchor_transaction(&anchor_tx_id, "pending_user_transfer_start");
+    for expected in ["pending", "processing"] {
+        let (status, body) = ctx.callback(&awaiting).await;
+        assert_eq!(status, StatusCode::OK, "{:?}", body);
+        assert_eq!(body["status"], expected);
+        landed.delete();
+        landed = ctx.mock_transaction_status("SUCCESS");
+        // Callbacks signed in the same second would be replays
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    }
+    sign.assert_hits(1);
+    send.assert_hits(1);
+
+    let (status, body) = ctx
+        .callback(&anchor_transaction(&anchor_tx_id, "completed"))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert_eq!(body["status"], "completed");
+
+    let detail = ctx.withdrawal(&user, &id).await;
+    assert_eq!(detail["anchor_status"], "completed");
+    assert_eq!(
+        statuses(&detail),
+        vec!["pending", "processing", "completed"]
+    );
+    assert_eq!(ctx.balance(&user).await, 9_000);
+
+    let (status, _) = ctx
+        .callback(&anchor_transaction("unknown", "completed"))
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_completion_without_our_payment_refunds_user() {
+    let ctx = setup().await;
+    let (user, address) = ctx.register_user().await;
+    ctx.mock_web_auth(&address);
+    ctx.fund(&user, 10_000).await;
+    let anchor_tx_id = ctx.mock_start();
+
+    let (_, body) = ctx.create_withdrawal(&user, 1_000).await;
+    let id = body["id"].as_str().unwrap().to_string();
+
+    // Reporting progress does not move the withdrawal before it is paid
+    let (status, body) = ctx
+        .callback(&anchor_transaction(&anchor_tx_id, "pending_anchor"))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert_eq!(body["status"], "pending");
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+
+    let (status, body) = ctx
+        .callback(&anchor_transaction(&anchor_tx_id, "completed"))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{:?}", body);
+    assert_eq!(body["status"], "failed");
+
+    let detail = ctx.withdrawal(&user, &id).await;
+    assert_eq!(statuses(&detail), vec!["pending", "failed"]);
+    assert_eq!(ctx.balance(&user).await, 10_000);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_expired_withdrawal_refunds_user() {
+    let ctx = setup().await;
+    let (user, address) = ctx.register_user().await;
+    ctx.mock_web_auth(&address);
+    ctx.fund(&user, 10_000).await;
+    let anchor_tx_id = ctx.mock_start();
+
+    let (_, body) = ctx.create_withdrawal(&user, 1_000).await;
+    let id = body["id"].as_str().unwrap().to_string();
+
+    ctx.mock_anchor_status(&anchor_tx_id, "expired");
+    ctx.services.withdrawal.poll_pending().await.unwrap();
+
+    let detail = ctx.withdrawal(&user, &id).await;
+    assert_eq!(detail["status"], "failed");
+    assert_eq!(statuses(&detail), vec!["pending", "failed"]);
+    assert_eq!(ctx.balance(&user).await, 10_000);
+    assert_eq!(
+        ctx.notification_titles(&user).await,
+        vec!["Withdrawal failed"]
+    );
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_failure_after_payment_credits_only_what_the_anchor_returned() {
+    let ctx = setup().await;
+    let (user, address) = ctx.register_user().await;
+    ctx.mock_web_auth(&address);
+    ctx.fund(&user, 10_000).await;
+    let anchor_tx_id = ctx.mock_start();
+
+    let (_, body) = ctx.create_withdrawal(&user, 1_000).await;
+    let id = body["id"].as_str().unwrap().to_string();
+
+    // The anchor reports refunding 600 stroops on-chain, which landed, and
+    // 400 more in a payment that never did
+    let usdc = asset::resolve_asset("USDC", &ctx.config.stellar_network.assets).unwrap();
+    let refund = transaction::build_payment(
+        &anchor_key().address(),
+        1,
+        &address,
+        usdc,
+        600,
+        Memo::None,
+        transaction::BASE_FEE,
+        0,
+    )
+    .unwrap();
+    let refund_hash = "a".repeat(64);
+    let missing_hash = "b".repeat(64);
+    let refund_envelope = transaction::encode_envelope(&refund).unwrap();
+    ctx.server.mock(|when, then| {
+        when.method(POST)
+            .json_body_partial(r#"{"method":"getTransaction"}"#)
+            .body_contains(&refund_hash);
+        then.status(200).json_body(rpc_result(json!({
+            "status": "SUCCESS",
+            "latestLedger": 121,
+            "ledger": 121,
+            "envelopeXdr": refund_envelope,
+        })));
+    });
+    ctx.server.mock(|when, then| {
+        when.method(POST)
+            .json_body_partial(r#"{"method":"getTransaction"}"#)
+            .body_contains(&missing_hash);
+        then.status(200).json_body(rpc_result(json!({
+            "status": "NOT_FOUND",
+            "latestLedger": 121,
+        })));
+    });
+
+    // Our payment to the anchor lands
+    let mut anchor = ctx.mock_anchor_status(&anchor_tx_id, "pending_user_transfer_start");
+    ctx.mock_network(&address);
+    ctx.mock_transaction_status("SUCCESS");
+    ctx.services.withdrawal.poll_pending().await.unwrap();
+    assert_eq!(ctx.withdrawal(&user, &id).await["status"], "processing");
+
+    anchor.delete();
+    let mut refunded = anchor_transaction(&anchor_tx_id, "refunded");
+    refunded["transaction"]["refunds"] = json!({
+        "amount_refunded": "0.0001",
+        "payments": [
+            { "id": refund_hash, "id_type": "stellar", "amount": "0.00006" },
+            { "id": refund_hash, "id_type": "stellar", "amount": "0.00006" },
+            { "id": missing_hash, "id_type": "stellar", "amount": "0.00004" },
+            { "id": "wire-1", "id_type": "external", "amount": "0.00004" },
+        ],
+    });
+    ctx.server.mock(|when, then| {
+        when.method(GET)
+            .path("/sep24/transaction")
+            .query_param("id", &anchor_tx_id);
+        then.status(200).json_body(refunded);
+    });
+    ctx.services.withdrawal.poll_pending().await.unwrap();
+
+    let detail = ctx.withdrawal(&user, &id).await;
+    assert_eq!(detail["status"], "failed");
+    assert_eq!(ctx.balance(&user).await, 9_600);
+    assert_eq!(
+        ctx.notification_titles(&user).await,
+        vec!["Withdrawal failed"]
+    );
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_anchor_outage_fails_withdrawal() {
+    let ctx = setup().await;
+    let (user, address) = ctx.register_user().await;
+    ctx.mock_web_auth(&address);
+    ctx.fund(&user, 10_000).await;
+    ctx.server.mock(|when, then| {
+        when.method(POST)
+            .path("/sep24/transactions/withdraw/interactive");
+        then.status(503).body("maintenance");
+    });
+
+    let (status, body) = ctx.create_withdrawal(&user, 1_000).await;
+
+    assert_eq!(status, StatusCode::BAD_GATEWAY, "{:?}", body);
+    assert_eq!(body["code"], "ANCHOR_ERROR");
+    assert_eq!(ctx.balance(&user).await, 10_000);
+
+    let client = ctx.pool.get().await.unwrap();
+    let status: String = client
+        .query_one(
+            "SELECT status FROM withdrawals WHERE user_id = $1",
+            &[&user],
+        )
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(status, "failed");
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_withdrawal_requires_sufficient_balance() {
+    let ctx = setup().await;
+    let (user, address) = ctx.register_user().await;
+    ctx.mock_web_auth(&address);
+    ctx.fund(&user, 500).await;
+    let start = ctx.server.mock(|when, then| {
+        when.method(POST)
+            .path("/sep24/transactions/withdraw/interactive");
+        then.status(200);
+    });
+
+    let (status, body) = ctx.create_withdrawal(&user, 1_000).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{:?}", body);
+    assert_eq!(ctx.balance(&user).await, 500);
+    start.assert_hits(0);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_withdrawal_rejects_invalid_destination() {
+    let ctx = setup().await;
+    let (user, address) = ctx.register_user().await;
+    ctx.mock_web_auth(&address);
+    ctx.fund(&user, 10_000).await;
+    let start = ctx.server.mock(|when, then| {
+        when.method(POST)
+            .path("/sep24/transactions/withdraw/interactive");
+        then.status(200);
+    });
+
+    let (status, body) = ctx
+        .send(
+            "POST",
+            "/withdrawals/withdrawals",
+            Some(&ctx.token(&user)),
+            Some(json!({
+                "amount": 1_000,
+                "asset": "USDC",
+                "destination_address": "GEXAMPLE_ADDRESS",
+            })),
+        )
+        .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{:?}", body);
+    assert!(body.to_string().contains("destination_address"));
+    assert_eq!(ctx.balance(&user).await, 10_000);
+    start.assert_hits(0);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_withdrawal_hidden_from_other_users() {
+    let ctx = setup().await;
+    let (user, address) = ctx.register_user().await;
+    ctx.mock_web_auth(&address);
+    let (outsider, _) = ctx.register_user().await;
+    ctx.fund(&user, 10_000).await;
+    ctx.mock_start();
+
+    let (_, body) = ctx.create_withdrawal(&user, 1_000).await;
+
+    let (status, _) = ctx
+        .send(
+            "GET",
+            &format!(
+                "/withdrawals/withdrawals/{}/status",
+                body["id"].as_str().unwrap()
+            ),
+            Some(&ctx.token(&outsider)),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let admin = ctx.token_as(&outsider, Role::Admin);
+    let (status, _) = ctx
+        .send(
+            "GET",
+            &format!(
+                "/withdrawals/withdrawals/{}/status",
+                body["id"].as_str().unwrap()
+            ),
+            Some(&admin),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+}